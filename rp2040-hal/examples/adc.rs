@@ -108,7 +108,7 @@ fn main() -> ! {
     let mut temperature_sensor = adc.enable_temp_sensor();
 
     // Configure GPIO26 as an ADC input
-    let mut adc_pin_0 = pins.gpio26.into_floating_input();
+    let mut adc_pin_0 = pins.gpio26.into_analog_input();
     loop {
         // Read the raw ADC counts from the temperature sensor channel.
         let temp_sens_adc_counts: u16 = adc.read(&mut temperature_sensor).unwrap();
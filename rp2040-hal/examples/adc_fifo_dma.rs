@@ -0,0 +1,134 @@
+//! # ADC FIFO DMA Example
+//!
+//! This application demonstrates how to capture ADC samples from a pin (e.g.
+//! a potentiometer connected to GPIO26) into a buffer using DMA, and output
+//! them to the UART on pins 1 and 2 at 9600 baud.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use core::fmt::Write;
+use hal::dma::{single_buffer, DMAExt};
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, captures 1024 ADC samples
+/// using DMA, then prints them.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // UART TX (characters sent from pico) on pin 1 (GPIO0) and RX (on pin 2 (GPIO1)
+    let uart_pins = (
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+
+    // Create a UART driver
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // Write to the UART
+    uart.write_full_blocking(b"ADC FIFO DMA example\r\n");
+
+    // Split the DMA block into its channels
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // Enable ADC
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+
+    // Configure GPIO26 as an ADC input
+    let mut adc_pin_0 = pins.gpio26.into_analog_input();
+
+    // Configure the FIFO to raise a DREQ for each sample, and start sampling
+    let fifo = adc
+        .build_fifo()
+        .set_channel(&mut adc_pin_0)
+        .enable_dma()
+        .start();
+
+    // Buffer the samples are captured into
+    let samples = cortex_m::singleton!(: [u16; 1024] = [0; 1024]).unwrap();
+
+    // Let DMA channel 0 move the samples from the FIFO into our buffer, and
+    // wait for the buffer to be full
+    let transfer = single_buffer::Config::new(dma.ch0, fifo.dma_read_target(), samples).start();
+    let (_ch0, _, samples) = transfer.wait().unwrap();
+
+    // Stop sampling, so the ADC could be used for one-shot reads again
+    fifo.stop();
+
+    for sample in samples.iter() {
+        writeln!(uart, "{}\r", sample).unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
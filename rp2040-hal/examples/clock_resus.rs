@@ -0,0 +1,163 @@
+//! # Clock Resuscitation Example
+//!
+//! This application demonstrates how to recover from a failing system clock.
+//!
+//! It blinks the LED on GPIO25, then deliberately powers down PLL_SYS. The
+//! resuscitation logic of the RP2040 notices that clk_sys stopped, switches it
+//! to the reference clock and raises an interrupt. The main loop then restarts
+//! PLL_SYS and clears the resuscitation status, in that order.
+//!
+//! The LED blinks slowly while clk_sys runs from the 12 MHz reference clock, as
+//! the delay was set up for 125 MHz.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_hal::digital::v2::OutputPin;
+use embedded_time::fixed_point::FixedPoint;
+use rp2040_hal::clocks::Clock;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Set by the interrupt handler when clk_sys was resuscitated
+static RESUSSED: AtomicBool = AtomicBool::new(false);
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then stops and recovers
+/// the system clock in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let mut clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().integer());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Switch clk_sys to clk_ref if it stops for 255 cycles of clk_ref, and
+    // raise an interrupt when that happens
+    clocks.enable_resus(0xff);
+    clocks.enable_resus_interrupt();
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::CLOCKS_IRQ);
+    }
+
+    loop {
+        for _ in 0..5 {
+            led_pin.set_high().unwrap();
+            delay.delay_ms(200);
+            led_pin.set_low().unwrap();
+            delay.delay_ms(200);
+        }
+
+        // Power down PLL_SYS behind the back of the clocks manager, so clk_sys
+        // stops
+        unsafe {
+            (*pac::PLL_SYS::ptr())
+                .pwr
+                .modify(|_, w| w.pd().set_bit().vcopd().set_bit());
+        }
+
+        while !RESUSSED.load(Ordering::Relaxed) {
+            cortex_m::asm::nop();
+        }
+
+        for _ in 0..2 {
+            led_pin.set_high().unwrap();
+            delay.delay_ms(20);
+            led_pin.set_low().unwrap();
+            delay.delay_ms(20);
+        }
+
+        // Give clk_sys a valid source again first, then clear the
+        // resuscitation, which also clears the interrupt
+        clocks
+            .set_sys_clock(
+                hal::pll::common_configs::PLL_SYS_125MHZ,
+                None,
+                &mut pac.RESETS,
+            )
+            .ok()
+            .unwrap();
+        clocks.clear_resus();
+
+        RESUSSED.store(false, Ordering::Relaxed);
+        unsafe {
+            pac::NVIC::unmask(pac::Interrupt::CLOCKS_IRQ);
+        }
+    }
+}
+
+#[interrupt]
+fn CLOCKS_IRQ() {
+    // The interrupt stays pending until the resuscitation is cleared, which
+    // needs the clocks manager, so leave that to the main loop
+    pac::NVIC::mask(pac::Interrupt::CLOCKS_IRQ);
+    RESUSSED.store(true, Ordering::Relaxed);
+}
+
+// End of file
@@ -0,0 +1,193 @@
+//! # Deep Sleep Example
+//!
+//! This application demonstrates how to sleep until an interrupt handler sets a
+//! flag. The core sleeps with all clocks but those of the GPIO block gated until
+//! GPIO14 is pulled low, e.g. by a button connected to ground, then toggles the
+//! LED on GPIO25.
+//!
+//! It first checks that an interrupt arriving right before the core goes to
+//! sleep still wakes it, and prints how long that took over the UART.
+//!
+//! While the core sleeps, the current drawn by the board drops, which can be
+//! checked with a current probe. Replacing `deep_sleep_until` with
+//! `light_sleep_until` shows the difference the gated clocks make.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::interrupt::Mutex;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use embedded_hal::serial::Write as _;
+use hal::clocks::{Clock, ClockGate, SleepConfig};
+use hal::gpio::Interrupt;
+use hal::sleep::deep_sleep_until;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The button pin, shared with the interrupt handler
+type ButtonPin = hal::gpio::Pin<hal::gpio::bank0::Gpio14, hal::gpio::PullUpInput>;
+
+/// The button, so the interrupt handler can clear its interrupt
+static BUTTON: Mutex<RefCell<Option<ButtonPin>>> = Mutex::new(RefCell::new(None));
+
+/// Set by the interrupt handler when the button was pressed
+static PRESSED: AtomicBool = AtomicBool::new(false);
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, checks the wake-up of an
+/// interrupt right before sleeping, then sleeps until the button is pressed and
+/// toggles the LED in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let mut clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Raise an interrupt when GPIO14 is pulled low
+    let button = pins.gpio14.into_pull_up_input();
+    button.set_interrupt_enabled(Interrupt::EdgeLow, true);
+    cortex_m::interrupt::free(|cs| {
+        BUTTON.borrow(cs).replace(Some(button));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Only the GPIO block, which detects the edge, and the timer keep their
+    // clocks while the core sleeps
+    let config: SleepConfig = (ClockGate::Io | ClockGate::Timer).into();
+
+    // Raise the interrupt after the flag was checked, right before the WFI. If
+    // the interrupt was lost, the core would sleep until the button is pressed
+    let start = timer.get_counter();
+    let mut pended = false;
+    deep_sleep_until(&mut clocks, &mut core.SCB, config, || {
+        if !pended {
+            pac::NVIC::pend(pac::Interrupt::IO_IRQ_BANK0);
+            pended = true;
+        }
+        take_pressed()
+    });
+    let elapsed = timer.get_counter() - start;
+    writeln!(
+        uart,
+        "woke up {} us after an interrupt right before sleeping\r",
+        elapsed
+    )
+    .unwrap();
+
+    loop {
+        writeln!(uart, "sleeping until GPIO14 is pulled low\r").unwrap();
+        // The UART's clocks are gated while sleeping, so let it finish first
+        nb::block!(uart.flush()).unwrap();
+
+        deep_sleep_until(&mut clocks, &mut core.SCB, config, take_pressed);
+
+        led_pin.toggle().unwrap();
+    }
+}
+
+/// Clear the flag, returning whether the button was pressed. The Cortex-M0+
+/// has no atomic swap, so the flag is read and cleared in a critical section
+fn take_pressed() -> bool {
+    cortex_m::interrupt::free(|_| {
+        let pressed = PRESSED.load(Ordering::Relaxed);
+        PRESSED.store(false, Ordering::Relaxed);
+        pressed
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(button) = BUTTON.borrow(cs).borrow_mut().as_mut() {
+            button.clear_interrupt(Interrupt::EdgeLow);
+        }
+    });
+    PRESSED.store(true, Ordering::Relaxed);
+}
+
+// End of file
@@ -0,0 +1,222 @@
+//! # DMA CRC-32 Example
+//!
+//! This application checks the CRC-32 computed by the DMA sniffer against a
+//! software implementation. For several variants of the CRC-32, the check input
+//! of the CRC catalogues and a buffer of pseudo-random bytes are copied by a DMA
+//! channel with the sniffer attached, and the results are printed to the UART on
+//! pins 1 and 2 at 9600 baud. The LED on GPIO25 is turned on if all of them
+//! match.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::digital::v2::OutputPin;
+use hal::clocks::Clock;
+use hal::dma::{single_buffer, DMAExt, SingleChannel, SniffMode, Sniffer};
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The variants of the CRC-32 which are checked: their name, whether the bits
+/// are reversed, whether the result is inverted, and their CRC of "123456789"
+const VARIANTS: [(&str, bool, bool, u32); 3] = [
+    ("CRC-32 (zlib)", true, true, 0xcbf4_3926),
+    ("CRC-32/MPEG-2", false, false, 0x0376_e6e7),
+    ("CRC-32/BZIP2", false, true, 0xfc89_1918),
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then compares the CRCs of
+/// the sniffer with the software ones.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // UART TX (characters sent from pico) on pin 1 (GPIO0) and RX (on pin 2 (GPIO1)
+    let uart_pins = (
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+
+    // Create a UART driver
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Split the DMA block into its channels
+    let mut dma = pac.DMA.split(&mut pac.RESETS);
+    let mut ch0 = dma.ch0;
+
+    // The check input of the CRC catalogues, and pseudo-random data
+    let check_input = cortex_m::singleton!(: [u8; 9] = *b"123456789").unwrap();
+    let mut check_output = cortex_m::singleton!(: [u8; 9] = [0; 9]).unwrap();
+    let data = cortex_m::singleton!(: [u8; 1024] = [0; 1024]).unwrap();
+    let mut output = cortex_m::singleton!(: [u8; 1024] = [0; 1024]).unwrap();
+    let mut state = 0x1234_5678u32;
+    for byte in data.iter_mut() {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *byte = state as u8;
+    }
+    let check_input: &'static [u8; 9] = check_input;
+    let data: &'static [u8; 1024] = data;
+
+    let mut all_match = true;
+    for &(name, reversed, inverted, check) in VARIANTS.iter() {
+        let mode = SniffMode::Crc32 {
+            reversed,
+            inverted,
+            seed: 0xffff_ffff,
+        };
+
+        // Both implementations have to match the catalogue
+        let software = crc32(check_input, reversed, inverted);
+        let (hardware, ch, buf) = sniff(&mut dma.sniffer, ch0, mode, check_input, check_output);
+        ch0 = ch;
+        check_output = buf;
+        all_match &= hardware == check && software == check;
+        writeln!(
+            uart,
+            "{} of \"123456789\": sniffer {:#010x}, software {:#010x}, expected {:#010x}\r",
+            name, hardware, software, check
+        )
+        .unwrap();
+
+        let software = crc32(data, reversed, inverted);
+        let (hardware, ch, buf) = sniff(&mut dma.sniffer, ch0, mode, data, output);
+        ch0 = ch;
+        output = buf;
+        all_match &= hardware == software;
+        writeln!(
+            uart,
+            "{} of {} random bytes: sniffer {:#010x}, software {:#010x}\r",
+            name,
+            data.len(),
+            hardware,
+            software
+        )
+        .unwrap();
+    }
+    dma.sniffer.detach();
+
+    if all_match {
+        led_pin.set_high().unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Copy `from` to `to` with `ch`, returning the checksum computed by the
+/// sniffer in `mode`
+fn sniff<CH: SingleChannel, const N: usize>(
+    sniffer: &mut Sniffer,
+    ch: CH,
+    mode: SniffMode,
+    from: &'static [u8; N],
+    to: &'static mut [u8; N],
+) -> (u32, CH, &'static mut [u8; N]) {
+    sniffer.attach(&ch, mode);
+    let (ch, _, to) = single_buffer::Config::new(ch, from, to)
+        .start()
+        .wait()
+        .unwrap();
+    (sniffer.result(), ch, to)
+}
+
+/// CRC-32 with the polynomial 0x04C11DB7 and the seed 0xFFFFFFFF, computed bit
+/// by bit
+///
+/// If `reversed`, the bits of each byte and of the result are reversed, i.e.
+/// the least significant bit comes first. If `inverted`, the result is inverted.
+fn crc32(data: &[u8], reversed: bool, inverted: bool) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        let byte = if reversed { byte.reverse_bits() } else { byte };
+        crc ^= u32::from(byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & (1 << 31) != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    if reversed {
+        crc = crc.reverse_bits();
+    }
+    if inverted {
+        crc = !crc;
+    }
+    crc
+}
+
+// End of file
@@ -0,0 +1,120 @@
+//! # DORMANT GPIO Wake Example
+//!
+//! This application demonstrates how to stop all clocks of the RP2040 until a
+//! GPIO pin changes.
+//!
+//! It blinks the LED on GPIO25 three times, then enters DORMANT state until
+//! GPIO14 is pulled low, e.g. by a button connected to ground. While dormant,
+//! the LED is off and only the oscillators' leakage current flows, so this is a
+//! good point to measure the current drawn by the board.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+use embedded_time::fixed_point::FixedPoint;
+use rp2040_hal::clocks::Clock;
+use rp2040_hal::gpio::Interrupt;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then alternates between
+/// blinking the LED and sleeping in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let mut clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().integer());
+
+    // Nothing runs from the ring oscillator once the clocks are set up, so
+    // stop it to save power
+    let _rosc = hal::rosc::RingOscillator::new(pac.ROSC)
+        .initialize()
+        .disable();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Wake up when GPIO14 is pulled low
+    let mut wake_pin = pins.gpio14.into_pull_up_input();
+    wake_pin.set_dormant_wake_enabled(Interrupt::EdgeLow, true);
+
+    loop {
+        for _ in 0..3 {
+            led_pin.set_high().unwrap();
+            delay.delay_ms(200);
+            led_pin.set_low().unwrap();
+            delay.delay_ms(200);
+        }
+
+        // The clocks manager stops the PLLs and the crystal oscillator, and
+        // restores them once GPIO14 wakes the chip up again
+        clocks.dormant().ok().unwrap();
+
+        // The edge is latched, and would wake the chip again right away
+        wake_pin.clear_interrupt(Interrupt::EdgeLow);
+    }
+}
+
+// End of file
@@ -0,0 +1,137 @@
+//! # Flash Boot Counter Example
+//!
+//! This application demonstrates how to erase and program the flash from the
+//! running program. It counts its boots in the last sector of a 2 MiB flash
+//! and prints the count over the UART.
+//!
+//! To spread the wear, every boot programs the next free 256 byte page of the
+//! sector, and the sector is only erased once all of its 16 pages are used.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use core::fmt::Write;
+use hal::flash::{FLASH_PAGE_SIZE, FLASH_SECTOR_SIZE, XIP_BASE};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Offset of the last sector of a 2 MiB flash, which the program doesn't use
+const COUNTER_OFFSET: u32 = 2 * 1024 * 1024 - FLASH_SECTOR_SIZE;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, increments the boot counter
+/// stored in flash and prints it.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // Each page starts with the count it was programmed with. Erased pages
+    // read as 0xffff_ffff
+    let pages = FLASH_SECTOR_SIZE / FLASH_PAGE_SIZE;
+    let read_page = |page: u32| unsafe {
+        core::ptr::read_volatile((XIP_BASE + COUNTER_OFFSET + page * FLASH_PAGE_SIZE) as *const u32)
+    };
+    let free_page = (0..pages).find(|&page| read_page(page) == 0xffff_ffff);
+
+    let boots = match free_page {
+        Some(0) => 1,
+        Some(page) => read_page(page - 1).wrapping_add(1),
+        None => read_page(pages - 1).wrapping_add(1),
+    };
+
+    // The data to program has to be in RAM
+    let mut buf = [0xffu8; FLASH_PAGE_SIZE as usize];
+    buf[..4].copy_from_slice(&boots.to_le_bytes());
+
+    // Only core 0 runs, so nothing else reads the flash meanwhile
+    let result = unsafe {
+        match free_page {
+            Some(page) => {
+                hal::flash::flash_range_program(COUNTER_OFFSET + page * FLASH_PAGE_SIZE, &buf)
+            }
+            None => hal::flash::flash_range_erase(COUNTER_OFFSET, FLASH_SECTOR_SIZE)
+                .and_then(|_| hal::flash::flash_range_program(COUNTER_OFFSET, &buf)),
+        }
+    };
+
+    writeln!(uart, "boot number {}: {:?}\r", boots, result).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
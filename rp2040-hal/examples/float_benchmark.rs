@@ -0,0 +1,164 @@
+//! # Float Benchmark Example
+//!
+//! This application times the inner loop of a Mandelbrot set renderer, which
+//! is mostly `f32` arithmetic, and prints the number of cycles over the UART.
+//!
+//! Run it with and without the `rom-intrinsics` feature to compare the
+//! `compiler-builtins` float implementation with the one in the bootrom:
+//!
+//! ```text
+//! cargo run --release --example float_benchmark
+//! cargo run --release --example float_benchmark --features rom-intrinsics
+//! ```
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use core::fmt::Write;
+use cortex_m::peripheral::syst::SystClkSource;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Size of the rendered area, in points
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 16;
+
+/// Iterations after which a point is considered part of the set
+const MAX_ITERATIONS: u32 = 32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, renders the Mandelbrot set
+/// while counting the cycles with the SysTick, then prints the result.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // Let the SysTick count down the processor clock cycles
+    core.SYST.set_clock_source(SystClkSource::Core);
+    core.SYST.set_reload(0x00ff_ffff);
+    core.SYST.clear_current();
+    core.SYST.enable_counter();
+
+    // Read the scale through a volatile access so the compiler can't compute
+    // the results
+    let scale: f32 = 1.0;
+    let scale = unsafe { core::ptr::read_volatile(&scale) };
+
+    let mut cycles = 0;
+    let mut in_set = 0;
+    for row in 0..HEIGHT {
+        let start = cortex_m::peripheral::SYST::get_current();
+        for column in 0..WIDTH {
+            let cx = (column as f32 / WIDTH as f32 * 3.0 - 2.0) * scale;
+            let cy = (row as f32 / HEIGHT as f32 * 2.0 - 1.0) * scale;
+            if iterations(cx, cy) == MAX_ITERATIONS {
+                in_set += 1;
+            }
+        }
+        // Each row takes well below the 2^24 cycles of the SysTick counter
+        cycles += (start - cortex_m::peripheral::SYST::get_current()) & 0x00ff_ffff;
+    }
+
+    writeln!(
+        uart,
+        "{} of {} points in the set, {} cycles, bootrom intrinsics: {}\r",
+        in_set,
+        WIDTH * HEIGHT,
+        cycles,
+        cfg!(feature = "rom-intrinsics")
+    )
+    .unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Count the iterations of the point `cx + cy * i` until it escapes
+fn iterations(cx: f32, cy: f32) -> u32 {
+    let (mut x, mut y) = (0.0f32, 0.0f32);
+    let mut i = 0;
+    while i < MAX_ITERATIONS && x * x + y * y <= 4.0 {
+        let next_x = x * x - y * y + cx;
+        y = 2.0 * x * y + cy;
+        x = next_x;
+        i += 1;
+    }
+    i
+}
+
+// End of file
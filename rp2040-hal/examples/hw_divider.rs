@@ -0,0 +1,138 @@
+//! # Hardware Divider Example
+//!
+//! This application compares the cycle count of the SIO hardware divider with
+//! the division routine the compiler provides, and prints the results over the
+//! UART.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use core::fmt::Write;
+use cortex_m::peripheral::syst::SystClkSource;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of divisions to time
+const ITERATIONS: u32 = 1000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, times both ways of dividing
+/// using the SysTick counter, then prints the results.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // Let the SysTick count down the processor clock cycles
+    core.SYST.set_clock_source(SystClkSource::Core);
+    core.SYST.set_reload(0x00ff_ffff);
+    core.SYST.clear_current();
+    core.SYST.enable_counter();
+
+    // Read the operands through volatile accesses so the compiler can't compute the results
+    let dividend: u32 = 123_456_789;
+    let divisor: u32 = 1_234;
+    let dividend = unsafe { core::ptr::read_volatile(&dividend) };
+    let divisor = unsafe { core::ptr::read_volatile(&divisor) };
+
+    let start = cortex_m::peripheral::SYST::get_current();
+    let mut software = 0;
+    for i in 0..ITERATIONS {
+        software ^= (dividend + i) / divisor;
+    }
+    let software_cycles = start - cortex_m::peripheral::SYST::get_current();
+
+    let start = cortex_m::peripheral::SYST::get_current();
+    let mut hardware = 0;
+    for i in 0..ITERATIONS {
+        hardware ^= sio.hwdivider.unsigned(dividend + i, divisor).quotient;
+    }
+    let hardware_cycles = start - cortex_m::peripheral::SYST::get_current();
+
+    writeln!(
+        uart,
+        "software: {} cycles, hardware: {} cycles for {} divisions\r",
+        software_cycles, hardware_cycles, ITERATIONS
+    )
+    .unwrap();
+    writeln!(uart, "results match: {}\r", software == hardware).unwrap();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
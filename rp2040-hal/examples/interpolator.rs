@@ -0,0 +1,136 @@
+//! # Interpolator Example
+//!
+//! This application uses the blend mode of interpolator 0 to resample a
+//! wavetable with fixed-point linear interpolation, and prints the samples over
+//! the UART.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use core::fmt::Write;
+use hal::sio::LaneConfig;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// One period of a sine wave, as signed 16-bit samples
+const WAVETABLE: [i16; 16] = [
+    0, 12539, 23170, 30273, 32767, 30273, 23170, 12539, 0, -12539, -23170, -30273, -32767, -30273,
+    -23170, -12539,
+];
+
+/// Phase increment per output sample, as a 24.8 fixed-point index into the wavetable
+const PHASE_STEP: u32 = 0x50;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals and INTERP0 in blend mode, then
+/// prints a wavetable resampled with linear interpolation.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let mut sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // In blend mode, the full result is base 0 blended with base 1 by the lower
+    // 8 bits of the lane 1 result: base0 + (base1 - base0) * alpha / 256.
+    // Lane 1 is signed, so the bases are treated as signed samples.
+    let interp = &mut sio.interp0;
+    interp.set_lane0(LaneConfig::new());
+    interp.set_lane1(LaneConfig {
+        mask_msb: 7,
+        signed: true,
+        ..LaneConfig::new()
+    });
+    interp.set_blend(true);
+
+    let mut phase = 0u32;
+    for _ in 0..64 {
+        let index = (phase >> 8) as usize % WAVETABLE.len();
+        let next = (index + 1) % WAVETABLE.len();
+
+        // The samples to blend, and the fractional part of the phase as alpha
+        interp.set_base(0, WAVETABLE[index] as u32);
+        interp.set_base(1, WAVETABLE[next] as u32);
+        interp.set_accum(1, phase & 0xff);
+        let sample = interp.peek_full() as i32;
+
+        writeln!(uart, "{:4}: {:6}\r", phase, sample).unwrap();
+        phase = (phase + PHASE_STEP) % ((WAVETABLE.len() as u32) << 8);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
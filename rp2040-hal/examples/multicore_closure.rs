@@ -0,0 +1,132 @@
+//! # Multicore Closure Example
+//!
+//! This application demonstrates spawning a closure on core 1. The closure takes
+//! ownership of the LED pin and blinks it, while core 0 prints over the UART.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use embedded_time::fixed_point::FixedPoint;
+use hal::clocks::Clock;
+use hal::multicore::{Multicore, Stack};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Stack for core 1
+///
+/// The closure spawned on core 1 runs on this stack. We use the `Stack` struct to
+/// ensure that it has 32-byte alignment, which allows the stack guard to take up
+/// the least amount of usable RAM.
+static mut CORE1_STACK: Stack<4096> = Stack::new();
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, hands the LED to core 1, then
+/// prints a counter over the UART in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sys_freq = clocks.system_clock.freq().integer();
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, sys_freq);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let mut sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.into(),
+        )
+        .unwrap();
+
+    // The LED pin is moved into the closure, so core 1 owns it
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = mc.cores();
+    let core1 = &mut cores[1];
+    core1
+        .spawn(unsafe { &mut CORE1_STACK.mem }, move || loop {
+            led_pin.toggle().unwrap();
+            // Half a second, counted in clock cycles as the SysTick belongs to core 0
+            cortex_m::asm::delay(sys_freq / 2);
+        })
+        .unwrap();
+
+    uart.write_full_blocking(b"Multicore closure example\r\n");
+
+    let mut value = 0u32;
+    loop {
+        writeln!(uart, "core 0 says: {:02}\r", value).unwrap();
+        delay.delay_ms(1000);
+        value += 1
+    }
+}
+
+// End of file
@@ -0,0 +1,153 @@
+//! # Multicore Critical Section Example
+//!
+//! This application checks that critical sections exclude each other across both
+//! CPU cores. Both cores increment a shared counter in critical sections many
+//! times; the LED lights up if no increment was lost.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+use core::cell::RefCell;
+use cortex_m::interrupt::{CriticalSection, Mutex};
+use hal::multicore::{Multicore, Stack};
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of increments done by each core
+const INCREMENTS: u32 = 1_000_000;
+
+/// Value sent by core 1 once it is done
+const CORE1_TASK_COMPLETE: u32 = 0xEE;
+
+/// Stack for core 1
+static mut CORE1_STACK: Stack<4096> = Stack::new();
+
+/// The counter shared by both cores
+static COUNTER: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+
+/// Run `f` in a critical section across both cores, with the token of
+/// `cortex_m` needed to borrow from its `Mutex`
+fn with<R>(f: impl FnOnce(&CriticalSection) -> R) -> R {
+    critical_section::with(|_| {
+        // Safety: `critical_section::with` disables interrupts and holds the
+        // spinlock, which excludes the other core as well
+        let cs = unsafe { CriticalSection::new() };
+        f(&cs)
+    })
+}
+
+/// Increment the shared counter. The read and the write are separate, so an
+/// increment would be lost if the other core ran in between.
+fn hammer() {
+    for _ in 0..INCREMENTS {
+        with(|cs| {
+            let mut counter = COUNTER.borrow(cs).borrow_mut();
+            let value = unsafe { core::ptr::read_volatile(&*counter) };
+            *counter = value + 1;
+        });
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, runs the increments on both
+/// cores, then turns the LED on if the count is right.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::watchdog::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let mut sio = hal::sio::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // A watchdog reset may have left the critical section spinlock locked
+    unsafe { hal::sio::spinlock_reset() };
+
+    let sio_core1 = sio.core1;
+    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = mc.cores();
+    let core1 = &mut cores[1];
+    core1
+        .spawn(unsafe { &mut CORE1_STACK.mem }, move || {
+            // Core 1 has its own end of the FIFO
+            let mut fifo = sio_core1.split().ok().unwrap().fifo;
+            hammer();
+            fifo.write_blocking(CORE1_TASK_COMPLETE);
+            loop {
+                cortex_m::asm::wfe();
+            }
+        })
+        .unwrap();
+
+    hammer();
+
+    // Wait for core 1 to be done too
+    while sio.fifo.read_blocking() != CORE1_TASK_COMPLETE {}
+
+    let total = with(|cs| *COUNTER.borrow(cs).borrow());
+    if total == 2 * INCREMENTS {
+        led_pin.set_high().unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
@@ -117,10 +117,10 @@ fn main() -> ! {
     // The single-cycle I/O block controls our GPIO pins
     let mut sio = hal::sio::Sio::new(pac.SIO);
 
-    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio);
+    let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
     let cores = mc.cores();
     let core1 = &mut cores[1];
-    let _test = core1.spawn(core1_task, unsafe { &mut CORE1_STACK.mem });
+    let _test = core1.spawn(unsafe { &mut CORE1_STACK.mem }, core1_task);
 
     // Let core1 know how fast the system clock is running
     let sys_freq = clocks.system_clock.freq().integer();
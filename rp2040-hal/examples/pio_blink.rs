@@ -60,7 +60,8 @@ fn main() -> ! {
     let (sm, _, _) = rp2040_hal::pio::PIOBuilder::from_program(installed)
         .set_pins(led_pin_id, 1)
         .clock_divisor(div)
-        .build(sm0);
+        .build(sm0)
+        .unwrap();
     sm.start();
 
     // PIO runs in background, independently from CPU
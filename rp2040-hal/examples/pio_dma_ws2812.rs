@@ -0,0 +1,175 @@
+//! # PIO DMA WS2812 Example
+//!
+//! This application demonstrates how to drive a chain of WS2812 LEDs with a
+//! PIO state machine, fed by double-buffered DMA. While the DMA sends one frame
+//! out of GPIO16, the CPU renders the next frame into the other buffer.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use embedded_time::fixed_point::FixedPoint;
+use hal::clocks::Clock;
+use hal::dma::{double_buffer, DMAExt};
+use hal::gpio::{FunctionPio0, Pin};
+use hal::pio::PIOExt;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of LEDs in the chain
+const LED_COUNT: usize = 16;
+
+/// Render a rainbow, rotated by `offset`, as GRB words in the upper 24 bits
+fn fill_rainbow(buf: &mut [u32], offset: u8) {
+    for (i, led) in buf.iter_mut().enumerate() {
+        let pos = (i * 256 / LED_COUNT) as u8;
+        let pos = pos.wrapping_add(offset);
+        let (r, g, b) = match pos {
+            0..=84 => (255 - pos * 3, pos * 3, 0),
+            85..=169 => (0, 255 - (pos - 85) * 3, (pos - 85) * 3),
+            _ => ((pos - 170) * 3, 0, 255 - (pos - 170) * 3),
+        };
+        // Keep the brightness down
+        let (r, g, b) = (r / 8, g / 8, b / 8);
+        *led = (u32::from(g) << 24) | (u32::from(r) << 16) | (u32::from(b) << 8);
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then keeps sending frames
+/// to the LEDs forever.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().integer());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure the data pin of the LEDs for PIO0
+    let _data: Pin<_, FunctionPio0> = pins.gpio16.into_mode();
+    let data_pin_id = 16;
+
+    // Each bit takes 10 cycles: high for 3 (a 0) or 8 (a 1) cycles, then low
+    let program = pio_proc::pio!(
+        32,
+        "
+.side_set 1
+.wrap_target
+bitloop:
+    out x, 1        side 0 [2]
+    jmp !x do_zero  side 1 [1]
+do_one:
+    jmp bitloop     side 1 [4]
+do_zero:
+    nop             side 0 [4]
+.wrap
+        "
+    );
+
+    // Run the state machine at 10 cycles per bit at 800 kbit/s. Each FIFO
+    // entry holds the 24 color bits of one LED, most significant bit first.
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let installed = pio.install(&program.program).unwrap();
+    let div = clocks.system_clock.freq().integer() as f32 / 8_000_000.0;
+    let (mut sm, _, tx) = hal::pio::PIOBuilder::from_program(installed)
+        .side_set_pin_base(data_pin_id)
+        .out_shift_direction(hal::pio::ShiftDirection::Left)
+        .autopull(true)
+        .pull_threshold(24)
+        .buffers(hal::pio::Buffers::OnlyTx)
+        .clock_divisor(div)
+        .build(sm0)
+        .unwrap();
+    sm.set_pindirs([(data_pin_id, hal::pio::PinDir::Output)]);
+    sm.start();
+
+    // Split the DMA block into its channels
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // Send the first frame. Each transfer is paced by the DREQ of the TX FIFO
+    let mut offset = 0u8;
+    let frame1 = cortex_m::singleton!(: [u32; LED_COUNT] = [0; LED_COUNT]).unwrap();
+    let mut frame2 = cortex_m::singleton!(: [u32; LED_COUNT] = [0; LED_COUNT]).unwrap();
+    fill_rainbow(frame1, offset);
+    let mut transfer = double_buffer::Config::new((dma.ch0, dma.ch1), frame1, tx).start();
+
+    loop {
+        // Render the next frame while the current one is sent
+        offset = offset.wrapping_add(1);
+        fill_rainbow(frame2, offset);
+
+        // The LEDs latch their colors once the data line is low for a while,
+        // so the next frame is only queued after a pause. The DMA is done
+        // before the FIFO is empty, which takes up to another 270 us
+        while !transfer.is_done() {}
+        delay.delay_us(600);
+
+        // The current frame is done, so the next one starts right away and
+        // the sent buffer is returned immediately
+        let (sent_frame, next_transfer) = transfer.read_next(frame2).wait().unwrap();
+        frame2 = sent_frame;
+        transfer = next_transfer;
+    }
+}
+
+// End of file
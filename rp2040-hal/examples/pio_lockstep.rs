@@ -0,0 +1,101 @@
+//! This example checks that the state machines of a `StateMachineGroup` run in
+//! lock-step.
+//!
+//! Two state machines run the same program of four `nop`s with a slow clock
+//! divisor, and are started together. The instruction address of the second
+//! state machine is read between two equal reads of the address of the first
+//! one, so it must be the same. The LED on GPIO25 is turned on if it always is,
+//! and if the addresses advanced.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use embedded_hal::digital::v2::OutputPin;
+use hal::pac;
+use hal::pio::{PIOBuilder, PIOExt};
+use hal::Sio;
+use panic_halt as _;
+use rp2040_hal as hal;
+
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// Number of times the addresses are compared
+const SAMPLES: u32 = 1000;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Each state machine advances by one instruction per cycle
+    let program = pio_proc::pio!(
+        32,
+        "
+.wrap_target
+    nop
+    nop
+    nop
+    nop
+.wrap
+        "
+    );
+
+    let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let installed = pio.install(&program.program).unwrap();
+    // A slow clock, so the state machines execute one instruction every 50000
+    // cycles of the system clock
+    let div = 50_000f32;
+    // Safety: Both state machines are uninitialized before the program is
+    // uninstalled
+    let (sm0, _, _) = PIOBuilder::from_program(unsafe { installed.share() })
+        .clock_divisor(div)
+        .build(sm0)
+        .unwrap();
+    let (sm1, _, _) = PIOBuilder::from_program(installed)
+        .clock_divisor(div)
+        .build(sm1)
+        .unwrap();
+    let group = sm0.with(sm1).start();
+    let (sm0, sm1) = group.members();
+
+    let mut checked = 0;
+    let mut mismatches = 0;
+    let mut advanced = false;
+    let mut last = None;
+    while checked < SAMPLES {
+        let before = sm0.current_instruction_address();
+        let other = sm1.current_instruction_address();
+        let after = sm0.current_instruction_address();
+        if before != after {
+            // The first state machine advanced between the reads
+            continue;
+        }
+        if other != before {
+            mismatches += 1;
+        }
+        if last.is_some() && last != Some(before) {
+            advanced = true;
+        }
+        last = Some(before);
+        checked += 1;
+        // Sample at different points of the program
+        cortex_m::asm::delay(12_345);
+    }
+
+    if mismatches == 0 && advanced {
+        led_pin.set_high().unwrap();
+    }
+
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
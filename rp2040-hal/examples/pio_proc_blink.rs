@@ -51,7 +51,8 @@ fn main() -> ! {
     let (mut sm, _, _) = rp2040_hal::pio::PIOBuilder::from_program(installed)
         .set_pins(led_pin_id, 1)
         .clock_divisor(div)
-        .build(sm0);
+        .build(sm0)
+        .unwrap();
     // The GPIO pin needs to be configured as an output.
     sm.set_pindirs([(led_pin_id, hal::pio::PinDir::Output)]);
     sm.start();
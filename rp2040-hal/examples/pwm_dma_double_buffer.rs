@@ -0,0 +1,149 @@
+//! # PWM DMA Double Buffer Example
+//!
+//! This application demonstrates how to stream a continuously computed
+//! waveform to a PWM channel using double-buffered DMA. While the DMA plays one
+//! buffer out of GPIO0, the CPU fills the other one with the next part of a
+//! sawtooth wave, so the output has no gaps. Only the compare value of channel
+//! A is streamed, so channel B keeps its own duty cycle.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use hal::dma::{double_buffer, DMAExt};
+use hal::pwm::{ChannelDmaWriteTarget, A};
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of PWM periods per period of the sawtooth
+const SAWTOOTH_LENGTH: u16 = 100;
+
+/// Number of samples in each buffer
+const BUFFER_LENGTH: usize = 256;
+
+/// Fill `buf` with the next samples of the sawtooth, starting at `*phase`,
+/// encoded for `target`. `*previous` is the last sample played before them
+fn fill_sawtooth<S: hal::pwm::SliceId>(
+    target: &ChannelDmaWriteTarget<S, A>,
+    buf: &mut [u32],
+    phase: &mut u16,
+    previous: &mut u16,
+) {
+    let mut samples = [0; BUFFER_LENGTH];
+    for sample in samples.iter_mut() {
+        // Scale to the PWM TOP of 255
+        *sample = *phase * 255 / (SAWTOOTH_LENGTH - 1);
+        *phase = (*phase + 1) % SAWTOOTH_LENGTH;
+    }
+    *previous = target.encode(*previous, &samples, buf);
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then keeps feeding the
+/// sawtooth to the DMA forever.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Init PWMs
+    let mut pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+
+    // Configure PWM0 to wrap at 125 MHz / 8 / 256 = ~61 kHz
+    let pwm = &mut pwm_slices.pwm0;
+    pwm.default_config();
+    pwm.set_top(255);
+    pwm.set_div_int(8);
+
+    // Output channel A on PWM0 to GPIO 0
+    pwm.channel_a.output_to(pins.gpio0);
+
+    // Split the DMA block into its channels
+    let dma = pac.DMA.split(&mut pac.RESETS);
+
+    // Channel B runs at a fixed duty cycle of 50%
+    pwm.channel_b.set_duty_fraction(1, 2);
+
+    // Fill the first buffer, and start playing it. Each transfer is paced by
+    // the wrap of the PWM slice. The duty cycle of channel A starts at 0
+    pwm.channel_a.set_duty_fraction(0, 1);
+    let target = pwm.channel_a.dma_write_target();
+    let encoder = pwm.channel_a.dma_write_target();
+    let mut phase = 0;
+    let mut previous = 0;
+    let tx_buf1 = cortex_m::singleton!(: [u32; BUFFER_LENGTH] = [0; BUFFER_LENGTH]).unwrap();
+    let mut tx_buf2 = cortex_m::singleton!(: [u32; BUFFER_LENGTH] = [0; BUFFER_LENGTH]).unwrap();
+    fill_sawtooth(&encoder, tx_buf1, &mut phase, &mut previous);
+    let mut transfer = double_buffer::Config::new((dma.ch0, dma.ch1), tx_buf1, target).start();
+    pwm.enable();
+
+    loop {
+        // Fill the free buffer and queue it, then wait for the buffer which is
+        // currently playing to be done, so it can be refilled
+        fill_sawtooth(&encoder, tx_buf2, &mut phase, &mut previous);
+        let (played_buf, next_transfer) = transfer.read_next(tx_buf2).wait().unwrap();
+        tx_buf2 = played_buf;
+        transfer = next_transfer;
+    }
+}
+
+// End of file
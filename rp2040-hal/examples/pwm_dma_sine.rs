@@ -0,0 +1,143 @@
+//! # PWM DMA Sine Example
+//!
+//! This application demonstrates how to stream duty cycle values into a PWM
+//! channel using DMA. A sine table is played out of GPIO0 once per PWM period,
+//! producing a tone of roughly 950 Hz after low-pass filtering (e.g. an RC
+//! filter or a small speaker connected to GPIO0).
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use hal::dma::{Word, WriteTarget};
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Wrapper forcing the alignment required by the DMA ring buffer mode
+#[repr(C, align(128))]
+struct Aligned([u16; 64]);
+
+/// One period of a sine wave, scaled to a PWM TOP of 255
+///
+/// The table is 128 bytes long, so the DMA can wrap around it using an address
+/// ring of 2^7 bytes.
+static SINE: Aligned = Aligned([
+    128, 140, 152, 165, 176, 188, 198, 208, 218, 226, 234, 240, 245, 250, 253, 254, 255, 254, 253,
+    250, 245, 240, 234, 226, 218, 208, 198, 188, 176, 165, 152, 140, 128, 115, 103, 90, 79, 67, 57,
+    47, 37, 29, 21, 15, 10, 5, 2, 1, 0, 1, 2, 5, 10, 15, 21, 29, 37, 47, 57, 67, 79, 90, 103, 115,
+]);
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then lets the DMA feed the
+/// sine table to the PWM forever.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Init PWMs
+    let mut pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+
+    // Configure PWM0 to wrap at 125 MHz / 8 / 256 = ~61 kHz, so the 64 entry
+    // table repeats at ~954 Hz
+    let pwm = &mut pwm_slices.pwm0;
+    pwm.default_config();
+    pwm.set_top(255);
+    pwm.set_div_int(8);
+
+    // Output channel A on PWM0 to GPIO 0
+    pwm.channel_a.output_to(pins.gpio0);
+
+    // Bring the DMA block out of reset
+    pac.RESETS.reset.modify(|_, w| w.dma().clear_bit());
+    while pac.RESETS.reset_done.read().dma().bit_is_clear() {}
+
+    // Program DMA channel 0 to copy the table into the compare register, one
+    // entry per PWM wrap, wrapping around the table forever
+    let mut target = pwm.dma_write_target_mirrored();
+    let (cc_address, _) = target.tx_address_count();
+    let ch = &pac.DMA.ch[0];
+    ch.ch_read_addr
+        .write(|w| unsafe { w.bits(SINE.0.as_ptr() as u32) });
+    ch.ch_write_addr.write(|w| unsafe { w.bits(cc_address) });
+    ch.ch_trans_count.write(|w| unsafe { w.bits(u32::MAX) });
+    ch.ch_ctrl_trig.write(|w| unsafe {
+        w.treq_sel().bits(hal::dma::DREQ_PWM_WRAP0);
+        w.chain_to().bits(0);
+        w.ring_sel().clear_bit();
+        w.ring_size().bits(7);
+        w.data_size().bits(<u16 as Word>::DATA_SIZE);
+        w.incr_read().set_bit();
+        w.incr_write().bit(target.tx_increment());
+        w.en().set_bit();
+        w
+    });
+
+    // Start the PWM. From now on the DMA keeps the output going on its own
+    pwm.enable();
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+// End of file
@@ -0,0 +1,146 @@
+//! # PWM Simultaneous Start Example
+//!
+//! This application checks that PWM slices started with
+//! `Slices::enable_simultaneous` count in lock-step.
+//!
+//! Slices 0, 1 and 2 are configured with the same divider and TOP, and started
+//! together. While they run, their counters are read one after the other, which
+//! takes less than one count, so they must stay within one count of each other.
+//! Once they are stopped together with `Slices::disable_simultaneous`, their
+//! counters must be equal. The LED on GPIO25 is turned on if both checks pass.
+//!
+//! It may need to be adapted to your particular board layout and/or pin assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The counters wrap after TOP, so they count TOP + 1 values
+const TOP: u16 = 999;
+
+/// Number of times the running counters are compared
+const SAMPLES: u32 = 1000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, compares the counters of
+/// the slices, then turns the LED on if they count in lock-step.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Init PWMs
+    let mut pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+
+    // Count once every 100 cycles of the system clock, from 0
+    pwm_slices.pwm0.default_config();
+    pwm_slices.pwm1.default_config();
+    pwm_slices.pwm2.default_config();
+    pwm_slices.pwm0.set_div_int(100);
+    pwm_slices.pwm1.set_div_int(100);
+    pwm_slices.pwm2.set_div_int(100);
+    pwm_slices.pwm0.set_top(TOP);
+    pwm_slices.pwm1.set_top(TOP);
+    pwm_slices.pwm2.set_top(TOP);
+    pwm_slices.pwm0.set_counter(0);
+    pwm_slices.pwm1.set_counter(0);
+    pwm_slices.pwm2.set_counter(0);
+
+    pwm_slices.enable_simultaneous(0b0000_0111);
+
+    let mut in_step = true;
+    for _ in 0..SAMPLES {
+        let counter0 = pwm_slices.pwm0.get_counter();
+        let counter1 = pwm_slices.pwm1.get_counter();
+        let counter2 = pwm_slices.pwm2.get_counter();
+        in_step &= within_one_count(counter0, counter1) && within_one_count(counter1, counter2);
+        // Sample at different points of the period
+        cortex_m::asm::delay(12_345);
+    }
+
+    pwm_slices.disable_simultaneous(0b0000_0111);
+
+    let counter0 = pwm_slices.pwm0.get_counter();
+    let stopped_together =
+        counter0 == pwm_slices.pwm1.get_counter() && counter0 == pwm_slices.pwm2.get_counter();
+
+    if in_step && stopped_together {
+        led_pin.set_high().unwrap();
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Whether `later` was read at most one count after `earlier`, taking the
+/// wrap after TOP into account
+fn within_one_count(earlier: u16, later: u16) -> bool {
+    let period = u32::from(TOP) + 1;
+    let difference = (u32::from(later) + period - u32::from(earlier)) % period;
+    difference <= 1
+}
+
+// End of file
@@ -100,6 +100,7 @@ fn main() -> ! {
         hal::rom_data::git_revision()
     )
     .unwrap();
+    writeln!(uart, "ROM Version: {}", hal::rom_data::rom_version_number()).unwrap();
 
     // Some ROM functions are exported directly, so we can just call them
     writeln!(
@@ -161,12 +162,7 @@ fn main() -> ! {
     }
 
     // Reboot back into USB mode (no activity, both interfaces enabled)
-    rp2040_hal::rom_data::reset_to_usb_boot(0, 0);
-
-    // In case the reboot fails
-    loop {
-        cortex_m::asm::nop();
-    }
+    rp2040_hal::rom_data::reset_to_usb_boot(0, 0)
 }
 
 /// Calculate the number of systicks elapsed between two counter readings.
@@ -0,0 +1,140 @@
+//! # RTC Alarm Example
+//!
+//! This application demonstrates a recurring alarm of the real time clock. The
+//! alarm fires at second 0 of every minute, and the interrupt handler toggles
+//! the LED on GPIO25 and keeps the alarm armed for the next minute.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use hal::rtc::{DateTime, DateTimeFilter, DayOfWeek, RealTimeClock};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The LED pin, toggled by the interrupt handler
+type LedPin = hal::gpio::Pin<hal::gpio::bank0::Gpio25, hal::gpio::PushPullOutput>;
+
+/// The real time clock and the LED, shared with the interrupt handler
+static SHARED: Mutex<RefCell<Option<(RealTimeClock, LedPin)>>> = Mutex::new(RefCell::new(None));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, starts the real time clock
+/// and schedules the alarm, then sleeps until an interrupt occurs.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure GPIO25 as an output
+    let led_pin = pins.gpio25.into_push_pull_output();
+
+    // Start the real time clock at an arbitrary date
+    let initial_date = DateTime {
+        year: 2022,
+        month: 1,
+        day: 1,
+        day_of_week: DayOfWeek::Saturday,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+    let mut rtc =
+        RealTimeClock::new(pac.RTC, &clocks.rtc_clock, &mut pac.RESETS, initial_date).unwrap();
+
+    // Fire at second 0 of every minute. A filter on the hour and minute, e.g.
+    // `.hour(3).minute(0).second(0)`, fires once a day instead
+    rtc.schedule_alarm(DateTimeFilter::default().second(0));
+    rtc.enable_interrupt();
+
+    cortex_m::interrupt::free(|cs| {
+        SHARED.borrow(cs).replace(Some((rtc, led_pin)));
+    });
+
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::RTC_IRQ);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn RTC_IRQ() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some((rtc, led_pin)) = SHARED.borrow(cs).borrow_mut().as_mut() {
+            led_pin.toggle().unwrap();
+
+            // The match lasts for the whole second, so the alarm has to be
+            // disabled and enabled again, or this interrupt fires continuously
+            rtc.clear_interrupt();
+        }
+    });
+}
+
+// End of file
@@ -0,0 +1,135 @@
+//! # RTC Sleep Example
+//!
+//! This application demonstrates how to sleep between RTC alarms. The real time
+//! clock runs from the crystal oscillator and keeps counting while all other
+//! clocks are gated. Its alarm wakes the core at second 0 of every minute, when
+//! the LED on GPIO25 is toggled.
+//!
+//! While the core sleeps, the current drawn by the board drops, which can be
+//! checked with a current probe.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Some traits we need
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use hal::clocks::SleepConfig;
+use hal::rtc::{DateTime, DateTimeFilter, DayOfWeek, RealTimeClock};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, starts the real time clock
+/// and schedules the alarm, then sleeps and toggles the LED in an infinite
+/// loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let mut clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // Run clk_rtc from the crystal at 46875Hz instead of PLL_USB, then start
+    // the real time clock, whose divider is set from that frequency
+    clocks.rtc_clock_from_xosc().ok().unwrap();
+    let initial_date = DateTime {
+        year: 2022,
+        month: 1,
+        day: 1,
+        day_of_week: DayOfWeek::Saturday,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+    let mut rtc =
+        RealTimeClock::new(pac.RTC, &clocks.rtc_clock, &mut pac.RESETS, initial_date).unwrap();
+
+    // Fire at second 0 of every minute
+    rtc.schedule_alarm(DateTimeFilter::default().second(0));
+    rtc.enable_interrupt();
+
+    // Only the RTC keeps its clocks while the core sleeps
+    clocks.configure_sleep_enable(SleepConfig::none().with_rtc());
+
+    // The pending interrupt wakes the core even though interrupts are disabled
+    // below, so no interrupt handler is needed
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::RTC_IRQ);
+    }
+
+    loop {
+        cortex_m::interrupt::free(|_| {
+            clocks.sleep(&mut core.SCB);
+
+            // The clocks are running again. Clear the alarm, which stays
+            // enabled as it recurs, and the pending interrupt, or the next
+            // sleep would end right away
+            rtc.clear_interrupt();
+            pac::NVIC::unpend(pac::Interrupt::RTC_IRQ);
+        });
+
+        led_pin.toggle().unwrap();
+    }
+}
+
+// End of file
@@ -0,0 +1,220 @@
+//! # Sleep Race Example
+//!
+//! This application checks that an interrupt arriving right before the core
+//! executes `WFI` still wakes it, for both `light_sleep_until` and
+//! `deep_sleep_until`.
+//!
+//! Each round pends an interrupt from the condition, i.e. inside the critical
+//! section in which the sleep functions check it and execute `WFI`. If that
+//! interrupt was lost, the core would only be woken by a backstop alarm 10 ms
+//! later. The number of lost wake-ups and the slowest wake-up are printed over
+//! the UART, and the LED on GPIO25 is turned on if no wake-up was lost.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial::Write as _;
+use embedded_time::duration::Microseconds;
+use hal::clocks::{Clock, SleepConfig};
+use hal::sleep::{deep_sleep_until, light_sleep_until};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Number of wake-ups checked for each sleep function
+const ROUNDS: u32 = 1000;
+
+/// Delay of the backstop alarm, which only wakes the core if the pended
+/// interrupt was lost
+const BACKSTOP_US: u32 = 10_000;
+
+/// Set by the handler of the pended interrupt
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+/// Set by the handler of the backstop alarm
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, checks the wake-ups with
+/// both sleep functions and prints the results.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let mut clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+    let mut alarm = timer.alarm_0().unwrap();
+    alarm.enable_interrupt(&mut timer);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            hal::uart::common_configs::_9600_8_N_1,
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Configure GPIO25 as an output
+    let mut led_pin = pins.gpio25.into_push_pull_output();
+
+    // No GPIO interrupt is enabled, so IO_IRQ_BANK0 only fires when pended
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+        pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+    }
+
+    // Only the timer, which runs the backstop alarm, keeps its clocks while
+    // the core sleeps
+    let config = SleepConfig::none().with_timer();
+
+    let mut all_woken = true;
+    for deep in [false, true].iter().copied() {
+        let mut lost = 0;
+        let mut slowest = 0;
+        for _ in 0..ROUNDS {
+            alarm.schedule(Microseconds(BACKSTOP_US)).unwrap();
+            let start = timer.get_counter();
+
+            // The condition runs with interrupts disabled, right before the
+            // WFI: the pended interrupt can only wake the core, its handler
+            // runs after the WFI
+            let mut pended = false;
+            let condition = || {
+                if !pended {
+                    pac::NVIC::pend(pac::Interrupt::IO_IRQ_BANK0);
+                    pended = true;
+                }
+                take(&WOKEN) || TIMED_OUT.load(Ordering::Relaxed)
+            };
+            if deep {
+                deep_sleep_until(&mut clocks, &mut core.SCB, config, condition);
+            } else {
+                light_sleep_until(&mut core.SCB, condition);
+            }
+
+            let elapsed = timer.get_counter() - start;
+            alarm.cancel();
+            if take(&TIMED_OUT) {
+                lost += 1;
+            }
+            slowest = slowest.max(elapsed);
+        }
+
+        writeln!(
+            uart,
+            "{} sleep: {} of {} wake-ups lost, slowest after {} us\r",
+            if deep { "deep" } else { "light" },
+            lost,
+            ROUNDS,
+            slowest
+        )
+        .unwrap();
+        // The UART's clocks are gated while deep sleeping, so let it finish
+        nb::block!(uart.flush()).unwrap();
+        all_woken &= lost == 0;
+    }
+
+    if all_woken {
+        led_pin.set_high().unwrap();
+    }
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Clear `flag`, returning whether it was set. The Cortex-M0+ has no atomic
+/// swap, so the flag is read and cleared in a critical section
+fn take(flag: &AtomicBool) -> bool {
+    cortex_m::interrupt::free(|_| {
+        let set = flag.load(Ordering::Relaxed);
+        flag.store(false, Ordering::Relaxed);
+        set
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    WOKEN.store(true, Ordering::Relaxed);
+}
+
+#[interrupt]
+fn TIMER_IRQ_0() {
+    // The alarm belongs to `main`, so clear its interrupt directly. INTR is
+    // write-1-to-clear, so this only clears the interrupt of alarm 0
+    unsafe { &*pac::TIMER::ptr() }
+        .intr
+        .write(|w| w.alarm_0().set_bit());
+    TIMED_OUT.store(true, Ordering::Relaxed);
+}
+
+// End of file
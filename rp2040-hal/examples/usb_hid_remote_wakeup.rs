@@ -0,0 +1,188 @@
+//! # USB HID Remote Wakeup Example
+//!
+//! This application demonstrates how a USB device survives a suspended bus and
+//! wakes up the host. It creates a USB HID mouse which clicks its left button
+//! whenever GPIO14 is pulled low, e.g. by a button connected to ground.
+//!
+//! When the host is suspended, e.g. asleep, the button press signals a remote
+//! wakeup instead, if the host enabled it. The button is serviced by the GPIO
+//! interrupt.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::interrupt::Mutex;
+use hal::gpio::Interrupt;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::descriptor::generator_prelude::*;
+use usbd_hid::descriptor::MouseReport;
+use usbd_hid::hid_class::HIDClass;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// The button pin, shared with the interrupt handler
+type ButtonPin = hal::gpio::Pin<hal::gpio::bank0::Gpio14, hal::gpio::PullUpInput>;
+
+/// The button, so the interrupt handler can clear its interrupt
+static BUTTON: Mutex<RefCell<Option<ButtonPin>>> = Mutex::new(RefCell::new(None));
+
+/// Set by the interrupt handler when the button was pressed
+static PRESSED: AtomicBool = AtomicBool::new(false);
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then polls the USB device
+/// and reports button presses in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Raise an interrupt when GPIO14 is pulled low
+    let button = pins.gpio14.into_pull_up_input();
+    button.set_interrupt_enabled(Interrupt::EdgeLow, true);
+    cortex_m::interrupt::free(|cs| {
+        BUTTON.borrow(cs).replace(Some(button));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB HID Class Device driver, providing Mouse Reports
+    let mut usb_hid = HIDClass::new(&usb_bus, MouseReport::desc(), 60);
+
+    // Create a USB device with a fake VID and PID, which may wake up the host
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27da))
+        .manufacturer("Fake company")
+        .product("Wakeup Mouse")
+        .serial_number("TEST")
+        .supports_remote_wakeup(true)
+        .build();
+
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        if !take_pressed() {
+            continue;
+        }
+
+        if usb_dev.state() == UsbDeviceState::Suspend {
+            // The device state is restored once the host resumed the bus
+            if usb_dev.remote_wakeup_enabled() {
+                usb_dev.bus().remote_wakeup();
+            }
+        } else {
+            // Press and release the left button
+            for buttons in [1, 0] {
+                let report = MouseReport {
+                    x: 0,
+                    y: 0,
+                    buttons,
+                    wheel: 0,
+                    pan: 0,
+                };
+                while let Err(UsbError::WouldBlock) = usb_hid.push_input(&report) {
+                    usb_dev.poll(&mut [&mut usb_hid]);
+                }
+            }
+        }
+    }
+}
+
+/// Clear the flag, returning whether the button was pressed. The Cortex-M0+
+/// has no atomic swap, so the flag is read and cleared in a critical section
+fn take_pressed() -> bool {
+    cortex_m::interrupt::free(|_| {
+        let pressed = PRESSED.load(Ordering::Relaxed);
+        PRESSED.store(false, Ordering::Relaxed);
+        pressed
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(button) = BUTTON.borrow(cs).borrow_mut().as_mut() {
+            button.clear_interrupt(Interrupt::EdgeLow);
+        }
+    });
+    PRESSED.store(true, Ordering::Relaxed);
+}
+
+// End of file
@@ -0,0 +1,129 @@
+//! # USB Serial Echo Example
+//!
+//! This application demonstrates the USB device driver. It creates a USB
+//! Serial (CDC-ACM) device which echoes anything it receives, with the USB
+//! driver polled from the main loop. Its serial number is the unique ID of the
+//! flash chip.
+//!
+//! It may need to be adapted to your particular board layout and/or pin
+//! assignment.
+//!
+//! See the `Cargo.toml` file for Copyright and licence details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Digits of the serial number
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then echoes any characters
+/// received over USB Serial.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks, which runs clk_usb at 48 MHz from PLL_USB
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Use the unique ID of the flash chip as the serial number, in hex. Only
+    // core 0 runs, so nothing else reads the flash meanwhile
+    let unique_id = unsafe { hal::flash::unique_id() };
+    let serial_number = cortex_m::singleton!(: [u8; 16] = [0; 16]).unwrap();
+    for (i, byte) in unique_id.iter().enumerate() {
+        serial_number[i * 2] = HEX_DIGITS[(byte >> 4) as usize];
+        serial_number[i * 2 + 1] = HEX_DIGITS[(byte & 0xf) as usize];
+    }
+    let serial_number = core::str::from_utf8(serial_number).unwrap();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number(serial_number)
+        .device_class(usbd_serial::USB_CLASS_CDC)
+        .build();
+
+    loop {
+        // Check for new data
+        if !usb_dev.poll(&mut [&mut serial]) {
+            continue;
+        }
+
+        let mut buf = [0u8; 64];
+        if let Ok(count) = serial.read(&mut buf) {
+            // Send back to the host
+            let mut wr_ptr = &buf[..count];
+            while !wr_ptr.is_empty() {
+                match serial.write(wr_ptr) {
+                    Ok(len) => wr_ptr = &wr_ptr[len..],
+                    // On error, just drop unwritten data. One possible error
+                    // is Err(WouldBlock), meaning the USB write buffer is full.
+                    Err(_) => break,
+                };
+            }
+        }
+    }
+}
+
+// End of file
@@ -14,12 +14,13 @@
 //! // Enable adc
 //! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
 //! // Configure one of the pins as an ADC input
-//! let mut adc_pin_0 = pins.gpio26.into_floating_input();
+//! let mut adc_pin_0 = pins.gpio26.into_analog_input();
 //! // Read the ADC counts from the ADC channel
 //! let pin_adc_counts: u16 = adc.read(&mut adc_pin_0).unwrap();
 //! ```
 //!
-//! Capture ADC reading from temperature sensor. Note that this needs conversion to be a real-world temperature.
+//! Capture ADC reading from temperature sensor. Note that this needs conversion to be a real-world temperature,
+//! see [`Adc::read_temperature_celsius`].
 //! ```no_run
 //! use embedded_hal::adc::OneShot;
 //! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
@@ -34,26 +35,84 @@
 //! let temperature_adc_counts: u16 = adc.read(&mut temperature_sensor).unwrap();
 //! ```
 //!
+//! Capture ADC readings continuously using the FIFO (free-running mode)
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! // Enable adc
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! // Configure one of the pins as an ADC input
+//! let mut adc_pin_0 = pins.gpio26.into_analog_input();
+//! // Start free-running conversions of the ADC channel
+//! let mut fifo = adc.free_running(&mut adc_pin_0);
+//! // Wait for the next sample and read it
+//! let sample: u16 = fifo.read_blocking();
+//! // Stop conversions. The FIFO is drained so the ADC can be used for one-shot reads again
+//! fifo.stop();
+//! ```
+//!
+//! Sample several channels in turn using round-robin mode
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let adc_pin_0 = pins.gpio26.into_analog_input();
+//! let adc_pin_2 = pins.gpio28.into_analog_input();
+//! let temperature_sensor = adc.enable_temp_sensor();
+//! // Samples are pushed to the FIFO in channel order: gpio26, gpio28, temperature sensor, gpio26, ...
+//! let mut fifo = adc
+//!     .build_fifo()
+//!     .round_robin((&adc_pin_0, &adc_pin_2, &temperature_sensor))
+//!     .start();
+//! let pin_0_counts = fifo.read_blocking();
+//! let pin_2_counts = fifo.read_blocking();
+//! let temperature_counts = fifo.read_blocking();
+//! ```
+//!
 //! See [examples/adc.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/adc.rs) and
 //! [pimoroni_pico_explorer_showcase.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pimoroni_pico_explorer/examples/pimoroni_pico_explorer_showcase.rs) for more complete examples
 
+use core::marker::PhantomData;
+
+use embedded_time::{fixed_point::FixedPoint, rate::Hertz};
 use hal::adc::{Channel, OneShot};
 use pac::{ADC, RESETS};
 
 use crate::{
+    clocks::{ClockGate, ClockGated},
+    dma,
     gpio::Pin,
     gpio::{
         bank0::{Gpio26, Gpio27, Gpio28, Gpio29},
-        FloatingInput,
+        FloatingDisabled,
     },
     resets::SubsystemReset,
 };
 
 const TEMPERATURE_SENSOR_CHANNEL: u8 = 4;
 
+/// Number of ADC clock cycles taken by a single conversion
+const CONVERSION_CYCLES: u64 = 96;
+
+/// Nominal ADC reference voltage (ADC_AVDD) in millivolts
+const DEFAULT_REFERENCE_MV: u32 = 3300;
+
+/// Error returned by the checked read functions, e.g. [`Adc::read_checked`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdcError {
+    /// The conversion failed, e.g. because the comparator did not settle. The sample value
+    /// must not be trusted.
+    ConversionFailed,
+}
+
 /// Adc
 pub struct Adc {
     device: ADC,
+    reference_mv: u32,
 }
 
 impl Adc {
@@ -68,7 +127,10 @@ impl Adc {
         // Wait for adc ready
         while !device.cs.read().ready().bit_is_set() {}
 
-        Self { device }
+        Self {
+            device,
+            reference_mv: DEFAULT_REFERENCE_MV,
+        }
     }
 
     /// Free underlying register block
@@ -76,11 +138,46 @@ impl Adc {
         self.device
     }
 
+    /// The clock gates of the ADC, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate {
+        ADC::CLOCK_GATE
+    }
+
     /// Read single
     pub fn read_single(&self) -> u16 {
         self.device.result.read().result().bits()
     }
 
+    /// Perform a one-shot conversion of `pin`, checking the conversion error flag.
+    ///
+    /// Unlike the `OneShot` implementation, this reports failed conversions instead of returning
+    /// a wrong sample.
+    pub fn read_checked<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+    ) -> Result<u16, AdcError> {
+        let value = self.convert_one_shot(PIN::channel());
+        if self.device.cs.read().err().bit_is_set() {
+            Err(AdcError::ConversionFailed)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Check if a conversion failed since the sticky error flag was last cleared.
+    ///
+    /// One-shot reads clear the flag before converting, so after a one-shot read this only
+    /// reflects that conversion. In free-running mode it reflects all conversions since
+    /// [`Adc::clear_sticky_error`] was called.
+    pub fn has_sticky_error(&self) -> bool {
+        self.device.cs.read().err_sticky().bit_is_set()
+    }
+
+    /// Clear the sticky error flag, see [`Adc::has_sticky_error`].
+    pub fn clear_sticky_error(&mut self) {
+        self.device.cs.modify(|_, w| w.err_sticky().set_bit());
+    }
+
     /// Enable temperature sensor, returns a channel to use
     pub fn enable_temp_sensor(&mut self) -> TempSense {
         self.device.cs.modify(|_, w| w.ts_en().set_bit());
@@ -92,11 +189,547 @@ impl Adc {
     pub fn disable_temp_sensor(&mut self, _: TempSense) {
         self.device.cs.modify(|_, w| w.ts_en().clear_bit());
     }
+
+    /// Set the actual voltage of the ADC reference (ADC_AVDD) in millivolts.
+    ///
+    /// This is only used to convert ADC counts to voltages, e.g. by
+    /// [`Adc::read_temperature_celsius`]. It defaults to the nominal 3300 mV. The error of the
+    /// temperature conversion is dominated by the reference voltage, so measuring it and calling
+    /// this improves the accuracy significantly.
+    pub fn set_reference_voltage_mv(&mut self, millivolts: u32) {
+        self.reference_mv = millivolts;
+    }
+
+    /// The ADC reference voltage in millivolts, see [`Adc::set_reference_voltage_mv`].
+    pub fn reference_voltage_mv(&self) -> u32 {
+        self.reference_mv
+    }
+
+    /// Read the temperature sensor and convert the result to degrees Celsius.
+    ///
+    /// This uses the formula from the datasheet: `T = 27 - (V - 0.706) / 0.001721`, where `V`
+    /// is the sensor voltage derived from the reference set by
+    /// [`Adc::set_reference_voltage_mv`].
+    pub fn read_temperature_celsius(&mut self, _sensor: &mut TempSense) -> f32 {
+        let counts = self.convert_one_shot(TEMPERATURE_SENSOR_CHANNEL);
+        let volts = counts as f32 * (self.reference_mv as f32 / 1000.0) / 4096.0;
+        27.0 - (volts - 0.706) / 0.001721
+    }
+
+    /// Read the temperature sensor and convert the result to thousandths of a degree Celsius.
+    ///
+    /// This is the integer-only equivalent of [`Adc::read_temperature_celsius`], for
+    /// applications avoiding soft-float code.
+    pub fn read_temperature_millicelsius(&mut self, _sensor: &mut TempSense) -> i32 {
+        let counts = self.convert_one_shot(TEMPERATURE_SENSOR_CHANNEL);
+        let microvolts = i64::from(counts) * i64::from(self.reference_mv) * 1000 / 4096;
+        (27_000 - (microvolts - 706_000) * 1000 / 1721) as i32
+    }
+
+    /// Set the clock divider used in free-running mode, as a 16.8 fixed-point value.
+    ///
+    /// A conversion is started every `1 + int + frac / 256` cycles of the ADC clock. A conversion
+    /// takes 96 cycles, so periods shorter than that run at full speed. Setting both parts to 0
+    /// also runs at full speed, starting conversions back-to-back.
+    ///
+    /// The divider only takes effect in free-running mode (`START_MANY`), one-shot reads always
+    /// convert immediately.
+    pub fn set_clkdiv(&mut self, int: u16, frac: u8) {
+        self.device
+            .div
+            .write(|w| unsafe { w.int().bits(int).frac().bits(frac) });
+    }
+
+    /// Set the sample rate used in free-running mode and return the rate actually achieved.
+    ///
+    /// `adc_freq` is the frequency of the ADC clock, typically 48 MHz. Rates faster than the
+    /// ADC can convert (500 ksps at 48 MHz) run at full speed. Rates slower than the divider can
+    /// reach (about 732 sps at 48 MHz) are clamped to the slowest rate.
+    ///
+    /// Like [`Adc::set_clkdiv`], this only takes effect in free-running mode.
+    pub fn set_sample_rate(&mut self, adc_freq: Hertz, sample_rate: Hertz) -> Hertz {
+        let adc_freq_hz = u64::from(adc_freq.integer());
+        let rate_hz = u64::from(sample_rate.integer());
+
+        if rate_hz == 0 || rate_hz * CONVERSION_CYCLES >= adc_freq_hz {
+            self.set_clkdiv(0, 0);
+        } else {
+            // Period in ADC clock cycles, with 8 fractional bits, minus the implicit cycle
+            let div = (adc_freq_hz * 256 / rate_hz - 256).min(0xff_ffff);
+            self.set_clkdiv((div >> 8) as u16, div as u8);
+        }
+
+        self.sample_rate(adc_freq)
+    }
+
+    /// The sample rate achieved in free-running mode with the current clock divider.
+    ///
+    /// `adc_freq` is the frequency of the ADC clock, typically 48 MHz.
+    pub fn sample_rate(&self, adc_freq: Hertz) -> Hertz {
+        let div = self.device.div.read();
+        let div = u64::from(div.int().bits()) << 8 | u64::from(div.frac().bits());
+        let period = if div == 0 {
+            CONVERSION_CYCLES << 8
+        } else {
+            (div + 256).max(CONVERSION_CYCLES << 8)
+        };
+        Hertz((u64::from(adc_freq.integer()) * 256 / period) as u32)
+    }
+
+    /// Start free-running conversions of `pin` into the FIFO, using the default FIFO configuration.
+    ///
+    /// This is a shorthand for `adc.build_fifo().set_channel(pin).start()`.
+    /// See [`AdcFifoBuilder`] for the other configuration options.
+    pub fn free_running<PIN: Channel<Adc, ID = u8>>(&mut self, pin: &mut PIN) -> AdcFifo<'_, u16> {
+        self.build_fifo().set_channel(pin).start()
+    }
+
+    /// Configure the FIFO used by free-running mode.
+    ///
+    /// Conversions only start once [`AdcFifoBuilder::start`] is called.
+    pub fn build_fifo(&mut self) -> AdcFifoBuilder<'_, u16> {
+        // Start from a known FIFO configuration: enabled, 12-bit samples, no error bit
+        self.device.fcs.write(|w| w.en().set_bit());
+        AdcFifoBuilder {
+            adc: self,
+            marker: PhantomData,
+        }
+    }
+
+    fn select_channel(&mut self, chan: u8) {
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+
+        self.device
+            .cs
+            .modify(|_, w| unsafe { w.ainsel().bits(chan) });
+    }
+
+    fn enable_fifo_interrupt(&mut self, threshold: u8) {
+        self.device
+            .fcs
+            .modify(|_, w| unsafe { w.thresh().bits(threshold) });
+        self.device.inte.write(|w| w.fifo().set_bit());
+    }
+
+    fn convert_one_shot(&mut self, chan: u8) -> u16 {
+        if chan == TEMPERATURE_SENSOR_CHANNEL {
+            self.device.cs.modify(|_, w| w.ts_en().set_bit())
+        }
+
+        self.wait_ready();
+
+        // Clear the sticky error flag, so errors don't leak across reads
+        self.device.cs.modify(|_, w| unsafe {
+            w.ainsel()
+                .bits(chan)
+                .err_sticky()
+                .set_bit()
+                .start_once()
+                .set_bit()
+        });
+
+        self.wait_ready();
+
+        self.device.result.read().result().bits()
+    }
+
+    fn wait_ready(&self) {
+        while !self.device.cs.read().ready().bit_is_set() {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+/// Sample width of the values read from an [`AdcFifo`]
+///
+/// This is `u16` for full 12 bit samples, or `u8` if the FIFO was configured with
+/// [`AdcFifoBuilder::shift_8bit`].
+pub trait AdcFifoWord: dma::Word {
+    #[doc(hidden)]
+    fn from_fifo(raw: u32) -> Self;
+}
+
+impl AdcFifoWord for u8 {
+    fn from_fifo(raw: u32) -> Self {
+        raw as u8
+    }
+}
+
+impl AdcFifoWord for u16 {
+    fn from_fifo(raw: u32) -> Self {
+        raw as u16
+    }
+}
+
+/// A set of channels sampled in round-robin mode, see [`AdcFifoBuilder::round_robin`].
+///
+/// In round-robin mode, the ADC converts the channel currently selected by `AINSEL`, then moves
+/// `AINSEL` to the next enabled channel above it, wrapping around to the lowest enabled channel
+/// after the highest one. The samples in the FIFO are therefore interleaved in ascending channel
+/// order.
+///
+/// The first sample always comes from the channel selected when conversions start. If that is
+/// not the lowest enabled channel, the sequence starts mid-cycle. If it is not an enabled channel
+/// at all, it is sampled once and the sequence continues with the next enabled channel above it.
+/// [`RoundRobin::next_channel`] gives the channel that follows any given channel:
+///
+/// ```
+/// use rp2040_hal::adc::RoundRobin;
+/// // Channels 0, 2 and 4 (temperature sensor) enabled
+/// let channels = RoundRobin::from_mask(0b10101);
+/// assert_eq!(channels.first(), Some(0));
+/// // Starting on channel 2 gives 2, 4, 0, 2, 4, 0, ...
+/// assert_eq!(channels.next_channel(2), 4);
+/// assert_eq!(channels.next_channel(4), 0);
+/// assert_eq!(channels.next_channel(0), 2);
+/// // Starting on channel 3, which is not enabled, gives 3, 4, 0, 2, 4, 0, ...
+/// assert_eq!(channels.next_channel(3), 4);
+/// ```
+///
+/// If the FIFO overflows, samples are lost and the phase of the samples read afterwards is
+/// unknown. Check [`AdcFifo::is_over`] when de-interleaving.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundRobin(u8);
+
+impl RoundRobin {
+    /// Create a set of channels from a raw bit mask, where bit `n` enables channel `n`.
+    ///
+    /// Only the 5 lowest bits are used. Prefer converting from channel references, which
+    /// guarantees the channels are configured for ADC use.
+    pub const fn from_mask(mask: u8) -> Self {
+        RoundRobin(mask & 0b1_1111)
+    }
+
+    /// The raw bit mask, where bit `n` is set if channel `n` is sampled.
+    pub const fn mask(&self) -> u8 {
+        self.0
+    }
+
+    /// Check if `channel` is part of the set.
+    pub const fn contains(&self, channel: u8) -> bool {
+        channel < 5 && self.0 & (1 << channel) != 0
+    }
+
+    /// The lowest channel of the set, or `None` if the set is empty.
+    pub fn first(&self) -> Option<u8> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as u8)
+        }
+    }
+
+    /// The channel sampled after `channel`.
+    ///
+    /// This is the lowest enabled channel above `channel`, or the lowest enabled channel
+    /// overall if there is none above it. Returns `channel` if the set is empty.
+    pub fn next_channel(&self, channel: u8) -> u8 {
+        let above = if channel >= 4 {
+            0
+        } else {
+            self.0 & !((2 << channel) - 1)
+        };
+        if above != 0 {
+            above.trailing_zeros() as u8
+        } else {
+            self.first().unwrap_or(channel)
+        }
+    }
+}
+
+impl<PIN: Channel<Adc, ID = u8>> From<&PIN> for RoundRobin {
+    fn from(_: &PIN) -> Self {
+        Self(1 << PIN::channel())
+    }
+}
+
+macro_rules! round_robin_tuple {
+    ($($pin:ident),+) => {
+        impl<$($pin),+> From<($(&$pin),+)> for RoundRobin
+        where
+            $($pin: Channel<Adc, ID = u8>),+
+        {
+            fn from(_: ($(&$pin),+)) -> Self {
+                Self(0 $(| (1 << $pin::channel()))+)
+            }
+        }
+    };
+}
+
+round_robin_tuple!(A, B);
+round_robin_tuple!(A, B, C);
+round_robin_tuple!(A, B, C, D);
+round_robin_tuple!(A, B, C, D, E);
+
+/// Builder for the ADC FIFO, returned by [`Adc::build_fifo`]
+pub struct AdcFifoBuilder<'a, Word> {
+    adc: &'a mut Adc,
+    marker: PhantomData<Word>,
+}
+
+impl<'a, Word: AdcFifoWord> AdcFifoBuilder<'a, Word> {
+    /// Select the channel to sample.
+    pub fn set_channel<PIN: Channel<Adc, ID = u8>>(self, _pin: &mut PIN) -> Self {
+        self.adc.select_channel(PIN::channel());
+        self
+    }
+
+    /// Sample several channels in turn.
+    ///
+    /// `channels` is a reference to a channel, or a tuple of up to five references to channels,
+    /// e.g. `(&adc_pin_0, &adc_pin_2, &temperature_sensor)`. Taking the channels by reference
+    /// guarantees that they are configured for ADC use while sampling.
+    ///
+    /// This also selects the lowest channel of the set as the first channel to sample, so samples
+    /// are pushed to the FIFO in ascending channel order, starting with the lowest one. See
+    /// [`RoundRobin`] for details on the sampling order, in particular if
+    /// [`AdcFifoBuilder::set_channel`] is called afterwards to start on another channel.
+    pub fn round_robin<T: Into<RoundRobin>>(self, channels: T) -> Self {
+        let RoundRobin(mask) = channels.into();
+        if let Some(first) = RoundRobin(mask).first() {
+            self.adc.select_channel(first);
+        }
+        self.adc
+            .device
+            .cs
+            .modify(|_, w| unsafe { w.rrobin().bits(mask) });
+        self
+    }
+
+    /// Include the conversion error bit in the samples pushed to the FIFO.
+    ///
+    /// When set, bit 15 of every sample read from the FIFO is set if that conversion failed.
+    /// [`AdcFifo::read_checked`] splits it from the sample value. Note that [`AdcFifo::read`]
+    /// can't return this bit when the FIFO is in 8 bit mode, and neither can DMA byte transfers.
+    pub fn include_error_bit(self) -> Self {
+        self.adc.device.fcs.modify(|_, w| w.err().set_bit());
+        self
+    }
+
+    /// Right-shift the samples pushed to the FIFO to 8 bits.
+    ///
+    /// This discards the 4 least significant bits of each conversion and is useful to fill
+    /// byte-sized buffers.
+    pub fn shift_8bit(self) -> AdcFifoBuilder<'a, u8> {
+        self.adc.device.fcs.modify(|_, w| w.shift().set_bit());
+        AdcFifoBuilder {
+            adc: self.adc,
+            marker: PhantomData,
+        }
+    }
+
+    /// Enable the `ADC_IRQ_FIFO` interrupt, see [`AdcFifo::enable_interrupt`].
+    pub fn enable_interrupt(self, threshold: u8) -> Self {
+        self.adc.enable_fifo_interrupt(threshold);
+        self
+    }
+
+    /// Generate a DMA request (DREQ) whenever a sample is available in the FIFO.
+    ///
+    /// This is required to read the FIFO using DMA, see [`AdcFifo::dma_read_target`].
+    pub fn enable_dma(self) -> Self {
+        self.adc
+            .device
+            .fcs
+            .modify(|_, w| unsafe { w.dreq_en().set_bit().thresh().bits(1) });
+        self
+    }
+
+    /// Start free-running conversions.
+    pub fn start(self) -> AdcFifo<'a, Word> {
+        self.adc.wait_ready();
+        self.adc.device.cs.modify(|_, w| w.start_many().set_bit());
+        AdcFifo {
+            adc: self.adc,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Handle to the ADC FIFO while the ADC is in free-running mode, returned by
+/// [`AdcFifoBuilder::start`] or [`Adc::free_running`].
+///
+/// Conversions stop when this is dropped or when [`AdcFifo::stop`] is called. Any samples left in
+/// the FIFO are discarded at that point, so they cannot corrupt a subsequent one-shot read.
+///
+/// Reading samples only requires a shared reference, as popping a sample from the FIFO is a
+/// single register read. This allows reading from an interrupt handler while another context
+/// holds the same reference.
+pub struct AdcFifo<'a, Word: AdcFifoWord> {
+    adc: &'a mut Adc,
+    marker: PhantomData<Word>,
+}
+
+impl<'a, Word: AdcFifoWord> AdcFifo<'a, Word> {
+    /// Number of samples currently in the FIFO
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u8 {
+        self.adc.device.fcs.read().level().bits()
+    }
+
+    /// Check if the FIFO overflowed, i.e. samples were lost because it was full.
+    pub fn is_over(&self) -> bool {
+        self.adc.device.fcs.read().over().bit_is_set()
+    }
+
+    /// Check if the FIFO underflowed, i.e. it was read while empty.
+    pub fn is_under(&self) -> bool {
+        self.adc.device.fcs.read().under().bit_is_set()
+    }
+
+    /// Clear the overflow and underflow flags.
+    pub fn clear_flags(&mut self) {
+        self.adc
+            .device
+            .fcs
+            .modify(|_, w| w.over().set_bit().under().set_bit());
+    }
+
+    /// Read a single sample from the FIFO.
+    ///
+    /// This does not check if the FIFO holds any samples. Reading an empty FIFO sets the
+    /// underflow flag and returns an undefined value, so check [`AdcFifo::len`] first or use
+    /// [`AdcFifo::read_blocking`].
+    ///
+    /// If [`AdcFifoBuilder::include_error_bit`] was used, bit 15 of the returned sample is set
+    /// when the conversion failed.
+    pub fn read(&self) -> Word {
+        Word::from_fifo(self.adc.device.fifo.read().bits())
+    }
+
+    /// Read a single sample from the FIFO, splitting out the conversion error bit.
+    ///
+    /// Returns an error if the conversion of this sample failed. This requires the FIFO to be
+    /// configured with [`AdcFifoBuilder::include_error_bit`], otherwise errors go undetected and
+    /// this always returns `Ok`.
+    ///
+    /// Like [`AdcFifo::read`], this does not check if the FIFO holds any samples.
+    pub fn read_checked(&self) -> Result<Word, AdcError> {
+        let sample = self.adc.device.fifo.read();
+        if sample.err().bit_is_set() {
+            Err(AdcError::ConversionFailed)
+        } else {
+            Ok(Word::from_fifo(u32::from(sample.val().bits())))
+        }
+    }
+
+    /// Wait for a sample to be available and read it from the FIFO.
+    pub fn read_blocking(&self) -> Word {
+        while self.len() == 0 {
+            cortex_m::asm::nop();
+        }
+        self.read()
+    }
+
+    /// Enable the `ADC_IRQ_FIFO` interrupt.
+    ///
+    /// The interrupt is asserted as long as the FIFO holds at least `threshold` samples (1 to 4,
+    /// the depth of the FIFO). It is level based: it can't be cleared, the handler must read
+    /// samples from the FIFO until fewer than `threshold` remain to deassert it:
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::adc::AdcFifo;
+    /// # fn handler(fifo: &AdcFifo<'_, u16>, buffer: &mut [u16]) {
+    /// // In the ADC_IRQ_FIFO handler
+    /// let mut i = 0;
+    /// while fifo.len() > 0 && i < buffer.len() {
+    ///     buffer[i] = fifo.read();
+    ///     i += 1;
+    /// }
+    /// // The FIFO is now below the threshold, so the interrupt is no longer pending
+    /// # }
+    /// ```
+    ///
+    /// The threshold is shared with the DMA request, so don't combine this with
+    /// [`AdcFifoBuilder::enable_dma`].
+    pub fn enable_interrupt(&mut self, threshold: u8) {
+        self.adc.enable_fifo_interrupt(threshold);
+    }
+
+    /// Disable the `ADC_IRQ_FIFO` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.adc.device.inte.write(|w| w.fifo().clear_bit());
+    }
+
+    /// Check if the `ADC_IRQ_FIFO` interrupt is asserted, i.e. it is enabled and the FIFO level
+    /// is at or above the threshold.
+    pub fn is_interrupt_pending(&self) -> bool {
+        self.adc.device.ints.read().fifo().bit_is_set()
+    }
+
+    /// Discard all samples currently in the FIFO.
+    pub fn clear(&mut self) {
+        while self.len() > 0 {
+            self.adc.device.fifo.read();
+        }
+    }
+
+    /// Returns a DMA read target for the FIFO.
+    ///
+    /// The transfer width of the target matches the FIFO configuration: `u16` for 12 bit samples,
+    /// or `u8` if [`AdcFifoBuilder::shift_8bit`] was used.
+    ///
+    /// The FIFO must be configured with [`AdcFifoBuilder::enable_dma`], otherwise the DMA channel
+    /// never receives a DREQ.
+    ///
+    /// The target borrows the FIFO, so the FIFO can't be stopped while a transfer still reads
+    /// from it.
+    pub fn dma_read_target(&self) -> DmaReadTarget<'_, Word> {
+        DmaReadTarget(&self.adc.device.fifo as *const _ as u32, PhantomData)
+    }
+
+    /// Stop free-running conversions and release the ADC.
+    ///
+    /// Waits for the conversion in progress to finish, then empties the FIFO and restores the
+    /// default FIFO configuration.
+    pub fn stop(self) {
+        // Stopping is done by the `Drop` implementation
+    }
+}
+
+/// DMA read target for the ADC FIFO, returned by [`AdcFifo::dma_read_target`]
+pub struct DmaReadTarget<'a, Word>(u32, PhantomData<&'a Word>);
+
+/// Safety: rx_address_count points to the FIFO register, which can be read endlessly.
+unsafe impl<'a, Word: AdcFifoWord> dma::ReadTarget for DmaReadTarget<'a, Word> {
+    type ReceivedWord = Word;
+
+    fn rx_treq() -> Option<u8> {
+        Some(dma::DREQ_ADC)
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.0, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, Word: AdcFifoWord> dma::EndlessReadTarget for DmaReadTarget<'a, Word> {}
+
+impl<'a, Word: AdcFifoWord> Drop for AdcFifo<'a, Word> {
+    fn drop(&mut self) {
+        self.adc
+            .device
+            .cs
+            .modify(|_, w| unsafe { w.start_many().clear_bit().rrobin().bits(0) });
+        // Let the conversion in progress finish, so it doesn't land in the FIFO after it was drained
+        self.adc.wait_ready();
+        self.clear();
+        self.clear_flags();
+        self.disable_interrupt();
+        // Also disables the DREQ
+        self.adc.device.fcs.reset();
+    }
 }
 
 macro_rules! channel {
     ($pin:ident, $channel:expr) => {
-        impl Channel<Adc> for Pin<$pin, FloatingInput> {
+        impl Channel<Adc> for Pin<$pin, FloatingDisabled> {
             type ID = u8; // ADC channels are identified numerically
 
             fn channel() -> u8 {
@@ -105,7 +738,7 @@ macro_rules! channel {
         }
 
         #[cfg(feature = "eh1_0_alpha")]
-        impl eh1_0_alpha::adc::nb::Channel<Adc> for Pin<$pin, FloatingInput> {
+        impl eh1_0_alpha::adc::nb::Channel<Adc> for Pin<$pin, FloatingDisabled> {
             type ID = u8; // ADC channels are identified numerically
 
             fn channel(&self) -> u8 {
@@ -152,23 +785,7 @@ where
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
         let chan = PIN::channel();
 
-        if chan == 4 {
-            self.device.cs.modify(|_, w| w.ts_en().set_bit())
-        }
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        self.device
-            .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        Ok(self.device.result.read().result().bits().into())
+        Ok(self.convert_one_shot(chan).into())
     }
 }
 
@@ -183,22 +800,6 @@ where
     fn read(&mut self, pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
         let chan = PIN::channel(pin);
 
-        if chan == 4 {
-            self.device.cs.modify(|_, w| w.ts_en().set_bit())
-        }
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        self.device
-            .cs
-            .modify(|_, w| unsafe { w.ainsel().bits(chan).start_once().set_bit() });
-
-        while !self.device.cs.read().ready().bit_is_set() {
-            cortex_m::asm::nop();
-        }
-
-        Ok(self.device.result.read().result().bits().into())
+        Ok(self.convert_one_shot(chan).into())
     }
 }
@@ -7,6 +7,22 @@
 
 use core::ptr::write_volatile;
 
+/// Perform atomic bitmask XOR operation on register
+///
+/// See [section 2.1.2 of the RP2040 datasheet][section_2_1_2] for details.
+///
+/// [section_2_1_2]: https://datasheets.raspberrypi.com/rp2040/rp2040-datasheet.pdf#atomic-rwtype
+///
+/// # Safety
+///
+/// In addition to the requirements of [core::ptr::write_volatile],
+/// `register` must point to a register providing atomic aliases.
+#[inline]
+pub(crate) unsafe fn write_bitmask_xor(register: *mut u32, bits: u32) {
+    let alias = (register as usize + 0x1000) as *mut u32;
+    write_volatile(alias, bits);
+}
+
 /// Perform atomic bitmask set operation on register
 ///
 /// See [section 2.1.2 of the RP2040 datasheet][section_2_1_2] for details.
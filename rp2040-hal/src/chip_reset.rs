@@ -0,0 +1,84 @@
+//! Reason of the last reset
+//!
+//! The CHIP_RESET register of the VREG_AND_CHIP_RESET block latches the source of the last
+//! chip-level reset, and the REASON register of the watchdog whether the watchdog reset the chip
+//! since. See [Chapter 2 Section 13](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf)
+//! and [Chapter 4 Section 7](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for
+//! more details.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::chip_reset::{reset_reason, ResetReason};
+//!
+//! // Can be called before the clocks are initialized
+//! if reset_reason() == ResetReason::WatchdogTimeout {
+//!     // Enter safe mode
+//! }
+//! ```
+
+use crate::pac;
+
+/// Bits of CHIP_RESET
+const HAD_POR: u32 = 1 << 8;
+const HAD_RUN: u32 = 1 << 16;
+const HAD_PSM_RESTART: u32 = 1 << 20;
+const PSM_RESTART_FLAG: u32 = 1 << 24;
+
+/// Bits of the watchdog REASON register
+const REASON_TIMER: u32 = 1 << 0;
+const REASON_FORCE: u32 = 1 << 1;
+
+/// Source of the last reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    /// Power-on reset, or brown-out reset
+    ///
+    /// The hardware latches both in the same flag, so they can't be told apart. See
+    /// [`Bod`](crate::vreg::Bod) to configure the brown-out detector.
+    PowerOn,
+    /// The RUN pin was pulled low
+    RunPin,
+    /// The debugger restarted the chip through the debug port
+    DebuggerRestart,
+    /// The watchdog timer expired
+    WatchdogTimeout,
+    /// The watchdog reset was triggered by software
+    WatchdogForced,
+    /// None of the reset flags is set
+    Unknown,
+}
+
+/// Returns the source of the last reset.
+///
+/// A watchdog reset does not reset CHIP_RESET, so the watchdog reason takes precedence. The
+/// debugger restart flag, which the boot ROM checks to enter a safe mode, is cleared so the next
+/// boot reads fresh state.
+///
+/// This only reads registers which are not affected by the clock setup, so it can be called at any
+/// point, including before the clocks are initialized.
+pub fn reset_reason() -> ResetReason {
+    // Safety: reading status registers, and clearing a write-1-to-clear flag nothing else uses
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    let vreg = unsafe { &*pac::VREG_AND_CHIP_RESET::ptr() };
+
+    let chip_reset = vreg.chip_reset.read().bits();
+    if chip_reset & PSM_RESTART_FLAG != 0 {
+        vreg.chip_reset
+            .write(|w| unsafe { w.bits(PSM_RESTART_FLAG) });
+    }
+
+    let reason = watchdog.reason.read().bits();
+    if reason & REASON_FORCE != 0 {
+        ResetReason::WatchdogForced
+    } else if reason & REASON_TIMER != 0 {
+        ResetReason::WatchdogTimeout
+    } else if chip_reset & HAD_PSM_RESTART != 0 {
+        ResetReason::DebuggerRestart
+    } else if chip_reset & HAD_RUN != 0 {
+        ResetReason::RunPin
+    } else if chip_reset & HAD_POR != 0 {
+        ResetReason::PowerOn
+    } else {
+        ResetReason::Unknown
+    }
+}
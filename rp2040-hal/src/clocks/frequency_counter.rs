@@ -0,0 +1,137 @@
+//! Frequency counter (FC0)
+//!
+//! Measures the frequency of internal clocks and clock inputs against clk_ref.
+
+use super::*;
+
+/// Clock measured by the [`FrequencyCounter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FcSrc {
+    /// Output of PLL_SYS, before clk_sys' divider
+    PllSys = 1,
+    /// Output of PLL_USB, before clk_usb's divider
+    PllUsb = 2,
+    /// Ring oscillator
+    Rosc = 3,
+    /// Phase shifted ring oscillator
+    RoscPhaseShifted = 4,
+    /// Crystal oscillator
+    Xosc = 5,
+    /// GPIO20 clock input
+    GPin0 = 6,
+    /// GPIO22 clock input
+    GPin1 = 7,
+    /// Reference clock
+    ReferenceClock = 8,
+    /// System clock
+    SystemClock = 9,
+    /// Peripheral clock
+    PeripheralClock = 10,
+    /// USB clock
+    UsbClock = 11,
+    /// ADC clock
+    AdcClock = 12,
+    /// RTC clock
+    RtcClock = 13,
+}
+
+/// Error of a frequency measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FcError {
+    /// The measured clock stopped during the measurement
+    Died,
+    /// The measured frequency is below the minimum
+    Slow,
+    /// The measured frequency is above the maximum
+    Fast,
+}
+
+/// The frequency counter of the CLOCKS block
+///
+/// Created by [`ClocksManager::frequency_counter`]. There is only one counter, so it borrows the
+/// clocks manager exclusively.
+pub struct FrequencyCounter<'a> {
+    clocks: &'a CLOCKS,
+}
+
+impl<'a> FrequencyCounter<'a> {
+    /// Measure the frequency of `src`
+    ///
+    /// `ref_khz` is the frequency of clk_ref in kHz, e.g.
+    /// `clocks.reference_clock.freq().integer() / 1000`. The result has a resolution of 1/32 kHz.
+    pub fn measure(&mut self, src: FcSrc, ref_khz: u32) -> Result<Hertz, FcError> {
+        self.measure_range(src, ref_khz, 0, 0x1ff_ffff)
+    }
+
+    /// Measure the frequency of `src`, and check that it is between `min_khz` and `max_khz`
+    ///
+    /// See [`measure`](Self::measure).
+    pub fn measure_range(
+        &mut self,
+        src: FcSrc,
+        ref_khz: u32,
+        min_khz: u32,
+        max_khz: u32,
+    ) -> Result<Hertz, FcError> {
+        // Wait for a previous measurement to finish
+        while self.clocks.fc0_status.read().running().bit_is_set() {}
+
+        self.clocks
+            .fc0_ref_khz
+            .write(|w| unsafe { w.fc0_ref_khz().bits(ref_khz) });
+        // Count for 2^10 / 2^20 = 1 ms
+        self.clocks
+            .fc0_interval
+            .write(|w| unsafe { w.fc0_interval().bits(10) });
+        self.clocks
+            .fc0_min_khz
+            .write(|w| unsafe { w.fc0_min_khz().bits(min_khz) });
+        self.clocks
+            .fc0_max_khz
+            .write(|w| unsafe { w.fc0_max_khz().bits(max_khz) });
+        // Writing the source starts the measurement
+        self.clocks
+            .fc0_src
+            .write(|w| unsafe { w.fc0_src().bits(src as u8) });
+
+        while self.clocks.fc0_status.read().done().bit_is_clear() {}
+
+        let status = self.clocks.fc0_status.read();
+        if status.died().bit_is_set() {
+            return Err(FcError::Died);
+        }
+        if status.slow().bit_is_set() {
+            return Err(FcError::Slow);
+        }
+        if status.fast().bit_is_set() {
+            return Err(FcError::Fast);
+        }
+
+        // The result is in kHz, with 5 fractional bits
+        let result = self.clocks.fc0_result.read();
+        let khz = result.khz().bits();
+        let frac = u32::from(result.frac().bits());
+        Ok(Hertz(khz * 1000 + frac * 1000 / 32))
+    }
+}
+
+impl ClocksManager {
+    /// Get the frequency counter, to measure the frequency of a clock
+    ///
+    /// ```no_run
+    /// use embedded_time::fixed_point::FixedPoint;
+    /// use rp2040_hal::{clocks::{init_clocks_and_plls, Clock, FcSrc}, pac, watchdog::Watchdog};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+    /// let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+    ///
+    /// let ref_khz = clocks.reference_clock.freq().integer() / 1000;
+    /// let rosc_freq = clocks.frequency_counter().measure(FcSrc::Rosc, ref_khz);
+    /// ```
+    pub fn frequency_counter(&mut self) -> FrequencyCounter<'_> {
+        FrequencyCounter {
+            clocks: &self.clocks,
+        }
+    }
+}
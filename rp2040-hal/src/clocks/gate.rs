@@ -0,0 +1,425 @@
+//! Clock gates of the peripherals
+//!
+//! Every peripheral has a gate on each clock it receives. The gates are controlled by the
+//! WAKE_EN0/1 registers while a core is awake, and by the SLEEP_EN0/1 registers while both cores
+//! sleep. All gates are open after reset.
+//!
+//! The SRAM banks, the USB DPRAM and the boot ROM can also be powered down with
+//! [`power_down_memory`], which saves more than gating their clocks.
+
+use super::*;
+use core::ops::{BitOr, BitOrAssign};
+
+/// Clock gates of a peripheral
+///
+/// Some peripherals have more than one gate, e.g. [`Uart0`](Self::Uart0) covers both clk_sys and
+/// clk_peri of UART0. Gates can be combined into a [`ClockGateMask`] with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockGate {
+    /// clk_sys of the CLOCKS block
+    Clocks,
+    /// clk_adc and clk_sys of the ADC
+    Adc,
+    /// clk_sys of the bus controller
+    Busctrl,
+    /// clk_sys of the bus fabric
+    Busfabric,
+    /// clk_sys of the DMA
+    Dma,
+    /// clk_sys of I2C0
+    I2c0,
+    /// clk_sys of I2C1
+    I2c1,
+    /// clk_sys of IO_BANK0 and IO_QSPI
+    Io,
+    /// clk_sys of the JTAG debug port
+    Jtag,
+    /// clk_sys of VREG_AND_CHIP_RESET
+    VregAndChipReset,
+    /// clk_sys of PADS_BANK0 and PADS_QSPI
+    Pads,
+    /// clk_sys of PIO0
+    Pio0,
+    /// clk_sys of PIO1
+    Pio1,
+    /// clk_sys of the PLL_SYS registers
+    PllSys,
+    /// clk_sys of the PLL_USB registers
+    PllUsb,
+    /// clk_sys of the power-on state machine
+    Psm,
+    /// clk_sys of the PWM
+    Pwm,
+    /// clk_sys of RESETS
+    Resets,
+    /// clk_sys of the boot ROM
+    Rom,
+    /// clk_sys of the ROSC registers
+    Rosc,
+    /// clk_rtc and clk_sys of the RTC
+    Rtc,
+    /// clk_sys of the SIO
+    Sio,
+    /// clk_peri and clk_sys of SPI0
+    Spi0,
+    /// clk_peri and clk_sys of SPI1
+    Spi1,
+    /// clk_sys of SRAM bank 0
+    Sram0,
+    /// clk_sys of SRAM bank 1
+    Sram1,
+    /// clk_sys of SRAM bank 2
+    Sram2,
+    /// clk_sys of SRAM bank 3
+    Sram3,
+    /// clk_sys of SRAM bank 4
+    Sram4,
+    /// clk_sys of SRAM bank 5
+    Sram5,
+    /// clk_sys of SYSCFG
+    Syscfg,
+    /// clk_sys of SYSINFO
+    Sysinfo,
+    /// clk_sys of the testbench manager
+    Tbman,
+    /// clk_sys of the timer
+    Timer,
+    /// clk_peri and clk_sys of UART0
+    Uart0,
+    /// clk_peri and clk_sys of UART1
+    Uart1,
+    /// clk_sys and clk_usb of the USB controller
+    Usb,
+    /// clk_sys of the watchdog
+    Watchdog,
+    /// clk_sys of the XIP cache and SSI
+    Xip,
+    /// clk_sys of the XOSC registers
+    Xosc,
+}
+
+impl ClockGate {
+    /// The bits of this gate
+    pub const fn mask(self) -> ClockGateMask {
+        let (en0, en1) = match self {
+            ClockGate::Clocks => (1 << 0, 0),
+            ClockGate::Adc => (1 << 1 | 1 << 2, 0),
+            ClockGate::Busctrl => (1 << 3, 0),
+            ClockGate::Busfabric => (1 << 4, 0),
+            ClockGate::Dma => (1 << 5, 0),
+            ClockGate::I2c0 => (1 << 6, 0),
+            ClockGate::I2c1 => (1 << 7, 0),
+            ClockGate::Io => (1 << 8, 0),
+            ClockGate::Jtag => (1 << 9, 0),
+            ClockGate::VregAndChipReset => (1 << 10, 0),
+            ClockGate::Pads => (1 << 11, 0),
+            ClockGate::Pio0 => (1 << 12, 0),
+            ClockGate::Pio1 => (1 << 13, 0),
+            ClockGate::PllSys => (1 << 14, 0),
+            ClockGate::PllUsb => (1 << 15, 0),
+            ClockGate::Psm => (1 << 16, 0),
+            ClockGate::Pwm => (1 << 17, 0),
+            ClockGate::Resets => (1 << 18, 0),
+            ClockGate::Rom => (1 << 19, 0),
+            ClockGate::Rosc => (1 << 20, 0),
+            ClockGate::Rtc => (1 << 21 | 1 << 22, 0),
+            ClockGate::Sio => (1 << 23, 0),
+            ClockGate::Spi0 => (1 << 24 | 1 << 25, 0),
+            ClockGate::Spi1 => (1 << 26 | 1 << 27, 0),
+            ClockGate::Sram0 => (1 << 28, 0),
+            ClockGate::Sram1 => (1 << 29, 0),
+            ClockGate::Sram2 => (1 << 30, 0),
+            ClockGate::Sram3 => (1 << 31, 0),
+            ClockGate::Sram4 => (0, 1 << 0),
+            ClockGate::Sram5 => (0, 1 << 1),
+            ClockGate::Syscfg => (0, 1 << 2),
+            ClockGate::Sysinfo => (0, 1 << 3),
+            ClockGate::Tbman => (0, 1 << 4),
+            ClockGate::Timer => (0, 1 << 5),
+            ClockGate::Uart0 => (0, 1 << 6 | 1 << 7),
+            ClockGate::Uart1 => (0, 1 << 8 | 1 << 9),
+            ClockGate::Usb => (0, 1 << 10 | 1 << 11),
+            ClockGate::Watchdog => (0, 1 << 12),
+            ClockGate::Xip => (0, 1 << 13),
+            ClockGate::Xosc => (0, 1 << 14),
+        };
+        ClockGateMask { en0, en1 }
+    }
+}
+
+/// A set of clock gates, as in the WAKE_EN0/1, SLEEP_EN0/1 and ENABLED0/1 registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockGateMask {
+    en0: u32,
+    en1: u32,
+}
+
+impl ClockGateMask {
+    const EN1_BITS: u32 = 0x7fff;
+
+    /// No gates
+    pub const fn empty() -> Self {
+        ClockGateMask { en0: 0, en1: 0 }
+    }
+
+    /// All gates
+    pub const fn all() -> Self {
+        ClockGateMask {
+            en0: u32::MAX,
+            en1: Self::EN1_BITS,
+        }
+    }
+
+    /// The gates given by raw EN0 and EN1 register values
+    pub const fn from_bits(en0: u32, en1: u32) -> Self {
+        ClockGateMask {
+            en0,
+            en1: en1 & Self::EN1_BITS,
+        }
+    }
+
+    /// The raw EN0 and EN1 register values
+    pub const fn bits(&self) -> (u32, u32) {
+        (self.en0, self.en1)
+    }
+
+    /// Add the gates of `gate`
+    pub const fn with(self, gate: ClockGate) -> Self {
+        let gate = gate.mask();
+        ClockGateMask {
+            en0: self.en0 | gate.en0,
+            en1: self.en1 | gate.en1,
+        }
+    }
+
+    /// Remove the gates of `gate`
+    pub const fn without(self, gate: ClockGate) -> Self {
+        let gate = gate.mask();
+        ClockGateMask {
+            en0: self.en0 & !gate.en0,
+            en1: self.en1 & !gate.en1,
+        }
+    }
+
+    /// Whether all gates of `gate` are in this set
+    pub const fn contains(&self, gate: ClockGate) -> bool {
+        let gate = gate.mask();
+        self.en0 & gate.en0 == gate.en0 && self.en1 & gate.en1 == gate.en1
+    }
+}
+
+impl From<ClockGate> for ClockGateMask {
+    fn from(gate: ClockGate) -> Self {
+        gate.mask()
+    }
+}
+
+impl From<ClockGateMask> for SleepConfig {
+    fn from(mask: ClockGateMask) -> Self {
+        SleepConfig::from_bits(mask.en0, mask.en1)
+    }
+}
+
+impl BitOr for ClockGateMask {
+    type Output = ClockGateMask;
+
+    fn bitor(self, rhs: ClockGateMask) -> ClockGateMask {
+        ClockGateMask {
+            en0: self.en0 | rhs.en0,
+            en1: self.en1 | rhs.en1,
+        }
+    }
+}
+
+impl BitOr<ClockGate> for ClockGateMask {
+    type Output = ClockGateMask;
+
+    fn bitor(self, rhs: ClockGate) -> ClockGateMask {
+        self.with(rhs)
+    }
+}
+
+impl BitOr for ClockGate {
+    type Output = ClockGateMask;
+
+    fn bitor(self, rhs: ClockGate) -> ClockGateMask {
+        self.mask().with(rhs)
+    }
+}
+
+impl BitOrAssign for ClockGateMask {
+    fn bitor_assign(&mut self, rhs: ClockGateMask) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitOrAssign<ClockGate> for ClockGateMask {
+    fn bitor_assign(&mut self, rhs: ClockGate) {
+        *self = self.with(rhs);
+    }
+}
+
+/// A peripheral with clock gates
+///
+/// The drivers owning these peripherals return the gates with `clock_gate()`, so a mask for
+/// [`ClocksManager::set_wake_enables`] can be composed from the drivers in use.
+pub trait ClockGated {
+    /// The clock gates of the peripheral
+    const CLOCK_GATE: ClockGate;
+}
+
+macro_rules! clock_gated {
+    ($($PERIPHERAL:ident: $Gate:ident,)*) => {
+        $(
+            impl ClockGated for pac::$PERIPHERAL {
+                const CLOCK_GATE: ClockGate = ClockGate::$Gate;
+            }
+        )*
+    };
+}
+
+clock_gated! {
+    ADC: Adc,
+    DMA: Dma,
+    I2C0: I2c0,
+    I2C1: I2c1,
+    PIO0: Pio0,
+    PIO1: Pio1,
+    PWM: Pwm,
+    RTC: Rtc,
+    SPI0: Spi0,
+    SPI1: Spi1,
+    TIMER: Timer,
+    UART0: Uart0,
+    UART1: Uart1,
+    USBCTRL_REGS: Usb,
+    WATCHDOG: Watchdog,
+}
+
+impl ClocksManager {
+    /// Select the clock gates which are open while a core is awake
+    ///
+    /// A peripheral whose gate is closed stops immediately, including any transfer in progress,
+    /// and its registers can't be accessed. Closing the gate of a peripheral a driver owns leaves
+    /// the driver unusable until the gate is opened again; the drivers' `clock_gate()` methods
+    /// help to keep them in the mask. Closing the gates of e.g. the SRAM bank holding the stack,
+    /// the bus fabric or XIP while running from flash stops the core.
+    pub fn set_wake_enables(&mut self, mask: ClockGateMask) {
+        self.clocks.wake_en0.write(|w| unsafe { w.bits(mask.en0) });
+        self.clocks.wake_en1.write(|w| unsafe { w.bits(mask.en1) });
+    }
+
+    /// Select the clock gates which are open while both cores sleep
+    ///
+    /// This is the same as [`configure_sleep_enable`](Self::configure_sleep_enable), using a
+    /// [`ClockGateMask`].
+    pub fn set_sleep_enables(&mut self, mask: ClockGateMask) {
+        self.configure_sleep_enable(mask.into());
+    }
+
+    /// Close the gates of `gate` while awake, stopping the peripheral immediately
+    ///
+    /// See [`set_wake_enables`](Self::set_wake_enables) about the peripherals which must keep
+    /// their clocks.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{clocks::{init_clocks_and_plls, ClockGate}, pac, timer::Timer, watchdog::Watchdog};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+    /// let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+    /// let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+    ///
+    /// // Stop the peripherals the application doesn't use
+    /// for gate in [ClockGate::Pio0, ClockGate::Pio1, ClockGate::Spi1, ClockGate::Usb].iter() {
+    ///     clocks.disable_clock_gate(*gate);
+    /// }
+    /// assert!(clocks.enabled_clocks().contains(timer.clock_gate()));
+    /// ```
+    pub fn disable_clock_gate(&mut self, gate: ClockGate) {
+        let gate = gate.mask();
+        self.clocks
+            .wake_en0
+            .modify(|r, w| unsafe { w.bits(r.bits() & !gate.en0) });
+        self.clocks
+            .wake_en1
+            .modify(|r, w| unsafe { w.bits(r.bits() & !gate.en1) });
+    }
+
+    /// Open the gates of `gate` while awake
+    pub fn enable_clock_gate(&mut self, gate: ClockGate) {
+        let gate = gate.mask();
+        self.clocks
+            .wake_en0
+            .modify(|r, w| unsafe { w.bits(r.bits() | gate.en0) });
+        self.clocks
+            .wake_en1
+            .modify(|r, w| unsafe { w.bits(r.bits() | gate.en1) });
+    }
+
+    /// The clock gates which are open right now
+    ///
+    /// A gate is open if it is enabled in WAKE_EN0/1, or in SLEEP_EN0/1 while both cores sleep.
+    pub fn enabled_clocks(&self) -> ClockGateMask {
+        ClockGateMask::from_bits(
+            self.clocks.enabled0.read().bits(),
+            self.clocks.enabled1.read().bits(),
+        )
+    }
+}
+
+/// A memory which can be powered down through SYSCFG MEMPOWERDOWN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Memory {
+    /// SRAM bank 0, striped with banks 1 to 3 at 0x20000000
+    Sram0,
+    /// SRAM bank 1, striped with banks 0, 2 and 3 at 0x20000000
+    Sram1,
+    /// SRAM bank 2, striped with banks 0, 1 and 3 at 0x20000000
+    Sram2,
+    /// SRAM bank 3, striped with banks 0 to 2 at 0x20000000
+    Sram3,
+    /// SRAM bank 4 at 0x20040000
+    Sram4,
+    /// SRAM bank 5 at 0x20041000
+    Sram5,
+    /// The DPRAM of the USB controller
+    Usb,
+    /// The boot ROM
+    Rom,
+}
+
+impl Memory {
+    fn mask(self) -> u32 {
+        // MEMPOWERDOWN bits 0 to 7 are in the order of the variants
+        1 << self as u32
+    }
+}
+
+/// Power down `memory`, which can't be accessed until it is powered up again
+///
+/// The contents of the memory must be considered lost.
+///
+/// # Safety
+///
+/// Nothing may access the memory while it is powered down, from either core or the DMA. Banks 0 to
+/// 3 are striped word by word over the first 256KiB of RAM, which usually holds `.data`, `.bss`
+/// and the stack of core 0, so none of them can be powered down while that region is in use. The
+/// stack of core 1 is often placed in bank 4 or 5. The boot ROM holds the floating point
+/// functions, the flash functions and the interrupt vectors used before the runtime starts.
+pub unsafe fn power_down_memory(syscfg: &mut pac::SYSCFG, memory: Memory) {
+    syscfg
+        .mempowerdown
+        .modify(|r, w| w.bits(r.bits() | memory.mask()));
+}
+
+/// Power `memory` up again after [`power_down_memory`]
+pub fn power_up_memory(syscfg: &mut pac::SYSCFG, memory: Memory) {
+    syscfg
+        .mempowerdown
+        .modify(|r, w| unsafe { w.bits(r.bits() & !memory.mask()) });
+}
+
+/// Whether `memory` is powered down
+pub fn is_memory_powered_down(syscfg: &pac::SYSCFG, memory: Memory) -> bool {
+    syscfg.mempowerdown.read().bits() & memory.mask() != 0
+}
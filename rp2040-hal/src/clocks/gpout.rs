@@ -0,0 +1,133 @@
+//! Clock outputs on GPIO pins
+
+use super::*;
+use crate::gpio::{
+    bank0::{Gpio21, Gpio23, Gpio24, Gpio25},
+    FunctionClock, OutputOverride, Pin, PinId, ValidPinMode,
+};
+
+/// A clock output, driving a pin
+///
+/// Created by e.g. [`ClocksManager::configure_gpout0`]. The output is stopped when it is created,
+/// and has to be started with [`enable`](Self::enable).
+pub struct GpioOutput<C, I: PinId>
+where
+    FunctionClock: ValidPinMode<I>,
+{
+    shared_dev: ShareableClocks,
+    pin: Pin<I, FunctionClock>,
+    clock: PhantomData<C>,
+}
+
+macro_rules! gpout {
+    ($($name:ident: ($fn:ident, $reg:ident, $Gpio:ident, $gpio:literal),)*) => {
+        $crate::paste::paste! {
+            $(
+                impl ClocksManager {
+                    #[doc = "Configure `" $name "` to output `src` divided by `div_int + div_frac / 256` on GPIO" $gpio]
+                    ///
+                    /// The output is stopped while the source and the divider are changed. The
+                    /// divider is written before the output is started again, so no pulse is shorter
+                    /// than configured.
+                    ///
+                    /// `div_int` can be at most 2^24 - 1, a value of 0 means 2^24. If the divider
+                    /// is out of range, or the output frequency doesn't fit, the output is left as
+                    /// it was and the pin is returned with the error.
+                    pub fn $fn<S: ValidSrc<$name>>(
+                        &mut self,
+                        src: &S,
+                        div_int: u32,
+                        div_frac: u8,
+                        pin: Pin<$Gpio, FunctionClock>,
+                    ) -> Result<GpioOutput<$name, $Gpio>, (ClockError, Pin<$Gpio, FunctionClock>)> {
+                        if div_int >= 1 << 24 {
+                            return Err((ClockError::DivisorOutOfRange, pin));
+                        }
+                        let div = div_int << 8 | u32::from(div_frac);
+                        let src_freq: Hertz<u64> = src.get_freq().into();
+                        let full_div = u64::from(div) + if div_int == 0 { 1 << 32 } else { 0 };
+                        let frequency = match (src_freq * (1 << 8) / full_div).try_into() {
+                            Ok(frequency) => frequency,
+                            Err(_) => return Err((ClockError::FrequencyToHigh, pin)),
+                        };
+
+                        let sys_freq = self.system_clock.freq().integer();
+                        let clock = &mut self.[<$name:snake>];
+                        let shared_dev = clock.shared_dev;
+                        let regs = unsafe { shared_dev.get() };
+
+                        // Stop cleanly and wait 3 cycles of the output for ENABLE to propagate.
+                        // Then kill the output in case the old source is stopped, so changing the
+                        // aux mux can't glitch.
+                        regs.[<$reg _ctrl>].modify(|_, w| w.enable().clear_bit());
+                        if clock.frequency > 0u32.Hz() {
+                            cortex_m::asm::delay(3 * (sys_freq / clock.frequency.integer() + 1));
+                        }
+                        regs.[<$reg _ctrl>].modify(|_, w| w.kill().set_bit());
+
+                        clock.set_aux(src);
+                        clock.set_div(div);
+                        regs.[<$reg _ctrl>].modify(|_, w| w.kill().clear_bit());
+
+                        clock.frequency = frequency;
+
+                        Ok(GpioOutput {
+                            shared_dev,
+                            pin,
+                            clock: PhantomData,
+                        })
+                    }
+                }
+
+                impl GpioOutput<$name, $Gpio> {
+                    /// Start the output
+                    pub fn enable(&mut self) {
+                        unsafe { self.shared_dev.get() }
+                            .[<$reg _ctrl>]
+                            .modify(|_, w| w.enable().set_bit());
+                    }
+
+                    /// Stop the output cleanly, after the current cycle
+                    pub fn disable(&mut self) {
+                        unsafe { self.shared_dev.get() }
+                            .[<$reg _ctrl>]
+                            .modify(|_, w| w.enable().clear_bit());
+                    }
+
+                    /// Enable the duty cycle correction, which keeps the duty cycle at 50% for
+                    /// odd and fractional divisors
+                    pub fn set_duty_cycle_correction(&mut self, enable: bool) {
+                        unsafe { self.shared_dev.get() }
+                            .[<$reg _ctrl>]
+                            .modify(|_, w| w.dc50().bit(enable));
+                    }
+
+                    /// Invert the output, with the output override of the pin
+                    pub fn set_inverted(&mut self, invert: bool) {
+                        self.pin.set_output_override(if invert {
+                            OutputOverride::Invert
+                        } else {
+                            OutputOverride::DontInvert
+                        });
+                    }
+
+                    /// Stop the output and release the pin
+                    ///
+                    /// The output override of the pin is reset, in case the output was inverted.
+                    pub fn free(mut self) -> Pin<$Gpio, FunctionClock> {
+                        self.disable();
+                        self.pin.set_output_override(OutputOverride::DontInvert);
+                        self.pin
+                    }
+                }
+            )*
+        }
+    };
+}
+
+gpout! {
+    GpioOutput0Clock: (configure_gpout0, clk_gpout0, Gpio21, 21),
+    GpioOutput1Clock: (configure_gpout1, clk_gpout1, Gpio23, 23),
+    GpioOutput2Clock: (configure_gpout2, clk_gpout2, Gpio24, 24),
+    GpioOutput3Clock: (configure_gpout3, clk_gpout3, Gpio25, 25),
+}
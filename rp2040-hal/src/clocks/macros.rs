@@ -17,6 +17,12 @@ macro_rules! clocks {
             /// Abstraction layer providing Clock Management.
             pub struct ClocksManager {
                 clocks: CLOCKS,
+                /// PLL_SYS and the frequency of its reference, for [`ClocksManager::set_sys_clock`]
+                pll_sys: Option<(PllSys, Hertz)>,
+                /// PLL_USB, for [`ClocksManager::dormant`]
+                pll_usb: Option<PllUsb>,
+                /// XOSC, for [`ClocksManager::dormant`]
+                xosc: Option<Xosc>,
                 $(
                     #[doc = "`" $name "` field"]
                     pub [<$name:snake>]: $name,
@@ -34,6 +40,9 @@ macro_rules! clocks {
                     let shared_clocks = ShareableClocks::new(&mut clocks_block);
                     ClocksManager {
                         clocks: clocks_block,
+                        pll_sys: None,
+                        pll_usb: None,
+                        xosc: None,
                         $(
                             [<$name:snake>]: $name {
                                 shared_dev: shared_clocks,
@@ -140,7 +149,8 @@ macro_rules! clock {
                         w.src().variant(self.get_default_clock_source())
                     });
 
-                    self.frequency = 12_000_000.Hz(); //TODO Get actual clock source.. Most likely 12 MHz though
+                    self.frequency = self.get_default_frequency();
+                    self.frequency_changed();
 
                     self.await_select(&ChangingClockToken{clock_nr:0, clock: PhantomData::<Self>})
                 }
@@ -222,9 +232,16 @@ macro_rules! clock {
                     // divisor is a safe value.
                     self.set_div(div);
 
-                    // Store the configured frequency
+                    // Store the configured frequency. Read back the divisor, as the fractional
+                    // bits or the upper bits of the integer part may not be implemented.
                     // div contains both the integer part and the fractional part so we need to shift the src_freq equally
-                    self.frequency = (shifted_src_freq / div as u64).try_into().map_err(|_| ClockError::FrequencyToHigh)?;
+                    let actual_div = self.get_div();
+                    self.frequency = (shifted_src_freq / actual_div as u64).try_into().map_err(|_| ClockError::FrequencyToHigh)?;
+                    self.frequency_changed();
+
+                    if actual_div >> 8 != div >> 8 {
+                        return Err(ClockError::DivisorOutOfRange);
+                    }
 
                     Ok(())
                 }
@@ -247,7 +264,7 @@ macro_rules! clock {
         // Just to match proper divisible clocks so we don't have to do something special in configure function
         impl ClockDivision for $name {
             fn set_div(&mut self, _: u32) {}
-            fn get_div(&self) -> u32 {1}
+            fn get_div(&self) -> u32 {1 << 8}
         }
 
         stoppable_clock!($name, $reg);
@@ -368,10 +385,10 @@ macro_rules! stoppable_clock {
                     if (self.frequency > 0u32.Hz()) {
                         // Delay for 3 cycles of the target clock, for ENABLE propagation.
                         // Note XOSC_COUNT is not helpful here because XOSC is not
-                        // necessarily running, nor is timer... so, 3 cycles per loop:
-                        let sys_freq = 125_000_000; // TODO get actual sys_clk frequency
+                        // necessarily running, nor is timer... so, use the system clock:
+                        let sys_freq = SYSTEM_CLOCK_HZ.load(Ordering::Relaxed);
                         let delay_cyc = sys_freq / self.frequency.integer() + 1u32;
-                        cortex_m::asm::delay(delay_cyc);
+                        cortex_m::asm::delay(3 * delay_cyc);
                     }
 
                     // Set aux mux first, and then glitchless mux if this self has one
@@ -385,8 +402,14 @@ macro_rules! stoppable_clock {
                     // divisor is a safe value.
                     self.set_div(div);
 
-                    // Store the configured frequency
-                    self.frequency = (shifted_src_freq / div as u64).try_into().map_err(|_| ClockError::FrequencyToHigh)?;
+                    // Store the configured frequency. Read back the divisor, as the fractional
+                    // bits or the upper bits of the integer part may not be implemented.
+                    let actual_div = self.get_div();
+                    self.frequency = (shifted_src_freq / actual_div as u64).try_into().map_err(|_| ClockError::FrequencyToHigh)?;
+
+                    if actual_div >> 8 != div >> 8 {
+                        return Err(ClockError::DivisorOutOfRange);
+                    }
 
                     Ok(())
                 }
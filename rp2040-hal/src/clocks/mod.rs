@@ -64,22 +64,36 @@
 use crate::{
     pll::{
         common_configs::{PLL_SYS_125MHZ, PLL_USB_48MHZ},
-        setup_pll_blocking, Error as PllError, Locked, PhaseLockedLoop,
+        power_down as power_down_pll, setup_pll_blocking, Disabled, Error as PllError, Locked,
+        PLLConfig, PhaseLockedLoop,
     },
+    rosc::{Enabled, RingOscillator},
     typelevel::Sealed,
-    watchdog::Watchdog,
+    vreg::{Vreg, VregVoltage},
+    watchdog::{TickError, Watchdog},
     xosc::{setup_xosc_blocking, CrystalOscillator, Error as XoscError, Stable},
 };
 use core::{
     convert::{Infallible, TryInto},
     marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
 };
 use embedded_time::rate::*;
-use pac::{CLOCKS, PLL_SYS, PLL_USB, RESETS, XOSC};
+use pac::{CLOCKS, PLL_SYS, PLL_USB, RESETS, ROSC, XOSC};
 
 #[macro_use]
 mod macros;
 mod clock_sources;
+mod frequency_counter;
+mod gate;
+mod gpout;
+
+pub use frequency_counter::{FcError, FcSrc, FrequencyCounter};
+pub use gate::{
+    is_memory_powered_down, power_down_memory, power_up_memory, ClockGate, ClockGateMask,
+    ClockGated, Memory,
+};
+pub use gpout::GpioOutput;
 
 use clock_sources::PllSys;
 
@@ -107,6 +121,16 @@ pub enum ClockError {
     CantIncreaseFreq,
     /// The desired frequency is to high (would overflow an u32)
     FrequencyToHigh,
+    /// PLL_SYS is not managed by the [`ClocksManager`], see [`ClocksManager::manage_pll_sys`]
+    PllSysNotManaged,
+    /// The PLL configuration is invalid
+    PllError(PllError),
+    /// The divisor is out of the range of the divider
+    DivisorOutOfRange,
+    /// The XOSC is not managed by the [`ClocksManager`], see [`ClocksManager::manage_xosc`]
+    XoscNotManaged,
+    /// The watchdog tick can't be generated from clk_ref
+    TickError(TickError),
 }
 
 /// For clocks
@@ -118,6 +142,25 @@ pub trait Clock: Sealed + Sized {
     fn freq(&self) -> Hertz;
 
     /// Configure this clock based on a clock source and desired frequency
+    ///
+    /// Only valid sources for this clock implement [`ValidSrc`]. clk_sys and clk_ref are
+    /// switched to their glitchless default source while the aux source is changed, the other
+    /// clocks are stopped while their source is changed.
+    ///
+    /// The frequency returned by [`freq`](Self::freq) is computed from the divisor which was
+    /// actually set, as some clocks only have integer divisors. If the divisor does not fit, e.g.
+    /// because clk_peri can't divide at all, [`ClockError::DivisorOutOfRange`] is returned.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{clocks::{Clock, ClocksManager}, pac, xosc::setup_xosc_blocking};
+    /// use embedded_time::rate::*;
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut clocks = ClocksManager::new(peripherals.CLOCKS);
+    /// let xosc = setup_xosc_blocking(peripherals.XOSC, 12_000_000u32.Hz()).ok().unwrap();
+    ///
+    /// // Keep the peripheral clock independent of PLL_SYS
+    /// clocks.peripheral_clock.configure_clock(&xosc, xosc.operating_frequency()).ok().unwrap();
+    /// ```
     fn configure_clock<S: ValidSrc<Self>>(
         &mut self,
         src: &S,
@@ -245,11 +288,25 @@ clocks! {
     }
 }
 
+/// Frequency of clk_sys, to compute delays in cycles
+pub(crate) static SYSTEM_CLOCK_HZ: AtomicU32 = AtomicU32::new(12_000_000);
+
+/// Frequency of clk_ref, which clk_sys runs from by default
+static REFERENCE_CLOCK_HZ: AtomicU32 = AtomicU32::new(12_000_000);
+
 impl SystemClock {
     fn get_default_clock_source(&self) -> pac::clocks::clk_sys_ctrl::SRC_A {
         pac::clocks::clk_sys_ctrl::SRC_A::CLK_REF
     }
 
+    fn get_default_frequency(&self) -> Hertz {
+        Hertz(REFERENCE_CLOCK_HZ.load(Ordering::Relaxed))
+    }
+
+    fn frequency_changed(&self) {
+        SYSTEM_CLOCK_HZ.store(self.frequency.integer(), Ordering::Relaxed);
+    }
+
     fn get_aux_source(&self) -> pac::clocks::clk_sys_ctrl::SRC_A {
         pac::clocks::clk_sys_ctrl::SRC_A::CLKSRC_CLK_SYS_AUX
     }
@@ -260,6 +317,15 @@ impl ReferenceClock {
         pac::clocks::clk_ref_ctrl::SRC_A::ROSC_CLKSRC_PH
     }
 
+    fn get_default_frequency(&self) -> Hertz {
+        // The ROSC varies with input voltage etc, but 12 MHz seems to be a good value
+        12_000_000.Hz()
+    }
+
+    fn frequency_changed(&self) {
+        REFERENCE_CLOCK_HZ.store(self.frequency.integer(), Ordering::Relaxed);
+    }
+
     fn get_aux_source(&self) -> pac::clocks::clk_ref_ctrl::SRC_A {
         pac::clocks::clk_ref_ctrl::SRC_A::CLKSRC_CLK_REF_AUX
     }
@@ -299,12 +365,405 @@ impl ClocksManager {
             .configure_clock(&self.system_clock, self.system_clock.freq())
     }
 
+    /// Hand PLL_SYS to the clocks manager, so the system clock can be changed with
+    /// [`set_sys_clock`](Self::set_sys_clock)
+    ///
+    /// `xosc_frequency` is the frequency of the crystal oscillator PLL_SYS runs from.
+    /// [`init_clocks_and_plls`] already does this.
+    pub fn manage_pll_sys(
+        &mut self,
+        pll_sys: PhaseLockedLoop<Locked, PLL_SYS>,
+        xosc_frequency: Hertz,
+    ) {
+        self.pll_sys = Some((pll_sys, xosc_frequency));
+    }
+
+    /// Hand PLL_USB to the clocks manager, so it is stopped and restarted by
+    /// [`dormant`](Self::dormant)
+    ///
+    /// [`init_clocks_and_plls`] already does this.
+    pub fn manage_pll_usb(&mut self, pll_usb: PhaseLockedLoop<Locked, PLL_USB>) {
+        self.pll_usb = Some(pll_usb);
+    }
+
+    /// Hand the XOSC to the clocks manager, so the chip can enter DORMANT state with
+    /// [`dormant`](Self::dormant)
+    ///
+    /// [`init_clocks_and_plls`] already does this.
+    pub fn manage_xosc(&mut self, xosc: CrystalOscillator<Stable>) {
+        self.xosc = Some(xosc);
+    }
+
+    /// Change the frequency of PLL_SYS and run clk_sys from it, returning the new system clock
+    /// frequency
+    ///
+    /// clk_sys is switched to clk_ref while PLL_SYS is reconfigured, and clk_sys is not divided
+    /// afterwards. If clk_peri runs from clk_sys, its frequency changes too.
+    ///
+    /// The RP2040 is only specified up to 133 MHz at the default core voltage of 1.10 V. If `vreg`
+    /// is given, the core voltage is changed along with the frequency by
+    /// [`Vreg::with_voltage`]: to at least 1.20 V above 133 MHz, and back to 1.10 V otherwise.
+    ///
+    /// Peripherals and timers which were configured with the old frequency keep their dividers,
+    /// so e.g. UART baud rates, SPI and I2C clocks and `cortex_m::delay::Delay` are wrong
+    /// afterwards. They have to be configured again with the frequencies of
+    /// [`system_clock`](Self::system_clock) and [`peripheral_clock`](Self::peripheral_clock).
+    ///
+    /// If the configuration is invalid, the clocks are not changed. If clk_sys can't be switched to
+    /// PLL_SYS afterwards, it is left running from clk_ref, and the frequencies of
+    /// [`system_clock`](Self::system_clock) and [`peripheral_clock`](Self::peripheral_clock) are
+    /// updated accordingly.
+    ///
+    /// ```no_run
+    /// use embedded_time::rate::*;
+    /// use rp2040_hal::{clocks::{init_clocks_and_plls, Clock}, pac, pll::PLLConfig, vreg::Vreg, watchdog::Watchdog};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+    /// let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+    ///
+    /// // 12 MHz * 100 = 1200 MHz / 6 / 1 = 200 MHz
+    /// let config = PLLConfig { vco_freq: Megahertz(1200), refdiv: 1, post_div1: 6, post_div2: 1 };
+    /// let mut vreg = Vreg::new(peripherals.VREG_AND_CHIP_RESET);
+    /// let sys_freq = clocks.set_sys_clock(config, Some(&mut vreg), &mut peripherals.RESETS).ok().unwrap();
+    /// assert_eq!(clocks.peripheral_clock.freq(), sys_freq);
+    /// ```
+    pub fn set_sys_clock<R: Rate>(
+        &mut self,
+        config: PLLConfig<R>,
+        vreg: Option<&mut Vreg>,
+        resets: &mut RESETS,
+    ) -> Result<Hertz, ClockError>
+    where
+        R: Into<Hertz<u64>>,
+    {
+        const MAX_FREQ_DEFAULT_VOLTAGE: Hertz = Hertz(133_000_000);
+
+        let xosc_frequency = match &self.pll_sys {
+            Some((_, xosc_frequency)) => *xosc_frequency,
+            None => return Err(ClockError::PllSysNotManaged),
+        };
+        let state =
+            Disabled::from_config(xosc_frequency.into(), config).map_err(ClockError::PllError)?;
+
+        match vreg {
+            Some(vreg) => {
+                let voltage = if state.frequency() > MAX_FREQ_DEFAULT_VOLTAGE {
+                    vreg.get_voltage().max(VregVoltage::V1_20)
+                } else {
+                    VregVoltage::V1_10
+                };
+                vreg.with_voltage(voltage, || {
+                    self.switch_pll_sys(state, xosc_frequency, resets)
+                })
+            }
+            None => self.switch_pll_sys(state, xosc_frequency, resets),
+        }
+    }
+
+    fn switch_pll_sys(
+        &mut self,
+        state: Disabled,
+        xosc_frequency: Hertz,
+        resets: &mut RESETS,
+    ) -> Result<Hertz, ClockError> {
+        // Run clk_sys from clk_ref while PLL_SYS is stopped
+        let peri_from_sys = self.clocks.clk_peri_ctrl.read().auxsrc().is_clk_sys();
+        nb::block!(self.system_clock.reset_source_await()).unwrap();
+
+        let (pll_sys, _) = self.pll_sys.take().unwrap();
+        let initialized_pll = PhaseLockedLoop::with_state(pll_sys.free(), state).initialize(resets);
+        let locked_pll_token = nb::block!(initialized_pll.await_lock()).unwrap();
+        let pll_sys = initialized_pll.get_locked(locked_pll_token);
+
+        let result = self
+            .system_clock
+            .configure_clock(&pll_sys, pll_sys.get_freq());
+        self.pll_sys = Some((pll_sys, xosc_frequency));
+        if result.is_err() {
+            // Leave clk_sys on clk_ref, undivided, so its stored frequency matches the hardware
+            nb::block!(self.system_clock.reset_source_await()).unwrap();
+            self.system_clock.set_div(1 << 8);
+        }
+        if peri_from_sys {
+            self.peripheral_clock.frequency = self.system_clock.freq();
+        }
+
+        result.map(|()| self.system_clock.freq())
+    }
+
+    /// Enable the resuscitation of clk_sys
+    ///
+    /// If clk_sys stops for `timeout` cycles of clk_ref, e.g. because PLL_SYS lost its lock, the
+    /// hardware switches clk_sys to clk_ref. This can be detected with
+    /// [`resus_happened`](Self::resus_happened), or by an interrupt, see
+    /// [`enable_resus_interrupt`](Self::enable_resus_interrupt).
+    pub fn enable_resus(&mut self, timeout: u8) {
+        self.clocks
+            .clk_sys_resus_ctrl
+            .write(|w| unsafe { w.timeout().bits(timeout).enable().set_bit() });
+    }
+
+    /// Disable the resuscitation of clk_sys
+    pub fn disable_resus(&mut self) {
+        self.clocks
+            .clk_sys_resus_ctrl
+            .modify(|_, w| w.enable().clear_bit());
+    }
+
+    /// Did the resuscitation switch clk_sys to clk_ref?
+    ///
+    /// The frequency stored in [`system_clock`](Self::system_clock) is wrong afterwards. Configure
+    /// clk_sys again, e.g. with [`set_sys_clock`](Self::set_sys_clock), before calling
+    /// [`clear_resus`](Self::clear_resus).
+    pub fn resus_happened(&self) -> bool {
+        self.clocks
+            .clk_sys_resus_status
+            .read()
+            .resussed()
+            .bit_is_set()
+    }
+
+    /// Clear the resuscitation status, which also clears the interrupt
+    ///
+    /// This has to be called after clk_sys was given a valid source again. Otherwise the
+    /// resuscitation fires again right away.
+    pub fn clear_resus(&mut self) {
+        self.clocks
+            .clk_sys_resus_ctrl
+            .modify(|_, w| w.clear().set_bit());
+        self.clocks
+            .clk_sys_resus_ctrl
+            .modify(|_, w| w.clear().clear_bit());
+    }
+
+    /// Raise the CLOCKS_IRQ interrupt when the resuscitation fires
+    ///
+    /// The interrupt stays pending until [`clear_resus`](Self::clear_resus) is called, so the
+    /// interrupt handler has to either reconfigure clk_sys and clear it, or mask the interrupt.
+    pub fn enable_resus_interrupt(&mut self) {
+        self.clocks.inte.modify(|_, w| w.clk_sys_resus().set_bit());
+    }
+
+    /// Stop raising the CLOCKS_IRQ interrupt when the resuscitation fires
+    pub fn disable_resus_interrupt(&mut self) {
+        self.clocks
+            .inte
+            .modify(|_, w| w.clk_sys_resus().clear_bit());
+    }
+
+    /// Select the clocks which keep running while both cores sleep
+    ///
+    /// The configuration takes effect when both cores execute `WFI` or `WFE` with
+    /// `SCR.SLEEPDEEP` set, e.g. in [`sleep`](Self::sleep).
+    pub fn configure_sleep_enable(&mut self, config: SleepConfig) {
+        self.clocks
+            .sleep_en0
+            .write(|w| unsafe { w.bits(config.sleep_en0) });
+        self.clocks
+            .sleep_en1
+            .write(|w| unsafe { w.bits(config.sleep_en1) });
+    }
+
+    /// The clocks which keep running while both cores sleep, see
+    /// [`configure_sleep_enable`](Self::configure_sleep_enable)
+    pub fn sleep_config(&self) -> SleepConfig {
+        SleepConfig::from_bits(
+            self.clocks.sleep_en0.read().bits(),
+            self.clocks.sleep_en1.read().bits(),
+        )
+    }
+
+    /// Run clk_rtc from the XOSC divided by 256, e.g. 46875Hz from a 12MHz crystal
+    ///
+    /// Unlike the default PLL_USB source, this keeps the RTC counting if PLL_USB is powered down
+    /// to save power while sleeping. Configure clk_rtc before creating the
+    /// [`RealTimeClock`](crate::rtc::RealTimeClock), which programs its divider from the
+    /// frequency of clk_rtc.
+    pub fn rtc_clock_from_xosc(&mut self) -> Result<(), ClockError> {
+        let xosc = self.xosc.as_ref().ok_or(ClockError::XoscNotManaged)?;
+        let freq = xosc.get_freq().integer() / 256;
+        self.rtc_clock.configure_clock(xosc, freq.Hz())
+    }
+
+    /// Sleep until an interrupt is pending, gating the clocks not selected by
+    /// [`configure_sleep_enable`](Self::configure_sleep_enable)
+    ///
+    /// The clocks are only gated if the other core sleeps as well. The peripheral whose interrupt
+    /// should wake the core has to keep its clock, and the gated clocks are restored when it
+    /// wakes up. A pending interrupt which is enabled in the NVIC wakes the core even while
+    /// interrupts are disabled, so the handler can be skipped by sleeping in a critical section.
+    /// To sleep until an interrupt handler sets a flag, see [`sleep`](crate::sleep).
+    ///
+    /// To wake up from an RTC alarm, keep the RTC clocks with [`SleepConfig::with_rtc`]:
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{clocks::{init_clocks_and_plls, SleepConfig}, pac, rtc::{DateTime, DateTimeFilter, RealTimeClock}, watchdog::Watchdog};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut core = pac::CorePeripherals::take().unwrap();
+    /// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+    /// let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+    /// # let initial_date: DateTime = unsafe { core::mem::zeroed() };
+    ///
+    /// clocks.rtc_clock_from_xosc().ok().unwrap();
+    /// let mut rtc = RealTimeClock::new(peripherals.RTC, &clocks.rtc_clock, &mut peripherals.RESETS, initial_date).unwrap();
+    /// rtc.schedule_alarm(DateTimeFilter::default().second(0));
+    /// rtc.enable_interrupt();
+    /// unsafe { pac::NVIC::unmask(pac::Interrupt::RTC_IRQ) };
+    ///
+    /// clocks.configure_sleep_enable(SleepConfig::none().with_rtc());
+    /// cortex_m::interrupt::free(|_| {
+    ///     clocks.sleep(&mut core.SCB);
+    ///     rtc.clear_interrupt();
+    ///     pac::NVIC::unpend(pac::Interrupt::RTC_IRQ);
+    /// });
+    /// ```
+    pub fn sleep(&mut self, scb: &mut cortex_m::peripheral::SCB) {
+        scb.set_sleepdeep();
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+    }
+
+    /// Stop all oscillators until a GPIO (see `Pin::set_dormant_wake_enabled`) or the RTC
+    /// running from an external clock wakes the chip
+    ///
+    /// Before entering DORMANT state, clk_ref is switched to the XOSC, clk_sys to clk_ref and
+    /// the PLLs managed by the clocks manager are powered down. After waking up, the XOSC is
+    /// awaited, the PLLs are locked again and clk_sys is switched back to PLL_SYS if it ran from
+    /// it. The other clocks keep their configuration and resume once their source runs again.
+    ///
+    /// The ROSC is not stopped, as the clocks manager does not own it. It can be put into
+    /// DORMANT state as well, or disabled, to save a little more power.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{clocks::init_clocks_and_plls, gpio::{Interrupt, Pins}, pac, watchdog::Watchdog, Sio};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+    /// let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+    /// let sio = Sio::new(peripherals.SIO);
+    /// let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+    ///
+    /// let mut button = pins.gpio14.into_pull_up_input();
+    /// button.set_dormant_wake_enabled(Interrupt::EdgeLow, true);
+    /// clocks.dormant().ok().unwrap();
+    /// button.clear_interrupt(Interrupt::EdgeLow);
+    /// ```
+    pub fn dormant(&mut self) -> Result<(), ClockError> {
+        let xosc = self.xosc.take().ok_or(ClockError::XoscNotManaged)?;
+
+        let sys_ctrl = self.clocks.clk_sys_ctrl.read();
+        let sys_from_pll =
+            sys_ctrl.src().is_clksrc_clk_sys_aux() && sys_ctrl.auxsrc().is_clksrc_pll_sys();
+        let sys_freq = self.system_clock.freq();
+        let peri_from_sys = self.clocks.clk_peri_ctrl.read().auxsrc().is_clk_sys();
+
+        // Run clk_ref and clk_sys from the XOSC, so nothing depends on the PLLs
+        if !self.clocks.clk_ref_ctrl.read().src().is_xosc_clksrc() {
+            let result = self.reference_clock.configure_clock(&xosc, xosc.get_freq());
+            if let Err(e) = result {
+                self.xosc = Some(xosc);
+                return Err(e);
+            }
+        }
+        nb::block!(self.system_clock.reset_source_await()).unwrap();
+        if let Some((pll_sys, _)) = &self.pll_sys {
+            pll_sys.power_down();
+        }
+        if let Some(pll_usb) = &self.pll_usb {
+            pll_usb.power_down();
+        }
+
+        // safety: nothing but clk_ref and clk_sys runs from the XOSC, and they keep running
+        // from it after waking up
+        let xosc = unsafe { xosc.dormant() }.wake();
+        let token = nb::block!(xosc.await_stabilization()).unwrap();
+        let xosc = xosc.get_stable(token);
+
+        if let Some(pll_usb) = &self.pll_usb {
+            pll_usb.power_up_blocking();
+        }
+        let mut result = Ok(());
+        if let Some((pll_sys, _)) = &self.pll_sys {
+            pll_sys.power_up_blocking();
+            if sys_from_pll {
+                result = self.system_clock.configure_clock(pll_sys, sys_freq);
+            }
+        }
+        if peri_from_sys {
+            self.peripheral_clock.frequency = self.system_clock.freq();
+        }
+        self.xosc = Some(xosc);
+
+        result
+    }
+
     /// Releases the CLOCKS block
     pub fn free(self) -> CLOCKS {
         self.clocks
     }
 }
 
+/// Clocks which keep running while both cores sleep, see [`ClocksManager::configure_sleep_enable`]
+///
+/// The bits correspond to the SLEEP_EN0 and SLEEP_EN1 registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepConfig {
+    sleep_en0: u32,
+    sleep_en1: u32,
+}
+
+impl SleepConfig {
+    const CLK_RTC_RTC: u32 = 1 << 21;
+    const CLK_SYS_RTC: u32 = 1 << 22;
+    const CLK_SYS_TIMER: u32 = 1 << 5;
+    const CLK_SYS_USBCTRL: u32 = 1 << 10;
+    const CLK_USB_USBCTRL: u32 = 1 << 11;
+
+    /// Gate all clocks while sleeping
+    pub const fn none() -> Self {
+        SleepConfig {
+            sleep_en0: 0,
+            sleep_en1: 0,
+        }
+    }
+
+    /// Keep the clocks given by the raw SLEEP_EN0 and SLEEP_EN1 register values
+    pub const fn from_bits(sleep_en0: u32, sleep_en1: u32) -> Self {
+        SleepConfig {
+            sleep_en0,
+            sleep_en1,
+        }
+    }
+
+    /// Keep the timer running, so its alarms can wake the core
+    pub const fn with_timer(self) -> Self {
+        SleepConfig {
+            sleep_en0: self.sleep_en0,
+            sleep_en1: self.sleep_en1 | Self::CLK_SYS_TIMER,
+        }
+    }
+
+    /// Keep the USB controller running, so a resume of a suspended bus can wake the core
+    pub const fn with_usb(self) -> Self {
+        SleepConfig {
+            sleep_en0: self.sleep_en0,
+            sleep_en1: self.sleep_en1 | Self::CLK_SYS_USBCTRL | Self::CLK_USB_USBCTRL,
+        }
+    }
+
+    /// Keep the RTC running, so its alarm can wake the core
+    pub const fn with_rtc(self) -> Self {
+        SleepConfig {
+            sleep_en0: self.sleep_en0 | Self::CLK_RTC_RTC | Self::CLK_SYS_RTC,
+            sleep_en1: self.sleep_en1,
+        }
+    }
+
+    /// The raw SLEEP_EN0 and SLEEP_EN1 register values
+    pub const fn bits(&self) -> (u32, u32) {
+        (self.sleep_en0, self.sleep_en1)
+    }
+}
+
 /// Possible init errors
 pub enum InitError {
     /// Something went wrong setting up the Xosc
@@ -352,5 +811,347 @@ pub fn init_clocks_and_plls(
     clocks
         .init_default(&xosc, &pll_sys, &pll_usb)
         .map_err(InitError::ClockError)?;
+    clocks.manage_pll_sys(pll_sys, xosc.operating_frequency());
+    clocks.manage_pll_usb(pll_usb);
+    clocks.manage_xosc(xosc);
     Ok(clocks)
 }
+
+/// Source of CLK_ADC, see [`ClocksConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AdcSource {
+    Disabled,
+    Xosc,
+    PllUsb,
+}
+
+/// Builder for a clock setup which only powers the blocks it needs
+///
+/// Unlike [`init_clocks_and_plls`], a PLL which is not requested is powered down and held in
+/// reset, and the clocks which depend on it are stopped. The frequencies reported by the returned
+/// [`ClocksManager`] are the ones actually achieved.
+///
+/// The defaults are the same as [`init_clocks_and_plls`]: CLK_SYS at 125MHz from PLL_SYS, CLK_USB
+/// and CLK_ADC at 48MHz from PLL_USB. CLK_RTC runs from PLL_USB if it is enabled, from the XOSC
+/// divided by 256 otherwise.
+///
+/// ```no_run
+/// use embedded_time::rate::*;
+/// use rp2040_hal::{clocks::ClocksConfig, pac, pll::common_configs::PLL_SYS_133MHZ, watchdog::Watchdog};
+///
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+/// let clocks = ClocksConfig::new(12_000_000u32.Hz())
+///     .sys_from_pll(PLL_SYS_133MHZ)
+///     .usb_disabled()
+///     .adc_from_xosc()
+///     .init(
+///         peripherals.XOSC,
+///         peripherals.CLOCKS,
+///         peripherals.PLL_SYS,
+///         peripherals.PLL_USB,
+///         &mut peripherals.RESETS,
+///         &mut watchdog,
+///     )
+///     .ok()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ClocksConfig {
+    xosc_frequency: Hertz,
+    /// `None` if CLK_SYS runs from the XOSC
+    pll_sys: Result<Option<PLLConfig<Hertz>>, PllError>,
+    /// `None` if PLL_USB is disabled
+    pll_usb: Result<Option<PLLConfig<Hertz>>, PllError>,
+    adc: AdcSource,
+}
+
+impl ClocksConfig {
+    /// Start from the default configuration, with a crystal of the given frequency
+    ///
+    /// The crystal must be between 1 and 15MHz, and between 5 and 15MHz if it drives a PLL.
+    pub fn new(xosc_frequency: Hertz) -> Self {
+        ClocksConfig {
+            xosc_frequency,
+            pll_sys: Self::convert(PLL_SYS_125MHZ).map(Some),
+            pll_usb: Self::convert(PLL_USB_48MHZ).map(Some),
+            adc: AdcSource::PllUsb,
+        }
+    }
+
+    fn convert<R: Rate>(config: PLLConfig<R>) -> Result<PLLConfig<Hertz>, PllError>
+    where
+        R: Into<Hertz<u64>>,
+    {
+        let vco_freq: Hertz<u64> = config.vco_freq.into();
+        Ok(PLLConfig {
+            vco_freq: vco_freq.try_into().map_err(|_| PllError::BadArgument)?,
+            refdiv: config.refdiv,
+            post_div1: config.post_div1,
+            post_div2: config.post_div2,
+        })
+    }
+
+    fn find(&self, target: Hertz) -> Result<Option<PLLConfig<Hertz>>, PllError> {
+        PLLConfig::from_target(self.xosc_frequency, target)
+            .map(Some)
+            .ok_or(PllError::RefFreqOutOfRange)
+    }
+
+    /// Run CLK_SYS from PLL_SYS with the given configuration
+    pub fn sys_from_pll<R: Rate>(self, config: PLLConfig<R>) -> Self
+    where
+        R: Into<Hertz<u64>>,
+    {
+        ClocksConfig {
+            pll_sys: Self::convert(config).map(Some),
+            ..self
+        }
+    }
+
+    /// Run CLK_SYS from PLL_SYS, at the frequency closest to `target` the crystal allows
+    ///
+    /// The PLL dividers, including the reference divider, are derived from the crystal frequency
+    /// with [`PLLConfig::from_target`].
+    pub fn sys_pll_frequency(self, target: Hertz) -> Self {
+        ClocksConfig {
+            pll_sys: self.find(target),
+            ..self
+        }
+    }
+
+    /// Run CLK_SYS directly from the XOSC, and power down PLL_SYS
+    pub fn sys_from_xosc(self) -> Self {
+        ClocksConfig {
+            pll_sys: Ok(None),
+            ..self
+        }
+    }
+
+    /// Run CLK_USB from PLL_USB with the given configuration
+    ///
+    /// The USB controller needs 48MHz.
+    pub fn usb_from_pll<R: Rate>(self, config: PLLConfig<R>) -> Self
+    where
+        R: Into<Hertz<u64>>,
+    {
+        ClocksConfig {
+            pll_usb: Self::convert(config).map(Some),
+            ..self
+        }
+    }
+
+    /// Run CLK_USB from PLL_USB at 48MHz, or as close as the crystal allows
+    pub fn usb_pll_48mhz(self) -> Self {
+        ClocksConfig {
+            pll_usb: self.find(48_000_000u32.Hz()),
+            ..self
+        }
+    }
+
+    /// Power down PLL_USB and stop CLK_USB
+    ///
+    /// CLK_ADC is stopped too if it was to run from PLL_USB.
+    pub fn usb_disabled(self) -> Self {
+        ClocksConfig {
+            pll_usb: Ok(None),
+            ..self
+        }
+    }
+
+    /// Run CLK_ADC from PLL_USB (the default)
+    pub fn adc_from_pll_usb(self) -> Self {
+        ClocksConfig {
+            adc: AdcSource::PllUsb,
+            ..self
+        }
+    }
+
+    /// Run CLK_ADC from the XOSC
+    ///
+    /// The ADC is specified for 48MHz, so it samples slower.
+    pub fn adc_from_xosc(self) -> Self {
+        ClocksConfig {
+            adc: AdcSource::Xosc,
+            ..self
+        }
+    }
+
+    /// Stop CLK_ADC
+    pub fn adc_disabled(self) -> Self {
+        ClocksConfig {
+            adc: AdcSource::Disabled,
+            ..self
+        }
+    }
+
+    /// Start the XOSC and the requested PLLs, and configure the clocks
+    pub fn init(
+        self,
+        xosc_dev: XOSC,
+        clocks_dev: CLOCKS,
+        pll_sys_dev: PLL_SYS,
+        pll_usb_dev: PLL_USB,
+        resets: &mut RESETS,
+        watchdog: &mut Watchdog,
+    ) -> Result<ClocksManager, InitError> {
+        let pll_sys_config = self.pll_sys.map_err(InitError::PllError)?;
+        let pll_usb_config = self.pll_usb.map_err(InitError::PllError)?;
+
+        let xosc =
+            setup_xosc_blocking(xosc_dev, self.xosc_frequency).map_err(InitError::XoscErr)?;
+        let xosc_frequency = xosc.operating_frequency();
+
+        // Configure watchdog tick generation to tick over every microsecond
+        let xosc_mhz = (xosc_frequency.integer() + 500_000) / 1_000_000;
+        watchdog.enable_tick_generation(xosc_mhz as u8);
+
+        let mut clocks = ClocksManager::new(clocks_dev);
+
+        let pll_sys = match pll_sys_config {
+            Some(config) => Some(
+                setup_pll_blocking(
+                    pll_sys_dev,
+                    xosc_frequency.into(),
+                    config,
+                    &mut clocks,
+                    resets,
+                )
+                .map_err(InitError::PllError)?,
+            ),
+            None => {
+                nb::block!(clocks.system_clock.reset_source_await()).unwrap();
+                power_down_pll(&pll_sys_dev, resets);
+                None
+            }
+        };
+        let pll_usb = match pll_usb_config {
+            Some(config) => Some(
+                setup_pll_blocking(
+                    pll_usb_dev,
+                    xosc_frequency.into(),
+                    config,
+                    &mut clocks,
+                    resets,
+                )
+                .map_err(InitError::PllError)?,
+            ),
+            None => {
+                power_down_pll(&pll_usb_dev, resets);
+                None
+            }
+        };
+
+        // CLK_REF = XOSC / 1
+        clocks
+            .reference_clock
+            .configure_clock(&xosc, xosc.get_freq())
+            .map_err(InitError::ClockError)?;
+
+        // CLK_SYS = PLL_SYS / 1, or XOSC / 1
+        let sys_result = match &pll_sys {
+            Some(pll_sys) => clocks
+                .system_clock
+                .configure_clock(pll_sys, pll_sys.get_freq()),
+            None => clocks.system_clock.configure_clock(&xosc, xosc.get_freq()),
+        };
+        sys_result.map_err(InitError::ClockError)?;
+
+        match &pll_usb {
+            Some(pll_usb) => {
+                // CLK_USB = PLL_USB / 1
+                clocks
+                    .usb_clock
+                    .configure_clock(pll_usb, pll_usb.get_freq())
+                    .map_err(InitError::ClockError)?;
+
+                // CLK_RTC = PLL_USB / 1024
+                clocks
+                    .rtc_clock
+                    .configure_clock(pll_usb, pll_usb.get_freq() / 1024)
+                    .map_err(InitError::ClockError)?;
+            }
+            None => {
+                clocks.usb_clock.disable();
+
+                // CLK_RTC = XOSC / 256
+                clocks
+                    .rtc_clock
+                    .configure_clock(&xosc, xosc.get_freq() / 256)
+                    .map_err(InitError::ClockError)?;
+            }
+        }
+
+        // CLK_ADC = PLL_USB / 1, XOSC / 1 or stopped
+        match (self.adc, &pll_usb) {
+            (AdcSource::PllUsb, Some(pll_usb)) => clocks
+                .adc_clock
+                .configure_clock(pll_usb, pll_usb.get_freq())
+                .map_err(InitError::ClockError)?,
+            (AdcSource::Xosc, _) => clocks
+                .adc_clock
+                .configure_clock(&xosc, xosc.get_freq())
+                .map_err(InitError::ClockError)?,
+            (AdcSource::Disabled, _) | (AdcSource::PllUsb, None) => clocks.adc_clock.disable(),
+        }
+
+        // CLK_PERI = CLK_SYS
+        clocks
+            .peripheral_clock
+            .configure_clock(&clocks.system_clock, clocks.system_clock.freq())
+            .map_err(InitError::ClockError)?;
+
+        if let Some(pll_sys) = pll_sys {
+            clocks.manage_pll_sys(pll_sys, xosc_frequency);
+        }
+        if let Some(pll_usb) = pll_usb {
+            clocks.manage_pll_usb(pll_usb);
+        }
+        clocks.manage_xosc(xosc);
+        Ok(clocks)
+    }
+}
+
+/// Initialize the clocks from the ring oscillator, without an external crystal
+///
+/// CLK_REF and CLK_SYS both run from the ROSC at its nominal 6.5 MHz, and CLK_PERI runs from
+/// CLK_SYS. The PLLs and the XOSC are left untouched, and CLK_USB, CLK_ADC and CLK_RTC are not
+/// configured, as they need an accurate source.
+///
+/// The ROSC frequency varies with process, voltage and temperature, so every frequency reported by
+/// the returned [`ClocksManager`] is approximate. In particular:
+/// - UART baud rates derived from [`PeripheralClock::freq`] may be too far off to communicate.
+/// - The watchdog tick divisor is rounded from the nominal CLK_REF frequency, so the
+///   [`Timer`](crate::timer::Timer) does not count exact microseconds.
+///
+/// The returned [`RingOscillator`] must be kept enabled while it drives the clocks.
+pub fn init_clocks_from_rosc(
+    rosc_dev: ROSC,
+    clocks_dev: CLOCKS,
+    watchdog: &mut Watchdog,
+) -> Result<(ClocksManager, RingOscillator<Enabled>), ClockError> {
+    let rosc = RingOscillator::new(rosc_dev).initialize();
+    let mut clocks = ClocksManager::new(clocks_dev);
+
+    // CLK_REF = ROSC (~6.5MHz) / 1
+    clocks
+        .reference_clock
+        .configure_clock(&rosc, rosc.get_freq())?;
+
+    // Configure watchdog tick generation to tick over (approximately) every microsecond
+    watchdog
+        .enable_tick_generation_from(&clocks.reference_clock)
+        .map_err(ClockError::TickError)?;
+
+    // CLK_SYS = ROSC (~6.5MHz) / 1
+    clocks
+        .system_clock
+        .configure_clock(&rosc, rosc.get_freq())?;
+
+    // CLK PERI = clk_sys
+    clocks
+        .peripheral_clock
+        .configure_clock(&clocks.system_clock, clocks.system_clock.freq())?;
+
+    Ok((clocks, rosc))
+}
@@ -1,3 +1,11 @@
+//! `critical_section` implementation which is sound across both cores
+//!
+//! A critical section masks interrupts on the current core and claims spinlock 31, so the other
+//! core waits at the start of its own critical section. Nested critical sections on the same core
+//! only record that the lock was already owned. While spinning, interrupts are re-enabled (if
+//! they were), so an interrupt handler can still run and enter its own critical section once the
+//! other core is done.
+
 use core::sync::atomic::{AtomicU8, Ordering};
 
 struct RpSpinlockCs;
@@ -11,7 +19,7 @@ const LOCK_UNOWNED: u8 = 0;
 /// Indicates which core owns the lock so that we can call critical_section recursively.
 ///
 /// 0 = no one has the lock, 1 = core0 has the lock, 2 = core1 has the lock
-static mut LOCK_OWNER: AtomicU8 = AtomicU8::new(LOCK_UNOWNED);
+static LOCK_OWNER: AtomicU8 = AtomicU8::new(LOCK_UNOWNED);
 
 /// Marker value to indicate that we already owned the lock when we started the `critical_section`.
 ///
@@ -0,0 +1,338 @@
+//! Bidirectional DMA transfers, for full-duplex buses like SPI
+//!
+//! A full-duplex bus receives a word for each word it sends, so the transmitting and the
+//! receiving channel have to run at the same time, or the RX FIFO overflows. Both channels are
+//! programmed first and then started together with a single write to MULTI_CHAN_TRIGGER.
+//!
+//! ```no_run
+//! use embedded_hal::spi::MODE_0;
+//! use embedded_time::rate::*;
+//! use rp2040_hal::{dma::{bidirectional, DMAExt}, gpio::{FunctionSpi, Pins}, pac, spi::Spi, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let _ = pins.gpio2.into_mode::<FunctionSpi>();
+//! let _ = pins.gpio3.into_mode::<FunctionSpi>();
+//! let _ = pins.gpio4.into_mode::<FunctionSpi>();
+//! let spi = Spi::<_, _, 8>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//!
+//! let tx_buf = cortex_m::singleton!(: [u8; 4] = [0x9f, 0, 0, 0]).unwrap();
+//! let rx_buf = cortex_m::singleton!(: [u8; 4] = [0; 4]).unwrap();
+//! let transfer = bidirectional::Config::new((dma.ch0, dma.ch1), tx_buf, spi, rx_buf).start();
+//! let ((ch0, ch1), tx_buf, spi, rx_buf) = transfer.wait().unwrap();
+//!
+//! // Send and receive through the same buffer
+//! let transfer = bidirectional::Config::new_in_place((ch0, ch1), tx_buf, spi).start();
+//! let ((ch0, ch1), buf, spi) = transfer.wait().unwrap();
+//!
+//! // Only send, dropping the received words
+//! let transfer =
+//!     bidirectional::Config::new((ch0, ch1), buf, spi, bidirectional::DummySink::new()).start();
+//! let ((ch0, ch1), buf, spi, _sink) = transfer.wait().unwrap();
+//! ```
+
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
+
+use super::{
+    ChannelConfig, DmaError, Pace, ReadTarget, SingleChannel, TransferError, Word, WriteTarget,
+};
+use crate::pac::DMA;
+
+/// Word overwritten by all [`DummySink`]s
+static DUMMY: AtomicU32 = AtomicU32::new(0);
+
+/// Destination which discards all words written to it
+///
+/// The address is not incremented, so any number of words can be written.
+pub struct DummySink<WORD: Word> {
+    word: PhantomData<WORD>,
+}
+
+impl<WORD: Word> DummySink<WORD> {
+    /// Create a new sink
+    pub fn new() -> Self {
+        DummySink { word: PhantomData }
+    }
+}
+
+impl<WORD: Word> Default for DummySink<WORD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Safety: The static word is never freed, and is large enough for every word size. Its value is
+// never used, so concurrent writes by several channels don't matter.
+unsafe impl<WORD: Word> WriteTarget for DummySink<WORD> {
+    type TransmittedWord = WORD;
+
+    fn tx_treq() -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (&DUMMY as *const AtomicU32 as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+/// Marker for transfers which store the received words in the transmitted buffer
+///
+/// Each word is only received after it has been sent, so the buffer can be overwritten while
+/// it is sent.
+pub struct InPlace {
+    _private: (),
+}
+
+/// Configuration for a bidirectional DMA transfer
+///
+/// `CH1` sends `FROM` to `BUS`, `CH2` receives from `BUS` into `TO`.
+pub struct Config<CH1, CH2, FROM, BUS, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bus: BUS,
+    to: TO,
+}
+
+impl<CH1, CH2, FROM, BUS, TO, WORD> Config<CH1, CH2, FROM, BUS, TO>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BUS: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration which sends `from` and receives into `to`
+    ///
+    /// As many words are received as are sent, `to` has to be at least as large as `from`. Use
+    /// a [`DummySink`] to drop the received words.
+    pub fn new(ch: (CH1, CH2), from: FROM, bus: BUS, to: TO) -> Self {
+        Config { ch, from, bus, to }
+    }
+
+    /// Start the DMA transfer
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to` is smaller than `from`.
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, BUS, TO> {
+        let (_, count) = self.from.rx_address_count();
+        assert!(
+            self.to.tx_address_count().1 >= count,
+            "The receive buffer is too small"
+        );
+
+        self.ch.0.config(
+            &self.from,
+            &mut self.bus,
+            Pace::PreferSink,
+            false,
+            None,
+            false,
+        );
+        self.ch.1.config(
+            &self.bus,
+            &mut self.to,
+            Pace::PreferSource,
+            false,
+            None,
+            false,
+        );
+        start_both(&mut self.ch, count);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            bus: self.bus,
+            to: self.to,
+        }
+    }
+}
+
+impl<CH1, CH2, BUF, BUS, WORD> Config<CH1, CH2, BUF, BUS, InPlace>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    BUF: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    BUS: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration which sends `buf`, replacing its contents with the received
+    /// words
+    pub fn new_in_place(ch: (CH1, CH2), buf: BUF, bus: BUS) -> Self {
+        Config {
+            ch,
+            from: buf,
+            bus,
+            to: InPlace { _private: () },
+        }
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH1, CH2, BUF, BUS, InPlace> {
+        let (_, count) = self.from.rx_address_count();
+
+        self.ch.0.config(
+            &self.from,
+            &mut self.bus,
+            Pace::PreferSink,
+            false,
+            None,
+            false,
+        );
+        self.ch.1.config(
+            &self.bus,
+            &mut self.from,
+            Pace::PreferSource,
+            false,
+            None,
+            false,
+        );
+        start_both(&mut self.ch, count);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            bus: self.bus,
+            to: self.to,
+        }
+    }
+}
+
+/// Make the receiving channel stop after `count` words, then start both channels
+fn start_both<CH1: SingleChannel, CH2: SingleChannel>(ch: &mut (CH1, CH2), count: u32) {
+    // Both the bus and a `DummySink` are endless, so the receiving channel would never stop
+    ch.1.ch().ch_trans_count.write(|w| unsafe { w.bits(count) });
+
+    // safety: MULTI_CHAN_TRIGGER only affects the channels whose bits are set
+    unsafe { &*DMA::ptr() }
+        .multi_chan_trigger
+        .write(|w| unsafe { w.bits(1 << ch.0.id() | 1 << ch.1.id()) });
+}
+
+/// Instance of a bidirectional DMA transfer
+///
+/// Dropping the transfer aborts it, so the buffers are never accessed by the DMA after they are
+/// released.
+pub struct Transfer<CH1, CH2, FROM, BUS, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    bus: BUS,
+    to: TO,
+}
+
+impl<CH1, CH2, FROM, BUS, TO> Transfer<CH1, CH2, FROM, BUS, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+{
+    /// Have all words been sent and received?
+    pub fn is_done(&self) -> bool {
+        !self.ch.0.is_busy() && !self.ch.1.is_busy()
+    }
+
+    fn wait_both(&mut self) -> Result<(), DmaError> {
+        let result = self.ch.0.wait_done().and_then(|()| self.ch.1.wait_done());
+        if result.is_err() {
+            self.ch.0.abort();
+            self.ch.1.abort();
+        }
+
+        // Make sure the buffers are not read before the DMA finished writing them
+        compiler_fence(Ordering::SeqCst);
+        result
+    }
+
+    fn into_parts(self) -> ((CH1, CH2), FROM, BUS, TO) {
+        let this = core::mem::ManuallyDrop::new(self);
+        // safety: `this` is never used or dropped again, so each field is moved out exactly once
+        unsafe {
+            (
+                core::ptr::read(&this.ch),
+                core::ptr::read(&this.from),
+                core::ptr::read(&this.bus),
+                core::ptr::read(&this.to),
+            )
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, BUS, TO, WORD> Transfer<CH1, CH2, FROM, BUS, TO>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    BUS: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Block until all words have been sent and received, returning the channels, the buffers
+    /// and the bus
+    ///
+    /// If the DMA reports a bus error, both channels are aborted and the error is returned along
+    /// with the channels, the buffers and the bus.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<((CH1, CH2), FROM, BUS, TO), TransferError<((CH1, CH2), FROM, BUS, TO)>> {
+        let result = self.wait_both();
+        let parts = self.into_parts();
+        match result {
+            Ok(()) => Ok(parts),
+            Err(error) => Err(TransferError { error, parts }),
+        }
+    }
+}
+
+impl<CH1, CH2, BUF, BUS, WORD> Transfer<CH1, CH2, BUF, BUS, InPlace>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    BUF: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+    BUS: ReadTarget<ReceivedWord = WORD> + WriteTarget<TransmittedWord = WORD>,
+{
+    /// Block until all words have been sent and received, returning the channels, the buffer and
+    /// the bus
+    ///
+    /// If the DMA reports a bus error, both channels are aborted and the error is returned along
+    /// with the channels, the buffer and the bus.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> Result<((CH1, CH2), BUF, BUS), TransferError<((CH1, CH2), BUF, BUS)>> {
+        let result = self.wait_both();
+        let (ch, buf, bus, InPlace { .. }) = self.into_parts();
+        match result {
+            Ok(()) => Ok((ch, buf, bus)),
+            Err(error) => Err(TransferError {
+                error,
+                parts: (ch, buf, bus),
+            }),
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, BUS, TO> Drop for Transfer<CH1, CH2, FROM, BUS, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+{
+    fn drop(&mut self) {
+        self.ch.0.abort();
+        self.ch.1.abort();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,416 @@
+//! Double-buffered DMA transfers
+//!
+//! Two DMA channels take turns: while one channel works on the current buffer, the next buffer is
+//! queued on the other channel, which is triggered by the first one as soon as it finishes. This
+//! lets the CPU fill or process one buffer while the DMA works on the other, without a gap in
+//! the data stream.
+//!
+//! ```no_run
+//! use rp2040_hal::{adc::Adc, dma::{double_buffer, DMAExt}, gpio::Pins, pac, Sio};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let sio = Sio::new(peripherals.SIO);
+//! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let mut adc = Adc::new(peripherals.ADC, &mut peripherals.RESETS);
+//! let mut adc_pin_0 = pins.gpio26.into_analog_input();
+//! let fifo = adc.build_fifo().set_channel(&mut adc_pin_0).enable_dma().start();
+//!
+//! let rx_buf1 = cortex_m::singleton!(: [u16; 256] = [0; 256]).unwrap();
+//! let mut rx_buf2 = cortex_m::singleton!(: [u16; 256] = [0; 256]).unwrap();
+//! // Capture into the first buffer
+//! let mut transfer =
+//!     double_buffer::Config::new((dma.ch0, dma.ch1), fifo.dma_read_target(), rx_buf1).start();
+//! loop {
+//!     // Queue the second buffer, then wait for the first one to be full
+//!     let (full_buf, next_transfer) = transfer.write_next(rx_buf2).wait().unwrap();
+//!     // ... process `full_buf` while the DMA fills the other buffer ...
+//!     rx_buf2 = full_buf;
+//!     transfer = next_transfer;
+//! }
+//! ```
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    ChannelConfig, DmaError, EndlessReadTarget, EndlessWriteTarget, Pace, ReadTarget,
+    SingleChannel, TransferError, Word, WriteTarget,
+};
+
+/// Configuration for double-buffered DMA transfer
+pub struct Config<CH1: SingleChannel, CH2: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    pace: Pace,
+    bswap: bool,
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Config<CH1, CH2, FROM, TO>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration for a double buffer
+    ///
+    /// The first transfer moves data from `from` to `to`. Either `from` or `to` has to be a
+    /// peripheral (an endless target), and the other side is the buffer that is replaced by the
+    /// next one.
+    pub fn new(ch: (CH1, CH2), from: FROM, to: TO) -> Config<CH1, CH2, FROM, TO> {
+        Config {
+            ch,
+            from,
+            to,
+            pace: Pace::PreferSource,
+            bswap: false,
+        }
+    }
+
+    /// Sets the (preferred) pace for the DMA transfers
+    ///
+    /// Usually, the code will automatically configure the correct pace, but
+    /// peripheral-peripheral transfers require the user to manually select whether the source or
+    /// the sink shall be queried for the pace signal.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Enable/disable byte-swapping for the DMA transfers
+    pub fn bswap(&mut self, bswap: bool) {
+        self.bswap = bswap;
+    }
+
+    /// Start the DMA transfer on the first channel
+    pub fn start(mut self) -> Transfer<CH1, CH2, FROM, TO, ()> {
+        self.ch
+            .0
+            .config(&self.from, &mut self.to, self.pace, self.bswap, None, true);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+            pace: self.pace,
+            bswap: self.bswap,
+            state: (),
+            second_ch: false,
+        }
+    }
+}
+
+/// State of a transfer where the next source buffer has been queued
+pub struct ReadNext<BUF: ReadTarget>(pub BUF);
+/// State of a transfer where the next destination buffer has been queued
+pub struct WriteNext<BUF: WriteTarget>(pub BUF);
+
+/// Instance of a double-buffered DMA transfer
+///
+/// `NEXT` is `()` while no further buffer is queued, or [`ReadNext`]/[`WriteNext`] once the next
+/// buffer has been queued on the other channel. Dropping the transfer aborts both channels.
+pub struct Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    ch: (CH1, CH2),
+    from: FROM,
+    to: TO,
+    pace: Pace,
+    bswap: bool,
+    state: NEXT,
+    second_ch: bool,
+}
+
+impl<CH1, CH2, FROM, TO, NEXT> Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    /// Is the transfer of the current buffer complete?
+    pub fn is_done(&self) -> bool {
+        if self.second_ch {
+            !self.ch.1.is_busy()
+        } else {
+            !self.ch.0.is_busy()
+        }
+    }
+
+    /// Stop issuing transfers, until [`resume`](Transfer::resume) is called
+    pub fn pause(&mut self) {
+        if self.second_ch {
+            self.ch.1.pause();
+        } else {
+            self.ch.0.pause();
+        }
+    }
+
+    /// Continue a paused transfer
+    pub fn resume(&mut self) {
+        if self.second_ch {
+            self.ch.1.resume();
+        } else {
+            self.ch.0.resume();
+        }
+    }
+
+    /// Stop the transfer, returning the channels, the targets and the queued buffer, if any
+    pub fn abort(mut self) -> ((CH1, CH2), FROM, TO, NEXT) {
+        self.abort_both();
+        let (ch, from, to, next, ..) = self.into_parts();
+        (ch, from, to, next)
+    }
+
+    fn abort_both(&mut self) {
+        // Abort the current channel first. If it finishes before it is aborted it starts the
+        // queued channel, which is aborted afterwards.
+        if self.second_ch {
+            self.ch.1.abort();
+            self.ch.0.abort();
+        } else {
+            self.ch.0.abort();
+            self.ch.1.abort();
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn wait_current(&mut self) -> Result<(), DmaError> {
+        let result = if self.second_ch {
+            self.ch.1.wait_done()
+        } else {
+            self.ch.0.wait_done()
+        };
+        if result.is_err() {
+            // The queued channel is not started by a channel which failed, but it might have
+            // been started already
+            self.abort_both();
+        }
+
+        // Make sure the buffers are not read before the DMA finished writing them
+        compiler_fence(Ordering::SeqCst);
+        result
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn into_parts(self) -> ((CH1, CH2), FROM, TO, NEXT, Pace, bool, bool) {
+        let this = core::mem::ManuallyDrop::new(self);
+        // safety: `this` is never used or dropped again, so each field is moved out exactly once
+        unsafe {
+            (
+                core::ptr::read(&this.ch),
+                core::ptr::read(&this.from),
+                core::ptr::read(&this.to),
+                core::ptr::read(&this.state),
+                this.pace,
+                this.bswap,
+                this.second_ch,
+            )
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD> + EndlessWriteTarget,
+{
+    /// Queue the next source buffer, which is transferred to the same destination as soon as
+    /// the current buffer is done
+    pub fn read_next<BUF: ReadTarget<ReceivedWord = WORD>>(
+        self,
+        buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, ReadNext<BUF>> {
+        let (mut ch, from, mut to, (), pace, bswap, second_ch) = self.into_parts();
+
+        // Configure the other channel without starting it, then let the current channel trigger
+        // it when it finishes
+        if second_ch {
+            ch.0.config(&buf, &mut to, pace, bswap, None, false);
+            ch.1.set_chain_to_enabled(&mut ch.0);
+        } else {
+            ch.1.config(&buf, &mut to, pace, bswap, None, false);
+            ch.0.set_chain_to_enabled(&mut ch.1);
+        }
+
+        Transfer {
+            ch,
+            from,
+            to,
+            pace,
+            bswap,
+            state: ReadNext(buf),
+            second_ch,
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, WORD> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    WORD: Word,
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD> + EndlessReadTarget,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Queue the next destination buffer, which is filled from the same source as soon as the
+    /// current buffer is done
+    pub fn write_next<BUF: WriteTarget<TransmittedWord = WORD>>(
+        self,
+        mut buf: BUF,
+    ) -> Transfer<CH1, CH2, FROM, TO, WriteNext<BUF>> {
+        let (mut ch, from, to, (), pace, bswap, second_ch) = self.into_parts();
+
+        // Configure the other channel without starting it, then let the current channel trigger
+        // it when it finishes
+        if second_ch {
+            ch.0.config(&from, &mut buf, pace, bswap, None, false);
+            ch.1.set_chain_to_enabled(&mut ch.0);
+        } else {
+            ch.1.config(&from, &mut buf, pace, bswap, None, false);
+            ch.0.set_chain_to_enabled(&mut ch.1);
+        }
+
+        Transfer {
+            ch,
+            from,
+            to,
+            pace,
+            bswap,
+            state: WriteNext(buf),
+            second_ch,
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO> Transfer<CH1, CH2, FROM, TO, ()>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    /// Block until the transfer is complete, returning the channels and targets
+    ///
+    /// If the DMA reports a bus error, the transfer is aborted and the error is returned along
+    /// with the channels and targets.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> Result<((CH1, CH2), FROM, TO), TransferError<((CH1, CH2), FROM, TO)>> {
+        let result = self.wait_current();
+        let (ch, from, to, (), ..) = self.into_parts();
+        match result {
+            Ok(()) => Ok((ch, from, to)),
+            Err(error) => Err(TransferError {
+                error,
+                parts: (ch, from, to),
+            }),
+        }
+    }
+}
+
+impl<CH1, CH2, FROM, TO, BUF> Transfer<CH1, CH2, FROM, TO, ReadNext<BUF>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+    BUF: ReadTarget,
+{
+    /// Block until the current buffer is done, returning it
+    ///
+    /// The transfer continues with the queued buffer, which can be replaced with
+    /// [`read_next`](Transfer::read_next) on the returned transfer.
+    ///
+    /// If the DMA reports a bus error, both channels are aborted and the error is returned along
+    /// with the channels, the targets and the queued buffer.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<(FROM, Transfer<CH1, CH2, BUF, TO, ()>), TransferError<((CH1, CH2), FROM, TO, BUF)>>
+    {
+        let result = self.wait_current();
+        let (ch, from, to, ReadNext(buf), pace, bswap, second_ch) = self.into_parts();
+        if let Err(error) = result {
+            return Err(TransferError {
+                error,
+                parts: (ch, from, to, buf),
+            });
+        }
+
+        Ok((
+            from,
+            Transfer {
+                ch,
+                from: buf,
+                to,
+                pace,
+                bswap,
+                state: (),
+                second_ch: !second_ch,
+            },
+        ))
+    }
+}
+
+impl<CH1, CH2, FROM, TO, BUF> Transfer<CH1, CH2, FROM, TO, WriteNext<BUF>>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+    BUF: WriteTarget,
+{
+    /// Block until the current buffer is done, returning it
+    ///
+    /// The transfer continues with the queued buffer, which can be replaced with
+    /// [`write_next`](Transfer::write_next) on the returned transfer.
+    ///
+    /// If the DMA reports a bus error, both channels are aborted and the error is returned along
+    /// with the channels, the targets and the queued buffer.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<(TO, Transfer<CH1, CH2, FROM, BUF, ()>), TransferError<((CH1, CH2), FROM, TO, BUF)>>
+    {
+        let result = self.wait_current();
+        let (ch, from, to, WriteNext(buf), pace, bswap, second_ch) = self.into_parts();
+        if let Err(error) = result {
+            return Err(TransferError {
+                error,
+                parts: (ch, from, to, buf),
+            });
+        }
+
+        Ok((
+            to,
+            Transfer {
+                ch,
+                from,
+                to: buf,
+                pace,
+                bswap,
+                state: (),
+                second_ch: !second_ch,
+            },
+        ))
+    }
+}
+
+impl<CH1, CH2, FROM, TO, NEXT> Drop for Transfer<CH1, CH2, FROM, TO, NEXT>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    FROM: ReadTarget,
+    TO: WriteTarget,
+{
+    fn drop(&mut self) {
+        self.abort_both();
+    }
+}
@@ -0,0 +1,860 @@
+//! Direct memory access (DMA)
+//!
+//! See [Chapter 2 Section 5](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) of the datasheet for more details
+//!
+//! The DMA block is split into its 12 channels with [`DMAExt::split`]. Transfers are set up with
+//! the configuration types in the submodules, which take ownership of a channel and of the source
+//! and destination for the duration of the transfer.
+//!
+//! Peripherals that can be used as the source or the destination of a DMA transfer implement
+//! [`ReadTarget`] or [`WriteTarget`], which describe the address, transfer width and DREQ to
+//! configure on the DMA channel. Memory buffers are supported through the
+//! [`ReadBuffer`](embedded_dma::ReadBuffer) and [`WriteBuffer`](embedded_dma::WriteBuffer) traits,
+//! which require the buffer to live for `'static`, so it can't be freed while the DMA accesses it.
+//!
+//! The width of each transfer is given by the word type of the source and the destination, which
+//! have to match. [`FixedSource`], [`RawReadTarget`] and [`RawWriteTarget`] cover sources and
+//! destinations which are not buffers, e.g. to fill memory with a constant value.
+//!
+//! ## Usage
+//!
+//! Copy a buffer in RAM
+//! ```no_run
+//! use rp2040_hal::{dma::{single_buffer, DMAExt}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let from = cortex_m::singleton!(: [u32; 16] = [0x1234_5678; 16]).unwrap();
+//! let to = cortex_m::singleton!(: [u32; 16] = [0; 16]).unwrap();
+//! // Start the copy, and wait for it to finish
+//! let transfer = single_buffer::Config::new(dma.ch0, from, to).start();
+//! let (ch0, from, to) = transfer.wait().unwrap();
+//! assert_eq!(from, to);
+//! ```
+
+use core::marker::PhantomData;
+
+use embedded_dma::{ReadBuffer, WriteBuffer};
+
+use crate::{
+    atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    pac::{self, DMA},
+    resets::SubsystemReset,
+    typelevel::Sealed,
+};
+
+pub mod bidirectional;
+pub mod double_buffer;
+mod pace_timer;
+pub mod scatter_gather;
+pub mod single_buffer;
+mod sniffer;
+
+pub use pace_timer::{PaceTimer, PaceTimerError, PaceTimerId};
+pub use sniffer::{SniffMode, Sniffer};
+
+/// The DREQ value for PIO0's TX FIFO 0
+pub const DREQ_PIO0_TX0: u8 = 0;
+/// The DREQ value for PIO0's TX FIFO 1
+pub const DREQ_PIO0_TX1: u8 = 1;
+/// The DREQ value for PIO0's TX FIFO 2
+pub const DREQ_PIO0_TX2: u8 = 2;
+/// The DREQ value for PIO0's TX FIFO 3
+pub const DREQ_PIO0_TX3: u8 = 3;
+/// The DREQ value for PIO0's RX FIFO 0
+pub const DREQ_PIO0_RX0: u8 = 4;
+/// The DREQ value for PIO0's RX FIFO 1
+pub const DREQ_PIO0_RX1: u8 = 5;
+/// The DREQ value for PIO0's RX FIFO 2
+pub const DREQ_PIO0_RX2: u8 = 6;
+/// The DREQ value for PIO0's RX FIFO 3
+pub const DREQ_PIO0_RX3: u8 = 7;
+/// The DREQ value for PIO1's TX FIFO 0
+pub const DREQ_PIO1_TX0: u8 = 8;
+/// The DREQ value for PIO1's TX FIFO 1
+pub const DREQ_PIO1_TX1: u8 = 9;
+/// The DREQ value for PIO1's TX FIFO 2
+pub const DREQ_PIO1_TX2: u8 = 10;
+/// The DREQ value for PIO1's TX FIFO 3
+pub const DREQ_PIO1_TX3: u8 = 11;
+/// The DREQ value for PIO1's RX FIFO 0
+pub const DREQ_PIO1_RX0: u8 = 12;
+/// The DREQ value for PIO1's RX FIFO 1
+pub const DREQ_PIO1_RX1: u8 = 13;
+/// The DREQ value for PIO1's RX FIFO 2
+pub const DREQ_PIO1_RX2: u8 = 14;
+/// The DREQ value for PIO1's RX FIFO 3
+pub const DREQ_PIO1_RX3: u8 = 15;
+/// The DREQ value for SPI0's TX FIFO
+pub const DREQ_SPI0_TX: u8 = 16;
+/// The DREQ value for SPI0's RX FIFO
+pub const DREQ_SPI0_RX: u8 = 17;
+/// The DREQ value for SPI1's TX FIFO
+pub const DREQ_SPI1_TX: u8 = 18;
+/// The DREQ value for SPI1's RX FIFO
+pub const DREQ_SPI1_RX: u8 = 19;
+/// The DREQ value for UART0's TX FIFO
+pub const DREQ_UART0_TX: u8 = 20;
+/// The DREQ value for UART0's RX FIFO
+pub const DREQ_UART0_RX: u8 = 21;
+/// The DREQ value for UART1's TX FIFO
+pub const DREQ_UART1_TX: u8 = 22;
+/// The DREQ value for UART1's RX FIFO
+pub const DREQ_UART1_RX: u8 = 23;
+/// The DREQ value for PWM Counter 0's Wrap Value
+pub const DREQ_PWM_WRAP0: u8 = 24;
+/// The DREQ value for PWM Counter 1's Wrap Value
+pub const DREQ_PWM_WRAP1: u8 = 25;
+/// The DREQ value for PWM Counter 2's Wrap Value
+pub const DREQ_PWM_WRAP2: u8 = 26;
+/// The DREQ value for PWM Counter 3's Wrap Value
+pub const DREQ_PWM_WRAP3: u8 = 27;
+/// The DREQ value for PWM Counter 4's Wrap Value
+pub const DREQ_PWM_WRAP4: u8 = 28;
+/// The DREQ value for PWM Counter 5's Wrap Value
+pub const DREQ_PWM_WRAP5: u8 = 29;
+/// The DREQ value for PWM Counter 6's Wrap Value
+pub const DREQ_PWM_WRAP6: u8 = 30;
+/// The DREQ value for PWM Counter 7's Wrap Value
+pub const DREQ_PWM_WRAP7: u8 = 31;
+/// The DREQ value for I2C0's TX FIFO
+pub const DREQ_I2C0_TX: u8 = 32;
+/// The DREQ value for I2C0's RX FIFO
+pub const DREQ_I2C0_RX: u8 = 33;
+/// The DREQ value for I2C1's TX FIFO
+pub const DREQ_I2C1_TX: u8 = 34;
+/// The DREQ value for I2C1's RX FIFO
+pub const DREQ_I2C1_RX: u8 = 35;
+/// The DREQ value for the ADC
+pub const DREQ_ADC: u8 = 36;
+/// The DREQ value for the XIP Streaming FIFO
+pub const DREQ_XIP_STREAM: u8 = 37;
+/// The DREQ value for the XIP SSI TX FIFO
+pub const DREQ_XIP_SSITX: u8 = 38;
+/// The DREQ value for the XIP SSI RX FIFO
+pub const DREQ_XIP_SSIRX: u8 = 39;
+
+/// Size of a single DMA transfer
+///
+/// Implemented for `u8`, `u16` and `u32`, matching the 8, 16 and 32 bit transfer widths
+/// supported by the DMA. Words are `'static`, so sources like [`FixedSource`] can keep a
+/// `'static` reference to one.
+pub trait Word: Sealed + Copy + 'static {
+    /// Value to use for the `DATA_SIZE` field of the channel control register
+    const DATA_SIZE: u8;
+}
+
+impl Sealed for u8 {}
+impl Word for u8 {
+    const DATA_SIZE: u8 = 0;
+}
+
+impl Sealed for u16 {}
+impl Word for u16 {
+    const DATA_SIZE: u8 = 1;
+}
+
+impl Sealed for u32 {}
+impl Word for u32 {
+    const DATA_SIZE: u8 = 2;
+}
+
+/// Trait which is implemented by anything that can be read via DMA.
+///
+/// # Safety
+///
+/// The implementing type must be safe to use for DMA reads. This means:
+///
+/// - The range returned by `rx_address_count` must point to a valid address, and if
+///   `rx_increment` is true, `count` must fit into the allocated buffer.
+/// - As long as no `&mut self` method is called on the implementing object, `rx_address_count`
+///   must always return the same value, and the memory it points to must not be freed.
+pub unsafe trait ReadTarget {
+    /// Type which is transferred in a single DMA transfer.
+    type ReceivedWord: Word;
+
+    /// Returns the DREQ number for this data source (`None` for memory buffers).
+    fn rx_treq() -> Option<u8>;
+
+    /// Returns the address and the maximum number of words that can be transferred from this
+    /// data source in a single DMA operation.
+    ///
+    /// For peripherals, the count should be `u32::MAX`.
+    fn rx_address_count(&self) -> (u32, u32);
+
+    /// Returns whether the address shall be incremented after each transfer.
+    fn rx_increment(&self) -> bool;
+}
+
+/// Marker which signals that `rx_address_count()` can be called multiple times.
+///
+/// This is the case for peripheral FIFOs, which can be read from endlessly.
+pub trait EndlessReadTarget: ReadTarget {}
+
+/// Trait which is implemented by anything that can be written via DMA.
+///
+/// # Safety
+///
+/// The implementing type must be safe to use for DMA writes. This means:
+///
+/// - The range returned by `tx_address_count` must point to a valid address, and if
+///   `tx_increment` is true, `count` must fit into the allocated buffer.
+/// - As long as no other `&mut self` method is called on the implementing object,
+///   `tx_address_count` must always return the same value, and the memory it points to must not
+///   be freed.
+pub unsafe trait WriteTarget {
+    /// Type which is transferred in a single DMA transfer.
+    type TransmittedWord: Word;
+
+    /// Returns the DREQ number for this data sink (`None` for memory buffers).
+    fn tx_treq() -> Option<u8>;
+
+    /// Returns the address and the maximum number of words that can be transferred to this data
+    /// sink in a single DMA operation.
+    ///
+    /// For peripherals, the count should be `u32::MAX`.
+    fn tx_address_count(&mut self) -> (u32, u32);
+
+    /// Returns whether the address shall be incremented after each transfer.
+    fn tx_increment(&self) -> bool;
+}
+
+/// Marker which signals that `tx_address_count()` can be called multiple times.
+///
+/// This is the case for peripheral FIFOs, which can be written to endlessly.
+pub trait EndlessWriteTarget: WriteTarget {}
+
+unsafe impl<B: ReadBuffer<Word = W>, W: Word> ReadTarget for B {
+    type ReceivedWord = W;
+
+    fn rx_treq() -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.read_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn rx_increment(&self) -> bool {
+        true
+    }
+}
+
+unsafe impl<B: WriteBuffer<Word = W>, W: Word> WriteTarget for B {
+    type TransmittedWord = W;
+
+    fn tx_treq() -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let (ptr, len) = unsafe { self.write_buffer() };
+        (ptr as u32, len as u32)
+    }
+
+    fn tx_increment(&self) -> bool {
+        true
+    }
+}
+
+/// Source which reads the same word over and over again
+///
+/// This can be used to fill a buffer with a constant value, see also [`fill`].
+pub struct FixedSource<WORD: Word> {
+    word: &'static WORD,
+}
+
+impl<WORD: Word> FixedSource<WORD> {
+    /// Create a source which reads `word`
+    pub fn new(word: &'static WORD) -> Self {
+        FixedSource { word }
+    }
+}
+
+// Safety: The word lives for 'static (which `Word: 'static` allows), and the address is not
+// incremented
+unsafe impl<WORD: Word> ReadTarget for FixedSource<WORD> {
+    type ReceivedWord = WORD;
+
+    fn rx_treq() -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.word as *const WORD as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<WORD: Word> EndlessReadTarget for FixedSource<WORD> {}
+
+/// Source at an arbitrary address
+///
+/// The transfer width is given by `WORD`, independently of the type of the data at the address.
+pub struct RawReadTarget<WORD: Word> {
+    address: u32,
+    count: u32,
+    increment: bool,
+    word: PhantomData<WORD>,
+}
+
+impl<WORD: Word> RawReadTarget<WORD> {
+    /// Create a source reading `count` words from `address`
+    ///
+    /// If `increment` is false, all words are read from `address`.
+    ///
+    /// # Safety
+    ///
+    /// `address` must be aligned to the size of `WORD`, and valid for reads of `count` words (or
+    /// of one word, if it is not incremented) for as long as the target is used.
+    pub unsafe fn new(address: *const WORD, count: u32, increment: bool) -> Self {
+        RawReadTarget {
+            address: address as u32,
+            count,
+            increment,
+            word: PhantomData,
+        }
+    }
+}
+
+// Safety: Guaranteed by the caller of `RawReadTarget::new`
+unsafe impl<WORD: Word> ReadTarget for RawReadTarget<WORD> {
+    type ReceivedWord = WORD;
+
+    fn rx_treq() -> Option<u8> {
+        None
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.address, self.count)
+    }
+
+    fn rx_increment(&self) -> bool {
+        self.increment
+    }
+}
+
+/// Destination at an arbitrary address
+///
+/// The transfer width is given by `WORD`, independently of the type of the data at the address.
+pub struct RawWriteTarget<WORD: Word> {
+    address: u32,
+    count: u32,
+    increment: bool,
+    word: PhantomData<WORD>,
+}
+
+impl<WORD: Word> RawWriteTarget<WORD> {
+    /// Create a destination writing `count` words to `address`
+    ///
+    /// If `increment` is false, all words are written to `address`.
+    ///
+    /// # Safety
+    ///
+    /// `address` must be aligned to the size of `WORD`, and valid for writes of `count` words (or
+    /// of one word, if it is not incremented) for as long as the target is used. Nothing else
+    /// may access the memory while a transfer writes to it.
+    pub unsafe fn new(address: *mut WORD, count: u32, increment: bool) -> Self {
+        RawWriteTarget {
+            address: address as u32,
+            count,
+            increment,
+            word: PhantomData,
+        }
+    }
+}
+
+// Safety: Guaranteed by the caller of `RawWriteTarget::new`
+unsafe impl<WORD: Word> WriteTarget for RawWriteTarget<WORD> {
+    type TransmittedWord = WORD;
+
+    fn tx_treq() -> Option<u8> {
+        None
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.address, self.count)
+    }
+
+    fn tx_increment(&self) -> bool {
+        self.increment
+    }
+}
+
+/// Fill `dest` with `value`, blocking until the DMA is done
+///
+/// The source address is not incremented, so the DMA reads `value` for each word it writes. This
+/// is faster than a loop on the CPU for large buffers, like clearing a framebuffer.
+///
+/// ```no_run
+/// use rp2040_hal::{dma::{self, DMAExt}, pac};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut dma = peripherals.DMA.split(&mut peripherals.RESETS);
+/// let mut framebuffer = [0u32; 1024];
+/// dma::fill(&mut dma.ch0, 0xffff_ffff, &mut framebuffer).unwrap();
+/// ```
+pub fn fill<CH: SingleChannel, WORD: Word>(
+    ch: &mut CH,
+    value: WORD,
+    dest: &mut [WORD],
+) -> Result<(), DmaError> {
+    if dest.is_empty() {
+        return Ok(());
+    }
+
+    // safety: Both targets only live until the end of the function, which waits until the DMA
+    // is done, so `value` and `dest` are valid and not accessed otherwise while the DMA uses them
+    let (from, mut to) = unsafe {
+        (
+            RawReadTarget::new(&value, u32::MAX, false),
+            RawWriteTarget::new(dest.as_mut_ptr(), dest.len() as u32, true),
+        )
+    };
+    ch.config(&from, &mut to, Pace::PreferSource, false, None, true);
+    let result = ch.wait_done();
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    result
+}
+
+/// DMA unit
+pub trait DMAExt {
+    /// Splits the DMA unit into its individual channels
+    fn split(self, resets: &mut pac::RESETS) -> Channels;
+}
+
+/// DMA channel
+///
+/// Each channel is a singleton, obtained from [`DMAExt::split`].
+pub struct Channel<CH: ChannelIndex> {
+    _phantom: PhantomData<CH>,
+}
+
+/// DMA channel identifier
+pub trait ChannelIndex: Sealed {
+    /// Numerical index of the DMA channel (0..=11)
+    fn id() -> u8;
+}
+
+macro_rules! channels {
+    (
+        $($CHX:ident: ($chX:ident, $x:expr),)+
+    ) => {
+        $crate::paste::paste! {
+        impl DMAExt for DMA {
+            fn split(self, resets: &mut pac::RESETS) -> Channels {
+                self.reset_bring_down(resets);
+                self.reset_bring_up(resets);
+
+                Channels {
+                    $(
+                        $chX: Channel {
+                            _phantom: PhantomData,
+                        },
+                    )+
+                    sniffer: Sniffer { _private: () },
+                    pace_timer0: PaceTimer { index: 0 },
+                    pace_timer1: PaceTimer { index: 1 },
+                    pace_timer2: PaceTimer { index: 2 },
+                    pace_timer3: PaceTimer { index: 3 },
+                }
+            }
+        }
+
+        /// Set of DMA channels, along with the sniffer and the pacing timers
+        pub struct Channels {
+            $(
+                #[doc = "DMA channel " $x]
+                pub $chX: Channel<$CHX>,
+            )+
+            /// Checksum hardware observing the data moved by one channel
+            pub sniffer: Sniffer,
+            /// Pacing timer 0
+            pub pace_timer0: PaceTimer,
+            /// Pacing timer 1
+            pub pace_timer1: PaceTimer,
+            /// Pacing timer 2
+            pub pace_timer2: PaceTimer,
+            /// Pacing timer 3
+            pub pace_timer3: PaceTimer,
+        }
+
+        $(
+            #[doc = "DMA channel identifier for channel " $x]
+            pub struct $CHX;
+
+            impl ChannelIndex for $CHX {
+                fn id() -> u8 {
+                    $x
+                }
+            }
+
+            impl Sealed for $CHX {}
+        )+
+        }
+    }
+}
+
+channels! {
+    CH0: (ch0, 0),
+    CH1: (ch1, 1),
+    CH2: (ch2, 2),
+    CH3: (ch3, 3),
+    CH4: (ch4, 4),
+    CH5: (ch5, 5),
+    CH6: (ch6, 6),
+    CH7: (ch7, 7),
+    CH8: (ch8, 8),
+    CH9: (ch9, 9),
+    CH10: (ch10, 10),
+    CH11: (ch11, 11),
+}
+
+/// Trait which is implemented by a single DMA channel
+pub trait SingleChannel: Sealed {
+    /// Returns the registers associated with this DMA channel
+    fn ch(&self) -> &pac::dma::CH;
+
+    /// Returns the index of this DMA channel
+    fn id(&self) -> u8;
+
+    /// Enables the DMA_IRQ_0 signal for this channel
+    ///
+    /// The interrupt is raised each time the channel finishes a transfer.
+    fn enable_irq0(&mut self) {
+        // safety: The atomic alias only affects this channel's bit
+        unsafe { write_bitmask_set((*DMA::ptr()).inte0.as_ptr(), 1 << self.id()) }
+    }
+
+    /// Disables the DMA_IRQ_0 signal for this channel
+    fn disable_irq0(&mut self) {
+        // safety: The atomic alias only affects this channel's bit
+        unsafe { write_bitmask_clear((*DMA::ptr()).inte0.as_ptr(), 1 << self.id()) }
+    }
+
+    /// Enables the DMA_IRQ_1 signal for this channel
+    ///
+    /// The interrupt is raised each time the channel finishes a transfer.
+    fn enable_irq1(&mut self) {
+        // safety: The atomic alias only affects this channel's bit
+        unsafe { write_bitmask_set((*DMA::ptr()).inte1.as_ptr(), 1 << self.id()) }
+    }
+
+    /// Disables the DMA_IRQ_1 signal for this channel
+    fn disable_irq1(&mut self) {
+        // safety: The atomic alias only affects this channel's bit
+        unsafe { write_bitmask_clear((*DMA::ptr()).inte1.as_ptr(), 1 << self.id()) }
+    }
+
+    /// Checks whether this channel asserts DMA_IRQ_0, and clears the interrupt if it does
+    fn check_irq0(&mut self) -> bool {
+        let mask = 1 << self.id();
+        // safety: INTS0 is write-1-to-clear, so only this channel's interrupt is cleared
+        let dma = unsafe { &*DMA::ptr() };
+        if dma.ints0.read().bits() & mask != 0 {
+            dma.ints0.write(|w| unsafe { w.bits(mask) });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks whether this channel asserts DMA_IRQ_1, and clears the interrupt if it does
+    fn check_irq1(&mut self) -> bool {
+        let mask = 1 << self.id();
+        // safety: INTS1 is write-1-to-clear, so only this channel's interrupt is cleared
+        let dma = unsafe { &*DMA::ptr() };
+        if dma.ints1.read().bits() & mask != 0 {
+            dma.ints1.write(|w| unsafe { w.bits(mask) });
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Bitmask of the channels asserting DMA_IRQ_0
+///
+/// Bit `n` is set for channel `n`. An interrupt handler servicing several channels can use this to
+/// find out which channels are done, and then clear their interrupts with
+/// [`SingleChannel::check_irq0`].
+pub fn pending_irq0() -> u16 {
+    // safety: This is a read action and should not have any UB
+    unsafe { (*DMA::ptr()).ints0.read().bits() as u16 }
+}
+
+/// Bitmask of the channels asserting DMA_IRQ_1
+///
+/// See [`pending_irq0`].
+pub fn pending_irq1() -> u16 {
+    // safety: This is a read action and should not have any UB
+    unsafe { (*DMA::ptr()).ints1.read().bits() as u16 }
+}
+
+impl<CH: ChannelIndex> SingleChannel for Channel<CH> {
+    fn ch(&self) -> &pac::dma::CH {
+        // safety: Each channel is a singleton, so this is the only access to these registers
+        unsafe { &(*DMA::ptr()).ch[CH::id() as usize] }
+    }
+
+    fn id(&self) -> u8 {
+        CH::id()
+    }
+}
+
+impl<CH: ChannelIndex> Sealed for Channel<CH> {}
+
+/// How the DMA transfer is paced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pace {
+    /// Use the DREQ of the source, or the one of the destination if the source has none
+    PreferSource,
+    /// Use the DREQ of the destination, or the one of the source if the destination has none
+    PreferSink,
+    /// Use a pacing timer, ignoring the DREQs of the source and destination
+    ///
+    /// See [`PaceTimer::pace`].
+    Timer(PaceTimerId),
+}
+
+/// TREQ_SEL value for transfers which run as fast as possible
+const TREQ_UNPACED: u8 = 0x3f;
+
+// Bit positions in the channel CTRL register
+const CTRL_EN: u32 = 1 << 0;
+const CTRL_DATA_SIZE_SHIFT: u32 = 2;
+const CTRL_INCR_READ: u32 = 1 << 4;
+const CTRL_INCR_WRITE: u32 = 1 << 5;
+const CTRL_CHAIN_TO_SHIFT: u32 = 11;
+const CTRL_TREQ_SEL_SHIFT: u32 = 15;
+const CTRL_BSWAP: u32 = 1 << 22;
+const CTRL_SNIFF_EN: u32 = 1 << 23;
+const CTRL_WRITE_ERROR: u32 = 1 << 29;
+const CTRL_READ_ERROR: u32 = 1 << 30;
+
+/// Error reported by the DMA when a transfer fails
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaError {
+    /// A bus error occurred while reading from the source
+    Read,
+    /// A bus error occurred while writing to the destination
+    Write,
+}
+
+/// A failed DMA transfer
+///
+/// The transfer has been aborted, so the channels and targets in `parts` can be reused.
+pub struct TransferError<PARTS> {
+    /// The reason the transfer failed
+    pub error: DmaError,
+    /// The channels and targets which were used by the transfer
+    pub parts: PARTS,
+}
+
+impl<PARTS> core::fmt::Debug for TransferError<PARTS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TransferError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Channel programming shared by the different transfer types
+trait ChannelConfig {
+    /// Program the channel to transfer from `from` to `to`, optionally starting it right away
+    ///
+    /// When the channel finishes it triggers `chain_to`, if set.
+    fn config<WORD, FROM, TO>(
+        &mut self,
+        from: &FROM,
+        to: &mut TO,
+        pace: Pace,
+        bswap: bool,
+        chain_to: Option<u8>,
+        start: bool,
+    ) where
+        WORD: Word,
+        FROM: ReadTarget<ReceivedWord = WORD>,
+        TO: WriteTarget<TransmittedWord = WORD>;
+
+    /// Start the channel with the current configuration
+    fn start(&mut self);
+
+    /// Make the channel trigger `other` when it finishes
+    ///
+    /// If the channel has already finished, `other` is started right away.
+    fn set_chain_to_enabled<CH: SingleChannel>(&mut self, other: &mut CH);
+
+    /// Is the channel still transferring data?
+    fn is_busy(&self) -> bool;
+
+    /// The bus error which stopped the channel, if any
+    fn error(&self) -> Option<DmaError>;
+
+    /// Block until the channel is done, or abort it if it reports an error
+    fn wait_done(&mut self) -> Result<(), DmaError>;
+
+    /// Stop issuing transfers, until [`resume`](ChannelConfig::resume) is called
+    fn pause(&mut self);
+
+    /// Continue a paused transfer
+    fn resume(&mut self);
+
+    /// Stop the channel, and wait until it has finished the transfers in flight
+    fn abort(&mut self);
+}
+
+impl<CH: SingleChannel> ChannelConfig for CH {
+    fn config<WORD, FROM, TO>(
+        &mut self,
+        from: &FROM,
+        to: &mut TO,
+        pace: Pace,
+        bswap: bool,
+        chain_to: Option<u8>,
+        start: bool,
+    ) where
+        WORD: Word,
+        FROM: ReadTarget<ReceivedWord = WORD>,
+        TO: WriteTarget<TransmittedWord = WORD>,
+    {
+        let (src, src_count) = from.rx_address_count();
+        let (dest, dest_count) = to.tx_address_count();
+        let treq = match pace {
+            Pace::PreferSource => FROM::rx_treq().or_else(TO::tx_treq),
+            Pace::PreferSink => TO::tx_treq().or_else(FROM::rx_treq),
+            Pace::Timer(timer) => Some(timer.treq()),
+        }
+        .unwrap_or(TREQ_UNPACED);
+
+        // SNIFF_EN only has an effect if the sniffer is enabled and attached to this channel, so
+        // it is always set. This lets the sniffer observe any type of transfer.
+        let mut ctrl = CTRL_EN
+            | CTRL_SNIFF_EN
+            | u32::from(WORD::DATA_SIZE) << CTRL_DATA_SIZE_SHIFT
+            | u32::from(chain_to.unwrap_or_else(|| self.id())) << CTRL_CHAIN_TO_SHIFT
+            | u32::from(treq) << CTRL_TREQ_SEL_SHIFT;
+        if from.rx_increment() {
+            ctrl |= CTRL_INCR_READ;
+        }
+        if to.tx_increment() {
+            ctrl |= CTRL_INCR_WRITE;
+        }
+        if bswap {
+            ctrl |= CTRL_BSWAP;
+        }
+
+        // Make sure all writes to the buffers are done before the DMA reads them
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+
+        // The non-triggering aliases are used for everything but the last write, which starts the
+        // channel if requested
+        // Writing the error flags clears them, in case the previous transfer failed
+        let ch = self.ch();
+        ch.ch_al1_ctrl
+            .write(|w| unsafe { w.bits(ctrl | CTRL_READ_ERROR | CTRL_WRITE_ERROR) });
+        ch.ch_read_addr.write(|w| unsafe { w.bits(src) });
+        ch.ch_trans_count
+            .write(|w| unsafe { w.bits(u32::min(src_count, dest_count)) });
+        if start {
+            ch.ch_al2_write_addr_trig.write(|w| unsafe { w.bits(dest) });
+        } else {
+            ch.ch_write_addr.write(|w| unsafe { w.bits(dest) });
+        }
+    }
+
+    fn start(&mut self) {
+        // safety: MULTI_CHAN_TRIGGER only affects the channels whose bits are set
+        unsafe { &*DMA::ptr() }
+            .multi_chan_trigger
+            .write(|w| unsafe { w.bits(1 << self.id()) });
+    }
+
+    fn set_chain_to_enabled<OTHER: SingleChannel>(&mut self, other: &mut OTHER) {
+        // The channel is paused while CHAIN_TO is changed, so it can't finish between checking
+        // whether it is still busy and setting CHAIN_TO. Otherwise `other` might never be started.
+        self.pause();
+        self.ch().ch_al1_ctrl.modify(|r, w| unsafe {
+            w.bits(
+                (r.bits() & !(0xf << CTRL_CHAIN_TO_SHIFT | CTRL_READ_ERROR | CTRL_WRITE_ERROR))
+                    | u32::from(other.id()) << CTRL_CHAIN_TO_SHIFT,
+            )
+        });
+        if !self.is_busy() {
+            other.start();
+        }
+        self.resume();
+    }
+
+    fn is_busy(&self) -> bool {
+        self.ch().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    fn error(&self) -> Option<DmaError> {
+        let ctrl = self.ch().ch_ctrl_trig.read().bits();
+        if ctrl & CTRL_READ_ERROR != 0 {
+            Some(DmaError::Read)
+        } else if ctrl & CTRL_WRITE_ERROR != 0 {
+            Some(DmaError::Write)
+        } else {
+            None
+        }
+    }
+
+    fn wait_done(&mut self) -> Result<(), DmaError> {
+        loop {
+            // The error flags are checked first, as a channel which stopped because of an error
+            // is not busy anymore
+            if let Some(error) = self.error() {
+                self.abort();
+                return Err(error);
+            }
+            if !self.is_busy() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn pause(&mut self) {
+        // The error flags are write-1-to-clear, so they are not written back
+        self.ch().ch_al1_ctrl.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(CTRL_EN | CTRL_READ_ERROR | CTRL_WRITE_ERROR))
+        });
+    }
+
+    fn resume(&mut self) {
+        self.ch().ch_al1_ctrl.modify(|r, w| unsafe {
+            w.bits((r.bits() | CTRL_EN) & !(CTRL_READ_ERROR | CTRL_WRITE_ERROR))
+        });
+    }
+
+    fn abort(&mut self) {
+        let mask = 1 << self.id();
+        // safety: CHAN_ABORT, INTS0 and INTS1 only affect the channels whose bits are set, and
+        // INTE0/INTE1 are changed with the atomic aliases
+        let dma = unsafe { &*DMA::ptr() };
+
+        // RP2040-E13: If a read has been issued but the matching write has not, CHAN_ABORT clears
+        // too early, and the write completing later raises a spurious completion interrupt. The
+        // interrupts of the channel are disabled until it is idle, and then cleared.
+        let inte0 = dma.inte0.read().bits() & mask;
+        let inte1 = dma.inte1.read().bits() & mask;
+        unsafe {
+            write_bitmask_clear(dma.inte0.as_ptr(), mask);
+            write_bitmask_clear(dma.inte1.as_ptr(), mask);
+        }
+
+        dma.chan_abort.write(|w| unsafe { w.bits(mask) });
+        while dma.chan_abort.read().bits() & mask != 0 {}
+        while self.is_busy() {}
+
+        dma.ints0.write(|w| unsafe { w.bits(mask) });
+        dma.ints1.write(|w| unsafe { w.bits(mask) });
+        unsafe {
+            write_bitmask_set(dma.inte0.as_ptr(), inte0);
+            write_bitmask_set(dma.inte1.as_ptr(), inte1);
+        }
+    }
+}
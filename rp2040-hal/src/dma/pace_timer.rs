@@ -0,0 +1,125 @@
+//! DMA pacing timers, generating DREQs at a fraction of the system clock
+
+use embedded_time::{fixed_point::FixedPoint, rate::Hertz};
+
+use super::Pace;
+use crate::pac::DMA;
+
+/// TREQ_SEL value of pacing timer 0, the other timers follow
+const TREQ_TIMER0: u8 = 0x3b;
+
+/// Identifies the pacing timer used by [`Pace::Timer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaceTimerId(u8);
+
+impl PaceTimerId {
+    pub(super) fn treq(self) -> u8 {
+        TREQ_TIMER0 + self.0
+    }
+}
+
+/// Error returned when a pacing timer can't be configured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaceTimerError {
+    /// The numerator is larger than the denominator, or the denominator is 0
+    InvalidRatio,
+    /// The frequency is lower than the system clock divided by 65535
+    FrequencyTooLow,
+    /// The frequency is higher than the system clock
+    FrequencyTooHigh,
+}
+
+/// DMA pacing timer
+///
+/// Each of the four pacing timers generates a DREQ at `sys_clk * X / Y`. Transfers select one
+/// with [`Pace::Timer`] to run at a fixed rate, independently of any peripheral:
+///
+/// ```no_run
+/// use embedded_time::rate::Hertz;
+/// use rp2040_hal::{dma::{single_buffer, DMAExt}, pac};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut dma = peripherals.DMA.split(&mut peripherals.RESETS);
+/// let achieved = dma
+///     .pace_timer0
+///     .set_frequency(Hertz(125_000_000), Hertz(22_050))
+///     .unwrap();
+/// let from = cortex_m::singleton!(: [u32; 64] = [0; 64]).unwrap();
+/// let to = cortex_m::singleton!(: [u32; 64] = [0; 64]).unwrap();
+/// let mut config = single_buffer::Config::new(dma.ch0, from, to);
+/// config.pace(dma.pace_timer0.pace());
+/// let transfer = config.start();
+/// ```
+pub struct PaceTimer {
+    pub(super) index: u8,
+}
+
+impl PaceTimer {
+    /// [`Pace`] selecting this timer
+    pub fn pace(&self) -> Pace {
+        Pace::Timer(PaceTimerId(self.index))
+    }
+
+    /// Generate DREQs at `sys_clk * numerator / denominator`
+    ///
+    /// `numerator` must not be larger than `denominator`. A numerator of 0 stops the timer.
+    pub fn set_rate(&mut self, numerator: u16, denominator: u16) -> Result<(), PaceTimerError> {
+        if denominator == 0 || numerator > denominator {
+            return Err(PaceTimerError::InvalidRatio);
+        }
+        let bits = u32::from(numerator) << 16 | u32::from(denominator);
+        // safety: Each pacing timer is a singleton, so this is the only access to its register
+        let dma = unsafe { &*DMA::ptr() };
+        match self.index {
+            0 => dma.timer0.write(|w| unsafe { w.bits(bits) }),
+            1 => dma.timer1.write(|w| unsafe { w.bits(bits) }),
+            2 => dma.timer2.write(|w| unsafe { w.bits(bits) }),
+            _ => dma.timer3.write(|w| unsafe { w.bits(bits) }),
+        }
+        Ok(())
+    }
+
+    /// Generate DREQs as close to `freq` as the 16 bit ratio allows
+    ///
+    /// Returns the frequency that was achieved.
+    pub fn set_frequency(&mut self, sys_freq: Hertz, freq: Hertz) -> Result<Hertz, PaceTimerError> {
+        let sys_freq = u64::from(sys_freq.integer());
+        let freq = u64::from(freq.integer());
+        if freq > sys_freq {
+            return Err(PaceTimerError::FrequencyTooHigh);
+        }
+        if freq * 0xffff < sys_freq {
+            return Err(PaceTimerError::FrequencyTooLow);
+        }
+
+        let (numerator, denominator) = best_ratio(freq, sys_freq);
+        self.set_rate(numerator, denominator)?;
+        Ok(Hertz(
+            (sys_freq * u64::from(numerator) / u64::from(denominator)) as u32,
+        ))
+    }
+}
+
+/// Closest approximation of `num / denom` (at most 1) with a 16 bit numerator and denominator
+///
+/// Walks the convergents of the continued fraction of `num / denom`, which are the best
+/// approximations for their denominator size.
+fn best_ratio(num: u64, denom: u64) -> (u16, u16) {
+    // Previous and current convergents
+    let (mut p0, mut q0, mut p1, mut q1) = (0u64, 1u64, 1u64, 0u64);
+    let (mut n, mut d) = (num, denom);
+    while d != 0 {
+        let a = n / d;
+        let (p2, q2) = (a * p1 + p0, a * q1 + q0);
+        if p2 > 0xffff || q2 > 0xffff {
+            break;
+        }
+        p0 = p1;
+        q0 = q1;
+        p1 = p2;
+        q1 = q2;
+        let r = n - a * d;
+        n = d;
+        d = r;
+    }
+    (p1 as u16, q1 as u16)
+}
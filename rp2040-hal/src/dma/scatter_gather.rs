@@ -0,0 +1,357 @@
+//! Scatter-gather DMA transfers driven by a list of control blocks
+//!
+//! A control channel copies one [`ControlBlock`] at a time from a list in RAM into the registers
+//! of a data channel. Writing the last register of a block starts the data channel, which
+//! triggers the control channel again when it is done, so the data channel works through the
+//! whole list without any help from the CPU. The list ends with [`ControlBlock::END`], whose
+//! trigger write is ignored by the hardware (a null trigger), which stops the sequence.
+//!
+//! Send tiles from different places in memory to a peripheral:
+//! ```no_run
+//! use rp2040_hal::{dma::{scatter_gather::{self, ControlBlock}, DMAExt}, pac};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let dma = peripherals.DMA.split(&mut peripherals.RESETS);
+//! let tile_a = cortex_m::singleton!(: [u16; 64] = [0xf800; 64]).unwrap();
+//! let tile_b = cortex_m::singleton!(: [u16; 64] = [0x07e0; 64]).unwrap();
+//! let blocks = cortex_m::singleton!(: [ControlBlock<u16>; 3] = [ControlBlock::END; 3]).unwrap();
+//! blocks[0] = ControlBlock::new(tile_a);
+//! blocks[1] = ControlBlock::new(tile_b);
+//! // Any other `WriteTarget`, like the SPI bus of a display, can be used as the destination
+//! let to = cortex_m::singleton!(: [u16; 128] = [0; 128]).unwrap();
+//! let transfer = scatter_gather::Config::new((dma.ch0, dma.ch1), blocks, to).start();
+//! let ((ch0, ch1), blocks, to) = transfer.wait().unwrap();
+//! ```
+
+use core::marker::PhantomData;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{
+    ChannelConfig, Pace, SingleChannel, TransferError, Word, WriteTarget, CTRL_CHAIN_TO_SHIFT,
+    CTRL_DATA_SIZE_SHIFT, CTRL_EN, CTRL_INCR_READ, CTRL_INCR_WRITE, CTRL_READ_ERROR, CTRL_SNIFF_EN,
+    CTRL_TREQ_SEL_SHIFT, CTRL_WRITE_ERROR, TREQ_UNPACED,
+};
+
+// Bit positions in the channel CTRL register, only used by the control channel
+const CTRL_RING_SIZE_SHIFT: u32 = 6;
+const CTRL_RING_SEL: u32 = 1 << 10;
+
+/// Number of registers written by the control channel for each block
+const BLOCK_WORDS: u32 = 4;
+
+/// One entry of a scatter-gather list
+///
+/// The layout matches the first register alias of a DMA channel (READ_ADDR, WRITE_ADDR,
+/// TRANS_COUNT, CTRL_TRIG), so the control channel can copy it verbatim, with CTRL_TRIG starting
+/// the data channel. All words of a list have the same size `WORD`.
+///
+/// ```
+/// use rp2040_hal::dma::scatter_gather::ControlBlock;
+/// assert_eq!(core::mem::size_of::<ControlBlock<u8>>(), 16);
+/// assert_eq!(core::mem::align_of::<ControlBlock<u8>>(), 4);
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlBlock<WORD: Word> {
+    read_addr: u32,
+    write_addr: u32,
+    trans_count: u32,
+    ctrl: u32,
+    word: PhantomData<WORD>,
+}
+
+impl<WORD: Word> ControlBlock<WORD> {
+    /// Terminator of a list
+    ///
+    /// The control channel writes 0 to CTRL_TRIG for this block, which doesn't start the data
+    /// channel, so the sequence ends.
+    pub const END: ControlBlock<WORD> = ControlBlock {
+        read_addr: 0,
+        write_addr: 0,
+        trans_count: 0,
+        ctrl: 0,
+        word: PhantomData,
+    };
+
+    /// Block which copies `from` to the destination of the transfer
+    ///
+    /// If the destination is a buffer, the blocks are copied one after the other into it. The
+    /// destination address is filled in by the first transfer using the block, so a list reused
+    /// for another transfer keeps writing to the same destination.
+    pub fn new(from: &'static [WORD]) -> ControlBlock<WORD> {
+        ControlBlock {
+            read_addr: from.as_ptr() as u32,
+            // Filled in when the transfer starts
+            write_addr: 0,
+            trans_count: from.len() as u32,
+            ctrl: CTRL_INCR_READ,
+            word: PhantomData,
+        }
+    }
+
+    /// Block which copies `count` words from `from` to `to`, independently of the destination of
+    /// the transfer
+    ///
+    /// The addresses are only incremented after each word if `incr_read`/`incr_write` are set.
+    ///
+    /// # Safety
+    ///
+    /// `from` and `to` must be valid for `count` words (or for one word if they are not
+    /// incremented) until the transfer using this block has finished.
+    pub unsafe fn from_raw(
+        from: *const WORD,
+        to: *mut WORD,
+        count: u32,
+        incr_read: bool,
+        incr_write: bool,
+    ) -> ControlBlock<WORD> {
+        let mut ctrl = 0;
+        if incr_read {
+            ctrl |= CTRL_INCR_READ;
+        }
+        if incr_write {
+            ctrl |= CTRL_INCR_WRITE;
+        }
+        ControlBlock {
+            read_addr: from as u32,
+            write_addr: to as u32,
+            trans_count: count,
+            ctrl,
+            word: PhantomData,
+        }
+    }
+
+    fn is_end(&self) -> bool {
+        self.ctrl == 0 && self.read_addr == 0
+    }
+}
+
+/// Configuration for a scatter-gather DMA transfer
+pub struct Config<CH1: SingleChannel, CH2: SingleChannel, WORD: Word, TO: WriteTarget> {
+    ch: (CH1, CH2),
+    blocks: &'static mut [ControlBlock<WORD>],
+    to: TO,
+    pace: Pace,
+}
+
+impl<CH1, CH2, WORD, TO> Config<CH1, CH2, WORD, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    WORD: Word,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration for a list of control blocks
+    ///
+    /// `ch.0` moves the data, `ch.1` reprograms `ch.0` with the next block. The transfer owns the
+    /// list, so it can't be modified or freed while the DMA reads it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the last block of the list is not [`ControlBlock::END`].
+    pub fn new(
+        ch: (CH1, CH2),
+        blocks: &'static mut [ControlBlock<WORD>],
+        to: TO,
+    ) -> Config<CH1, CH2, WORD, TO> {
+        assert!(
+            matches!(blocks.last(), Some(block) if block.is_end()),
+            "The list must end with ControlBlock::END"
+        );
+        Config {
+            ch,
+            blocks,
+            to,
+            pace: Pace::PreferSink,
+        }
+    }
+
+    /// Sets the pace for the data transfers
+    ///
+    /// The source of each block is memory, so the transfers are paced by the DREQ of the
+    /// destination unless a [`Pace::Timer`] is selected.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Start the DMA transfer
+    ///
+    /// # Panics
+    ///
+    /// Panics if the destination is a buffer which is too small for the blocks created with
+    /// [`ControlBlock::new`].
+    pub fn start(mut self) -> Transfer<CH1, CH2, WORD, TO> {
+        let treq = match self.pace {
+            Pace::PreferSource | Pace::PreferSink => TO::tx_treq(),
+            Pace::Timer(timer) => Some(timer.treq()),
+        }
+        .unwrap_or(TREQ_UNPACED);
+        let (dest, dest_count) = self.to.tx_address_count();
+        let dest_incr = self.to.tx_increment();
+
+        // Complete the blocks: every block enables the data channel, and makes it trigger the
+        // control channel when it is done. Writing the error flags clears them.
+        let common = CTRL_EN
+            | CTRL_SNIFF_EN
+            | CTRL_READ_ERROR
+            | CTRL_WRITE_ERROR
+            | u32::from(WORD::DATA_SIZE) << CTRL_DATA_SIZE_SHIFT
+            | u32::from(self.ch.1.id()) << CTRL_CHAIN_TO_SHIFT
+            | u32::from(treq) << CTRL_TREQ_SEL_SHIFT;
+        let mut offset = 0;
+        for block in self.blocks.iter_mut().filter(|block| !block.is_end()) {
+            if block.write_addr == 0 {
+                if dest_incr {
+                    assert!(
+                        offset + block.trans_count <= dest_count,
+                        "The destination is too small"
+                    );
+                    block.write_addr = dest + offset * core::mem::size_of::<WORD>() as u32;
+                    block.ctrl |= CTRL_INCR_WRITE;
+                    offset += block.trans_count;
+                } else {
+                    block.write_addr = dest;
+                }
+            }
+            block.ctrl = (block.ctrl & (CTRL_INCR_READ | CTRL_INCR_WRITE)) | common;
+        }
+
+        // Make sure the list and the buffers are written before the DMA reads them
+        compiler_fence(Ordering::SeqCst);
+
+        // The control channel writes the four registers of the first alias of the data channel,
+        // wrapping around after each block. The registers of each channel are 64 byte aligned,
+        // so the 16 byte write ring is correctly aligned.
+        let ctrl = CTRL_EN
+            | 2 << CTRL_DATA_SIZE_SHIFT
+            | CTRL_INCR_READ
+            | CTRL_INCR_WRITE
+            | 4 << CTRL_RING_SIZE_SHIFT
+            | CTRL_RING_SEL
+            | u32::from(self.ch.1.id()) << CTRL_CHAIN_TO_SHIFT
+            | u32::from(TREQ_UNPACED) << CTRL_TREQ_SEL_SHIFT
+            | CTRL_READ_ERROR
+            | CTRL_WRITE_ERROR;
+        let data_regs = self.ch.0.ch().ch_read_addr.as_ptr() as u32;
+        let ch = self.ch.1.ch();
+        ch.ch_al1_ctrl.write(|w| unsafe { w.bits(ctrl) });
+        ch.ch_write_addr.write(|w| unsafe { w.bits(data_regs) });
+        ch.ch_trans_count.write(|w| unsafe { w.bits(BLOCK_WORDS) });
+        ch.ch_al3_read_addr_trig
+            .write(|w| unsafe { w.bits(self.blocks.as_ptr() as u32) });
+
+        Transfer {
+            ch: self.ch,
+            blocks: self.blocks,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a scatter-gather DMA transfer
+///
+/// Dropping the transfer aborts it, so the list and the buffers are never accessed by the DMA
+/// after they are released.
+pub struct Transfer<CH1: SingleChannel, CH2: SingleChannel, WORD: Word, TO: WriteTarget> {
+    ch: (CH1, CH2),
+    blocks: &'static mut [ControlBlock<WORD>],
+    to: TO,
+}
+
+impl<CH1, CH2, WORD, TO> Transfer<CH1, CH2, WORD, TO>
+where
+    CH1: SingleChannel,
+    CH2: SingleChannel,
+    WORD: Word,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Has the whole list been processed?
+    pub fn is_done(&self) -> bool {
+        // The control channel is briefly idle between blocks, so the transfer is only done once
+        // the terminator has been read
+        let end = self.blocks.as_ptr_range().end as u32;
+        !self.ch.1.is_busy()
+            && self.ch.1.ch().ch_read_addr.read().bits() == end
+            && !self.ch.0.is_busy()
+    }
+
+    /// Stop fetching new blocks and transferring data, until [`resume`](Self::resume) is called
+    pub fn pause(&mut self) {
+        // The control channel is only triggered by the data channel, so pausing the data channel
+        // pauses the whole sequence
+        self.ch.0.pause();
+    }
+
+    /// Continue a paused transfer
+    pub fn resume(&mut self) {
+        self.ch.0.resume();
+    }
+
+    /// Stop the transfer, returning the channels, the list and the destination
+    #[allow(clippy::type_complexity)]
+    pub fn abort(mut self) -> ((CH1, CH2), &'static mut [ControlBlock<WORD>], TO) {
+        self.abort_both();
+        self.into_parts()
+    }
+
+    /// Block until all blocks have been transferred, returning the channels, the list and the
+    /// destination
+    ///
+    /// If the DMA reports a bus error on either channel, the transfer is aborted and the error is
+    /// returned along with the channels, the list and the destination.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(
+        mut self,
+    ) -> Result<
+        ((CH1, CH2), &'static mut [ControlBlock<WORD>], TO),
+        TransferError<((CH1, CH2), &'static mut [ControlBlock<WORD>], TO)>,
+    > {
+        loop {
+            if let Some(error) = self.ch.0.error().or_else(|| self.ch.1.error()) {
+                self.abort_both();
+                return Err(TransferError {
+                    error,
+                    parts: self.into_parts(),
+                });
+            }
+            if self.is_done() {
+                break;
+            }
+        }
+
+        // Make sure the buffers are not read before the DMA finished writing them
+        compiler_fence(Ordering::SeqCst);
+
+        Ok(self.into_parts())
+    }
+
+    fn abort_both(&mut self) {
+        // Stop the control channel first, so it can't restart the data channel
+        self.ch.1.abort();
+        self.ch.0.abort();
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn into_parts(self) -> ((CH1, CH2), &'static mut [ControlBlock<WORD>], TO) {
+        let this = core::mem::ManuallyDrop::new(self);
+        // safety: `this` is never used or dropped again, so each field is moved out exactly once
+        unsafe {
+            (
+                core::ptr::read(&this.ch),
+                core::ptr::read(&this.blocks),
+                core::ptr::read(&this.to),
+            )
+        }
+    }
+}
+
+impl<CH1: SingleChannel, CH2: SingleChannel, WORD: Word, TO: WriteTarget> Drop
+    for Transfer<CH1, CH2, WORD, TO>
+{
+    fn drop(&mut self) {
+        // Stop the control channel first, so it can't restart the data channel
+        self.ch.1.abort();
+        self.ch.0.abort();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
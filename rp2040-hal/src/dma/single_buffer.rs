@@ -0,0 +1,147 @@
+//! Single-buffered or peripheral-peripheral DMA transfers
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use super::{ChannelConfig, Pace, ReadTarget, SingleChannel, TransferError, Word, WriteTarget};
+
+/// Configuration for single-buffered DMA transfer
+pub struct Config<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+    pace: Pace,
+    bswap: bool,
+}
+
+impl<CH, FROM, TO, WORD> Config<CH, FROM, TO>
+where
+    WORD: Word,
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Create a DMA configuration for a single buffer
+    ///
+    /// The transfer copies as many words as fit into both `from` and `to`. It is paced by the
+    /// DREQ of the source, if it has one, see [`pace`](Self::pace).
+    pub fn new(ch: CH, from: FROM, to: TO) -> Config<CH, FROM, TO> {
+        Config {
+            ch,
+            from,
+            to,
+            pace: Pace::PreferSource,
+            bswap: false,
+        }
+    }
+
+    /// Sets the (preferred) pace for the DMA transfers
+    ///
+    /// Usually, the code will automatically configure the correct pace, but
+    /// peripheral-peripheral transfers require the user to manually select whether the source or
+    /// the sink shall be queried for the pace signal.
+    pub fn pace(&mut self, pace: Pace) {
+        self.pace = pace;
+    }
+
+    /// Enable/disable byte-swapping for the DMA transfers
+    ///
+    /// This reverses the order of the bytes in each word transferred, so it has no effect on
+    /// 8 bit transfers.
+    pub fn bswap(&mut self, bswap: bool) {
+        self.bswap = bswap;
+    }
+
+    /// Start the DMA transfer
+    pub fn start(mut self) -> Transfer<CH, FROM, TO> {
+        self.ch
+            .config(&self.from, &mut self.to, self.pace, self.bswap, None, true);
+
+        Transfer {
+            ch: self.ch,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+/// Instance of a single-buffered DMA transfer
+///
+/// Dropping the transfer aborts it, so the buffers are never accessed by the DMA after they are
+/// released.
+pub struct Transfer<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> {
+    ch: CH,
+    from: FROM,
+    to: TO,
+}
+
+impl<CH, FROM, TO, WORD> Transfer<CH, FROM, TO>
+where
+    WORD: Word,
+    CH: SingleChannel,
+    FROM: ReadTarget<ReceivedWord = WORD>,
+    TO: WriteTarget<TransmittedWord = WORD>,
+{
+    /// Is this transfer complete?
+    pub fn is_done(&self) -> bool {
+        !self.ch.is_busy()
+    }
+
+    /// Stop issuing transfers, until [`resume`](Self::resume) is called
+    pub fn pause(&mut self) {
+        self.ch.pause();
+    }
+
+    /// Continue a paused transfer
+    pub fn resume(&mut self) {
+        self.ch.resume();
+    }
+
+    /// Stop the transfer, returning the channel and targets
+    ///
+    /// The channel can be used for a new transfer right away. This also works if the transfer
+    /// is stuck, e.g. because the peripheral it waits for never requests more data.
+    pub fn abort(mut self) -> (CH, FROM, TO) {
+        self.ch.abort();
+        compiler_fence(Ordering::SeqCst);
+        self.into_parts()
+    }
+
+    /// Block until the transfer is complete, returning the channel and targets
+    ///
+    /// If the DMA reports a bus error, the transfer is aborted and the error is returned along
+    /// with the channel and targets.
+    #[allow(clippy::type_complexity)]
+    pub fn wait(mut self) -> Result<(CH, FROM, TO), TransferError<(CH, FROM, TO)>> {
+        let result = self.ch.wait_done();
+
+        // Make sure the buffers are not read before the DMA finished writing them
+        compiler_fence(Ordering::SeqCst);
+
+        match result {
+            Ok(()) => Ok(self.into_parts()),
+            Err(error) => Err(TransferError {
+                error,
+                parts: self.into_parts(),
+            }),
+        }
+    }
+
+    fn into_parts(self) -> (CH, FROM, TO) {
+        let this = core::mem::ManuallyDrop::new(self);
+        // safety: `this` is never used or dropped again, so each field is moved out exactly once
+        unsafe {
+            (
+                core::ptr::read(&this.ch),
+                core::ptr::read(&this.from),
+                core::ptr::read(&this.to),
+            )
+        }
+    }
+}
+
+impl<CH: SingleChannel, FROM: ReadTarget, TO: WriteTarget> Drop for Transfer<CH, FROM, TO> {
+    fn drop(&mut self) {
+        self.ch.abort();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,144 @@
+//! DMA sniffer, computing checksums of the data moved by a channel
+
+use super::SingleChannel;
+use crate::pac::DMA;
+
+/// Checksum computed by the [`Sniffer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SniffMode {
+    /// CRC-32 with the polynomial 0x04C11DB7
+    ///
+    /// The CRC-32 used by zlib and Ethernet is
+    /// `Crc32 { reversed: true, inverted: true, seed: 0xffff_ffff }`.
+    Crc32 {
+        /// Bit-reverse each input byte and the result
+        reversed: bool,
+        /// Invert the bits of the result
+        inverted: bool,
+        /// Initial value of the checksum
+        seed: u32,
+    },
+    /// CRC-16-CCITT with the polynomial 0x1021
+    ///
+    /// The result is in the lower 16 bits.
+    Crc16Ccitt {
+        /// Bit-reverse each input byte and the result
+        reversed: bool,
+        /// Invert the bits of the result
+        inverted: bool,
+        /// Initial value of the checksum
+        seed: u16,
+    },
+    /// XOR of all bits transferred, resulting in the even parity in bit 0
+    EvenParity,
+    /// Sum of all words transferred
+    Sum {
+        /// Initial value of the sum
+        seed: u32,
+    },
+}
+
+// Values of the SNIFF_CTRL.CALC field
+const CALC_CRC32: u32 = 0x0;
+const CALC_CRC32_REV: u32 = 0x1;
+const CALC_CRC16: u32 = 0x2;
+const CALC_CRC16_REV: u32 = 0x3;
+const CALC_EVEN: u32 = 0xe;
+const CALC_SUM: u32 = 0xf;
+
+// Bit positions in the SNIFF_CTRL register
+const SNIFF_CTRL_EN: u32 = 1 << 0;
+const SNIFF_CTRL_DMACH_SHIFT: u32 = 1;
+const SNIFF_CTRL_CALC_SHIFT: u32 = 5;
+const SNIFF_CTRL_OUT_REV: u32 = 1 << 10;
+const SNIFF_CTRL_OUT_INV: u32 = 1 << 11;
+
+/// DMA sniffer
+///
+/// The sniffer observes the data moved by a single channel and computes a checksum over it, at no
+/// extra cost. There is only one sniffer, which is returned by [`DMAExt::split`](super::DMAExt::split).
+///
+/// Attach the sniffer before starting the transfer, and read the result once it is done:
+///
+/// ```no_run
+/// use rp2040_hal::{dma::{single_buffer, DMAExt, SniffMode}, pac};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let mut dma = peripherals.DMA.split(&mut peripherals.RESETS);
+/// let from = cortex_m::singleton!(: [u8; 9] = *b"123456789").unwrap();
+/// let to = cortex_m::singleton!(: [u8; 9] = [0; 9]).unwrap();
+/// dma.sniffer.attach(
+///     &dma.ch0,
+///     SniffMode::Crc32 { reversed: true, inverted: true, seed: 0xffff_ffff },
+/// );
+/// let transfer = single_buffer::Config::new(dma.ch0, from, to).start();
+/// let (_ch0, _from, _to) = transfer.wait().unwrap();
+/// assert_eq!(dma.sniffer.result(), 0xcbf4_3926);
+/// ```
+pub struct Sniffer {
+    pub(super) _private: (),
+}
+
+impl Sniffer {
+    /// Attach the sniffer to `ch`, and reset the checksum to the seed of `mode`
+    ///
+    /// Every transfer started on `ch` afterwards advances the checksum.
+    pub fn attach<CH: SingleChannel>(&mut self, ch: &CH, mode: SniffMode) {
+        let (calc, reversed, inverted, seed) = match mode {
+            SniffMode::Crc32 {
+                reversed,
+                inverted,
+                seed,
+            } => (
+                if reversed { CALC_CRC32_REV } else { CALC_CRC32 },
+                reversed,
+                inverted,
+                seed,
+            ),
+            SniffMode::Crc16Ccitt {
+                reversed,
+                inverted,
+                seed,
+            } => (
+                if reversed { CALC_CRC16_REV } else { CALC_CRC16 },
+                reversed,
+                inverted,
+                u32::from(seed),
+            ),
+            SniffMode::EvenParity => (CALC_EVEN, false, false, 0),
+            SniffMode::Sum { seed } => (CALC_SUM, false, false, seed),
+        };
+
+        let mut ctrl = SNIFF_CTRL_EN
+            | u32::from(ch.id()) << SNIFF_CTRL_DMACH_SHIFT
+            | calc << SNIFF_CTRL_CALC_SHIFT;
+        if reversed {
+            ctrl |= SNIFF_CTRL_OUT_REV;
+        }
+        if inverted {
+            ctrl |= SNIFF_CTRL_OUT_INV;
+        }
+
+        // safety: The sniffer registers are only accessed through this singleton
+        let dma = unsafe { &*DMA::ptr() };
+        dma.sniff_data.write(|w| unsafe { w.bits(seed) });
+        dma.sniff_ctrl.write(|w| unsafe { w.bits(ctrl) });
+    }
+
+    /// Stop the sniffer
+    ///
+    /// The last result can still be read.
+    pub fn detach(&mut self) {
+        // safety: The sniffer registers are only accessed through this singleton
+        unsafe { &*DMA::ptr() }
+            .sniff_ctrl
+            .modify(|r, w| unsafe { w.bits(r.bits() & !SNIFF_CTRL_EN) });
+    }
+
+    /// The checksum of the data transferred since the sniffer was attached
+    ///
+    /// The result is only complete once the transfer is done.
+    pub fn result(&self) -> u32 {
+        // safety: This is a read action and should not have any UB
+        unsafe { &*DMA::ptr() }.sniff_data.read().bits()
+    }
+}
@@ -0,0 +1,366 @@
+//! In-application flash erase and program
+//!
+//! The external QSPI flash is normally accessed through the execute-in-place (XIP) window at
+//! [`XIP_BASE`]. To erase or program it, XIP has to be disabled, so the code doing it must not run
+//! from flash. The functions in this module look up the bootrom flash functions while XIP still
+//! works, then call them from a RAM function with interrupts disabled. Afterwards, XIP is restored
+//! by calling a copy of the second stage bootloader, so the flash runs as fast as it did before,
+//! and the XIP cache is flushed. A clock divider selected with
+//! [`xip::set_clock_divider`](crate::xip::set_clock_divider) is kept.
+//!
+//! Addresses are offsets from the start of the flash, not addresses in the XIP window.
+//!
+//! [`unique_id`] and [`jedec_id`] identify the flash chip, and thereby the board, in the same way.
+//! They access the SSI registers while XIP is disabled, from code which stays in RAM in every
+//! build profile.
+//!
+//! # Multicore
+//!
+//! Interrupts are only disabled on the calling core. The other core must not access the flash
+//! while it is erased or programmed, i.e. neither execute code nor read data from it. Either park
+//! it first, e.g. with [`Core::reset`](crate::multicore::Core::reset), or make sure it spins in RAM
+//! for the duration of the call.
+//!
+//! ```no_run
+//! use rp2040_hal::flash::{flash_range_erase, flash_range_program, FLASH_SECTOR_SIZE};
+//! // The last sector of a 2MiB flash
+//! const OFFSET: u32 = 2 * 1024 * 1024 - FLASH_SECTOR_SIZE;
+//! let page = [0x42u8; 256];
+//! unsafe {
+//!     flash_range_erase(OFFSET, FLASH_SECTOR_SIZE).unwrap();
+//!     flash_range_program(OFFSET, &page).unwrap();
+//! }
+//! ```
+
+use crate::rom_data::{rom_table_lookup, FUNC_TABLE};
+use crate::xip;
+use core::cell::Cell;
+use cortex_m::interrupt::Mutex;
+
+/// Start of the XIP window the flash is mapped to
+pub const XIP_BASE: u32 = 0x1000_0000;
+
+/// Smallest unit which can be erased, in bytes
+pub const FLASH_SECTOR_SIZE: u32 = 4096;
+
+/// Smallest unit which can be programmed, in bytes
+pub const FLASH_PAGE_SIZE: u32 = 256;
+
+/// Size of the XIP window, the largest flash which can be addressed
+const FLASH_MAX_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Block erased by the 64KiB block erase command, used for large ranges
+const FLASH_BLOCK_SIZE: u32 = 65536;
+
+/// 64KiB block erase command
+const FLASH_BLOCK_ERASE_CMD: u8 = 0xd8;
+
+/// Errors which prevent a flash operation from starting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The address is not aligned to a sector (erase) or a page (program)
+    UnalignedAddress,
+    /// The length is not a multiple of the sector (erase) or page (program) size
+    UnalignedLength,
+    /// The range doesn't fit into the 16MiB the flash can be addressed with
+    OutOfRange,
+    /// The data to program is stored in the flash, which can't be read while it is programmed
+    DataInFlash,
+}
+
+/// Erase `len` bytes of the flash, starting at `addr`.
+///
+/// `addr` must be aligned to a [`FLASH_SECTOR_SIZE`] sector, and `len` a multiple of it.
+///
+/// # Safety
+///
+/// Nothing must execute or read from the flash while it is erased, see the [module
+/// documentation](self) about the other core. Erasing the program itself, or data which is still
+/// referenced, is undefined behaviour.
+pub unsafe fn flash_range_erase(addr: u32, len: u32) -> Result<(), Error> {
+    check_range(addr, len, FLASH_SECTOR_SIZE)?;
+    write_flash(addr, core::ptr::null(), len, true);
+    Ok(())
+}
+
+/// Program `data` to the flash, starting at `addr`.
+///
+/// `addr` must be aligned to a [`FLASH_PAGE_SIZE`] page, and the length of `data` a multiple of
+/// it. Programming can only clear bits, so the range should be erased first. `data` must not be
+/// stored in the flash itself, copy it to RAM first.
+///
+/// # Safety
+///
+/// Nothing must execute or read from the flash while it is programmed, see the [module
+/// documentation](self) about the other core. Overwriting the program itself, or data which is
+/// still referenced, is undefined behaviour.
+pub unsafe fn flash_range_program(addr: u32, data: &[u8]) -> Result<(), Error> {
+    let len = data.len() as u32;
+    check_range(addr, len, FLASH_PAGE_SIZE)?;
+    let start = data.as_ptr() as u32;
+    if start < XIP_BASE + 0x0400_0000 && start + len > XIP_BASE {
+        return Err(Error::DataInFlash);
+    }
+    write_flash(addr, data.as_ptr(), len, false);
+    Ok(())
+}
+
+fn check_range(addr: u32, len: u32, granularity: u32) -> Result<(), Error> {
+    if addr % granularity != 0 {
+        Err(Error::UnalignedAddress)
+    } else if len % granularity != 0 {
+        Err(Error::UnalignedLength)
+    } else if !matches!(addr.checked_add(len), Some(end) if end <= FLASH_MAX_SIZE) {
+        Err(Error::OutOfRange)
+    } else {
+        Ok(())
+    }
+}
+
+/// Read the 64-bit unique ID of the flash chip, with the 4Bh command.
+///
+/// The RP2040 has no unique ID of its own, so this is commonly used as a serial number, e.g. in
+/// USB descriptors. The flash is only accessed on the first call, the ID is cached afterwards.
+///
+/// # Safety
+///
+/// Nothing must execute or read from the flash during the first call, see the [module
+/// documentation](self) about the other core.
+pub unsafe fn unique_id() -> [u8; 8] {
+    static UNIQUE_ID: Mutex<Cell<Option<[u8; 8]>>> = Mutex::new(Cell::new(None));
+
+    cortex_m::interrupt::free(|cs| {
+        let cache = UNIQUE_ID.borrow(cs);
+        if let Some(id) = cache.get() {
+            return id;
+        }
+        // Command and 4 dummy bytes, followed by the ID
+        let tx = [0x4b, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut rx = [0u8; 13];
+        flash_do_cmd(&tx, &mut rx);
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&rx[5..]);
+        cache.set(Some(id));
+        id
+    })
+}
+
+/// Read the JEDEC ID of the flash chip, with the 9Fh command.
+///
+/// The ID is made of the manufacturer ID in bits 16 to 23, followed by the memory type and the
+/// capacity, e.g. `0xef4015` for the W25Q16JV on the Raspberry Pi Pico. The flash is only accessed
+/// on the first call, the ID is cached afterwards.
+///
+/// # Safety
+///
+/// Nothing must execute or read from the flash during the first call, see the [module
+/// documentation](self) about the other core.
+pub unsafe fn jedec_id() -> u32 {
+    static JEDEC_ID: Mutex<Cell<Option<u32>>> = Mutex::new(Cell::new(None));
+
+    cortex_m::interrupt::free(|cs| {
+        let cache = JEDEC_ID.borrow(cs);
+        if let Some(id) = cache.get() {
+            return id;
+        }
+        let tx = [0x9f, 0, 0, 0];
+        let mut rx = [0u8; 4];
+        flash_do_cmd(&tx, &mut rx);
+        let id = u32::from_be_bytes([0, rx[1], rx[2], rx[3]]);
+        cache.set(Some(id));
+        id
+    })
+}
+
+/// Bootrom functions used while XIP is disabled
+struct FlashFunctions {
+    connect_internal_flash: extern "C" fn(),
+    flash_exit_xip: extern "C" fn(),
+    flash_range_erase: extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: extern "C" fn(),
+}
+
+impl FlashFunctions {
+    fn lookup() -> Self {
+        FlashFunctions {
+            connect_internal_flash: rom_table_lookup(FUNC_TABLE, *b"IF"),
+            flash_exit_xip: rom_table_lookup(FUNC_TABLE, *b"EX"),
+            flash_range_erase: rom_table_lookup(FUNC_TABLE, *b"RE"),
+            flash_range_program: rom_table_lookup(FUNC_TABLE, *b"RP"),
+            flash_flush_cache: rom_table_lookup(FUNC_TABLE, *b"FC"),
+        }
+    }
+}
+
+/// Copy the second stage bootloader to RAM.
+///
+/// It sets up the fastest XIP mode for the flash chip, and returns when it is called as a
+/// function, so it is used to restore XIP. The last word is its checksum.
+unsafe fn copy_boot2() -> [u32; 64] {
+    let mut boot2 = [0u32; 64];
+    core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), 63);
+    boot2
+}
+
+/// Erase or program the flash, with interrupts disabled.
+///
+/// The lookups and the copy of the second stage bootloader need the flash, so they are done
+/// before XIP is disabled. The second stage bootloader selects its own SSI clock divider, so one
+/// selected with [`xip::set_clock_divider`] is selected again afterwards.
+unsafe fn write_flash(addr: u32, data: *const u8, len: u32, erase: bool) {
+    let functions = FlashFunctions::lookup();
+    let boot2 = copy_boot2();
+    let divider = xip::clock_divider();
+
+    cortex_m::interrupt::free(|_| {
+        write_flash_in_ram(&functions, boot2.as_ptr(), addr, data, len, erase);
+        xip::restore_clock_divider(divider);
+    });
+}
+
+/// Send `tx` to the flash and receive as many bytes into `rx`, with interrupts disabled.
+unsafe fn flash_do_cmd(tx: &[u8], rx: &mut [u8]) {
+    let functions = FlashFunctions::lookup();
+    let boot2 = copy_boot2();
+    let divider = xip::clock_divider();
+
+    cortex_m::interrupt::free(|_| {
+        flash_do_cmd_in_ram(&functions, boot2.as_ptr(), tx, rx);
+        xip::restore_clock_divider(divider);
+    });
+}
+
+/// The part which runs while XIP is disabled. It must not call any function in flash, which
+/// includes the non-inlined helpers of `core` in unoptimised builds.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn write_flash_in_ram(
+    functions: &FlashFunctions,
+    boot2: *const u32,
+    addr: u32,
+    data: *const u8,
+    len: u32,
+    erase: bool,
+) {
+    // Set the thumb bit to call the copy of the second stage bootloader
+    let enter_xip: extern "C" fn() = core::mem::transmute(boot2 as usize | 1);
+
+    (functions.connect_internal_flash)();
+    (functions.flash_exit_xip)();
+    if erase {
+        (functions.flash_range_erase)(addr, len as usize, FLASH_BLOCK_SIZE, FLASH_BLOCK_ERASE_CMD);
+    } else {
+        (functions.flash_range_program)(addr, data, len as usize);
+    }
+    (functions.flash_flush_cache)();
+    enter_xip();
+}
+
+/// Read a register in code which runs while XIP is disabled.
+///
+/// Unlike `read_volatile`, the single `ldr` can't end up as a call into the flash in an
+/// unoptimised build.
+#[inline(always)]
+pub(crate) unsafe fn ram_read(address: *const u32) -> u32 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value;
+        core::arch::asm!("ldr {0}, [{1}]", out(reg) value, in(reg) address, options(nostack, preserves_flags));
+        value
+    }
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::read_volatile(address)
+}
+
+/// Write a register in code which runs while XIP is disabled, see [`ram_read`].
+#[inline(always)]
+pub(crate) unsafe fn ram_write(address: *mut u32, value: u32) {
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!("str {0}, [{1}]", in(reg) value, in(reg) address, options(nostack, preserves_flags));
+    #[cfg(not(target_arch = "arm"))]
+    core::ptr::write_volatile(address, value)
+}
+
+/// Read the byte at `base + offset` while XIP is disabled, without bounds checks or pointer
+/// arithmetic that could call into the flash.
+#[inline(always)]
+unsafe fn ram_read_byte(base: *const u8, offset: usize) -> u8 {
+    #[cfg(target_arch = "arm")]
+    {
+        let value: u32;
+        core::arch::asm!("ldrb {0}, [{1}, {2}]", out(reg) value, in(reg) base, in(reg) offset, options(nostack, preserves_flags, readonly));
+        value as u8
+    }
+    #[cfg(not(target_arch = "arm"))]
+    base.wrapping_add(offset).read()
+}
+
+/// Write the byte at `base + offset` while XIP is disabled, see [`ram_read_byte`].
+#[inline(always)]
+unsafe fn ram_write_byte(base: *mut u8, offset: usize, value: u8) {
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!("strb {0}, [{1}, {2}]", in(reg) u32::from(value), in(reg) base, in(reg) offset, options(nostack, preserves_flags));
+    #[cfg(not(target_arch = "arm"))]
+    base.wrapping_add(offset).write(value)
+}
+
+/// XIP_SSI status register
+pub(crate) const SSI_SR: *const u32 = 0x1800_0028 as _;
+/// XIP_SSI data register
+const SSI_DR0: *mut u32 = 0x1800_0060 as _;
+/// Transmit FIFO not full
+const SSI_SR_TFNF: u32 = 1 << 1;
+/// Receive FIFO not empty
+const SSI_SR_RFNE: u32 = 1 << 3;
+/// IO_QSPI control register of the chip select
+const QSPI_SS_CTRL: *mut u32 = 0x4001_800c as _;
+/// Output override bits of the chip select
+const QSPI_SS_OUTOVER_MASK: u32 = 0x3 << 8;
+/// Drive the chip select low
+const QSPI_SS_OUTOVER_LOW: u32 = 0x2 << 8;
+/// Drive the chip select high
+const QSPI_SS_OUTOVER_HIGH: u32 = 0x3 << 8;
+
+/// Send a command to the flash while XIP is disabled. The same restrictions as for
+/// [`write_flash_in_ram`] apply, so the registers and buffers are accessed with the `ram_*`
+/// helpers, and the counters only use wrapping arithmetic, which has no panic path.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_do_cmd_in_ram(
+    functions: &FlashFunctions,
+    boot2: *const u32,
+    tx: &[u8],
+    rx: &mut [u8],
+) {
+    // Set the thumb bit to call the copy of the second stage bootloader
+    let enter_xip: extern "C" fn() = core::mem::transmute(boot2 as usize | 1);
+    // Read before XIP is disabled, `rx` has the same length
+    let (tx, rx, count) = (tx.as_ptr(), rx.as_mut_ptr(), tx.len());
+
+    (functions.connect_internal_flash)();
+    (functions.flash_exit_xip)();
+
+    let ss_ctrl = ram_read(QSPI_SS_CTRL) & !QSPI_SS_OUTOVER_MASK;
+    ram_write(QSPI_SS_CTRL, ss_ctrl | QSPI_SS_OUTOVER_LOW);
+
+    // Keep the bytes in flight below the depth of the receive FIFO, so it can't overflow
+    const MAX_IN_FLIGHT: usize = 16 - 2;
+    let (mut sent, mut received) = (0usize, 0usize);
+    while sent < count || received < count {
+        let sr = ram_read(SSI_SR);
+        if sr & SSI_SR_TFNF != 0 && sent < count && sent.wrapping_sub(received) < MAX_IN_FLIGHT {
+            ram_write(SSI_DR0, u32::from(ram_read_byte(tx, sent)));
+            sent = sent.wrapping_add(1);
+        }
+        if sr & SSI_SR_RFNE != 0 && received < count {
+            ram_write_byte(rx, received, ram_read(SSI_DR0) as u8);
+            received = received.wrapping_add(1);
+        }
+    }
+
+    ram_write(QSPI_SS_CTRL, ss_ctrl | QSPI_SS_OUTOVER_HIGH);
+
+    (functions.flash_flush_cache)();
+    enter_xip();
+}
@@ -503,6 +503,17 @@ where
         self.into_mode()
     }
 
+    /// Configure the pin for use as an analog input
+    ///
+    /// The digital input buffer and the pulls are disabled so they cannot
+    /// disturb the voltage seen by the ADC. This is the mode required by the
+    /// [`adc`](crate::adc) channel implementations. Converting the pin into any
+    /// input or function mode afterwards re-enables the digital input.
+    #[inline]
+    pub fn into_analog_input(self) -> Pin<I, FloatingDisabled> {
+        self.into_mode()
+    }
+
     /// Configure the pin to operate as a floating input
     #[inline]
     pub fn into_floating_input(self) -> Pin<I, FloatingInput> {
@@ -587,6 +598,21 @@ where
         self.regs.set_interrupt_enabled(interrupt, enabled);
     }
 
+    /// Does the interrupt wake the chip from DORMANT state.
+    #[inline]
+    pub fn is_dormant_wake_enabled(&self, interrupt: Interrupt) -> bool {
+        self.regs.is_dormant_wake_enabled(interrupt)
+    }
+
+    /// Enable or disable waking the chip from DORMANT state on the interrupt.
+    ///
+    /// The interrupt has to be cleared with [`clear_interrupt`](Self::clear_interrupt) after
+    /// waking up, edge interrupts would wake the chip again otherwise.
+    #[inline]
+    pub fn set_dormant_wake_enabled(&self, interrupt: Interrupt, enabled: bool) {
+        self.regs.set_dormant_wake_enabled(interrupt, enabled);
+    }
+
     /// Is interrupt forced.
     #[inline]
     pub fn is_interrupt_forced(&self, interrupt: Interrupt) -> bool {
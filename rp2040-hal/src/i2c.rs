@@ -46,6 +46,7 @@
 use core::{marker::PhantomData, ops::Deref};
 
 use crate::{
+    clocks::{ClockGate, ClockGated},
     gpio::pin::bank0::{
         BankPinId, Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17,
         Gpio18, Gpio19, Gpio2, Gpio20, Gpio21, Gpio26, Gpio27, Gpio3, Gpio4, Gpio5, Gpio6, Gpio7,
@@ -197,6 +198,13 @@ where
     }
 }
 
+impl<Block: ClockGated, PINS, Mode> I2C<Block, PINS, Mode> {
+    /// The clock gates of the I2C, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate {
+        Block::CLOCK_GATE
+    }
+}
+
 impl<Block: Deref<Target = I2CBlock>, PINS, Mode> I2C<Block, PINS, Mode> {
     /// Number of bytes currently in the RX FIFO
     #[inline]
@@ -0,0 +1,116 @@
+//! Compiler intrinsics backed by the bootrom
+//!
+//! With the `rom-intrinsics` feature, the `__aeabi_*` functions the compiler calls for `f32` and
+//! `f64` arithmetic are exported from here, and call the optimised implementations in the bootrom
+//! instead of the ones of `compiler-builtins`.
+//!
+//! Each function looks up its bootrom implementation on the first call and keeps the address in
+//! RAM. The `f64` functions are only in version 2 of the bootrom and later (B1 silicon), so on B0
+//! they fall back to the `compiler-builtins` implementation.
+//!
+//! The bootrom has no 64-bit integer division. `__aeabi_uldivmod` uses the hardware divider of the
+//! SIO when both operands fit in 32 bits, and `compiler-builtins` otherwise; `__aeabi_ldivmod` is
+//! left to `compiler-builtins`. The float to integer conversions are left as well, as the bootrom
+//! rounds towards minus infinity instead of zero.
+
+use crate::rom_data::{rom_version_number, soft_double_table, soft_float_table};
+use crate::sio::HwDivider;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// The software implementations of `compiler-builtins`
+extern "C" {
+    fn __addsf3(a: f32, b: f32) -> f32;
+    fn __subsf3(a: f32, b: f32) -> f32;
+    fn __mulsf3(a: f32, b: f32) -> f32;
+    fn __divsf3(a: f32, b: f32) -> f32;
+    fn __floatsisf(v: i32) -> f32;
+    fn __floatunsisf(v: u32) -> f32;
+    fn __extendsfdf2(v: f32) -> f64;
+    fn __adddf3(a: f64, b: f64) -> f64;
+    fn __subdf3(a: f64, b: f64) -> f64;
+    fn __muldf3(a: f64, b: f64) -> f64;
+    fn __divdf3(a: f64, b: f64) -> f64;
+    fn __floatsidf(v: i32) -> f64;
+    fn __floatunsidf(v: u32) -> f64;
+    fn __truncdfsf2(v: f64) -> f32;
+    fn __udivmoddi4(n: u64, d: u64, rem: *mut u64) -> u64;
+}
+
+/// Look up the entry at byte `offset` of `table` on the first call, or `fallback` if the bootrom
+/// is older than `min_version`, and return the address stored in `cache` afterwards.
+fn cached_entry(
+    cache: &AtomicUsize,
+    table: fn() -> *const usize,
+    offset: usize,
+    min_version: u8,
+    fallback: usize,
+) -> usize {
+    let mut ptr = cache.load(Ordering::Relaxed);
+    if ptr == 0 {
+        ptr = if rom_version_number() >= min_version {
+            unsafe { core::ptr::read(table().add(offset / 4)) }
+        } else {
+            fallback
+        };
+        cache.store(ptr, Ordering::Relaxed);
+    }
+    ptr
+}
+
+/// Define each `$name` to call the function at `$offset` of `$table`, if the bootrom version is at
+/// least `$min_version`, or `$fallback` otherwise
+macro_rules! rom_intrinsics {
+    (
+        $(
+            $table:ident[$offset:literal] $min_version:literal
+            fn $name:ident( $( $aname:ident : $aty:ty ),* ) -> $ret:ty = $fallback:ident;
+        )*
+    ) => {
+        $(
+            #[no_mangle]
+            pub extern "C" fn $name($( $aname: $aty ),*) -> $ret {
+                static FUNC: AtomicUsize = AtomicUsize::new(0);
+                let ptr = cached_entry(&FUNC, $table, $offset, $min_version, $fallback as *const () as usize);
+                let func: extern "C" fn($( $aty ),*) -> $ret = unsafe { core::mem::transmute(ptr) };
+                func($( $aname ),*)
+            }
+        )*
+    };
+}
+
+rom_intrinsics! {
+    soft_float_table[0x00] 1 fn __aeabi_fadd(a: f32, b: f32) -> f32 = __addsf3;
+    soft_float_table[0x04] 1 fn __aeabi_fsub(a: f32, b: f32) -> f32 = __subsf3;
+    soft_float_table[0x08] 1 fn __aeabi_fmul(a: f32, b: f32) -> f32 = __mulsf3;
+    soft_float_table[0x0c] 1 fn __aeabi_fdiv(a: f32, b: f32) -> f32 = __divsf3;
+    soft_float_table[0x2c] 1 fn __aeabi_i2f(v: i32) -> f32 = __floatsisf;
+    soft_float_table[0x34] 1 fn __aeabi_ui2f(v: u32) -> f32 = __floatunsisf;
+    soft_float_table[0x7c] 2 fn __aeabi_f2d(v: f32) -> f64 = __extendsfdf2;
+    soft_double_table[0x00] 2 fn __aeabi_dadd(a: f64, b: f64) -> f64 = __adddf3;
+    soft_double_table[0x04] 2 fn __aeabi_dsub(a: f64, b: f64) -> f64 = __subdf3;
+    soft_double_table[0x08] 2 fn __aeabi_dmul(a: f64, b: f64) -> f64 = __muldf3;
+    soft_double_table[0x0c] 2 fn __aeabi_ddiv(a: f64, b: f64) -> f64 = __divdf3;
+    soft_double_table[0x2c] 2 fn __aeabi_i2d(v: i32) -> f64 = __floatsidf;
+    soft_double_table[0x34] 2 fn __aeabi_ui2d(v: u32) -> f64 = __floatunsidf;
+    soft_double_table[0x7c] 2 fn __aeabi_d2f(v: f64) -> f32 = __truncdfsf2;
+}
+
+/// Divide `n` by `d`, returning the quotient in the low and the remainder in the high 64 bits,
+/// i.e. in r0-r1 and r2-r3 as the AEABI requires
+///
+/// The state of the hardware divider is saved and restored, as this may interrupt code using it.
+#[no_mangle]
+pub extern "C" fn __aeabi_uldivmod(n: u64, d: u64) -> u128 {
+    let (quotient, remainder) = if n >> 32 == 0 && d >> 32 == 0 {
+        let divider = HwDivider::steal();
+        let state = divider.save_state();
+        let result = divider.unsigned(n as u32, d as u32);
+        divider.restore_state(&state);
+        (u64::from(result.quotient), u64::from(result.remainder))
+    } else {
+        let mut remainder = 0;
+        let quotient = unsafe { __udivmoddi4(n, d, &mut remainder) };
+        (quotient, remainder)
+    };
+    u128::from(quotient) | u128::from(remainder) << 64
+}
@@ -17,11 +17,16 @@ pub extern crate rp2040_pac as pac;
 
 pub mod adc;
 pub(crate) mod atomic_register_access;
+pub mod chip_reset;
 pub mod clocks;
+#[cfg(feature = "critical-section-impl")]
 mod critical_section_impl;
 pub mod dma;
+pub mod flash;
 pub mod gpio;
 pub mod i2c;
+#[cfg(feature = "rom-intrinsics")]
+mod intrinsics;
 pub mod multicore;
 pub mod pio;
 pub mod pll;
@@ -32,13 +37,17 @@ pub mod rom_data;
 pub mod rosc;
 pub mod rtc;
 pub mod sio;
+pub mod sleep;
 pub mod spi;
 pub mod ssi;
 pub mod timer;
 pub mod typelevel;
 pub mod uart;
+#[cfg(feature = "usb")]
 pub mod usb;
+pub mod vreg;
 pub mod watchdog;
+pub mod xip;
 pub mod xosc;
 
 // Provide access to common datastructures to avoid repeating ourselves
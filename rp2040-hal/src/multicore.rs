@@ -3,40 +3,45 @@
 //! This module handles setup of the 2nd cpu core on the rp2040, which we refer to as core1.
 //! It provides functionality for setting up the stack, and starting core1.
 //!
-//! The options for an entrypoint for core1 are
-//! - a function that never returns - eg
-//! `fn core1_task() -> ! { loop{} }; `
-//! - a lambda (note: This requires a global allocator which requires a nightly compiler. Not recommended for beginners)
+//! The entrypoint for core1 is any closure (or function) that never returns. The closure is
+//! moved to core1, so it can capture the data core1 needs, eg its peripherals:
+//! `move || -> ! { loop {} }`
 //!
 //! # Usage
 //!
 //! ```no_run
+//! use rp2040_hal::{pac, sio::Sio, multicore::{Multicore, Stack}};
+//!
 //! static mut CORE1_STACK: Stack<4096> = Stack::new();
-//! fn core1_task() -> ! {
-//!     loop{}
-//! }
+//!
 //! // fn main() -> ! {
-//!     use rp2040_hal::{pac, gpio::Pins, sio::Sio, multicore::{Multicore, Stack}};
 //!     let mut pac = pac::Peripherals::take().unwrap();
 //!     let mut sio = Sio::new(pac.SIO);
 //!     // Other init code above this line
-//!     let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio);
+//!     let mut mc = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
 //!     let cores = mc.cores();
 //!     let core1 = &mut cores[1];
-//!     let _test = core1.spawn(core1_task, unsafe { &mut CORE1_STACK.mem });
+//!     let value = 42;
+//!     let _test = core1.spawn(unsafe { &mut CORE1_STACK.mem }, move || {
+//!         let _captured = value;
+//!         loop {}
+//!     });
 //!     // The rest of your application below this line
 //! //}
 //!
 //! ```
 //!
-//! For inter-processor communications, see [`crate::sio::SioFifo`] and [`crate::sio::Spinlock0`]
+//! For inter-processor communications, see [`crate::sio::SioFifo`] and [`crate::sio::Spinlock0`].
+//! Core 1's end of the FIFO is taken from [`crate::sio::SioCore1`], moved into the spawned closure.
 //!
 //! For a detailed example, see [examples/multicore_fifo_blink.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/multicore_fifo_blink.rs)
 
-use crate::pac;
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
 
-#[cfg(feature = "alloc")]
-extern crate alloc;
+use crate::pac;
+use crate::sio::{CoreId, Sio, SioFifo};
 
 /// Errors for multicore operations.
 #[derive(Debug)]
@@ -47,15 +52,6 @@ pub enum Error {
     Unresponsive,
 }
 
-// We pass data to cores via the stack, so we read
-// the data off the stack and into parameters that
-// rust can read here. Ideally this would be a
-// #[naked] function but that is not stable yet.
-static MULTICORE_TRAMPOLINE: [u16; 2] = [
-    0xbd03, // pop {r0, r1, pc} - call wrapper (pc) with r0 and r1
-    0x46c0, // nop - pad this out to 32 bits long
-];
-
 #[inline(always)]
 fn install_stack_guard(stack_bottom: *mut usize) {
     let core = unsafe { pac::CorePeripherals::steal() };
@@ -82,6 +78,19 @@ fn install_stack_guard(stack_bottom: *mut usize) {
     }
 }
 
+/// Force core 1 off and back on through the PSM, after which it runs the boot ROM and waits for
+/// the launch sequence.
+fn reset_core1(psm: &mut pac::PSM, fifo: &mut SioFifo) {
+    psm.frce_off.modify(|_, w| w.proc1().set_bit());
+    while !psm.frce_off.read().proc1().bit_is_set() {
+        cortex_m::asm::nop();
+    }
+    psm.frce_off.modify(|_, w| w.proc1().clear_bit());
+
+    // Throw away anything core 1 sent before it was reset
+    fifo.drain();
+}
+
 #[inline(always)]
 fn core1_setup(stack_bottom: *mut usize) {
     install_stack_guard(stack_bottom);
@@ -109,12 +118,12 @@ impl<const SIZE: usize> Stack<SIZE> {
 
 impl<'p> Multicore<'p> {
     /// Create a new |Multicore| instance.
-    pub fn new(psm: &'p mut pac::PSM, ppb: &'p mut pac::PPB, sio: &'p mut crate::Sio) -> Self {
+    pub fn new(psm: &'p mut pac::PSM, ppb: &'p mut pac::PPB, fifo: &'p mut SioFifo) -> Self {
         Self {
             cores: [
                 Core { inner: None },
                 Core {
-                    inner: Some((psm, ppb, sio)),
+                    inner: Some((psm, ppb, fifo)),
                 },
             ],
         }
@@ -128,7 +137,7 @@ impl<'p> Multicore<'p> {
 
 /// A handle for controlling a logical core.
 pub struct Core<'p> {
-    inner: Option<(&'p mut pac::PSM, &'p mut pac::PPB, &'p mut crate::Sio)>,
+    inner: Option<(&'p mut pac::PSM, &'p mut pac::PPB, &'p mut SioFifo)>,
 }
 
 impl<'p> Core<'p> {
@@ -140,31 +149,71 @@ impl<'p> Core<'p> {
         }
     }
 
-    fn inner_spawn(
-        &mut self,
-        wrapper: *mut (),
-        entry: *mut (),
-        stack: &'static mut [usize],
-    ) -> Result<(), Error> {
-        if let Some((psm, ppb, sio)) = self.inner.as_mut() {
-            // Reset the core
-            psm.frce_off.modify(|_, w| w.proc1().set_bit());
-            while !psm.frce_off.read().proc1().bit_is_set() {
-                cortex_m::asm::nop();
+    /// Reset this core back to its state after boot, where it waits to be launched.
+    ///
+    /// Whatever core 1 was running is stopped, and the FIFO is drained, so it can be started
+    /// again with [`spawn`](Self::spawn). This is fine to call if core 1 was never started.
+    /// Nothing core 1 had claimed, eg a spinlock, is released.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if let Some((psm, _, fifo)) = self.inner.as_mut() {
+            reset_core1(psm, fifo);
+            Ok(())
+        } else {
+            Err(Error::InvalidCore)
+        }
+    }
+
+    /// Spawn a function on this core.
+    ///
+    /// Core 1 is reset first, so this also works if it was running code from before a soft reset.
+    /// The closure is moved to core 1, and this only returns once core 1 has taken it over. Move
+    /// [`Sio::core1`](crate::sio::Sio::core1) into it for core 1's own SIO blocks.
+    pub fn spawn<F>(&mut self, stack: &'static mut [usize], entry: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> bad::Never + Send + 'static,
+    {
+        if let Some((psm, ppb, fifo)) = self.inner.as_mut() {
+            // The two ignored `u64` parameters take up all of the argument registers, so the other
+            // arguments are read from the stack, where core 0 puts them.
+            extern "C" fn core1_startup<F: FnOnce() -> bad::Never>(
+                _: u64,
+                _: u64,
+                entry: &mut ManuallyDrop<F>,
+                stack_bottom: *mut usize,
+            ) -> ! {
+                core1_setup(stack_bottom);
+
+                let entry = unsafe { ManuallyDrop::take(entry) };
+
+                // Signal that core 0 may now get rid of the original value.
+                let sio = unsafe { &(*pac::SIO::ptr()) };
+                while sio.fifo_st.read().rdy().bit_is_clear() {}
+                sio.fifo_wr.write(|w| unsafe { w.bits(1) });
+                cortex_m::asm::sev();
+
+                entry()
             }
-            psm.frce_off.modify(|_, w| w.proc1().clear_bit());
+
+            reset_core1(psm, fifo);
 
             // Set up the stack
             let mut stack_ptr = unsafe { stack.as_mut_ptr().add(stack.len()) };
 
-            let mut push = |v: usize| unsafe {
+            // The closure is moved to core 1, it must not be dropped here.
+            let mut entry = ManuallyDrop::new(entry);
+
+            // Push the arguments of `core1_startup` onto the stack.
+            unsafe {
+                stack_ptr = stack_ptr.sub(1);
+                stack_ptr.cast::<*mut usize>().write(stack.as_mut_ptr());
+
                 stack_ptr = stack_ptr.sub(1);
-                stack_ptr.write(v);
-            };
+                stack_ptr.cast::<&mut ManuallyDrop<F>>().write(&mut entry);
+            }
 
-            push(wrapper as usize);
-            push(stack.as_mut_ptr() as usize);
-            push(entry as usize);
+            // Make sure the stack writes are not reordered after the FIFO writes below, as core 1
+            // would not see them.
+            compiler_fence(Ordering::Release);
 
             let vector_table = ppb.vtor.read().bits();
 
@@ -176,7 +225,7 @@ impl<'p> Core<'p> {
                 1,
                 vector_table as usize,
                 stack_ptr as usize,
-                MULTICORE_TRAMPOLINE.as_ptr() as usize + 1,
+                core1_startup::<F> as *const () as usize,
             ];
 
             let mut seq = 0;
@@ -184,11 +233,11 @@ impl<'p> Core<'p> {
             loop {
                 let cmd = cmd_seq[seq] as u32;
                 if cmd == 0 {
-                    sio.fifo.drain();
+                    fifo.drain();
                     cortex_m::asm::sev();
                 }
-                sio.fifo.write_blocking(cmd);
-                let response = sio.fifo.read_blocking();
+                fifo.write_blocking(cmd);
+                let response = fifo.read_blocking();
                 if cmd == response {
                     seq += 1;
                 } else {
@@ -203,48 +252,17 @@ impl<'p> Core<'p> {
                 }
             }
 
+            // Wait until core 1 has taken the closure over before `entry` goes out of scope.
+            fifo.read_blocking();
+
             Ok(())
         } else {
             Err(Error::InvalidCore)
         }
     }
-
-    /// Spawn a function on this core.
-    #[cfg(not(feature = "alloc"))]
-    pub fn spawn(&mut self, entry: fn() -> !, stack: &'static mut [usize]) -> Result<(), Error> {
-        #[allow(improper_ctypes_definitions)]
-        extern "C" fn core1_no_alloc(entry: fn() -> !, stack_bottom: *mut usize) -> ! {
-            core1_setup(stack_bottom);
-            entry();
-        }
-
-        self.inner_spawn(core1_no_alloc as _, entry as _, stack)
-    }
-
-    /// Spawn a function on this core.
-    #[cfg(feature = "alloc")]
-    pub fn spawn<F>(&mut self, entry: F, stack: &'static mut [usize]) -> Result<(), Error>
-    where
-        F: FnOnce() -> bad::Never,
-        F: Send + 'static,
-    {
-        use alloc::boxed::Box;
-
-        let main: Box<dyn FnOnce() -> bad::Never> = Box::new(move || entry());
-        let p = Box::into_raw(Box::new(main));
-
-        extern "C" fn core1_alloc(entry: *mut (), stack_bottom: *mut usize) -> ! {
-            core1_setup(stack_bottom);
-            let main = unsafe { Box::from_raw(entry as *mut Box<dyn FnOnce() -> bad::Never>) };
-            main();
-        }
-
-        self.inner_spawn(core1_alloc as _, p as _, stack)
-    }
 }
 
 // https://github.com/nvzqz/bad-rs/blob/master/src/never.rs
-#[cfg(feature = "alloc")]
 mod bad {
     pub(crate) type Never = <F as HasOutput>::Output;
 
@@ -258,3 +276,61 @@ mod bad {
 
     type F = fn() -> !;
 }
+
+/// One value per core, each core only accessing its own
+///
+/// This is useful for state each core keeps for itself, eg a logger. The value is accessed with
+/// [`with`](Self::with), with interrupts disabled on the current core.
+///
+/// ```no_run
+/// use rp2040_hal::multicore::CoreLocal;
+///
+/// static COUNTER: CoreLocal<u32> = CoreLocal::new(0, 0);
+///
+/// let count = COUNTER.with(|counter| {
+///     *counter += 1;
+///     *counter
+/// });
+/// ```
+pub struct CoreLocal<T> {
+    values: [UnsafeCell<T>; 2],
+    borrowed: [AtomicBool; 2],
+}
+
+// Safety: each core only accesses its own value, with interrupts disabled and a check against
+// reentrancy. The value of core 1 may be created on core 0, hence `T: Send`.
+unsafe impl<T: Send> Sync for CoreLocal<T> {}
+
+impl<T> CoreLocal<T> {
+    /// Create the cell, with the initial values for core 0 and core 1.
+    pub const fn new(core0: T, core1: T) -> Self {
+        CoreLocal {
+            values: [UnsafeCell::new(core0), UnsafeCell::new(core1)],
+            borrowed: [AtomicBool::new(false), AtomicBool::new(false)],
+        }
+    }
+
+    /// Access the value of the current core.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within `f`.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let core = match Sio::core() {
+            CoreId::Core0 => 0,
+            CoreId::Core1 => 1,
+        };
+        cortex_m::interrupt::free(|_| {
+            assert!(
+                !self.borrowed[core].load(Ordering::Relaxed),
+                "CoreLocal already borrowed"
+            );
+            self.borrowed[core].store(true, Ordering::Relaxed);
+            // Safety: only this core accesses this value, interrupts are disabled and it is not
+            // borrowed yet
+            let result = f(unsafe { &mut *self.values[core].get() });
+            self.borrowed[core].store(false, Ordering::Relaxed);
+            result
+        })
+    }
+}
@@ -1,18 +1,31 @@
 //! Programmable IO (PIO)
 //! See [Chapter 3 of the datasheet](https://rptl.io/rp2040-datasheet#section_pio) for more details.
 use crate::{
-    atomic_register_access::{write_bitmask_clear, write_bitmask_set},
+    atomic_register_access::{write_bitmask_clear, write_bitmask_set, write_bitmask_xor},
+    dma::{EndlessReadTarget, EndlessWriteTarget, ReadTarget, Word, WriteTarget},
+    gpio::{Function, FunctionConfig, Pin, PinId, ValidPinMode},
     resets::SubsystemReset,
 };
+use embedded_time::{fixed_point::FixedPoint, rate::Hertz};
 use pio::{Program, SideSet, Wrap};
 use rp2040_pac::{PIO0, PIO1};
 
 const PIO_INSTRUCTION_COUNT: usize = 32;
 
+/// Bit of SM_SHIFTCTRL enabling autopush
+const SHIFTCTRL_AUTOPUSH: u32 = 16;
+/// Bit of SM_SHIFTCTRL giving the memory of the RX FIFO to the TX FIFO
+const SHIFTCTRL_FJOIN_TX: u32 = 30;
+/// Bit of SM_SHIFTCTRL giving the memory of the TX FIFO to the RX FIFO
+const SHIFTCTRL_FJOIN_RX: u32 = 31;
+
 /// PIO Instance
 pub trait PIOExt:
     core::ops::Deref<Target = rp2040_pac::pio0::RegisterBlock> + SubsystemReset + Sized + Send
 {
+    /// Numerical index of the PIO block (0 or 1).
+    fn id() -> usize;
+
     /// Create a new PIO wrapper and split the state machines into individual objects.
     #[allow(clippy::type_complexity)] // Required for symmetry with PIO::free().
     fn split(
@@ -73,8 +86,16 @@ pub trait PIOExt:
     }
 }
 
-impl PIOExt for PIO0 {}
-impl PIOExt for PIO1 {}
+impl PIOExt for PIO0 {
+    fn id() -> usize {
+        0
+    }
+}
+impl PIOExt for PIO1 {
+    fn id() -> usize {
+        1
+    }
+}
 
 /// Programmable IO Block
 pub struct PIO<P: PIOExt> {
@@ -118,6 +139,34 @@ impl<P: PIOExt> PIO<P> {
         &self.interrupts
     }
 
+    /// One of this PIO's interrupts.
+    ///
+    /// Each PIO block drives two interrupt lines (e.g. `PIO0_IRQ_0` and `PIO0_IRQ_1`), which can
+    /// be handled by different cores.
+    pub fn interrupt(&self, index: IrqIndex) -> &Interrupt<P> {
+        &self.interrupts[index as usize]
+    }
+
+    /// State of the 8 IRQ flags, set by `irq` instructions of the state machines.
+    ///
+    /// Flags 0 to 3 can be routed to the system interrupts with
+    /// [`Interrupt::enable_sm_interrupt`].
+    pub fn sm_irq_state(&self) -> u8 {
+        self.get_irq_raw()
+    }
+
+    /// Clear IRQ flag `n` (0 to 7), e.g. to acknowledge an `irq wait` of a state machine.
+    pub fn clear_sm_irq(&self, n: u8) {
+        assert!(n < 8, "invalid IRQ flag number");
+        self.clear_irq(1 << n);
+    }
+
+    /// Set IRQ flag `n` (0 to 7), as if a state machine executed `irq set n`.
+    pub fn force_sm_irq(&self, n: u8) {
+        assert!(n < 8, "invalid IRQ flag number");
+        self.force_irq(1 << n);
+    }
+
     /// Get raw irq flags.
     ///
     /// The PIO has 8 IRQ flags, of which 4 are visible to the host processor. Each bit of `flags` corresponds to one of
@@ -148,26 +197,38 @@ impl<P: PIOExt> PIO<P> {
             .write(|w| unsafe { w.irq_force().bits(flags) });
     }
 
-    fn find_offset_for_instructions(&self, i: &[u16], origin: Option<u8>) -> Option<usize> {
+    /// Number of free instructions in the instruction memory.
+    ///
+    /// The free space might be fragmented, so a program of this length can't necessarily be
+    /// installed.
+    pub fn free_instruction_space(&self) -> usize {
+        PIO_INSTRUCTION_COUNT - self.used_instruction_space.count_ones() as usize
+    }
+
+    fn find_offset_for_instructions(
+        &self,
+        i: &[u16],
+        origin: Option<u8>,
+    ) -> Result<usize, InstallError> {
         if i.len() > PIO_INSTRUCTION_COUNT {
-            None
+            Err(InstallError::NoSpace)
         } else {
-            let mask = (1 << i.len()) - 1;
+            let mask = instruction_mask(i.len());
             if let Some(origin) = origin {
-                if origin as usize > PIO_INSTRUCTION_COUNT - i.len()
-                    || self.used_instruction_space & (mask << origin) != 0
-                {
-                    None
+                if origin as usize > PIO_INSTRUCTION_COUNT - i.len() {
+                    Err(InstallError::OriginOutOfRange)
+                } else if self.used_instruction_space & (mask << origin) != 0 {
+                    Err(InstallError::OriginOccupied)
                 } else {
-                    Some(origin as usize)
+                    Ok(origin as usize)
                 }
             } else {
-                for i in (0..=32 - i.len()).rev() {
+                for i in (0..=PIO_INSTRUCTION_COUNT - i.len()).rev() {
                     if self.used_instruction_space & (mask << i) == 0 {
-                        return Some(i);
+                        return Ok(i);
                     }
                 }
-                None
+                Err(InstallError::NoSpace)
             }
         }
     }
@@ -177,57 +238,71 @@ impl<P: PIOExt> PIO<P> {
     /// The function returns a handle to the installed program that can be used to configure a
     /// `StateMachine` via `PIOBuilder`. The program can be uninstalled to free instruction memory
     /// via `uninstall()` once the state machine using the program has been uninitialized.
+    ///
+    /// Programs with an `.origin` are placed at that address, all others at the highest free
+    /// address which fits the program. Jumps are relocated to the address the program is placed
+    /// at.
     // Safety: PIOExt is marked send and should be the only object allowed to access pio.instr_mem
     pub fn install(
         &mut self,
         p: &Program<{ pio::RP2040_MAX_PROGRAM_SIZE }>,
     ) -> Result<InstalledProgram<P>, InstallError> {
-        if let Some(offset) = self.find_offset_for_instructions(&p.code, p.origin) {
-            for (i, instr) in p
-                .code
-                .iter()
-                .map(|instr| {
-                    let mut instr = pio::Instruction::decode(*instr, p.side_set).unwrap();
-
-                    instr.operands = match instr.operands {
-                        pio::InstructionOperands::JMP { condition, address } => {
-                            // JMP instruction. We need to apply offset here
-                            let address = address + offset as u8;
-                            assert!(
-                                address < pio::RP2040_MAX_PROGRAM_SIZE as u8,
-                                "Invalid JMP out of the program after offset addition"
-                            );
-                            pio::InstructionOperands::JMP { condition, address }
-                        }
-                        _ => instr.operands,
-                    };
-
-                    instr.encode(p.side_set)
-                })
-                .enumerate()
-            {
-                self.pio.instr_mem[i + offset].write(|w| unsafe { w.bits(instr as u32) })
-            }
-            self.used_instruction_space |= ((1 << p.code.len()) - 1) << offset;
-            Ok(InstalledProgram {
-                offset: offset as u8,
-                length: p.code.len() as u8,
-                side_set: p.side_set,
-                wrap: p.wrap,
-                _phantom: core::marker::PhantomData,
+        let offset = self.find_offset_for_instructions(&p.code, p.origin)?;
+        for (i, instr) in p
+            .code
+            .iter()
+            .map(|instr| {
+                let mut instr = pio::Instruction::decode(*instr, p.side_set).unwrap();
+
+                instr.operands = match instr.operands {
+                    pio::InstructionOperands::JMP { condition, address } => {
+                        // JMP instruction. We need to apply offset here
+                        let address = address + offset as u8;
+                        assert!(
+                            address < pio::RP2040_MAX_PROGRAM_SIZE as u8,
+                            "Invalid JMP out of the program after offset addition"
+                        );
+                        pio::InstructionOperands::JMP { condition, address }
+                    }
+                    _ => instr.operands,
+                };
+
+                instr.encode(p.side_set)
             })
-        } else {
-            Err(InstallError::NoSpace)
+            .enumerate()
+        {
+            self.pio.instr_mem[i + offset].write(|w| unsafe { w.bits(instr as u32) })
         }
+        self.used_instruction_space |= instruction_mask(p.code.len()) << offset;
+        Ok(InstalledProgram {
+            offset: offset as u8,
+            length: p.code.len() as u8,
+            side_set: p.side_set,
+            wrap: p.wrap,
+            _phantom: core::marker::PhantomData,
+        })
     }
 
     /// Removes the specified program from instruction memory, freeing the allocated space.
+    ///
+    /// The handle is consumed when a state machine is built with the program, and only returned
+    /// by `StateMachine::uninit()`, so a program can't be uninstalled while a state machine uses
+    /// it (unless the handle was duplicated with the unsafe `InstalledProgram::share()`).
     pub fn uninstall(&mut self, p: InstalledProgram<P>) {
-        let instr_mask = ((1 << p.length as u32) - 1) << p.offset as u32;
+        let instr_mask = instruction_mask(p.length as usize) << p.offset as u32;
         self.used_instruction_space &= !instr_mask;
     }
 }
 
+/// Bitmask with the lowest `len` bits set, for `used_instruction_space`.
+fn instruction_mask(len: usize) -> u32 {
+    if len >= PIO_INSTRUCTION_COUNT {
+        u32::MAX
+    } else {
+        (1 << len) - 1
+    }
+}
+
 /// Handle to a program that was placed in the PIO's instruction memory.
 ///
 /// Objects of this type can be reused for multiple state machines of the same PIO block to save
@@ -254,7 +329,7 @@ impl<P: PIOExt> PIO<P> {
 /// ).program;
 /// let installed = pio.install(&program).unwrap();
 /// // Configure a state machine to use the program.
-/// let (sm, rx, tx) = PIOBuilder::from_program(installed).build(sm0);
+/// let (sm, rx, tx) = PIOBuilder::from_program(installed).build(sm0).unwrap();
 /// // Uninitialize the state machine again, freeing the program.
 /// let (sm, installed) = sm.uninit(rx, tx);
 /// // Uninstall the program to free instruction memory.
@@ -293,6 +368,16 @@ impl<P: PIOExt> InstalledProgram<P> {
         self.offset + self.wrap.target
     }
 
+    /// Address of the first instruction of the program.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    /// Number of instructions occupied by the program.
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+
     /// Clones this program handle so that it can be executed by two state machines at the same
     /// time.
     ///
@@ -314,6 +399,36 @@ impl<P: PIOExt> InstalledProgram<P> {
     }
 }
 
+/// Clock divisor for a state machine which takes `cycles_per_bit` cycles to send or receive each
+/// bit at `baud` bits per second.
+///
+/// The result is the integer part and the fractional part in 1/256, as taken by
+/// [`StateMachine::set_clkdiv_int_frac`], rounded to the closest divisor. For example, a UART
+/// program which takes 8 cycles per bit at 115200 baud and 125 MHz needs a divisor of 135.63:
+///
+/// ```
+/// use embedded_time::rate::Hertz;
+/// use rp2040_hal::pio::clock_divisor_for_baud;
+/// assert_eq!(clock_divisor_for_baud(Hertz(125_000_000), 8, 115_200), (135, 162));
+/// ```
+///
+/// # Panics
+///
+/// Panics if the divisor is smaller than 1 or larger than 65536.
+pub fn clock_divisor_for_baud(sys_freq: Hertz, cycles_per_bit: u32, baud: u32) -> (u16, u8) {
+    let bit_freq = u64::from(cycles_per_bit) * u64::from(baud);
+    let div_256 = (u64::from(sys_freq.integer()) * 256 + bit_freq / 2) / bit_freq;
+    assert!(
+        (256..=65536 * 256).contains(&div_256),
+        "PIO clock divisor out of range"
+    );
+    if div_256 == 65536 * 256 {
+        (0, 0)
+    } else {
+        ((div_256 >> 8) as u16, div_256 as u8)
+    }
+}
+
 /// State machine identifier (without a specified PIO block).
 pub trait StateMachineIndex: Send {
     /// Numerical index of the state machine (0 to 3).
@@ -441,6 +556,40 @@ impl<SM: ValidStateMachine> UninitStateMachine<SM> {
         self.set_ctrl_bits(1 << (SM::id() + 8));
     }
 
+    // Safety: The Send trait assumes this is the only write to the FJOIN bits of sm_shiftctrl.
+    // Rx writes other bits of the register, so only its atomic aliases are used.
+    fn set_buffers(&mut self, buffers: Buffers) {
+        let (fjoin_rx, fjoin_tx) = buffers.fjoin_bits();
+        let set = (fjoin_rx as u32) << SHIFTCTRL_FJOIN_RX | (fjoin_tx as u32) << SHIFTCTRL_FJOIN_TX;
+        let clear = set ^ (1 << SHIFTCTRL_FJOIN_RX | 1 << SHIFTCTRL_FJOIN_TX);
+        let reg = self.sm().sm_shiftctrl.as_ptr();
+        // Safety: We only use the atomic aliases of the register.
+        unsafe {
+            write_bitmask_clear(reg, clear);
+            write_bitmask_set(reg, set);
+        }
+    }
+
+    fn buffers(&self) -> Buffers {
+        let shiftctrl = self.sm().sm_shiftctrl.read();
+        match (shiftctrl.fjoin_rx().bit(), shiftctrl.fjoin_tx().bit()) {
+            (false, true) => Buffers::OnlyTx,
+            (true, false) => Buffers::OnlyRx,
+            _ => Buffers::RxTx,
+        }
+    }
+
+    /// Clear both FIFOs.
+    fn clear_fifos(&mut self) {
+        // Changing FJOIN_RX clears both FIFOs, so toggle it twice.
+        let reg = self.sm().sm_shiftctrl.as_ptr();
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_xor(reg, 1 << SHIFTCTRL_FJOIN_RX);
+            write_bitmask_xor(reg, 1 << SHIFTCTRL_FJOIN_RX);
+        }
+    }
+
     // Safety: All ctrl set access should go through this function to ensure atomic access.
     fn set_ctrl_bits(&mut self, bits: u32) {
         // Safety: We only use the atomic alias of the register.
@@ -463,6 +612,11 @@ impl<SM: ValidStateMachine> UninitStateMachine<SM> {
         let int = divisor as u16;
         let frac = ((divisor - int as f32) * 256.0) as u8;
 
+        self.set_clkdiv_int_frac(int, frac);
+    }
+
+    // Safety: The Send trait assumes this is the only write to sm_clkdiv
+    fn set_clkdiv_int_frac(&self, int: u16, frac: u8) {
         self.sm().sm_clkdiv.write(|w| {
             unsafe {
                 w.int().bits(int);
@@ -505,10 +659,10 @@ impl<SM: ValidStateMachine, State> StateMachine<SM, State> {
     ///
     /// The program can be uninstalled to free space once it is no longer used by any state
     /// machine.
-    pub fn uninit(
+    pub fn uninit<RW: Word, TW: Word>(
         mut self,
-        _rx: Rx<SM>,
-        _tx: Tx<SM>,
+        _rx: Rx<SM, RW>,
+        _tx: Tx<SM, TW>,
     ) -> (UninitStateMachine<SM>, InstalledProgram<SM::PIO>) {
         self.sm.set_enabled(false);
         (self.sm, self.program)
@@ -539,6 +693,91 @@ impl<SM: ValidStateMachine, State> StateMachine<SM, State> {
     pub fn stalled(&self) -> bool {
         self.sm.sm().sm_execctrl.read().exec_stalled().bits()
     }
+
+    /// Clear both FIFOs, dropping all words which have not been read yet.
+    pub fn clear_fifos(&mut self) {
+        self.sm.clear_fifos();
+    }
+
+    /// The current buffer sharing of the FIFOs.
+    pub fn buffers(&self) -> Buffers {
+        self.sm.buffers()
+    }
+
+    /// Change the clock divisor while the state machine is stopped or running.
+    ///
+    /// The clock divider is restarted afterwards, so the new divisor takes effect right away. A
+    /// divisor of 0 is interpreted as 65536.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the divisor is between 0 and 1, or larger than 65536.
+    pub fn set_clock_divisor(&mut self, divisor: f32) {
+        assert!(
+            divisor == 0.0 || (1.0..=65536.0).contains(&divisor),
+            "PIO clock divisor out of range"
+        );
+        if divisor >= 65536.0 {
+            self.set_clkdiv_int_frac(0, 0);
+        } else {
+            let int = divisor as u16;
+            let frac = ((divisor - int as f32) * 256.0) as u8;
+            self.set_clkdiv_int_frac(int, frac);
+        }
+    }
+
+    /// Change the clock divisor to `int + frac / 256`, without the rounding errors of `f32`.
+    ///
+    /// The clock divider is restarted afterwards, so the new divisor takes effect right away. An
+    /// integer part of 0 is interpreted as 65536.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `int` is 0 and `frac` is not, as the divisor would be larger than 65536.
+    pub fn set_clkdiv_int_frac(&mut self, int: u16, frac: u8) {
+        assert!(int != 0 || frac == 0, "PIO clock divisor out of range");
+        self.sm.set_clkdiv_int_frac(int, frac);
+        self.sm.reset_clock();
+    }
+
+    /// The current clock divisor.
+    pub fn clock_divisor(&self) -> f32 {
+        let (int, frac) = self.clkdiv_int_frac();
+        let int = if int == 0 { 65536.0 } else { int as f32 };
+        int + frac as f32 / 256.0
+    }
+
+    /// The current clock divisor, as integer part and fractional part in 1/256.
+    pub fn clkdiv_int_frac(&self) -> (u16, u8) {
+        let clkdiv = self.sm.sm().sm_clkdiv.read();
+        (clkdiv.int().bits(), clkdiv.frac().bits())
+    }
+
+    /// The address in instruction memory of the instruction currently being executed.
+    ///
+    /// This is an absolute address, see [`InstalledProgram::offset`] for the start of the program.
+    pub fn current_instruction_address(&self) -> u8 {
+        self.sm.sm().sm_addr.read().bits() as u8
+    }
+
+    /// Jump to the instruction at `offset` within the program.
+    ///
+    /// On a running state machine this is a way to resynchronize a program, e.g. jumping to the
+    /// start of a protocol frame. See [`exec_instruction`](Self::exec_instruction).
+    pub fn jmp_to(&mut self, offset: u8) {
+        assert!(
+            offset < self.program.length,
+            "JMP target outside of the program"
+        );
+        self.exec_instruction(jmp_instruction(self.program.offset + offset));
+    }
+
+    /// Discard all words in the TX FIFO of this state machine.
+    ///
+    /// See [`Tx::drain_fifo`].
+    pub fn drain_tx_fifo<W: Word>(&mut self, tx: &mut Tx<SM, W>) {
+        tx.drain_fifo();
+    }
 }
 
 // Safety: All shared register accesses are atomic.
@@ -547,6 +786,16 @@ unsafe impl<SM: ValidStateMachine + Send, State> Send for StateMachine<SM, State
 // Safety: `StateMachine` is marked Send so ensure all accesses remain atomic and no new concurrent
 // accesses are added.
 impl<SM: ValidStateMachine> StateMachine<SM, Stopped> {
+    /// Change the buffer sharing of the FIFOs.
+    ///
+    /// This clears both FIFOs, even if the buffer sharing is unchanged. While the RX FIFO is given
+    /// to the TX FIFO, [`Rx::read`] always returns `None`, and while the TX FIFO is given to the RX
+    /// FIFO, [`Tx::write`] always returns `false`.
+    pub fn set_buffers(&mut self, buffers: Buffers) {
+        self.sm.set_buffers(buffers);
+        self.sm.clear_fifos();
+    }
+
     /// Starts execution of the selected program.
     pub fn start(mut self) -> StateMachine<SM, Running> {
         // Enable SM
@@ -574,13 +823,10 @@ impl<SM: ValidStateMachine> StateMachine<SM, Stopped> {
                 .sm()
                 .sm_pinctrl
                 .write(|w| unsafe { w.set_base().bits(pin_num).set_count().bits(1) });
-            self.exec_instruction(
-                pio::InstructionOperands::SET {
-                    destination: pio::SetDestination::PINS,
-                    data: if PinState::High == pin_state { 1 } else { 0 },
-                }
-                .encode(),
-            );
+            self.exec_instruction(set_instruction(
+                pio::SetDestination::PINS,
+                if PinState::High == pin_state { 1 } else { 0 },
+            ));
         }
         self.sm
             .sm()
@@ -603,13 +849,10 @@ impl<SM: ValidStateMachine> StateMachine<SM, Stopped> {
                 .sm()
                 .sm_pinctrl
                 .write(|w| unsafe { w.set_base().bits(pinnum).set_count().bits(1) });
-            self.exec_instruction(
-                pio::InstructionOperands::SET {
-                    destination: pio::SetDestination::PINDIRS,
-                    data: if PinDir::Output == pin_dir { 1 } else { 0 },
-                }
-                .encode(),
-            );
+            self.exec_instruction(set_instruction(
+                pio::SetDestination::PINDIRS,
+                if PinDir::Output == pin_dir { 1 } else { 0 },
+            ));
         }
         self.sm
             .sm()
@@ -632,6 +875,10 @@ impl<P: PIOExt, SM: StateMachineIndex> StateMachine<(P, SM), Stopped> {
     /// ```ignore
     /// sm0.synchronize_with(sm1).and_with(sm2);
     /// ```
+    ///
+    /// The state machines are still started one after the other. [`with`](Self::with) groups
+    /// them instead, so they are started on the same cycle, with their clock dividers restarted.
+    #[deprecated(note = "Use StateMachine::with and StateMachineGroup::restart_clocks or start")]
     pub fn synchronize_with<'sm, SM2: StateMachineIndex>(
         &'sm mut self,
         _other_sm: &'sm mut StateMachine<(P, SM2), Stopped>,
@@ -643,6 +890,8 @@ impl<P: PIOExt, SM: StateMachineIndex> StateMachine<(P, SM), Stopped> {
 
 /// Type which, once destructed, restarts the clock dividers for all selected state machines,
 /// effectively synchronizing them.
+///
+/// Superseded by [`StateMachineGroup`], see [`StateMachine::synchronize_with`].
 pub struct Synchronize<'sm, SM: ValidStateMachine> {
     sm: &'sm mut StateMachine<SM, Stopped>,
     sm_mask: u32,
@@ -672,6 +921,140 @@ impl<'sm, SM: ValidStateMachine> Drop for Synchronize<'sm, SM> {
     }
 }
 
+impl<P: PIOExt, SM: StateMachineIndex> StateMachine<(P, SM), Stopped> {
+    /// Groups this state machine with another one of the same PIO block, so they can be started
+    /// and stopped together.
+    ///
+    /// See [`StateMachineGroup`].
+    #[allow(clippy::type_complexity)]
+    pub fn with<SM2: StateMachineIndex>(
+        self,
+        other: StateMachine<(P, SM2), Stopped>,
+    ) -> StateMachineGroup<P, (Self, StateMachine<(P, SM2), Stopped>), Stopped> {
+        StateMachineGroup {
+            block: self.sm.block,
+            mask: (1 << SM::id()) | (1 << SM2::id()),
+            members: (self, other),
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+/// State machines of one PIO block, which are started and stopped on the same clock cycle.
+///
+/// Each operation on the group is a single atomic write to the CTRL register of the PIO block.
+/// Starting the group also restarts the clock dividers of all members, so state machines with
+/// the same clock divisor stay in lock-step.
+///
+/// Groups are built with [`StateMachine::with`], and can only contain state machines of the same
+/// PIO block. [`free`](StateMachineGroup::free) returns the members as nested tuples. This
+/// supersedes [`StateMachine::synchronize_with`], which only restarts the clock dividers.
+///
+/// See [examples/pio_lockstep.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/pio_lockstep.rs)
+/// for a check that the members execute the same instruction in each cycle.
+///
+/// ```no_run
+/// use rp2040_hal::{pac, pio::{PIOBuilder, PIOExt}};
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let (mut pio, sm0, sm1, _, _) = peripherals.PIO0.split(&mut peripherals.RESETS);
+/// let program = pio_proc::pio!(
+///     32,
+///     ".wrap_target
+///     set pins, 1 [31]
+///     set pins, 0 [31]
+/// .wrap
+///     "
+/// ).program;
+/// let installed = pio.install(&program).unwrap();
+/// // safety: Both state machines are uninitialized before the program is uninstalled
+/// let (sm0, _, _) = PIOBuilder::from_program(unsafe { installed.share() }).set_pins(0, 1).build(sm0).unwrap();
+/// let (sm1, _, _) = PIOBuilder::from_program(installed).set_pins(1, 1).build(sm1).unwrap();
+/// let group = sm0.with(sm1).start();
+/// // Both state machines execute the same instruction in each cycle
+/// let group = group.stop();
+/// let (sm0, sm1) = group.free();
+/// ```
+pub struct StateMachineGroup<P: PIOExt, T, State> {
+    block: *const rp2040_pac::pio0::RegisterBlock,
+    mask: u32,
+    members: T,
+    _phantom: core::marker::PhantomData<(P, State)>,
+}
+
+// Safety: All shared register accesses are atomic.
+unsafe impl<P: PIOExt, T: Send, State> Send for StateMachineGroup<P, T, State> {}
+
+impl<P: PIOExt, T, State> StateMachineGroup<P, T, State> {
+    /// The members of the group, as nested tuples.
+    ///
+    /// This gives access to the methods of running members which only read their state, like
+    /// [`current_instruction_address`](StateMachine::current_instruction_address).
+    pub fn members(&self) -> &T {
+        &self.members
+    }
+
+    /// Restarts the clock dividers of all members, so their fractional dividers are in phase.
+    pub fn restart_clocks(&mut self) {
+        // Bits 11:8 of CTRL contain CLKDIV_RESTART.
+        self.set_ctrl_bits(self.mask << 8);
+    }
+
+    fn set_ctrl_bits(&mut self, bits: u32) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_set((*self.block).ctrl.as_ptr(), bits);
+        }
+    }
+
+    fn into_state<State2>(self) -> StateMachineGroup<P, T, State2> {
+        StateMachineGroup {
+            block: self.block,
+            mask: self.mask,
+            members: self.members,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: PIOExt, T> StateMachineGroup<P, T, Stopped> {
+    /// Adds another state machine of the same PIO block to the group.
+    #[allow(clippy::type_complexity)]
+    pub fn with<SM: StateMachineIndex>(
+        self,
+        other: StateMachine<(P, SM), Stopped>,
+    ) -> StateMachineGroup<P, (T, StateMachine<(P, SM), Stopped>), Stopped> {
+        StateMachineGroup {
+            block: self.block,
+            mask: self.mask | (1 << SM::id()),
+            members: (self.members, other),
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Starts all members on the same clock cycle, with their clock dividers restarted.
+    pub fn start(mut self) -> StateMachineGroup<P, T, Running> {
+        // Bits 3:0 of CTRL are SM_ENABLE, bits 11:8 are CLKDIV_RESTART.
+        self.set_ctrl_bits(self.mask | self.mask << 8);
+        self.into_state()
+    }
+
+    /// Splits the group into its members.
+    pub fn free(self) -> T {
+        self.members
+    }
+}
+
+impl<P: PIOExt, T> StateMachineGroup<P, T, Running> {
+    /// Stops all members on the same clock cycle.
+    pub fn stop(self) -> StateMachineGroup<P, T, Stopped> {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_clear((*self.block).ctrl.as_ptr(), self.mask);
+        }
+        self.into_state()
+    }
+}
+
 impl<SM: ValidStateMachine> StateMachine<SM, Running> {
     /// Stops execution of the selected program.
     pub fn stop(mut self) -> StateMachine<SM, Stopped> {
@@ -690,13 +1073,8 @@ impl<SM: ValidStateMachine> StateMachine<SM, Running> {
         // pause the state machine
         self.sm.set_enabled(false);
         // revert it to its wrap target
-        self.sm.exec_instruction(
-            pio::InstructionOperands::JMP {
-                condition: pio::JmpCondition::Always,
-                address: self.program.wrap_target(),
-            }
-            .encode(),
-        );
+        self.sm
+            .exec_instruction(jmp_instruction(self.program.wrap_target()));
         // clear osr/isr
         self.sm.restart();
         // unpause the state machine
@@ -704,18 +1082,57 @@ impl<SM: ValidStateMachine> StateMachine<SM, Running> {
     }
 }
 
+/// `jmp address`, with `address` absolute in the instruction memory
+fn jmp_instruction(address: u8) -> u16 {
+    pio::InstructionOperands::JMP {
+        condition: pio::JmpCondition::Always,
+        address,
+    }
+    .encode()
+}
+
+/// `set pins, data` or `set pindirs, data`
+fn set_instruction(destination: pio::SetDestination, data: u8) -> u16 {
+    pio::InstructionOperands::SET { destination, data }.encode()
+}
+
+/// The pins of the range of `count` pins starting at `base`, wrapping around after pin 31
+fn pin_range_mask(base: u8, count: u8) -> u32 {
+    let pins = ((1u64 << count.min(32)) - 1) as u32;
+    pins.rotate_left(u32::from(base))
+}
+
+/// The instruction discarding a word of the TX FIFO: `out null, 32` with autopull, which makes
+/// `pull` a no-op while the OSR is full, and `pull noblock` otherwise
+fn drain_instruction(autopull: bool) -> u16 {
+    if autopull {
+        pio::InstructionOperands::OUT {
+            destination: pio::OutDestination::NULL,
+            bit_count: 32,
+        }
+    } else {
+        pio::InstructionOperands::PULL {
+            if_empty: false,
+            block: false,
+        }
+    }
+    .encode()
+}
+
 /// PIO RX FIFO handle.
-pub struct Rx<SM: ValidStateMachine> {
+///
+/// `W` is the width of DMA transfers from the FIFO, see [`transfer_size`](Rx::transfer_size).
+pub struct Rx<SM: ValidStateMachine, W: Word = u32> {
     block: *const rp2040_pac::pio0::RegisterBlock,
-    _phantom: core::marker::PhantomData<SM>,
+    _phantom: core::marker::PhantomData<(SM, W)>,
 }
 
 // Safety: All shared register accesses are atomic.
-unsafe impl<SM: ValidStateMachine + Send> Send for Rx<SM> {}
+unsafe impl<SM: ValidStateMachine + Send, W: Word> Send for Rx<SM, W> {}
 
 // Safety: `Rx` is marked Send so ensure all accesses remain atomic and no new concurrent accesses
 // are added.
-impl<SM: ValidStateMachine> Rx<SM> {
+impl<SM: ValidStateMachine, W: Word> Rx<SM, W> {
     fn register_block(&self) -> &pac::pio0::RegisterBlock {
         // Safety: The register is unique to this Tx instance.
         unsafe { &*self.block }
@@ -743,11 +1160,22 @@ impl<SM: ValidStateMachine> Rx<SM> {
         }
     }
 
+    /// Change the width of DMA transfers from the FIFO.
+    ///
+    /// 8 and 16 bit transfers read the least significant bits of each FIFO entry.
+    pub fn transfer_size<W2: Word>(self) -> Rx<SM, W2> {
+        Rx {
+            block: self.block,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
     /// Get the next element from RX FIFO.
     ///
-    /// Returns `None` if the FIFO is empty.
+    /// Returns `None` if the FIFO is empty, or if its memory is given to the TX FIFO (see
+    /// [`Buffers::OnlyTx`]).
     pub fn read(&mut self) -> Option<u32> {
-        if self.is_empty() {
+        if self.is_empty() || self.is_disabled() {
             return None;
         }
 
@@ -756,31 +1184,117 @@ impl<SM: ValidStateMachine> Rx<SM> {
     }
 
     /// Enable/Disable the autopush feature of the state machine.
-    // Safety: This register is read by Tx, this is the only write.
+    // Safety: This bit is read by Tx, this is the only write. The state machine writes the FJOIN
+    // bits of the register, so only its atomic aliases are used.
     pub fn enable_autopush(&mut self, enable: bool) {
-        self.register_block().sm[SM::id()]
-            .sm_shiftctrl
-            .modify(|_, w| w.autopush().bit(enable))
+        let reg = self.register_block().sm[SM::id()].sm_shiftctrl.as_ptr();
+        unsafe {
+            if enable {
+                write_bitmask_set(reg, 1 << SHIFTCTRL_AUTOPUSH);
+            } else {
+                write_bitmask_clear(reg, 1 << SHIFTCTRL_AUTOPUSH);
+            }
+        }
     }
 
-    /// Indicate if the tx FIFO is full
+    /// Indicate if the rx FIFO is empty
+    ///
+    /// This takes the depth of joined FIFOs into account.
     pub fn is_empty(&self) -> bool {
         self.register_block().fstat.read().rxempty().bits() & (1 << SM::id()) != 0
     }
+
+    /// The number of words in the rx FIFO
+    pub fn level(&self) -> u8 {
+        (self.register_block().flevel.read().bits() >> (SM::id() * 8 + 4)) as u8 & 0xf
+    }
+
+    /// Checks if the state machine has stalled on full RX FIFO during a blocking PUSH, or an IN
+    /// with autopush enabled.
+    ///
+    /// **Note this is a sticky flag and may not reflect the current state of the machine.**
+    pub fn has_stalled(&self) -> bool {
+        let mask = 1 << SM::id();
+        self.register_block().fdebug.read().rxstall().bits() & mask == mask
+    }
+
+    /// Clears the `rx_stalled` flag.
+    pub fn clear_stalled_flag(&self) {
+        let mask = 1 << SM::id();
+
+        // Safety: These bits are WC, only the one corresponding to this SM is set.
+        self.register_block()
+            .fdebug
+            .write(|w| unsafe { w.rxstall().bits(mask) });
+    }
+
+    /// Checks if the empty RX FIFO was read, e.g. by a DMA channel without pacing. The value
+    /// read was undefined.
+    ///
+    /// [`read`](Rx::read) never reads from an empty FIFO.
+    ///
+    /// **Note this is a sticky flag and may not reflect the current state of the machine.**
+    pub fn has_underflowed(&self) -> bool {
+        let mask = 1 << SM::id();
+        self.register_block().fdebug.read().rxunder().bits() & mask == mask
+    }
+
+    /// Clears the `rx_underflowed` flag.
+    pub fn clear_underflow_flag(&self) {
+        let mask = 1 << SM::id();
+
+        // Safety: These bits are WC, only the one corresponding to this SM is set.
+        self.register_block()
+            .fdebug
+            .write(|w| unsafe { w.rxunder().bits(mask) });
+    }
+
+    /// Indicate if the memory of the rx FIFO is given to the tx FIFO
+    pub fn is_disabled(&self) -> bool {
+        self.register_block().sm[SM::id()]
+            .sm_shiftctrl
+            .read()
+            .fjoin_tx()
+            .bit()
+    }
+
+    /// Raise the system interrupt `irq` of this PIO block while the RX FIFO is not empty.
+    pub fn enable_rx_not_empty_interrupt(&mut self, irq: IrqIndex) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_set(
+                self.register_block().sm_irq[irq as usize].irq_inte.as_ptr(),
+                1 << SM::id(),
+            );
+        }
+    }
+
+    /// Stop raising the system interrupt `irq` when the RX FIFO is not empty.
+    pub fn disable_rx_not_empty_interrupt(&mut self, irq: IrqIndex) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_clear(
+                self.register_block().sm_irq[irq as usize].irq_inte.as_ptr(),
+                1 << SM::id(),
+            );
+        }
+    }
 }
 
 /// PIO TX FIFO handle.
-pub struct Tx<SM: ValidStateMachine> {
+///
+/// `W` is the width of DMA transfers to the FIFO, see [`transfer_size`](Tx::transfer_size).
+pub struct Tx<SM: ValidStateMachine, W: Word = u32> {
     block: *const rp2040_pac::pio0::RegisterBlock,
-    _phantom: core::marker::PhantomData<SM>,
+    _phantom: core::marker::PhantomData<(SM, W)>,
 }
 
 // Safety: All shared register accesses are atomic.
-unsafe impl<SM: ValidStateMachine + Send> Send for Tx<SM> {}
+unsafe impl<SM: ValidStateMachine + Send, W: Word> Send for Tx<SM, W> {}
 
 // Safety: `Tx` is marked Send so ensure all accesses remain atomic and no new concurrent accesses
 // are added.
-impl<SM: ValidStateMachine> Tx<SM> {
+impl<SM: ValidStateMachine, W: Word> Tx<SM, W> {
     fn register_block(&self) -> &pac::pio0::RegisterBlock {
         // Safety: The register is unique to this Tx instance.
         unsafe { &*self.block }
@@ -808,14 +1322,26 @@ impl<SM: ValidStateMachine> Tx<SM> {
         }
     }
 
+    /// Change the width of DMA transfers to the FIFO.
+    ///
+    /// 8 and 16 bit writes are replicated across all bytes of the FIFO entry, so e.g. with 8 bit
+    /// transfers, an autopull threshold of 8 and shifting left, each FIFO entry provides one byte.
+    pub fn transfer_size<W2: Word>(self) -> Tx<SM, W2> {
+        Tx {
+            block: self.block,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+
     /// Write an element to TX FIFO.
     ///
-    /// Returns `true` if the value was written to FIFO, `false` otherwise.
+    /// Returns `true` if the value was written to FIFO, `false` if the FIFO is full or its
+    /// memory is given to the RX FIFO (see [`Buffers::OnlyRx`]).
     pub fn write<T>(&mut self, value: T) -> bool {
         // Safety: The register is never written by software.
         let is_full = self.is_full();
 
-        if is_full {
+        if is_full || self.is_disabled() {
             return false;
         }
 
@@ -852,10 +1378,69 @@ impl<SM: ValidStateMachine> Tx<SM> {
     }
 
     /// Indicate if the tx FIFO is full
+    ///
+    /// This takes the depth of joined FIFOs into account.
     pub fn is_full(&self) -> bool {
         self.register_block().fstat.read().txfull().bits() & (1 << SM::id()) != 0
     }
 
+    /// The number of words in the tx FIFO
+    pub fn level(&self) -> u8 {
+        (self.register_block().flevel.read().bits() >> (SM::id() * 8)) as u8 & 0xf
+    }
+
+    /// Checks if a word was written to the full TX FIFO, e.g. by a DMA channel without pacing.
+    /// The word was dropped.
+    ///
+    /// [`write`](Tx::write) never writes to a full FIFO.
+    ///
+    /// **Note this is a sticky flag and may not reflect the current state of the machine.**
+    pub fn has_overflowed(&self) -> bool {
+        let mask = 1 << SM::id();
+        self.register_block().fdebug.read().txover().bits() & mask == mask
+    }
+
+    /// Clears the `tx_overflowed` flag.
+    pub fn clear_overflow_flag(&self) {
+        let mask = 1 << SM::id();
+
+        // Safety: These bits are WC, only the one corresponding to this SM is set.
+        self.register_block()
+            .fdebug
+            .write(|w| unsafe { w.txover().bits(mask) });
+    }
+
+    /// Indicate if the memory of the tx FIFO is given to the rx FIFO
+    pub fn is_disabled(&self) -> bool {
+        self.register_block().sm[SM::id()]
+            .sm_shiftctrl
+            .read()
+            .fjoin_rx()
+            .bit()
+    }
+
+    /// Raise the system interrupt `irq` of this PIO block while the TX FIFO is not full.
+    pub fn enable_tx_not_full_interrupt(&mut self, irq: IrqIndex) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_set(
+                self.register_block().sm_irq[irq as usize].irq_inte.as_ptr(),
+                1 << (SM::id() + 4),
+            );
+        }
+    }
+
+    /// Stop raising the system interrupt `irq` when the TX FIFO is not full.
+    pub fn disable_tx_not_full_interrupt(&mut self, irq: IrqIndex) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe {
+            write_bitmask_clear(
+                self.register_block().sm_irq[irq as usize].irq_inte.as_ptr(),
+                1 << (SM::id() + 4),
+            );
+        }
+    }
+
     /// Drain Tx fifo.
     pub fn drain_fifo(&mut self) {
         // According to the datasheet 3.5.4.2 Page 358:
@@ -865,23 +1450,12 @@ impl<SM: ValidStateMachine> Tx<SM> {
         // DMA.  It behaves as a fence: either an autopull has already taken place, in which case
         // the 'PULL' has no effect, or the program will stall on the 'PULL' until data becomes
         // available in the FIFO.
-        let instr = if self.register_block().sm[SM::id()]
+        let autopull = self.register_block().sm[SM::id()]
             .sm_shiftctrl
             .read()
             .autopull()
-            .bit_is_set()
-        {
-            pio::InstructionOperands::OUT {
-                destination: pio::OutDestination::NULL,
-                bit_count: 32,
-            }
-        } else {
-            pio::InstructionOperands::PULL {
-                if_empty: false,
-                block: false,
-            }
-        }
-        .encode();
+            .bit_is_set();
+        let instr = drain_instruction(autopull);
         // Safety: The only other place this register is written is
         // `UninitStatemachine.exec_instruction`, `Tx` is only created after init.
         let mask = 1 << SM::id();
@@ -893,6 +1467,63 @@ impl<SM: ValidStateMachine> Tx<SM> {
     }
 }
 
+// Safety: The DMA only reads the FIFO register, which lives as long as the PIO block.
+unsafe impl<SM: ValidStateMachine, W: Word> ReadTarget for Rx<SM, W> {
+    type ReceivedWord = W;
+
+    fn rx_treq() -> Option<u8> {
+        Some(
+            crate::dma::DREQ_PIO0_RX0
+                + (crate::dma::DREQ_PIO1_RX0 - crate::dma::DREQ_PIO0_RX0)
+                    * (<SM::PIO as PIOExt>::id() as u8)
+                + SM::id() as u8,
+        )
+    }
+
+    fn rx_address_count(&self) -> (u32, u32) {
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn rx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<SM: ValidStateMachine, W: Word> EndlessReadTarget for Rx<SM, W> {}
+
+// Safety: The DMA only writes the FIFO register, which lives as long as the PIO block.
+unsafe impl<SM: ValidStateMachine, W: Word> WriteTarget for Tx<SM, W> {
+    type TransmittedWord = W;
+
+    fn tx_treq() -> Option<u8> {
+        Some(
+            crate::dma::DREQ_PIO0_TX0
+                + (crate::dma::DREQ_PIO1_TX0 - crate::dma::DREQ_PIO0_TX0)
+                    * (<SM::PIO as PIOExt>::id() as u8)
+                + SM::id() as u8,
+        )
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        (self.fifo_address() as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<SM: ValidStateMachine, W: Word> EndlessWriteTarget for Tx<SM, W> {}
+
+/// System interrupt line of a PIO block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqIndex {
+    /// `PIOx_IRQ_0`
+    Irq0 = 0,
+    /// `PIOx_IRQ_1`
+    Irq1 = 1,
+}
+
 /// PIO Interrupt controller.
 #[derive(Debug)]
 pub struct Interrupt<P: PIOExt> {
@@ -906,34 +1537,24 @@ unsafe impl<P: PIOExt> Send for Interrupt<P> {}
 
 // Safety: `Interrupt` is marked Send so ensure all accesses remain atomic and no new concurrent
 // accesses are added.
-// `Interrupt` provides exclusive access to `irq_intf` to `irq_inte` for it's state machine, this
-// must remain true to satisfy Send.
+// `irq_inte` and `irq_intf` are also written by `Rx` and `Tx`, so they are only changed through
+// their atomic aliases.
 impl<P: PIOExt> Interrupt<P> {
     /// Enable interrupts raised by state machines.
     ///
     /// The PIO peripheral has 4 outside visible interrupts that can be raised by the state machines. Note that this
     /// does not correspond with the state machine index; any state machine can raise any one of the four interrupts.
     pub fn enable_sm_interrupt(&self, id: u8) {
-        match id {
-            0 => self.irq().irq_inte.modify(|_, w| w.sm0().set_bit()),
-            1 => self.irq().irq_inte.modify(|_, w| w.sm1().set_bit()),
-            2 => self.irq().irq_inte.modify(|_, w| w.sm2().set_bit()),
-            3 => self.irq().irq_inte.modify(|_, w| w.sm3().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_inte(1 << (8 + id));
     }
 
     /// Disable interrupts raised by state machines.
     ///
     /// See [`Self::enable_sm_interrupt`] for info about the index.
     pub fn disable_sm_interrupt(&self, id: u8) {
-        match id {
-            0 => self.irq().irq_inte.modify(|_, w| w.sm0().clear_bit()),
-            1 => self.irq().irq_inte.modify(|_, w| w.sm1().clear_bit()),
-            2 => self.irq().irq_inte.modify(|_, w| w.sm2().clear_bit()),
-            3 => self.irq().irq_inte.modify(|_, w| w.sm3().clear_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.clear_inte(1 << (8 + id));
     }
 
     /// Force state machine interrupt.
@@ -942,13 +1563,8 @@ impl<P: PIOExt> Interrupt<P> {
     ///
     /// See [`Self::enable_sm_interrupt`] for info about the index.
     pub fn force_sm_interrupt(&self, id: u8) {
-        match id {
-            0 => self.irq().irq_intf.modify(|_, w| w.sm0().set_bit()),
-            1 => self.irq().irq_intf.modify(|_, w| w.sm1().set_bit()),
-            2 => self.irq().irq_intf.modify(|_, w| w.sm2().set_bit()),
-            3 => self.irq().irq_intf.modify(|_, w| w.sm3().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_intf(1 << (8 + id));
     }
 
     /// Enable TX FIFO not full interrupt.
@@ -956,51 +1572,24 @@ impl<P: PIOExt> Interrupt<P> {
     /// Each of the 4 state machines have their own TX FIFO. This interrupt is raised when the TX FIFO is not full, i.e.
     /// one could push more data to it.
     pub fn enable_tx_not_full_interrupt(&self, id: u8) {
-        match id {
-            0 => self.irq().irq_inte.modify(|_, w| w.sm0_txnfull().set_bit()),
-            1 => self.irq().irq_inte.modify(|_, w| w.sm1_txnfull().set_bit()),
-            2 => self.irq().irq_inte.modify(|_, w| w.sm2_txnfull().set_bit()),
-            3 => self.irq().irq_inte.modify(|_, w| w.sm3_txnfull().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_inte(1 << (4 + id));
     }
 
     /// Disable TX FIFO not full interrupt.
     ///
     /// See [`Self::enable_tx_not_full_interrupt`] for info about the index.
     pub fn disable_tx_not_full_interrupt(&self, id: u8) {
-        match id {
-            0 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm0_txnfull().clear_bit()),
-            1 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm1_txnfull().clear_bit()),
-            2 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm2_txnfull().clear_bit()),
-            3 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm3_txnfull().clear_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.clear_inte(1 << (4 + id));
     }
 
     /// Force TX FIFO not full interrupt.
     ///
     /// See [`Self::enable_tx_not_full_interrupt`] for info about the index.
     pub fn force_tx_not_full_interrupt(&self, id: u8) {
-        match id {
-            0 => self.irq().irq_intf.modify(|_, w| w.sm0_txnfull().set_bit()),
-            1 => self.irq().irq_intf.modify(|_, w| w.sm1_txnfull().set_bit()),
-            2 => self.irq().irq_intf.modify(|_, w| w.sm2_txnfull().set_bit()),
-            3 => self.irq().irq_intf.modify(|_, w| w.sm3_txnfull().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_intf(1 << (4 + id));
     }
 
     /// Enable RX FIFO not empty interrupt.
@@ -1008,75 +1597,24 @@ impl<P: PIOExt> Interrupt<P> {
     /// Each of the 4 state machines have their own RX FIFO. This interrupt is raised when the RX FIFO is not empty,
     /// i.e. one could read more data from it.
     pub fn enable_rx_not_empty_interrupt(&self, id: u8) {
-        match id {
-            0 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm0_rxnempty().set_bit()),
-            1 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm1_rxnempty().set_bit()),
-            2 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm2_rxnempty().set_bit()),
-            3 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm3_rxnempty().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_inte(1 << id);
     }
 
     /// Disable RX FIFO not empty interrupt.
     ///
     /// See [`Self::enable_rx_not_empty_interrupt`] for info about the index.
     pub fn disable_rx_not_empty_interrupt(&self, id: u8) {
-        match id {
-            0 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm0_rxnempty().clear_bit()),
-            1 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm1_rxnempty().clear_bit()),
-            2 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm2_rxnempty().clear_bit()),
-            3 => self
-                .irq()
-                .irq_inte
-                .modify(|_, w| w.sm3_rxnempty().clear_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.clear_inte(1 << id);
     }
 
     /// Force RX FIFO not empty interrupt.
     ///
     /// See [`Self::enable_rx_not_empty_interrupt`] for info about the index.
     pub fn force_rx_not_empty_interrupt(&self, id: u8) {
-        match id {
-            0 => self
-                .irq()
-                .irq_intf
-                .modify(|_, w| w.sm0_rxnempty().set_bit()),
-            1 => self
-                .irq()
-                .irq_intf
-                .modify(|_, w| w.sm1_rxnempty().set_bit()),
-            2 => self
-                .irq()
-                .irq_intf
-                .modify(|_, w| w.sm2_rxnempty().set_bit()),
-            3 => self
-                .irq()
-                .irq_intf
-                .modify(|_, w| w.sm3_rxnempty().set_bit()),
-            _ => panic!("invalid state machine interrupt number"),
-        }
+        assert!(id < 4, "invalid state machine interrupt number");
+        self.set_intf(1 << id);
     }
 
     /// Get the raw interrupt state.
@@ -1100,6 +1638,21 @@ impl<P: PIOExt> Interrupt<P> {
     fn irq(&self) -> &rp2040_pac::pio0::SM_IRQ {
         &self.register_block().sm_irq[self.id as usize]
     }
+
+    fn set_inte(&self, bits: u32) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe { write_bitmask_set(self.irq().irq_inte.as_ptr(), bits) }
+    }
+
+    fn clear_inte(&self, bits: u32) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe { write_bitmask_clear(self.irq().irq_inte.as_ptr(), bits) }
+    }
+
+    fn set_intf(&self, bits: u32) {
+        // Safety: We only use the atomic alias of the register.
+        unsafe { write_bitmask_set(self.irq().irq_intf.as_ptr(), bits) }
+    }
 }
 
 /// Provides easy access for decoding PIO's interrupt state.
@@ -1210,7 +1763,9 @@ pub struct PIOBuilder<P> {
 }
 
 /// Buffer sharing configuration.
-#[derive(Debug, Clone, Copy)]
+///
+/// Each FIFO is 4 entries deep, or 8 entries deep when it gets the memory of the other FIFO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Buffers {
     /// No sharing.
     RxTx,
@@ -1220,11 +1775,112 @@ pub enum Buffers {
     OnlyRx,
 }
 
+impl Buffers {
+    /// Values of the FJOIN_RX and FJOIN_TX bits.
+    fn fjoin_bits(self) -> (bool, bool) {
+        match self {
+            Buffers::RxTx => (false, false),
+            Buffers::OnlyTx => (false, true),
+            Buffers::OnlyRx => (true, false),
+        }
+    }
+}
+
 /// Errors that occurred during `PIO::install`.
 #[derive(Debug)]
 pub enum InstallError {
     /// There was not enough space for the instructions on the selected PIO.
     NoSpace,
+    /// The program has an `.origin`, but doesn't fit into instruction memory when placed there.
+    OriginOutOfRange,
+    /// The program has an `.origin`, but the instructions at that address are used by another
+    /// program.
+    OriginOccupied,
+}
+
+/// Errors that occurred during [`PIOBuilder::build`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A pin number is larger than 31.
+    PinOutOfRange,
+    /// More than 5 pins for `SET` or side-set, or more than 32 pins for `OUT`.
+    PinCountOutOfRange,
+    /// The inline `OUT` enable bit is larger than 31.
+    InlineOutOutOfRange,
+    /// The autopush or autopull threshold is not between 1 and 32.
+    ThresholdOutOfRange,
+    /// The wrap source or target of the program lies outside of the program.
+    WrapOutOfRange,
+    /// The `OUT` and `SET` pin ranges overlap without starting at the same pin.
+    ///
+    /// Both instructions may drive the same pins if their ranges start at the same base. Note
+    /// that `SET` drives 5 pins from pin 0 unless [`PIOBuilder::set_pins`] is called.
+    PinRangesOverlap,
+}
+
+/// A failed [`PIOBuilder::build`], which returns the state machine and the program.
+pub struct BuildError<P: PIOExt, SM: StateMachineIndex> {
+    /// The invalid setting.
+    pub error: ConfigError,
+    /// The state machine which was to be configured.
+    pub sm: UninitStateMachine<(P, SM)>,
+    /// The program which was to be run.
+    pub program: InstalledProgram<P>,
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> core::fmt::Debug for BuildError<P, SM> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BuildError")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<P: PIOExt + FunctionConfig> PIOBuilder<P> {
+    /// Like [`set_pins`](Self::set_pins), but takes the first pin, which has to be configured
+    /// for this PIO block.
+    pub fn set_pins_from<I: PinId>(self, _base: &Pin<I, Function<P>>, count: u8) -> Self
+    where
+        Function<P>: ValidPinMode<I>,
+    {
+        self.set_pins(I::DYN.num, count)
+    }
+
+    /// Like [`out_pins`](Self::out_pins), but takes the first pin, which has to be configured
+    /// for this PIO block.
+    pub fn out_pins_from<I: PinId>(self, _base: &Pin<I, Function<P>>, count: u8) -> Self
+    where
+        Function<P>: ValidPinMode<I>,
+    {
+        self.out_pins(I::DYN.num, count)
+    }
+
+    /// Like [`in_pin_base`](Self::in_pin_base), but takes a pin which has to be configured for
+    /// this PIO block.
+    pub fn in_pin_base_from<I: PinId>(self, _base: &Pin<I, Function<P>>) -> Self
+    where
+        Function<P>: ValidPinMode<I>,
+    {
+        self.in_pin_base(I::DYN.num)
+    }
+
+    /// Like [`jmp_pin`](Self::jmp_pin), but takes a pin which has to be configured for this PIO
+    /// block.
+    pub fn jmp_pin_from<I: PinId>(self, _pin: &Pin<I, Function<P>>) -> Self
+    where
+        Function<P>: ValidPinMode<I>,
+    {
+        self.jmp_pin(I::DYN.num)
+    }
+
+    /// Like [`side_set_pin_base`](Self::side_set_pin_base), but takes a pin which has to be
+    /// configured for this PIO block.
+    pub fn side_set_pin_base_from<I: PinId>(self, _base: &Pin<I, Function<P>>) -> Self
+    where
+        Function<P>: ValidPinMode<I>,
+    {
+        self.side_set_pin_base(I::DYN.num)
+    }
 }
 
 impl<P: PIOExt> PIOBuilder<P> {
@@ -1239,8 +1895,8 @@ impl<P: PIOExt> PIOBuilder<P> {
             inline_out: None,
             mov_status: MovStatusConfig::Tx(0),
             fifo_join: Buffers::RxTx,
-            pull_threshold: 0,
-            push_threshold: 0,
+            pull_threshold: 32,
+            push_threshold: 32,
             out_shiftdir: ShiftDirection::Left,
             in_shiftdir: ShiftDirection::Left,
             autopull: false,
@@ -1258,8 +1914,9 @@ impl<P: PIOExt> PIOBuilder<P> {
     ///
     /// The least-significant bit of `SET` instruction asserts the state of the pin indicated by `base`, the next bit
     /// asserts the state of the next pin, and so on up to `count` pins. The pin numbers are considered modulo 32.
+    ///
+    /// `count` can be at most 5.
     pub fn set_pins(mut self, base: u8, count: u8) -> Self {
-        assert!(count <= 5);
         self.set_base = base;
         self.set_count = count;
         self
@@ -1269,8 +1926,9 @@ impl<P: PIOExt> PIOBuilder<P> {
     ///
     /// The least-significant bit of `OUT` instruction asserts the state of the pin indicated by `base`, the next bit
     /// asserts the state of the next pin, and so on up to `count` pins. The pin numbers are considered modulo 32.
+    ///
+    /// `count` can be at most 32.
     pub fn out_pins(mut self, base: u8, count: u8) -> Self {
-        assert!(count <= 32);
         self.out_base = base;
         self.out_count = count;
         self
@@ -1305,7 +1963,8 @@ impl<P: PIOExt> PIOBuilder<P> {
 
     /// Set buffer sharing.
     ///
-    /// See [`Buffers`] for more information.
+    /// See [`Buffers`] for more information. It can be changed later with
+    /// [`StateMachine::set_buffers`].
     pub fn buffers(mut self, buffers: Buffers) -> Self {
         self.fifo_join = buffers;
         self
@@ -1348,6 +2007,8 @@ impl<P: PIOExt> PIOBuilder<P> {
     }
 
     /// Set the number of bits pushed into ISR before autopush or conditional push will take place.
+    ///
+    /// The threshold is between 1 and 32, and defaults to 32.
     pub fn push_threshold(mut self, threshold: u8) -> Self {
         self.push_threshold = threshold;
         self
@@ -1363,6 +2024,8 @@ impl<P: PIOExt> PIOBuilder<P> {
     }
 
     /// Set the number of bits pulled from out of OSR before autopull or conditional pull will take place.
+    ///
+    /// The threshold is between 1 and 32, and defaults to 32.
     pub fn pull_threshold(mut self, threshold: u8) -> Self {
         self.pull_threshold = threshold;
         self
@@ -1384,12 +2047,59 @@ impl<P: PIOExt> PIOBuilder<P> {
         self
     }
 
+    /// Check the configuration.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let pins = [
+            self.jmp_pin,
+            self.in_base,
+            self.side_set_base,
+            self.set_base,
+            self.out_base,
+        ];
+        if pins.iter().any(|&pin| pin >= 32) {
+            return Err(ConfigError::PinOutOfRange);
+        }
+        if self.set_count > 5 || self.out_count > 32 || self.program.side_set.bits() > 5 {
+            return Err(ConfigError::PinCountOutOfRange);
+        }
+        if matches!(self.inline_out, Some(bit) if bit >= 32) {
+            return Err(ConfigError::InlineOutOutOfRange);
+        }
+        let thresholds = 1..=32;
+        if !thresholds.contains(&self.pull_threshold) || !thresholds.contains(&self.push_threshold)
+        {
+            return Err(ConfigError::ThresholdOutOfRange);
+        }
+        let wrap = self.program.wrap;
+        if wrap.source >= self.program.length || wrap.target >= self.program.length {
+            return Err(ConfigError::WrapOutOfRange);
+        }
+        let set_pins = pin_range_mask(self.set_base, self.set_count);
+        let out_pins = pin_range_mask(self.out_base, self.out_count);
+        if set_pins & out_pins != 0 && self.set_base != self.out_base {
+            return Err(ConfigError::PinRangesOverlap);
+        }
+        Ok(())
+    }
+
     /// Build the config and deploy it to a StateMachine.
+    ///
+    /// The side-set configuration and the wrap points are taken from the program, and relocated
+    /// to the address the program is installed at. If a setting is invalid, the state machine
+    /// and the program are returned with the error.
     #[allow(clippy::type_complexity)] // The return type cannot really be simplified.
     pub fn build<SM: StateMachineIndex>(
         self,
         mut sm: UninitStateMachine<(P, SM)>,
-    ) -> (StateMachine<(P, SM), Stopped>, Rx<(P, SM)>, Tx<(P, SM)>) {
+    ) -> Result<(StateMachine<(P, SM), Stopped>, Rx<(P, SM)>, Tx<(P, SM)>), BuildError<P, SM>> {
+        if let Err(error) = self.validate() {
+            return Err(BuildError {
+                error,
+                sm,
+                program: self.program,
+            });
+        }
+
         let offset = self.program.offset;
 
         // Stop the SM
@@ -1441,18 +2151,14 @@ impl<P: PIOExt> PIOBuilder<P> {
         });
 
         sm.sm().sm_shiftctrl.write(|w| {
-            let (fjoin_rx, fjoin_tx) = match self.fifo_join {
-                Buffers::RxTx => (false, false),
-                Buffers::OnlyTx => (false, true),
-                Buffers::OnlyRx => (true, false),
-            };
+            let (fjoin_rx, fjoin_tx) = self.fifo_join.fjoin_bits();
             w.fjoin_rx().bit(fjoin_rx);
             w.fjoin_tx().bit(fjoin_tx);
 
             unsafe {
-                // TODO: Encode 32 as zero, and error on 0
-                w.pull_thresh().bits(self.pull_threshold);
-                w.push_thresh().bits(self.push_threshold);
+                // A threshold of 32 is encoded as 0
+                w.pull_thresh().bits(self.pull_threshold & 0x1f);
+                w.push_thresh().bits(self.push_threshold & 0x1f);
             }
 
             w.out_shiftdir().bit(self.out_shiftdir.bit());
@@ -1485,13 +2191,7 @@ impl<P: PIOExt> PIOBuilder<P> {
 
         // Set starting location by forcing the state machine to execute a jmp
         // to the beginning of the program we loaded in.
-        sm.exec_instruction(
-            pio::InstructionOperands::JMP {
-                condition: pio::JmpCondition::Always,
-                address: offset as u8,
-            }
-            .encode(),
-        );
+        sm.exec_instruction(jmp_instruction(offset));
 
         let rx = Rx {
             block: sm.block,
@@ -1501,7 +2201,7 @@ impl<P: PIOExt> PIOBuilder<P> {
             block: sm.block,
             _phantom: core::marker::PhantomData,
         };
-        (
+        Ok((
             StateMachine {
                 sm,
                 program: self.program,
@@ -1509,6 +2209,73 @@ impl<P: PIOExt> PIOBuilder<P> {
             },
             rx,
             tx,
-        )
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jmp_encoding() {
+        let program = pio_proc::pio!(
+            32,
+            "
+    jmp 0
+    jmp 7
+    jmp 31
+            "
+        );
+        let encoded = [jmp_instruction(0), jmp_instruction(7), jmp_instruction(31)];
+        assert_eq!(program.program.code[..], encoded);
+        assert_eq!(encoded, [0x0000, 0x0007, 0x001f]);
+    }
+
+    #[test]
+    fn set_encoding() {
+        let program = pio_proc::pio!(
+            32,
+            "
+    set pins, 0
+    set pins, 1
+    set pindirs, 0
+    set pindirs, 1
+            "
+        );
+        let encoded = [
+            set_instruction(pio::SetDestination::PINS, 0),
+            set_instruction(pio::SetDestination::PINS, 1),
+            set_instruction(pio::SetDestination::PINDIRS, 0),
+            set_instruction(pio::SetDestination::PINDIRS, 1),
+        ];
+        assert_eq!(program.program.code[..], encoded);
+        assert_eq!(encoded, [0xe000, 0xe001, 0xe080, 0xe081]);
+    }
+
+    #[test]
+    fn drain_encoding() {
+        let program = pio_proc::pio!(
+            32,
+            "
+    out null, 32
+    pull noblock
+            "
+        );
+        let encoded = [drain_instruction(true), drain_instruction(false)];
+        assert_eq!(program.program.code[..], encoded);
+        assert_eq!(encoded, [0x6060, 0x8080]);
+    }
+
+    #[test]
+    fn pin_ranges() {
+        assert_eq!(pin_range_mask(0, 0), 0);
+        assert_eq!(pin_range_mask(0, 5), 0b1_1111);
+        assert_eq!(pin_range_mask(3, 2), 0b1_1000);
+        assert_eq!(pin_range_mask(30, 4), 0xc000_0003);
+        assert_eq!(pin_range_mask(7, 32), u32::MAX);
+        // The default SET range overlaps OUT pins 4 and up, but not 5 and up
+        assert_ne!(pin_range_mask(0, 5) & pin_range_mask(4, 8), 0);
+        assert_eq!(pin_range_mask(0, 5) & pin_range_mask(5, 8), 0);
     }
 }
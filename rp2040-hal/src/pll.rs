@@ -4,7 +4,7 @@
 use core::{
     convert::{Infallible, TryFrom, TryInto},
     marker::PhantomData,
-    ops::{Deref, Range, RangeInclusive},
+    ops::Deref,
 };
 
 use embedded_time::{
@@ -76,6 +76,7 @@ impl<S: State, D: PhaseLockedLoopDevice> PhaseLockedLoop<S, D> {
 
 /// Error type for the PLL module.
 /// See Chapter 2, Section 18 §2 for details on constraints triggering these errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     /// Proposed VCO frequency is out of range.
     VcoFreqOutOfRange,
@@ -93,7 +94,23 @@ pub enum Error {
     BadArgument,
 }
 
+/// Allowed range of the VCO frequency, in Hz.
+pub const VCO_FREQ_RANGE_HZ: (u32, u32) = (750_000_000, 1_600_000_000);
+
+/// Minimum reference frequency (after the reference divider), in Hz.
+pub const MIN_REF_FREQ_HZ: u32 = 5_000_000;
+
+/// Allowed range of the feedback divider.
+pub const FBDIV_RANGE: (u16, u16) = (16, 320);
+
+/// Allowed range of each post divider.
+pub const POSTDIV_RANGE: (u8, u8) = (1, 7);
+
 /// Parameters for a PLL.
+///
+/// The output frequency is `xosc / refdiv * fbdiv / (post_div1 * post_div2)`, with the feedback
+/// divider `fbdiv` chosen so that `xosc / refdiv * fbdiv` is the VCO frequency.
+#[derive(Debug, Clone, Copy)]
 pub struct PLLConfig<R: Rate> {
     /// Voltage Controlled Oscillator frequency.
     pub vco_freq: R,
@@ -108,7 +125,133 @@ pub struct PLLConfig<R: Rate> {
     pub post_div2: u8,
 }
 
-/// Common configs for the two PLLs. Both assume the XOSC is cadenced at 12MHz !
+impl PLLConfig<Hertz> {
+    /// Check the configuration against the constraints of the PLL, and compute the output
+    /// frequency it achieves from the given XOSC frequency.
+    ///
+    /// This is a `const fn`, so a configuration can be checked at build time:
+    /// ```
+    /// use embedded_time::rate::Hertz;
+    /// use rp2040_hal::pll::PLLConfig;
+    ///
+    /// const CONFIG: PLLConfig<Hertz> = PLLConfig { vco_freq: Hertz(1_596_000_000), refdiv: 1, post_div1: 6, post_div2: 2 };
+    /// const OUTPUT: u32 = match CONFIG.output_frequency(Hertz(12_000_000)) {
+    ///     Ok(freq) => freq.0,
+    ///     Err(_) => 0,
+    /// };
+    /// assert_eq!(OUTPUT, 133_000_000);
+    /// ```
+    pub const fn output_frequency(&self, xosc_frequency: Hertz) -> Result<Hertz, Error> {
+        match self.check(xosc_frequency.0) {
+            Ok((_, frequency)) => Ok(Hertz(frequency)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the feedback divider and the output frequency, in Hz.
+    const fn check(&self, xosc_hz: u32) -> Result<(u16, u32), Error> {
+        let vco_hz = self.vco_freq.0;
+        if vco_hz < VCO_FREQ_RANGE_HZ.0 || vco_hz > VCO_FREQ_RANGE_HZ.1 {
+            return Err(Error::VcoFreqOutOfRange);
+        }
+
+        if self.post_div1 < POSTDIV_RANGE.0
+            || self.post_div1 > POSTDIV_RANGE.1
+            || self.post_div2 < POSTDIV_RANGE.0
+            || self.post_div2 > POSTDIV_RANGE.1
+        {
+            return Err(Error::PostDivOutOfRage);
+        }
+
+        if self.refdiv == 0 {
+            return Err(Error::BadArgument);
+        }
+        let ref_hz = xosc_hz / self.refdiv as u32;
+        if ref_hz < MIN_REF_FREQ_HZ || ref_hz > vco_hz / 16 {
+            return Err(Error::RefFreqOutOfRange);
+        }
+
+        let fbdiv = vco_hz / ref_hz;
+        if fbdiv < FBDIV_RANGE.0 as u32 || fbdiv > FBDIV_RANGE.1 as u32 {
+            return Err(Error::FeedbackDivOutOfRange);
+        }
+
+        let frequency = ref_hz * fbdiv / (self.post_div1 as u32 * self.post_div2 as u32);
+        Ok((fbdiv as u16, frequency))
+    }
+
+    /// Find the configuration whose output is closest to `target` from the given XOSC frequency.
+    ///
+    /// Like the SDK's `vcocalc.py`, the highest VCO frequency is preferred for the lowest jitter,
+    /// and among equivalent post dividers the one with `post_div1 >= post_div2` for the lowest
+    /// power. Returns `None` if no valid configuration exists, ie the XOSC frequency is out of
+    /// range.
+    ///
+    /// ```
+    /// use embedded_time::rate::Hertz;
+    /// use rp2040_hal::pll::PLLConfig;
+    ///
+    /// const CONFIG: Option<PLLConfig<Hertz>> = PLLConfig::from_target(Hertz(12_000_000), Hertz(96_000_000));
+    /// let config = CONFIG.unwrap();
+    /// assert_eq!((config.vco_freq, config.post_div1, config.post_div2), (Hertz(1_536_000_000), 4, 4));
+    /// ```
+    pub const fn from_target(xosc_frequency: Hertz, target: Hertz) -> Option<PLLConfig<Hertz>> {
+        let xosc_hz = xosc_frequency.0 as u64;
+        let target_hz = target.0 as u64;
+        let mut best: Option<PLLConfig<Hertz>> = None;
+        // Error of the best configuration, as the fraction `num / den` Hz
+        let (mut best_num, mut best_den) = (0, 1);
+
+        let mut refdiv = 1;
+        while refdiv <= 63 {
+            let ref_hz = xosc_hz / refdiv;
+            if ref_hz < MIN_REF_FREQ_HZ as u64 {
+                break;
+            }
+
+            let mut fbdiv = FBDIV_RANGE.1 as u64;
+            while fbdiv >= FBDIV_RANGE.0 as u64 {
+                let vco_hz = ref_hz * fbdiv;
+                if vco_hz >= VCO_FREQ_RANGE_HZ.0 as u64
+                    && vco_hz <= VCO_FREQ_RANGE_HZ.1 as u64
+                    && ref_hz <= vco_hz / 16
+                {
+                    let mut post_div2 = POSTDIV_RANGE.0;
+                    while post_div2 <= POSTDIV_RANGE.1 {
+                        let mut post_div1 = post_div2;
+                        while post_div1 <= POSTDIV_RANGE.1 {
+                            let div = post_div1 as u64 * post_div2 as u64;
+                            // `abs_diff` is only const since Rust 1.60, above the MSRV
+                            let num = if vco_hz > target_hz * div {
+                                vco_hz - target_hz * div
+                            } else {
+                                target_hz * div - vco_hz
+                            };
+                            if best.is_none() || num * best_den < best_num * div {
+                                best_num = num;
+                                best_den = div;
+                                best = Some(PLLConfig {
+                                    vco_freq: Hertz(vco_hz as u32),
+                                    refdiv: refdiv as u8,
+                                    post_div1,
+                                    post_div2,
+                                });
+                            }
+                            post_div1 += 1;
+                        }
+                        post_div2 += 1;
+                    }
+                }
+                fbdiv -= 1;
+            }
+            refdiv += 1;
+        }
+
+        best
+    }
+}
+
+/// Common configs for the two PLLs. All assume the XOSC is cadenced at 12MHz !
 /// See Chapter 2, Section 18, §2
 pub mod common_configs {
     use super::PLLConfig;
@@ -122,86 +265,117 @@ pub mod common_configs {
         post_div2: 2,
     };
 
+    /// PLL_SYS at 133MHz, the maximum rated system clock frequency
+    pub const PLL_SYS_133MHZ: PLLConfig<Megahertz> = PLLConfig {
+        vco_freq: Megahertz(1596),
+        refdiv: 1,
+        post_div1: 6,
+        post_div2: 2,
+    };
+
+    /// PLL_SYS at 96MHz, which divides down to common audio sample rates
+    pub const PLL_SYS_96MHZ: PLLConfig<Megahertz> = PLLConfig {
+        vco_freq: Megahertz(1536),
+        refdiv: 1,
+        post_div1: 4,
+        post_div2: 4,
+    };
+
     /// Default, nominal configuration for PLL_USB.
     pub const PLL_USB_48MHZ: PLLConfig<Megahertz> = PLLConfig {
-        vco_freq: Megahertz(480),
+        vco_freq: Megahertz(1440),
         refdiv: 1,
-        post_div1: 5,
-        post_div2: 2,
+        post_div1: 6,
+        post_div2: 5,
     };
 }
 
-impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
-    /// Instantiates a new Phase-Locked-Loop device.
-    pub fn new<R: Rate>(
-        dev: D,
+impl Disabled {
+    /// Check a configuration and compute the register values and the output frequency.
+    pub(crate) fn from_config<R: Rate>(
         xosc_frequency: Generic<u32>,
         config: PLLConfig<R>,
-    ) -> Result<PhaseLockedLoop<Disabled, D>, Error>
+    ) -> Result<Disabled, Error>
     where
         R: Into<Hertz<u64>>,
     {
-        const VCO_FREQ_RANGE: RangeInclusive<Hertz<u32>> =
-            Hertz(400_000_000)..=Hertz(1_600_000_000);
-        const POSTDIV_RANGE: Range<u8> = 1..7;
-        const FBDIV_RANGE: Range<u16> = 16..320;
-
         //First we convert our rate to Hertz<u64> as all other rates can be converted to that.
         let vco_freq: Hertz<u64> = config.vco_freq.into();
 
         //Then we try to downscale to u32.
         let vco_freq: Hertz<u32> = vco_freq.try_into().map_err(|_| Error::BadArgument)?;
-
-        if !VCO_FREQ_RANGE.contains(&vco_freq) {
-            return Err(Error::VcoFreqOutOfRange);
-        }
-
-        if !POSTDIV_RANGE.contains(&config.post_div1) || !POSTDIV_RANGE.contains(&config.post_div2)
-        {
-            return Err(Error::PostDivOutOfRage);
-        }
-
-        let ref_freq_range: Range<Hertz<u32>> = Hertz(5_000_000)..vco_freq.div(16);
-
-        let ref_freq_hz = Hertz::<u32>::try_from(xosc_frequency)
-            .map_err(|_| Error::BadArgument)?
-            .checked_div(&(config.refdiv as u32))
-            .ok_or(Error::BadArgument)?;
-
-        if !ref_freq_range.contains(&ref_freq_hz) {
-            return Err(Error::RefFreqOutOfRange);
-        }
-
-        let fbdiv = vco_freq
-            .checked_div(&ref_freq_hz.integer())
-            .ok_or(Error::BadArgument)?;
-
-        let fbdiv: u16 = (fbdiv.integer())
-            .try_into()
-            .map_err(|_| Error::BadArgument)?;
-
-        if !FBDIV_RANGE.contains(&fbdiv) {
-            return Err(Error::FeedbackDivOutOfRange);
-        }
+        let xosc_frequency =
+            Hertz::<u32>::try_from(xosc_frequency).map_err(|_| Error::BadArgument)?;
 
         let refdiv = config.refdiv;
         let post_div1 = config.post_div1;
         let post_div2 = config.post_div2;
-        let frequency: Hertz =
-            (ref_freq_hz / refdiv as u32) * fbdiv as u32 / (post_div1 as u32 * post_div2 as u32);
+        let (fbdiv, frequency) = PLLConfig {
+            vco_freq,
+            refdiv,
+            post_div1,
+            post_div2,
+        }
+        .check(xosc_frequency.integer())?;
+        let frequency = Hertz(frequency);
+
+        Ok(Disabled {
+            refdiv,
+            fbdiv,
+            post_div1,
+            post_div2,
+            frequency,
+        })
+    }
 
+    /// The output frequency of the PLL once it is locked.
+    pub(crate) fn frequency(&self) -> Hertz {
+        self.frequency
+    }
+}
+
+impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Disabled, D> {
+    /// Instantiates a new Phase-Locked-Loop device.
+    pub fn new<R: Rate>(
+        dev: D,
+        xosc_frequency: Generic<u32>,
+        config: PLLConfig<R>,
+    ) -> Result<PhaseLockedLoop<Disabled, D>, Error>
+    where
+        R: Into<Hertz<u64>>,
+    {
         Ok(PhaseLockedLoop {
-            state: Disabled {
-                refdiv,
-                fbdiv,
-                post_div1,
-                post_div2,
-                frequency,
-            },
+            state: Disabled::from_config(xosc_frequency, config)?,
             device: dev,
         })
     }
 
+    /// Change the configuration of the PLL before it is started.
+    ///
+    /// Returns the output frequency the PLL will achieve, or an error if the configuration is out of
+    /// range, in which case the previous configuration is kept.
+    pub fn configure<R: Rate>(
+        &mut self,
+        xosc_frequency: Generic<u32>,
+        config: PLLConfig<R>,
+    ) -> Result<Hertz, Error>
+    where
+        R: Into<Hertz<u64>>,
+    {
+        self.state = Disabled::from_config(xosc_frequency, config)?;
+        Ok(self.state.frequency)
+    }
+
+    /// The output frequency the PLL will achieve once it is locked.
+    pub fn output_frequency(&self) -> Hertz {
+        self.state.frequency
+    }
+
+    /// Instantiates a Phase-Locked-Loop device from an already checked configuration.
+    pub(crate) fn with_state(dev: D, state: Disabled) -> PhaseLockedLoop<Disabled, D> {
+        PhaseLockedLoop { device: dev, state }
+    }
+
     /// Configures and starts the PLL : it switches to Locking state.
     pub fn initialize(self, resets: &mut rp2040_pac::RESETS) -> PhaseLockedLoop<Locking, D> {
         self.device.reset_bring_up(resets);
@@ -282,6 +456,49 @@ impl<D: PhaseLockedLoopDevice> PhaseLockedLoop<Locked, D> {
     pub fn operating_frequency(&self) -> Hertz {
         self.state.frequency
     }
+
+    /// Power down the PLL, keeping its configuration.
+    ///
+    /// Nothing may run from the PLL until [`power_up_blocking`](Self::power_up_blocking) is
+    /// called.
+    pub(crate) fn power_down(&self) {
+        self.device.pwr.write(|w| {
+            w.pd().set_bit();
+            w.dsmpd().set_bit();
+            w.postdivpd().set_bit();
+            w.vcopd().set_bit();
+            w
+        });
+    }
+
+    /// Power up the PLL after [`power_down`](Self::power_down), and wait until it is locked.
+    pub(crate) fn power_up_blocking(&self) {
+        self.device.pwr.modify(|_, w| {
+            w.pd().clear_bit();
+            w.vcopd().clear_bit();
+            w
+        });
+        while self.device.cs.read().lock().bit_is_clear() {}
+        self.device.pwr.modify(|_, w| {
+            w.postdivpd().clear_bit();
+            w
+        });
+    }
+}
+
+/// Power down a PLL which is not going to be used, and hold it in reset.
+///
+/// This also stops a PLL left running by previous software, eg a bootloader.
+pub fn power_down<D: PhaseLockedLoopDevice>(dev: &D, resets: &mut RESETS) {
+    dev.reset_bring_up(resets);
+    dev.pwr.write(|w| {
+        w.pd().set_bit();
+        w.dsmpd().set_bit();
+        w.postdivpd().set_bit();
+        w.vcopd().set_bit();
+        w
+    });
+    dev.reset_bring_down(resets);
 }
 
 /// Blocking helper method to setup the PLL without going through all the steps.
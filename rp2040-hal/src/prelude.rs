@@ -1,3 +1,4 @@
 //! Prelude
 pub use crate::clocks::Clock as _rphal_clocks_Clock;
+pub use crate::dma::DMAExt as _rphal_dma_DMAExt;
 pub use crate::pio::PIOExt as _rphal_pio_PIOExt;
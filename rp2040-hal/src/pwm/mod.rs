@@ -52,6 +52,9 @@
 //! channel_a.get_duty();
 //! channel_a.set_inverted(); // Invert the output
 //! channel_a.clr_inverted(); // Don't invert the output
+//!
+//! // Detach the pin again, getting it back in the mode it had before
+//! let gpio24 = channel_a.disconnect(channel_pin_a);
 //! ```
 //!
 //! The following configuration options are also available:
@@ -72,6 +75,41 @@
 //!
 //! ```
 //!
+//! Slices in one of the input modes can measure the signal on their B pin:
+//!
+//! ```no_run
+//! # use rp2040_hal::{prelude::*, gpio::Pins, Sio, pwm::{CountRisingEdge, InputHighRunning, Slices}};
+//! # use embedded_time::rate::Hertz;
+//! # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+//! # let sio = Sio::new(pac.SIO);
+//! # let pins = Pins::new(pac.IO_BANK0, pac.PADS_BANK0, sio.gpio_bank0, &mut pac.RESETS);
+//! # let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! # let mut delay = cortex_m::delay::Delay::new(cortex_m::Peripherals::take().unwrap().SYST, 125_000_000);
+//! // Count the pulses of a fan tachometer connected to GPIO 25 over 100ms
+//! let mut pwm = pwm_slices.pwm4.into_mode::<CountRisingEdge>();
+//! pwm.default_config();
+//! let _tach = pwm.input_from(pins.gpio25);
+//! let pulses = pwm.count_rising_edges(&mut delay, 100_000).unwrap();
+//!
+//! // Measure how much of the time the signal is high
+//! let mut pwm = pwm.into_mode::<InputHighRunning>();
+//! pwm.set_div_int(16);
+//! let duty = pwm.measure_duty_cycle(&mut delay, 10_000, Hertz(125_000_000)).unwrap();
+//! ```
+//!
+//! A slice can also be configured from a frequency, and channels from a duty cycle:
+//!
+//! ```no_run
+//! # use rp2040_hal::{prelude::*, pwm::Slices};
+//! # use embedded_time::rate::Hertz;
+//! # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+//! # let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+//! # let mut pwm = pwm_slices.pwm4;
+//! // 25kHz at 40% duty cycle
+//! let actual_freq = pwm.set_freq(Hertz(125_000_000), Hertz(25_000)).unwrap();
+//! pwm.channel_a.set_duty_percent(40.0);
+//! ```
+//!
 //! default_config() sets ph_correct to false, the clock divider to 1, does not invert the output, sets top to 65535, and resets the counter.
 //! min_config() leaves those registers in the state they were before it was called (Careful, this can lead to unexpected behavior)
 //! It's recommended to only call min_config() after calling default_config() on a pin that shares a PWM block.
@@ -79,6 +117,7 @@
 use core::marker::PhantomData;
 
 use crate::{
+    dma::{EndlessWriteTarget, WriteTarget},
     gpio::{
         bank0::*, FunctionClock, FunctionI2C, FunctionPio0, FunctionPio1, FunctionPwm, FunctionSpi,
         FunctionUart, FunctionUsbAux, FunctionXip, Input, InputConfig, Output, OutputConfig, Pin,
@@ -89,7 +128,8 @@ use crate::{
 };
 #[cfg(feature = "eh1_0_alpha")]
 use eh1_0_alpha::pwm::blocking as eh1;
-use embedded_hal::PwmPin;
+use embedded_hal::{blocking::delay::DelayUs, PwmPin};
+use embedded_time::{fixed_point::FixedPoint, rate::Hertz};
 use pac::PWM;
 
 use crate::atomic_register_access::{write_bitmask_clear, write_bitmask_set};
@@ -315,11 +355,22 @@ where
     }
 
     /// Enable phase correct mode
+    ///
+    /// The counter counts up to TOP and back down to 0 instead of wrapping, which halves the output
+    /// frequency and centres the high pulses on the counter reaching 0. The wrap interrupt and DREQ
+    /// fire when the counter reaches 0, so they mark the middle of the high pulse, which is a good
+    /// moment to sample a current with the ADC.
     #[inline]
     pub fn set_ph_correct(&mut self) {
         self.regs.write_ph_correct(true)
     }
 
+    /// Is phase correct mode enabled?
+    #[inline]
+    pub fn is_ph_correct(&self) -> bool {
+        self.regs.read_ph_correct()
+    }
+
     /// Disables phase correct mode
     #[inline]
     pub fn clr_ph_correct(&mut self) {
@@ -351,6 +402,9 @@ where
     }
 
     /// Get the counter register value
+    ///
+    /// The hardware doesn't report the counting direction in phase correct mode. Use the wrap
+    /// interrupt, which fires when the counter reaches 0, to synchronise with the period instead.
     #[inline]
     pub fn get_counter(&self) -> u16 {
         self.regs.read_ctr()
@@ -374,6 +428,45 @@ where
         self.regs.write_top(value)
     }
 
+    /// Configure TOP and the clock divider to run the slice at `freq`
+    ///
+    /// `sys_freq` is the frequency of the system clock, which drives the slice. The smallest
+    /// divider that lets TOP fit into 16 bits is used, which gives the finest duty cycle
+    /// resolution. Phase correct mode halves the output frequency, so it should be selected
+    /// before calling this function.
+    ///
+    /// Returns the frequency that was achieved, which may differ slightly from `freq` because of
+    /// rounding. Duty cycles set before this call are not rescaled.
+    pub fn set_freq(&mut self, sys_freq: Hertz, freq: Hertz) -> Result<Hertz, FrequencyError> {
+        let sys_freq = u64::from(sys_freq.integer());
+        let freq = u64::from(freq.integer());
+        let phase_factor = if self.regs.read_ph_correct() { 2 } else { 1 };
+        if freq == 0 {
+            return Err(FrequencyError::TooLow);
+        }
+
+        // Length of one period, in 1/16ths of a system clock cycle
+        let period_16 = sys_freq * 16 / (freq * phase_factor);
+
+        // Pick the smallest divider (in 1/16ths) that lets TOP + 1 fit into 16 bits
+        let div_16 = core::cmp::max((period_16 + 0xffff) / 0x1_0000, 16);
+        if div_16 > 0xfff {
+            return Err(FrequencyError::TooLow);
+        }
+        let wrap = (period_16 + div_16 / 2) / div_16;
+        if wrap < 2 {
+            return Err(FrequencyError::TooHigh);
+        }
+
+        self.regs
+            .write_div((div_16 >> 4) as u8, (div_16 & 0xf) as u8);
+        self.regs.write_top((wrap - 1) as u16);
+
+        Ok(Hertz(
+            (sys_freq * 16 / (div_16 * wrap * phase_factor)) as u32,
+        ))
+    }
+
     /// Create the interrupt bitmask corresponding to this slice
     #[inline]
     fn bitmask(&self) -> u32 {
@@ -400,9 +493,20 @@ where
         };
     }
 
-    /// Did this slice trigger an overflow interrupt?
+    /// Has this slice's counter wrapped since the interrupt was last cleared?
+    ///
+    /// This reads the raw interrupt status, so it works whether or not the interrupt is enabled.
     #[inline]
     pub fn has_overflown(&self) -> bool {
+        let mask = self.bitmask();
+        unsafe { (*pac::PWM::ptr()).intr.read().bits() & mask == mask }
+    }
+
+    /// Is the PWM_IRQ_WRAP interrupt asserted for this slice?
+    ///
+    /// True when the interrupt is enabled and either the counter wrapped or the interrupt was forced.
+    #[inline]
+    pub fn is_interrupt_pending(&self) -> bool {
         let mask = self.bitmask();
         unsafe { (*pac::PWM::ptr()).ints.read().bits() & mask == mask }
     }
@@ -436,6 +540,250 @@ where
     }
 }
 
+impl<I, M> Slice<I, M>
+where
+    I: SliceId,
+    M: SliceMode + ValidSliceMode<I>,
+{
+    /// DMA write target updating the compare values of both channels with each transfer
+    ///
+    /// Each 32 bit word carries the channel A compare value in its lower half and the channel B
+    /// compare value in its upper half. See [`DmaWriteTarget`] for how transfers are paced.
+    pub fn dma_write_target(&self) -> DmaWriteTarget<I, u32> {
+        DmaWriteTarget::new()
+    }
+
+    /// DMA write target updating the compare values of both channels with the same 16 bit value
+    ///
+    /// The bus replicates narrow writes across the whole register, so every transfer sets channel
+    /// A and channel B to the same duty cycle. Use [`dma_write_target`](Self::dma_write_target)
+    /// to drive the channels independently, or [`Channel::dma_write_target`] to update a single
+    /// channel.
+    pub fn dma_write_target_mirrored(&self) -> DmaWriteTarget<I, u16> {
+        DmaWriteTarget::new()
+    }
+}
+
+/// DMA write target for the compare (CC) register of a slice
+///
+/// Transfers are paced by the slice's wrap DREQ, so a new duty cycle is loaded once per PWM
+/// period. The DREQ is raised on every counter wrap independently of the interrupt enable, so no
+/// further configuration of the slice is needed. Values written to CC only take effect at the next
+/// wrap, so the output never glitches mid-period.
+pub struct DmaWriteTarget<I: SliceId, Word> {
+    slice: PhantomData<I>,
+    word: PhantomData<Word>,
+}
+
+impl<I: SliceId, Word> DmaWriteTarget<I, Word> {
+    fn new() -> Self {
+        DmaWriteTarget {
+            slice: PhantomData,
+            word: PhantomData,
+        }
+    }
+}
+
+unsafe impl<I: SliceId, Word: crate::dma::Word> WriteTarget for DmaWriteTarget<I, Word> {
+    type TransmittedWord = Word;
+
+    fn tx_treq() -> Option<u8> {
+        Some(crate::dma::DREQ_PWM_WRAP0 + I::DYN.num)
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let num = I::DYN.num as usize;
+        let cc = unsafe { &(*pac::PWM::ptr()).ch[num].cc };
+        (cc as *const _ as u32, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<I: SliceId, Word: crate::dma::Word> EndlessWriteTarget for DmaWriteTarget<I, Word> {}
+
+/// DMA write target for the compare value of a single channel
+///
+/// Narrow writes are replicated across the whole CC register, so a 16 bit transfer would also
+/// overwrite the compare value of the other channel. This target instead writes 32 bit words to the
+/// XOR alias of CC: each word holds the bits that change between two compare values, in the half
+/// of the register belonging to channel `C`, and zeros in the other half, which is left as it is.
+/// Use [`encode`](Self::encode) to turn compare values into such words.
+///
+/// Transfers are paced like those of [`DmaWriteTarget`].
+pub struct ChannelDmaWriteTarget<S: SliceId, C: ChannelId> {
+    slice: PhantomData<S>,
+    channel: PhantomData<C>,
+}
+
+impl<S: SliceId, C: ChannelId> ChannelDmaWriteTarget<S, C> {
+    fn new() -> Self {
+        ChannelDmaWriteTarget {
+            slice: PhantomData,
+            channel: PhantomData,
+        }
+    }
+
+    /// Encode the compare values `values` into the words to transfer to this target
+    ///
+    /// `previous` is the compare value of the channel when the first word is transferred, i.e. its
+    /// current duty cycle or the last value of the previous buffer. The last value of `values` is
+    /// returned, to encode the next buffer. A buffer played in a loop must start from its own last
+    /// value, and the duty cycle must be set to that value before the transfer starts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` and `words` have different lengths.
+    pub fn encode(&self, previous: u16, values: &[u16], words: &mut [u32]) -> u16 {
+        assert_eq!(values.len(), words.len());
+        let shift = match C::DYN {
+            DynChannelId::A => 0,
+            DynChannelId::B => 16,
+        };
+        let mut previous = previous;
+        for (word, &value) in words.iter_mut().zip(values) {
+            *word = u32::from(previous ^ value) << shift;
+            previous = value;
+        }
+        previous
+    }
+}
+
+unsafe impl<S: SliceId, C: ChannelId> WriteTarget for ChannelDmaWriteTarget<S, C> {
+    type TransmittedWord = u32;
+
+    fn tx_treq() -> Option<u8> {
+        Some(crate::dma::DREQ_PWM_WRAP0 + S::DYN.num)
+    }
+
+    fn tx_address_count(&mut self) -> (u32, u32) {
+        let num = S::DYN.num as usize;
+        let cc = unsafe { &(*pac::PWM::ptr()).ch[num].cc };
+        // XOR alias of the register
+        (cc as *const _ as u32 + 0x1000, u32::MAX)
+    }
+
+    fn tx_increment(&self) -> bool {
+        false
+    }
+}
+
+impl<S: SliceId, C: ChannelId> EndlessWriteTarget for ChannelDmaWriteTarget<S, C> {}
+
+/// Error returned when a slice can't be configured to run at the requested frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyError {
+    /// The frequency is lower than what the maximum divider and TOP can produce
+    TooLow,
+    /// The frequency is too high to leave at least two steps of duty cycle resolution
+    TooHigh,
+}
+
+/// Error returned when measuring the input signal on channel B
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureError {
+    /// The counter wrapped at TOP during the measurement window, so the count is incomplete.
+    ///
+    /// Use a shorter window, a larger TOP value or (when measuring the high time) a larger clock divider.
+    CounterWrapped,
+}
+
+impl<S: SliceId, M: SliceMode + ValidSliceInputMode<S>> Slice<S, M> {
+    /// Reset the counter, let it run for `window_us` microseconds and return the final count
+    ///
+    /// The slice is left disabled, with the count still in the counter register.
+    fn count_over_window<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        window_us: u32,
+    ) -> Result<u16, MeasureError> {
+        self.regs.write_enable(false);
+        self.regs.write_ctr(0);
+        self.clear_interrupt();
+        self.regs.write_enable(true);
+        delay.delay_us(window_us);
+        self.regs.write_enable(false);
+
+        if self.has_overflown() {
+            Err(MeasureError::CounterWrapped)
+        } else {
+            Ok(self.regs.read_ctr())
+        }
+    }
+}
+
+impl<S: SliceId> Slice<S, CountRisingEdge>
+where
+    CountRisingEdge: ValidSliceInputMode<S>,
+{
+    /// Count the rising edges seen on the B pin during a window of `window_us` microseconds
+    ///
+    /// The counter increments once per edge divided by the clock divider, so leave the divider at 1
+    /// to count every edge. If more than TOP edges arrive during the window
+    /// [`MeasureError::CounterWrapped`] is returned.
+    ///
+    /// The slice is left disabled afterwards.
+    pub fn count_rising_edges<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        window_us: u32,
+    ) -> Result<u16, MeasureError> {
+        self.count_over_window(delay, window_us)
+    }
+}
+
+impl<S: SliceId> Slice<S, CountFallingEdge>
+where
+    CountFallingEdge: ValidSliceInputMode<S>,
+{
+    /// Count the falling edges seen on the B pin during a window of `window_us` microseconds
+    ///
+    /// See [`count_rising_edges`](Slice::count_rising_edges) for details.
+    pub fn count_falling_edges<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        window_us: u32,
+    ) -> Result<u16, MeasureError> {
+        self.count_over_window(delay, window_us)
+    }
+}
+
+impl<S: SliceId> Slice<S, InputHighRunning>
+where
+    InputHighRunning: ValidSliceInputMode<S>,
+{
+    /// Measure the fraction of a `window_us` microsecond window during which the B pin was high
+    ///
+    /// `sys_freq` is the frequency of the system clock, which drives the counter. The counter
+    /// advances at `sys_freq` divided by the clock divider while the pin is high, so the window
+    /// must satisfy `window_us * sys_freq / divider < TOP` or [`MeasureError::CounterWrapped`] is
+    /// returned. At 125MHz with a divider of 255 and TOP at 65535 the window can be up to ~133ms.
+    ///
+    /// The window should span many periods of the measured signal for an accurate result. The slice
+    /// is left disabled afterwards.
+    pub fn measure_duty_cycle<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        window_us: u32,
+        sys_freq: Hertz,
+    ) -> Result<f32, MeasureError> {
+        let high_ticks = self.count_over_window(delay, window_us)?;
+
+        // A divider integer part of 0 means 256
+        let div_int = match self.regs.read_div_int() {
+            0 => 256.0,
+            int => f32::from(int),
+        };
+        let divider = div_int + f32::from(self.regs.read_div_frac()) / 16.0;
+        let window_ticks = window_us as f32 * sys_freq.integer() as f32 / 1_000_000.0 / divider;
+
+        // The delay may overshoot slightly, so never report more than 100%
+        Ok((f32::from(high_ticks) / window_ticks).min(1.0))
+    }
+}
+
 macro_rules! pwm {
     ($PWMX:ident, [
         $($SXi:ident: ($slice:literal, [$($pin_a:ident, $pin_b:ident),*], $i:expr)),+
@@ -514,16 +862,30 @@ impl<C: OutputConfig> NonPwmPinMode for Output<C> {}
 
 /// Stores the attached gpio pin.
 ///
-/// This value can be ignored/dropped or stored to retrieve the original pin struct
-pub struct PwmPinToken<G: PinId + BankPinId> {
+/// This value can be ignored/dropped or stored to retrieve the original pin struct. `P` is the
+/// mode the pin was in before it was attached to the pwm.
+pub struct PwmPinToken<G: PinId + BankPinId, P: PinMode + ValidPinMode<G>> {
     pin: Pin<G, FunctionPwm>,
+    previous_mode: PhantomData<P>,
 }
 
-impl<G: PinId + BankPinId> PwmPinToken<G> {
+impl<G: PinId + BankPinId, P: PinMode + ValidPinMode<G>> PwmPinToken<G, P> {
+    fn new<PM: PinMode + ValidPinMode<G>>(pin: Pin<G, PM>) -> Self {
+        PwmPinToken {
+            pin: pin.into_mode(),
+            previous_mode: PhantomData,
+        }
+    }
+
     /// Retrieve the original pin while disconnecting it from the pwm
     pub fn into_mode<N: PinMode + ValidPinMode<G> + NonPwmPinMode>(self) -> Pin<G, N> {
         self.pin.into_mode::<N>()
     }
+
+    /// Retrieve the original pin in the mode it was in before it was attached to the pwm
+    pub fn into_previous_mode(self) -> Pin<G, P> {
+        self.pin.into_mode::<P>()
+    }
 }
 
 impl Slices {
@@ -532,17 +894,59 @@ impl Slices {
         self._pwm
     }
 
-    //     /// Enable multiple slices at the same time to make their counters sync up.
-    //     ///
-    //     /// You still need to call `slice` to get an actual slice
-    //     pub fn enable_simultaneous<S: SliceId>(&mut self, bits: u8) {
-    //         // Enable all slices at the same time
-    //         unsafe {
-    //             &(*pac::PWM::ptr())
-    //                 .en
-    //                 .modify(|r, w| w.bits(((r.bits() as u8) | bits) as u32));
-    //         }
-    //     }
+    /// Bitmask of the slices whose PWM_IRQ_WRAP interrupt is currently asserted
+    ///
+    /// Bit `n` is set for slice `n`. The interrupt is shared between all slices, so the handler can
+    /// use this to find out which slices need servicing:
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::pwm::Slices;
+    /// # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+    /// # let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+    /// let pending = pwm_slices.pending_interrupts();
+    /// if pending & (1 << 3) != 0 {
+    ///     // Load the next duty value for slice 3 ...
+    ///     pwm_slices.pwm3.clear_interrupt();
+    /// }
+    /// ```
+    #[inline]
+    pub fn pending_interrupts(&self) -> u8 {
+        self._pwm.ints.read().bits() as u8
+    }
+
+    /// Enable multiple slices at the same time to make their counters sync up.
+    ///
+    /// Bit `n` of `bits` enables slice `n`; slices whose bit is clear are left as they are. The
+    /// slices must be configured beforehand, and their counters will stay in lockstep as long as
+    /// they share the same divider and TOP. Reset the counters with
+    /// [`Slice::set_counter`] before starting to get identical counter values.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::pwm::Slices;
+    /// # let mut pac = rp2040_pac::Peripherals::take().unwrap();
+    /// let mut pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+    /// pwm_slices.pwm0.default_config();
+    /// pwm_slices.pwm1.default_config();
+    /// pwm_slices.pwm2.default_config();
+    /// // Start slices 0, 1 and 2 on the same clock cycle
+    /// pwm_slices.enable_simultaneous(0b0000_0111);
+    /// ```
+    #[inline]
+    pub fn enable_simultaneous(&mut self, bits: u8) {
+        self._pwm
+            .en
+            .modify(|r, w| unsafe { w.bits(r.bits() | u32::from(bits)) });
+    }
+
+    /// Disable multiple slices at the same time, freezing their counters on the same cycle.
+    ///
+    /// Bit `n` of `bits` disables slice `n`; slices whose bit is clear are left as they are.
+    #[inline]
+    pub fn disable_simultaneous(&mut self, bits: u8) {
+        self._pwm
+            .en
+            .modify(|r, w| unsafe { w.bits(r.bits() & !u32::from(bits)) });
+    }
 
     // /// Get pwm slice based on gpio pin
     // pub fn borrow_mut_from_pin<
@@ -589,6 +993,65 @@ impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C> {
 
 impl<S: SliceId, M: SliceMode, C: ChannelId> Sealed for Channel<S, M, C> {}
 
+impl<S: SliceId, M: SliceMode, C: ChannelId> Channel<S, M, C> {
+    #[inline]
+    fn write_cc(&mut self, value: u16) {
+        match C::DYN {
+            DynChannelId::A => self.regs.write_cc_a(value),
+            DynChannelId::B => self.regs.write_cc_b(value),
+        }
+    }
+
+    /// Detach a pin attached with `output_to` or `input_from` from this channel
+    ///
+    /// The pin is returned in the mode it was in before it was attached, so it can for example be
+    /// driven to a safe state through SIO and attached again later. Only the tokens of pins that
+    /// belong to this channel are accepted.
+    pub fn disconnect<G, P>(&mut self, token: PwmPinToken<G, P>) -> Pin<G, P>
+    where
+        G: PinId + BankPinId + ValidPwmOutputPin<S, C>,
+        P: PinMode + ValidPinMode<G>,
+    {
+        token.into_previous_mode()
+    }
+
+    /// Set the duty cycle to `num / denom` of the PWM period
+    ///
+    /// The compare value is scaled against the slice's current TOP, so this should be called
+    /// after [`Slice::set_top`] or [`Slice::set_freq`]. Phase correct mode doubles both the high
+    /// time and the period, so it doesn't change the scale. A fraction of 1 or more keeps the
+    /// output high for the whole period, unless TOP is 65535, where the longest possible high
+    /// time is one count short of the period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denom` is 0.
+    pub fn set_duty_fraction(&mut self, num: u16, denom: u16) {
+        let wrap = u32::from(self.regs.read_top()) + 1;
+        let num = core::cmp::min(num, denom);
+        let cc = wrap * u32::from(num) / u32::from(denom);
+        self.write_cc(core::cmp::min(cc, u32::from(u16::MAX)) as u16);
+    }
+
+    /// Set the duty cycle as a percentage of the PWM period
+    ///
+    /// Values are clamped to the range 0 to 100. See
+    /// [`set_duty_fraction`](Self::set_duty_fraction) for how the value is scaled.
+    pub fn set_duty_percent(&mut self, percent: f32) {
+        let wrap = f32::from(self.regs.read_top()) + 1.0;
+        let cc = wrap * percent.clamp(0.0, 100.0) / 100.0;
+        self.write_cc(cc.min(f32::from(u16::MAX)) as u16);
+    }
+
+    /// DMA write target updating the compare value of this channel only
+    ///
+    /// The compare value of the other channel can still be changed while a transfer is running.
+    /// See [`ChannelDmaWriteTarget`] for the format of the transferred words.
+    pub fn dma_write_target(&self) -> ChannelDmaWriteTarget<S, C> {
+        ChannelDmaWriteTarget::new()
+    }
+}
+
 impl<S: SliceId, M: SliceMode> PwmPin for Channel<S, M, A> {
     type Duty = u16;
 
@@ -607,8 +1070,13 @@ impl<S: SliceId, M: SliceMode> PwmPin for Channel<S, M, A> {
         self.regs.read_cc_a()
     }
 
+    /// Returns TOP + 1, the duty cycle that keeps the output high for the whole period
+    ///
+    /// When TOP is 65535 this saturates at 65535, one count short of a full period. Phase correct
+    /// mode doesn't change the scale. Duty cycles are not rescaled when TOP changes, so set the
+    /// duty cycle again after calling [`Slice::set_top`] or [`Slice::set_freq`].
     fn get_max_duty(&self) -> Self::Duty {
-        self.regs.read_top()
+        self.regs.read_top().saturating_add(1)
     }
 
     fn set_duty(&mut self, duty: Self::Duty) {
@@ -638,8 +1106,9 @@ impl<S: SliceId, M: SliceMode> eh1::PwmPin for Channel<S, M, A> {
         Ok(self.regs.read_cc_a())
     }
 
+    /// Returns TOP + 1, saturating at 65535. See [`PwmPin::get_max_duty`].
     fn get_max_duty(&self) -> Result<Self::Duty, Self::Error> {
-        Ok(self.regs.read_top())
+        Ok(self.regs.read_top().saturating_add(1))
     }
 
     fn set_duty(&mut self, duty: Self::Duty) -> Result<(), Self::Error> {
@@ -666,8 +1135,13 @@ impl<S: SliceId, M: SliceMode> PwmPin for Channel<S, M, B> {
         self.regs.read_cc_b()
     }
 
+    /// Returns TOP + 1, the duty cycle that keeps the output high for the whole period
+    ///
+    /// When TOP is 65535 this saturates at 65535, one count short of a full period. Phase correct
+    /// mode doesn't change the scale. Duty cycles are not rescaled when TOP changes, so set the
+    /// duty cycle again after calling [`Slice::set_top`] or [`Slice::set_freq`].
     fn get_max_duty(&self) -> Self::Duty {
-        self.regs.read_top()
+        self.regs.read_top().saturating_add(1)
     }
 
     fn set_duty(&mut self, duty: Self::Duty) {
@@ -696,8 +1170,9 @@ impl<S: SliceId, M: SliceMode> eh1::PwmPin for Channel<S, M, B> {
         Ok(self.regs.read_cc_b())
     }
 
+    /// Returns TOP + 1, saturating at 65535. See [`PwmPin::get_max_duty`].
     fn get_max_duty(&self) -> Result<Self::Duty, Self::Error> {
-        Ok(self.regs.read_top())
+        Ok(self.regs.read_top().saturating_add(1))
     }
 
     fn set_duty(&mut self, duty: Self::Duty) -> Result<(), Self::Error> {
@@ -714,10 +1189,8 @@ impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Channel<S, M, A> {
     >(
         &mut self,
         pin: Pin<G, PM>,
-    ) -> PwmPinToken<G> {
-        PwmPinToken {
-            pin: pin.into_mode(),
-        }
+    ) -> PwmPinToken<G, PM> {
+        PwmPinToken::new(pin)
     }
 
     /// Invert channel output
@@ -741,10 +1214,8 @@ impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Channel<S, M, B> {
     >(
         &mut self,
         pin: Pin<G, PM>,
-    ) -> PwmPinToken<G> {
-        PwmPinToken {
-            pin: pin.into_mode(),
-        }
+    ) -> PwmPinToken<G, PM> {
+        PwmPinToken::new(pin)
     }
 
     /// Invert channel output
@@ -765,10 +1236,8 @@ impl<S: SliceId, M: SliceMode + ValidSliceInputMode<S>> Channel<S, M, B> {
     pub fn input_from<G: PinId + BankPinId + ValidPwmInputPin<S>, PM: PinMode + ValidPinMode<G>>(
         &mut self,
         pin: Pin<G, PM>,
-    ) -> PwmPinToken<G> {
-        PwmPinToken {
-            pin: pin.into_mode(),
-        }
+    ) -> PwmPinToken<G, PM> {
+        PwmPinToken::new(pin)
     }
 }
 
@@ -781,10 +1250,8 @@ impl<S: SliceId, M: SliceMode + ValidSliceMode<S>> Slice<S, M> {
     >(
         &mut self,
         pin: Pin<G, PM>,
-    ) -> PwmPinToken<G> {
-        PwmPinToken {
-            pin: pin.into_mode(),
-        }
+    ) -> PwmPinToken<G, PM> {
+        PwmPinToken::new(pin)
     }
 }
 
@@ -793,9 +1260,45 @@ impl<S: SliceId, M: SliceMode + ValidSliceInputMode<S>> Slice<S, M> {
     pub fn input_from<G: PinId + BankPinId + ValidPwmInputPin<S>, PM: PinMode + ValidPinMode<G>>(
         &mut self,
         pin: Pin<G, PM>,
-    ) -> PwmPinToken<G> {
-        PwmPinToken {
-            pin: pin.into_mode(),
-        }
+    ) -> PwmPinToken<G, PM> {
+        PwmPinToken::new(pin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `words` to the XOR alias of a CC register holding `cc`
+    fn play(cc: u32, words: &[u32]) -> u32 {
+        words.iter().fold(cc, |cc, word| cc ^ word)
+    }
+
+    #[test]
+    fn channel_target_only_updates_its_half() {
+        let values = [10, 200, 0, 65535, 7];
+        let mut words = [0; 5];
+
+        let target = ChannelDmaWriteTarget::<Pwm0, A>::new();
+        assert_eq!(target.encode(3, &values, &mut words), 7);
+        assert!(words.iter().all(|word| word >> 16 == 0));
+        assert_eq!(play(0x1234_0003, &words[..2]), 0x1234_00c8);
+        assert_eq!(play(0x1234_0003, &words), 0x1234_0007);
+
+        let target = ChannelDmaWriteTarget::<Pwm0, B>::new();
+        assert_eq!(target.encode(3, &values, &mut words), 7);
+        assert!(words.iter().all(|word| word & 0xffff == 0));
+        assert_eq!(play(0x0003_1234, &words[..4]), 0xffff_1234);
+        assert_eq!(play(0x0003_1234, &words), 0x0007_1234);
+    }
+
+    #[test]
+    fn channel_target_loops_from_its_last_value() {
+        let values = [1, 2, 3];
+        let mut words = [0; 3];
+        let target = ChannelDmaWriteTarget::<Pwm0, A>::new();
+        target.encode(3, &values, &mut words);
+        assert_eq!(play(3, &words), 3);
+        assert_eq!(play(3, &[words, words].concat()), 3);
     }
 }
@@ -1,4 +1,5 @@
 use super::dyn_slice::{DynSliceId, DynSliceMode};
+use crate::atomic_register_access::write_bitmask_xor;
 use pac::pwm::CH;
 
 /// # Safety
@@ -53,6 +54,11 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().csr.modify(|_, w| w.ph_correct().bit(value));
     }
 
+    #[inline]
+    fn read_ph_correct(&self) -> bool {
+        self.ch().csr.read().ph_correct().bit_is_set()
+    }
+
     #[inline]
     fn write_enable(&mut self, value: bool) {
         self.ch().csr.modify(|_, w| w.en().bit(value));
@@ -67,6 +73,21 @@ pub(super) unsafe trait RegisterInterface {
         self.ch().div.modify(|_, w| unsafe { w.frac().bits(value) });
     }
 
+    #[inline]
+    fn write_div(&mut self, int: u8, frac: u8) {
+        self.ch()
+            .div
+            .write(|w| unsafe { w.int().bits(int).frac().bits(frac) });
+    }
+    #[inline]
+    fn read_div_int(&self) -> u8 {
+        self.ch().div.read().int().bits()
+    }
+    #[inline]
+    fn read_div_frac(&self) -> u8 {
+        self.ch().div.read().frac().bits()
+    }
+
     #[inline]
     fn write_ctr(&mut self, value: u16) {
         self.ch().ctr.write(|w| unsafe { w.ctr().bits(value) });
@@ -78,7 +99,10 @@ pub(super) unsafe trait RegisterInterface {
     }
     #[inline]
     fn write_cc_a(&mut self, value: u16) {
-        self.ch().cc.modify(|_, w| unsafe { w.a().bits(value) });
+        // Flip the bits that change through the XOR alias, so a DMA transfer updating the other
+        // channel at the same time isn't undone
+        let flip = u32::from(self.read_cc_a() ^ value);
+        unsafe { write_bitmask_xor(self.ch().cc.as_ptr(), flip) };
     }
     #[inline]
     fn read_cc_a(&self) -> u16 {
@@ -87,7 +111,10 @@ pub(super) unsafe trait RegisterInterface {
 
     #[inline]
     fn write_cc_b(&mut self, value: u16) {
-        self.ch().cc.modify(|_, w| unsafe { w.b().bits(value) });
+        // Flip the bits that change through the XOR alias, so a DMA transfer updating the other
+        // channel at the same time isn't undone
+        let flip = u32::from(self.read_cc_b() ^ value) << 16;
+        unsafe { write_bitmask_xor(self.ch().cc.as_ptr(), flip) };
     }
 
     #[inline]
@@ -8,6 +8,8 @@
 //! > functionality that would otherwise have to take up space in most user
 //! > binaries.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 /// A bootrom function table code.
 pub type RomFnTableCode = [u8; 2];
 
@@ -19,14 +21,37 @@ type RomTableLookupFn<T> = unsafe extern "C" fn(*const u16, u32) -> T;
 const ROM_TABLE_LOOKUP_PTR: *const u16 = 0x0000_0018 as _;
 
 /// Pointer to helper functions lookup table.
-const FUNC_TABLE: *const u16 = 0x0000_0014 as _;
+pub(crate) const FUNC_TABLE: *const u16 = 0x0000_0014 as _;
 
 /// Pointer to the public data lookup table.
 const DATA_TABLE: *const u16 = 0x0000_0016 as _;
 
+/// Pointer to the magic bytes identifying the bootrom, followed by its version.
+const ROM_MAGIC_PTR: *const [u8; 3] = 0x0000_0010 as _;
+
+/// Magic bytes identifying the bootrom.
+const ROM_MAGIC: [u8; 3] = [b'M', b'u', 0x01];
+
+/// Pointer to the version of the bootrom.
+const ROM_VERSION_PTR: *const u8 = 0x0000_0013 as _;
+
+/// The version of the bootrom, which identifies the chip revision: 1 for B0, 2 for B1 and 3 for
+/// B2.
+///
+/// The functions and data available depend on it, e.g. [`soft_double_table`] requires version 2.
+pub fn rom_version_number() -> u8 {
+    unsafe { *ROM_VERSION_PTR }
+}
+
 /// Retrive rom content from a table using a code.
-fn rom_table_lookup<T>(table: *const u16, tag: RomFnTableCode) -> T {
+///
+/// # Panics
+///
+/// If the bootrom doesn't start with the expected magic bytes, so the lookup function can't be
+/// trusted.
+pub(crate) fn rom_table_lookup<T>(table: *const u16, tag: RomFnTableCode) -> T {
     unsafe {
+        assert_eq!(*ROM_MAGIC_PTR, ROM_MAGIC, "unexpected bootrom magic");
         let rom_table_lookup_ptr: *const u32 = rom_hword_as_ptr(ROM_TABLE_LOOKUP_PTR);
         let rom_table_lookup: RomTableLookupFn<T> = core::mem::transmute(rom_table_lookup_ptr);
         rom_table_lookup(
@@ -47,6 +72,19 @@ unsafe fn rom_hword_as_ptr(rom_address: *const u16) -> *const u32 {
     ptr as *const u32
 }
 
+/// Look up a function in the bootrom on the first call, and return the address stored in `cache`
+/// afterwards.
+///
+/// The addresses differ between bootrom versions, so they are looked up at runtime.
+fn cached_lookup(cache: &AtomicUsize, tag: RomFnTableCode) -> usize {
+    let mut ptr = cache.load(Ordering::Relaxed);
+    if ptr == 0 {
+        ptr = rom_table_lookup(FUNC_TABLE, tag);
+        cache.store(ptr, Ordering::Relaxed);
+    }
+    ptr
+}
+
 macro_rules! rom_funcs {
     (
         $(
@@ -59,7 +97,9 @@ macro_rules! rom_funcs {
         $(
             $(#[$outer])*
             pub fn $name($( $aname:$aty ),*) -> $ret{
-                let func:  extern "C" fn( $( $aty ),* ) -> $ret = rom_table_lookup(FUNC_TABLE, *$c);
+                static FUNC: AtomicUsize = AtomicUsize::new(0);
+                let func: extern "C" fn( $( $aty ),* ) -> $ret =
+                    unsafe { core::mem::transmute(cached_lookup(&FUNC, *$c)) };
                 func($( $aname ),*)
             }
         )*
@@ -78,7 +118,9 @@ macro_rules! rom_funcs_unsafe {
         $(
             $(#[$outer])*
             pub unsafe fn $name($( $aname:$aty ),*) -> $ret{
-                let func:  extern "C" fn( $( $aty ),* ) -> $ret = rom_table_lookup(FUNC_TABLE, *$c);
+                static FUNC: AtomicUsize = AtomicUsize::new(0);
+                let func: extern "C" fn( $( $aty ),* ) -> $ret =
+                    core::mem::transmute(cached_lookup(&FUNC, *$c));
                 func($( $aname ),*)
             }
         )*
@@ -108,12 +150,12 @@ rom_funcs! {
     ///    * 0 To enable both interfaces (as per cold boot).
     ///    * 1 To disable the USB Mass Storage Interface.
     ///    * 2 to Disable the USB PICOBOOT Interface.
-    b"UB" reset_to_usb_boot(gpio_activity_pin_mask: u32, disable_interface_mask: u32) -> ();
+    b"UB" reset_to_usb_boot(gpio_activity_pin_mask: u32, disable_interface_mask: u32) -> !;
 }
 
 rom_funcs_unsafe! {
     /// Sets n bytes start at ptr to the value c and returns ptr
-    b"MS" memset(ptr: *mut u8, c: u8, n: u8) -> *mut u8;
+    b"MS" memset(ptr: *mut u8, c: u8, n: u32) -> *mut u8;
 
     /// Sets n bytes start at ptr to the value c and returns ptr.
     ///
@@ -123,14 +165,14 @@ rom_funcs_unsafe! {
 
     /// Copies n bytes starting at src to dest and returns dest. The results are undefined if the
     /// regions overlap.
-    b"MC" memcpy(dest: *mut u8, src: *mut u8, n: u32) -> u8;
+    b"MC" memcpy(dest: *mut u8, src: *const u8, n: u32) -> *mut u8;
 
     /// Copies n bytes starting at src to dest and returns dest. The results are undefined if the
     /// regions overlap.
     ///
     /// Note this is a slightly more efficient variant of _memcpy that may only be
     /// used if dest and src are word aligned.
-    b"C4" memcpy44(dest: *mut u32, src: *mut u32, n: u32) -> *mut u8;
+    b"C4" memcpy44(dest: *mut u32, src: *const u32, n: u32) -> *mut u32;
 
     /// Restore all QSPI pad controls to their default state, and connect the SSI to the QSPI pads.
     b"IF" connect_internal_flash() -> ();
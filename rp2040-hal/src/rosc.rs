@@ -4,6 +4,26 @@
 use embedded_time::rate::Extensions;
 use embedded_time::rate::Hertz;
 
+use crate::clocks::{FcError, FcSrc, FrequencyCounter};
+
+/// Password for the FREQA, FREQB and DIV registers
+const FREQ_PASSWD: u32 = 0x9696 << 16;
+const DIV_PASSWD: u32 = 0xaa0;
+
+/// Number of delay stages of the ring (see Chapter 2, Section 17, §2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum RoscRange {
+    /// 8 stages, the slowest range
+    Low = 0xfa4,
+    /// 6 stages
+    Medium = 0xfa5,
+    /// 4 stages
+    High = 0xfa7,
+    /// 2 stages, which may be too fast for the logic it drives
+    TooHigh = 0xfa6,
+}
+
 /// State of the Ring Oscillator (typestate trait)
 pub trait State {}
 
@@ -16,7 +36,9 @@ pub struct Enabled {
 }
 
 /// ROSC is in dormant mode (see Chapter 2, Section 17, §7)
-pub struct Dormant;
+pub struct Dormant {
+    freq_hz: Hertz,
+}
 
 impl State for Disabled {}
 impl State for Enabled {}
@@ -64,10 +86,83 @@ impl RingOscillator<Disabled> {
 
 impl RingOscillator<Enabled> {
     /// Approx operating frequency of the ROSC in hertz
+    ///
+    /// This is the nominal frequency of 6.5 MHz, until it is measured with
+    /// [`measure_frequency`](Self::measure_frequency).
     pub fn operating_frequency(&self) -> Hertz {
         self.state.freq_hz
     }
 
+    /// Measure the frequency of the ROSC output, after its divider
+    ///
+    /// The measurement is stored, and returned by [`operating_frequency`](Self::operating_frequency)
+    /// afterwards. `ref_khz` is the frequency of clk_ref in kHz, which should not run from the
+    /// ROSC for this to be meaningful. See [`FrequencyCounter::measure`].
+    pub fn measure_frequency(
+        &mut self,
+        counter: &mut FrequencyCounter,
+        ref_khz: u32,
+    ) -> Result<Hertz, FcError> {
+        let freq = counter.measure(FcSrc::Rosc, ref_khz)?;
+        self.state.freq_hz = freq;
+        Ok(freq)
+    }
+
+    /// Set the number of delay stages
+    ///
+    /// Changing the range may glitch the output, so nothing should run from the ROSC. The
+    /// frequency changes, see [`measure_frequency`](Self::measure_frequency).
+    pub fn set_range(&mut self, range: RoscRange) {
+        self.device
+            .ctrl
+            .modify(|_, w| unsafe { w.freq_range().bits(range as u16) });
+    }
+
+    /// Set the drive strengths of the delay stages 0 to 3 (`freqa`) and 4 to 7 (`freqb`)
+    ///
+    /// Each drive strength is between 0 and 7, higher values shorten the delay of the stage and
+    /// raise the frequency. Only the stages in use for the current [`RoscRange`] have an effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a drive strength is larger than 7.
+    pub fn set_drive_stages(&mut self, freqa: [u8; 4], freqb: [u8; 4]) {
+        fn stages(ds: [u8; 4]) -> u32 {
+            ds.iter().enumerate().fold(0, |bits, (i, &ds)| {
+                assert!(ds <= 7, "Drive strength out of range");
+                bits | u32::from(ds) << (i * 4)
+            })
+        }
+
+        self.device
+            .freqa
+            .write(|w| unsafe { w.bits(FREQ_PASSWD | stages(freqa)) });
+        self.device
+            .freqb
+            .write(|w| unsafe { w.bits(FREQ_PASSWD | stages(freqb)) });
+    }
+
+    /// Set the divider of the ROSC output, between 1 and 32
+    ///
+    /// The stored frequency is divided accordingly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `div` is out of range.
+    pub fn set_div(&mut self, div: u8) {
+        assert!((1..=32).contains(&div), "ROSC divider out of range");
+        let old_div = match self.device.div.read().bits() & 0x1f {
+            0 => 32,
+            div => div,
+        };
+
+        // A divider of 32 is encoded as 0
+        self.device
+            .div
+            .write(|w| unsafe { w.bits(DIV_PASSWD + u32::from(div & 0x1f)) });
+        self.state.freq_hz = self.state.freq_hz * old_div / u32::from(div);
+    }
+
     /// Disables the ROSC
     pub fn disable(self) -> RingOscillator<Disabled> {
         self.device.ctrl.modify(|_r, w| w.enable().disable());
@@ -92,9 +187,22 @@ impl RingOscillator<Enabled> {
         //taken from the C SDK
         const ROSC_DORMANT_VALUE: u32 = 0x636f6d61;
 
+        let freq_hz = self.state.freq_hz;
         self.device.dormant.write(|w| w.bits(ROSC_DORMANT_VALUE));
 
-        self.transition(Dormant)
+        self.transition(Dormant { freq_hz })
+    }
+}
+
+impl RingOscillator<Dormant> {
+    /// Get the oscillator back after the chip woke up from DORMANT state.
+    ///
+    /// Blocks until the ROSC is stable again.
+    pub fn wake(self) -> RingOscillator<Enabled> {
+        while self.device.status.read().stable().bit_is_clear() {}
+
+        let freq_hz = self.state.freq_hz;
+        self.transition(Enabled { freq_hz })
     }
 }
 
@@ -122,3 +230,115 @@ impl rand_core::RngCore for RingOscillator<Enabled> {
         Ok(())
     }
 }
+
+/// Random number generator sampling the RANDOMBIT register of an enabled ROSC
+///
+/// Consecutive samples are spaced a few ROSC periods apart, so they are not taken within the same
+/// oscillation. Whitening with a von Neumann extractor can be enabled with
+/// [`with_debias`](Self::with_debias), which removes the bias of the raw bits at the cost of
+/// (at least) four times as many samples.
+///
+/// This works whether or not the ROSC drives any clock, but the output is only weakly random and
+/// is not suited for security purposes.
+///
+/// ```no_run
+/// use rand_core::RngCore;
+/// use rp2040_hal::{pac, rosc::{RingOscillator, RoscRng}};
+///
+/// let mut peripherals = pac::Peripherals::take().unwrap();
+/// let rosc = RingOscillator::new(peripherals.ROSC).initialize();
+/// let mut rng = RoscRng::new(&rosc).with_debias(true);
+/// let nonce = rng.next_u64();
+/// ```
+pub struct RoscRng<'a> {
+    rosc: &'a RingOscillator<Enabled>,
+    debias: bool,
+}
+
+impl<'a> RoscRng<'a> {
+    /// Error code reported by [`try_fill_bytes`](rand_core::RngCore::try_fill_bytes) when the ROSC
+    /// is not running
+    pub const ROSC_DISABLED: u32 = rand_core::Error::CUSTOM_START;
+
+    /// Create a generator sampling the given ROSC, without debiasing
+    pub fn new(rosc: &'a RingOscillator<Enabled>) -> Self {
+        RoscRng {
+            rosc,
+            debias: false,
+        }
+    }
+
+    /// Enable or disable the von Neumann debias step
+    pub fn with_debias(self, debias: bool) -> Self {
+        RoscRng { debias, ..self }
+    }
+
+    /// Whether the ROSC is actually running
+    ///
+    /// A stopped ROSC returns constant bits.
+    pub fn is_running(&self) -> bool {
+        self.rosc.device.status.read().enabled().bit_is_set()
+    }
+
+    fn sample(&self) -> bool {
+        // Wait a few ROSC periods between samples
+        let sys_hz = crate::clocks::SYSTEM_CLOCK_HZ.load(core::sync::atomic::Ordering::Relaxed);
+        let rosc_hz = self.rosc.state.freq_hz.0.max(1);
+        cortex_m::asm::delay(4 * (sys_hz / rosc_hz).max(1));
+
+        self.rosc.get_random_bit()
+    }
+
+    fn next_bit(&self) -> bool {
+        if !self.debias {
+            return self.sample();
+        }
+
+        loop {
+            let (a, b) = (self.sample(), self.sample());
+            if a != b {
+                return a;
+            }
+        }
+    }
+
+    fn fill(&self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            *byte = 0;
+            for _ in 0..8 {
+                *byte = (*byte << 1) | self.next_bit() as u8;
+            }
+        }
+    }
+}
+
+impl rand_core::RngCore for RoscRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the ROSC is not running.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        assert!(
+            self.is_running(),
+            "ROSC is disabled, random bits would be constant"
+        );
+        self.fill(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        if !self.is_running() {
+            return Err(core::num::NonZeroU32::new(Self::ROSC_DISABLED)
+                .unwrap()
+                .into());
+        }
+        self.fill(dest);
+        Ok(())
+    }
+}
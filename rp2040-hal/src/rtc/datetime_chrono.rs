@@ -33,6 +33,11 @@ pub(crate) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
     }
 }
 
+pub(super) fn validate_day_of_week(_dt: &DateTime) -> Result<(), Error> {
+    // chrono computes the day of the week from the date
+    Ok(())
+}
+
 pub(super) fn write_setup_0(dt: &DateTime, w: &mut setup_0::W) {
     // Safety: the `.bits()` fields are marked `unsafe` but all bit values are valid
     unsafe {
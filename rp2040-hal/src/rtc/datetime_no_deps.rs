@@ -9,9 +9,11 @@ pub enum Error {
     InvalidYear,
     /// The [DateTime] contains an invalid month value. Must be between `1..=12`.
     InvalidMonth,
-    /// The [DateTime] contains an invalid day value. Must be between `1..=31`.
+    /// The [DateTime] contains an invalid day value. Must be between `1` and the number of days
+    /// in the month, eg `1..=29` for February of a leap year.
     InvalidDay,
-    /// The [DateTime] contains an invalid day of week. Must be between `0..=6` where 0 is Sunday.
+    /// The [DateTime] contains an invalid day of week. Must be between `0..=6` where 0 is Sunday,
+    /// and match the date if the RTC checks the day of the week.
     InvalidDayOfWeek(
         /// The value of the DayOfWeek that was given.
         u8,
@@ -25,6 +27,7 @@ pub enum Error {
 }
 
 /// Structure containing date and time information
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DateTime {
     /// 0..4095
     pub year: u16,
@@ -42,6 +45,87 @@ pub struct DateTime {
     pub second: u8,
 }
 
+impl DateTime {
+    /// Create a new [DateTime], computing the day of the week from the date.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if any of the values is out of range, including a day which doesn't
+    /// exist in the given month, like February 30th or February 29th of a non-leap year.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::rtc::{DateTime, DayOfWeek, DateTimeError};
+    /// let dt = DateTime::new(2024, 2, 29, 12, 0, 0).unwrap();
+    /// assert_eq!(dt.day_of_week, DayOfWeek::Thursday);
+    /// assert_eq!(DateTime::new(2023, 2, 29, 12, 0, 0), Err(DateTimeError::InvalidDay));
+    /// assert_eq!(DateTime::new(2100, 2, 29, 12, 0, 0), Err(DateTimeError::InvalidDay));
+    /// ```
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+    ) -> Result<Self, Error> {
+        // Check the date before computing the day of the week from it
+        if !(1..=12).contains(&month) {
+            return Err(Error::InvalidMonth);
+        }
+        let dt = DateTime {
+            year,
+            month,
+            day,
+            day_of_week: Self::day_of_week_for(year, month, day),
+            hour,
+            minute,
+            second,
+        };
+        validate_datetime(&dt)?;
+        Ok(dt)
+    }
+
+    /// Compute the day of the week of a date in the Gregorian calendar, using Sakamoto's method.
+    ///
+    /// The RTC doesn't compute the day of the week, it only increments the value it was set with,
+    /// so this can be used to get it right when setting the date.
+    ///
+    /// `month` must be between `1..=12`, the day is not checked against the length of the month.
+    ///
+    /// ```no_run
+    /// # use rp2040_hal::rtc::{DateTime, DayOfWeek};
+    /// assert_eq!(DateTime::day_of_week_for(2000, 1, 1), DayOfWeek::Saturday);
+    /// assert_eq!(DateTime::day_of_week_for(2000, 2, 29), DayOfWeek::Tuesday);
+    /// assert_eq!(DateTime::day_of_week_for(2000, 3, 1), DayOfWeek::Wednesday);
+    /// assert_eq!(DateTime::day_of_week_for(1999, 12, 31), DayOfWeek::Friday);
+    /// assert_eq!(DateTime::day_of_week_for(2100, 2, 28), DayOfWeek::Sunday);
+    /// assert_eq!(DateTime::day_of_week_for(2100, 3, 1), DayOfWeek::Monday);
+    /// assert_eq!(DateTime::day_of_week_for(0, 1, 1), DayOfWeek::Saturday);
+    /// ```
+    pub const fn day_of_week_for(year: u16, month: u8, day: u8) -> DayOfWeek {
+        const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+
+        // Shift the year by a full 400 year cycle, which keeps the days of the week, so January
+        // and February of year 0 don't underflow.
+        let mut y = year as u32 + 400;
+        if month < 3 {
+            y -= 1;
+        }
+        let dotw =
+            (y + y / 4 - y / 100 + y / 400 + OFFSETS[(month as usize - 1) % 12] + day as u32) % 7;
+
+        match dotw {
+            0 => DayOfWeek::Sunday,
+            1 => DayOfWeek::Monday,
+            2 => DayOfWeek::Tuesday,
+            3 => DayOfWeek::Wednesday,
+            4 => DayOfWeek::Thursday,
+            5 => DayOfWeek::Friday,
+            _ => DayOfWeek::Saturday,
+        }
+    }
+}
+
 /// A day of the week
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
@@ -73,12 +157,22 @@ pub(super) fn day_of_week_to_u8(dotw: DayOfWeek) -> u8 {
     dotw as u8
 }
 
+/// Number of days in the month, following the Gregorian calendar
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
 pub(super) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
     if dt.year > 4095 {
         Err(Error::InvalidYear)
     } else if dt.month < 1 || dt.month > 12 {
         Err(Error::InvalidMonth)
-    } else if dt.day < 1 || dt.day > 31 {
+    } else if dt.day < 1 || dt.day > days_in_month(dt.year, dt.month) {
         Err(Error::InvalidDay)
     } else if dt.hour > 23 {
         Err(Error::InvalidHour)
@@ -91,6 +185,14 @@ pub(super) fn validate_datetime(dt: &DateTime) -> Result<(), Error> {
     }
 }
 
+pub(super) fn validate_day_of_week(dt: &DateTime) -> Result<(), Error> {
+    if dt.day_of_week == DateTime::day_of_week_for(dt.year, dt.month, dt.day) {
+        Ok(())
+    } else {
+        Err(Error::InvalidDayOfWeek(dt.day_of_week as u8))
+    }
+}
+
 pub(super) fn write_setup_0(dt: &DateTime, w: &mut setup_0::W) {
     // Safety: the `.bits()` fields are marked `unsafe` but all bit values are valid
     unsafe {
@@ -131,3 +233,70 @@ pub(super) fn datetime_from_registers(rtc_0: rtc_0::R, rtc_1: rtc_1::R) -> Resul
         second,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_of_week_at_century_boundaries() {
+        let cases = [
+            // 1900 is not a leap year
+            ((1900, 1, 1), DayOfWeek::Monday),
+            ((1900, 2, 28), DayOfWeek::Wednesday),
+            ((1900, 3, 1), DayOfWeek::Thursday),
+            ((1900, 12, 31), DayOfWeek::Monday),
+            // 2000 is a leap year
+            ((1999, 12, 31), DayOfWeek::Friday),
+            ((2000, 1, 1), DayOfWeek::Saturday),
+            ((2000, 2, 28), DayOfWeek::Monday),
+            ((2000, 2, 29), DayOfWeek::Tuesday),
+            ((2000, 3, 1), DayOfWeek::Wednesday),
+            // 2100 is not a leap year
+            ((2100, 1, 1), DayOfWeek::Friday),
+            ((2100, 2, 28), DayOfWeek::Sunday),
+            ((2100, 3, 1), DayOfWeek::Monday),
+            ((2024, 2, 29), DayOfWeek::Thursday),
+        ];
+        for &((year, month, day), expected) in cases.iter() {
+            assert_eq!(
+                DateTime::day_of_week_for(year, month, day),
+                expected,
+                "{}-{}-{}",
+                year,
+                month,
+                day
+            );
+        }
+    }
+
+    #[test]
+    fn day_of_week_advances_every_day() {
+        let mut previous = day_of_week_to_u8(DateTime::day_of_week_for(1899, 12, 31));
+        for year in 1900..=2100 {
+            for month in 1..=12 {
+                for day in 1..=days_in_month(year, month) {
+                    let dotw = day_of_week_to_u8(DateTime::day_of_week_for(year, month, day));
+                    assert_eq!(dotw, (previous + 1) % 7, "{}-{}-{}", year, month, day);
+                    previous = dotw;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn february_29th() {
+        for &year in [2000, 2024, 2400].iter() {
+            let dt = DateTime::new(year, 2, 29, 0, 0, 0).unwrap();
+            assert_eq!(dt.day_of_week, DateTime::day_of_week_for(year, 2, 29));
+        }
+        for &year in [1900, 2023, 2100].iter() {
+            assert_eq!(DateTime::new(year, 2, 29, 0, 0, 0), Err(Error::InvalidDay));
+        }
+        assert_eq!(DateTime::new(2024, 2, 30, 0, 0, 0), Err(Error::InvalidDay));
+        assert_eq!(
+            DateTime::new(2024, 13, 1, 0, 0, 0),
+            Err(Error::InvalidMonth)
+        );
+    }
+}
@@ -12,9 +12,10 @@
 //!
 //! - **Day of week**: The RTC will not compute the correct day of the week; it will only increment the existing value.
 //!   - With the `chrono` feature, the day of week is calculated by chrono and should be correct. The value from the rp2040 itself is not used.
+//!   - Without it, create the date with `DateTime::new` or use `DateTime::day_of_week_for` to get the correct day of week, and call `RealTimeClock::set_day_of_week_check(true)` to reject a wrong one.
 //! - **Leap year**: If the current year is evenly divisible by 4, a leap year is detected, then Feb 28th is followed by Feb 29th instead  of  March  1st.
 //!   - There are cases where this is incorrect, e.g. century years have no leap day, but the chip will still add a Feb 29th.
-//!   - To disable leap year checking and never have a Feb 29th, call `RealTimeClock::set_leap_year_check(false)`.
+//!   - To disable leap year checking and never have a Feb 29th, call `RealTimeClock::set_leap_year_check(false)`, e.g. during the year 2100.
 //!
 //! Other limitations:
 //!
@@ -23,6 +24,7 @@
 
 use crate::clocks::Clock;
 use crate::clocks::RtcClock;
+use crate::clocks::{ClockGate, ClockGated};
 use embedded_time::fixed_point::FixedPoint;
 use rp2040_pac::{RESETS, RTC};
 
@@ -39,12 +41,15 @@ pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
 /// A reference to the real time clock of the system
 pub struct RealTimeClock {
     rtc: RTC,
+    day_of_week_check: bool,
 }
 
 impl RealTimeClock {
     /// Create a new instance of the real time clock, with the given date as an initial value.
     ///
     /// Note that the [`ClocksManager`] should be enabled first. See the [`clocks`] module for more information.
+    /// The divider of the RTC is set from the current frequency of `clock`, so clk_rtc has to be configured
+    /// before, e.g. with `ClocksManager::rtc_clock_from_xosc` to keep the RTC running while the PLLs are stopped.
     ///
     /// # Errors
     ///
@@ -54,7 +59,7 @@ impl RealTimeClock {
     /// [`clocks`]: ../clocks/index.html
     pub fn new(
         rtc: RTC,
-        clock: RtcClock,
+        clock: &RtcClock,
         resets: &mut RESETS,
         initial_date: DateTime,
     ) -> Result<Self, RtcError> {
@@ -69,12 +74,20 @@ impl RealTimeClock {
         let freq = clock.freq().integer() - 1;
         rtc.clkdiv_m1.write(|w| unsafe { w.bits(freq) });
 
-        let mut result = Self { rtc };
+        let mut result = Self {
+            rtc,
+            day_of_week_check: false,
+        };
         result.set_leap_year_check(true); // should be on by default, make sure this is the case.
         result.set_datetime(initial_date)?;
         Ok(result)
     }
 
+    /// The clock gates of the RTC, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate {
+        RTC::CLOCK_GATE
+    }
+
     /// Enable or disable the leap year check. The rp2040 chip will always add a Feb 29th on every year that is divisable by 4, but this may be incorrect (e.g. on century years). This function allows you to disable this check.
     ///
     /// Leap year checking is enabled by default.
@@ -84,6 +97,20 @@ impl RealTimeClock {
             .modify(|_, w| w.force_notleapyear().bit(!leap_year_check_enabled));
     }
 
+    /// Enable or disable checking the day of the week in [`set_datetime`]. When enabled, a
+    /// [`DateTime`] whose day of the week doesn't match its date is rejected with
+    /// `RtcError::InvalidDateTime`, as the rp2040 would carry the wrong day of the week forward.
+    /// [`DateTime::day_of_week_for`] computes the correct value.
+    ///
+    /// Day of week checking is disabled by default. With the `chrono` feature, the day of the week is
+    /// always computed from the date and this setting has no effect.
+    ///
+    /// [`set_datetime`]: #method.set_datetime
+    /// [`DateTime::day_of_week_for`]: struct.DateTime.html#method.day_of_week_for
+    pub fn set_day_of_week_check(&mut self, day_of_week_check_enabled: bool) {
+        self.day_of_week_check = day_of_week_check_enabled;
+    }
+
     /// Checks to see if this RealTimeClock is running
     pub fn is_running(&self) -> bool {
         self.rtc.ctrl.read().rtc_active().bit_is_set()
@@ -96,6 +123,9 @@ impl RealTimeClock {
     /// Will return `RtcError::InvalidDateTime` if the datetime is not a valid range.
     pub fn set_datetime(&mut self, t: DateTime) -> Result<(), RtcError> {
         self::datetime::validate_datetime(&t).map_err(RtcError::InvalidDateTime)?;
+        if self.day_of_week_check {
+            self::datetime::validate_day_of_week(&t).map_err(RtcError::InvalidDateTime)?;
+        }
 
         // disable RTC while we configure it
         self.rtc.ctrl.modify(|_, w| w.rtc_enable().clear_bit());
@@ -112,9 +142,10 @@ impl RealTimeClock {
             w
         });
 
-        // Load the new datetime and re-enable RTC
-        self.rtc.ctrl.write(|w| w.load().set_bit());
-        self.rtc.ctrl.write(|w| w.rtc_enable().set_bit());
+        // Load the new datetime and re-enable RTC. The control register is modified, not written,
+        // to keep the leap year setting.
+        self.rtc.ctrl.modify(|_, w| w.load().set_bit());
+        self.rtc.ctrl.modify(|_, w| w.rtc_enable().set_bit());
         while self.rtc.ctrl.read().rtc_active().bit_is_clear() {
             core::hint::spin_loop();
         }
@@ -132,8 +163,17 @@ impl RealTimeClock {
             return Err(RtcError::NotRunning);
         }
 
-        let rtc_0 = self.rtc.rtc_0.read();
-        let rtc_1 = self.rtc.rtc_1.read();
+        // RTC_0 must be read before RTC_1. Read both twice, until they agree, so the value can't be
+        // torn by the clock ticking in between.
+        let (rtc_0, rtc_1) = loop {
+            let rtc_0 = self.rtc.rtc_0.read();
+            let rtc_1 = self.rtc.rtc_1.read();
+            if rtc_0.bits() == self.rtc.rtc_0.read().bits()
+                && rtc_1.bits() == self.rtc.rtc_1.read().bits()
+            {
+                break (rtc_0, rtc_1);
+            }
+        };
 
         self::datetime::datetime_from_registers(rtc_0, rtc_1).map_err(RtcError::InvalidDateTime)
     }
@@ -153,7 +193,12 @@ impl RealTimeClock {
 
     /// Schedule an alarm. The `filter` determines at which point in time this alarm is set.
     ///
-    /// Keep in mind that the filter only triggers on the specified time. If you want to schedule this alarm every minute, you have to call:
+    /// Only the fields set in the filter take part in the match, so `DateTimeFilter::default().hour(3).minute(0).second(0)`
+    /// fires every day at 03:00:00, while a filter with every date and time field set fires once. Call [`enable_interrupt`] and unmask
+    /// `RTC_IRQ` in the NVIC to get an interrupt when the alarm fires.
+    ///
+    /// Keep in mind that the filter only triggers on the specified time. A filter on the minute alone matches for the whole
+    /// minute, so to schedule this alarm once every minute, you have to call:
     /// ```no_run
     /// # #[cfg(feature = "chrono")]
     /// # fn main() { }
@@ -168,6 +213,8 @@ impl RealTimeClock {
     /// );
     /// # }
     /// ```
+    ///
+    /// [`enable_interrupt`]: #method.enable_interrupt
     pub fn schedule_alarm(&mut self, filter: DateTimeFilter) {
         self.disable_alarm();
 
@@ -187,12 +234,47 @@ impl RealTimeClock {
         }
     }
 
-    /// Clear the interrupt. This should be called every time the `RTC_IRQ` interrupt is triggered,
-    /// or the next [`schedule_alarm`] will never fire.
+    /// Clear the interrupt. This should be called every time the `RTC_IRQ` interrupt is triggered.
     ///
-    /// [`schedule_alarm`]: #method.schedule_alarm
+    /// The match stays asserted for as long as the time matches the filter, e.g. for the whole
+    /// matching second, so the alarm is disabled to clear the interrupt. A recurring alarm, one where
+    /// any of the date and time fields of the filter is not set, is enabled again afterwards so it fires on the next match.
+    /// A one-shot alarm stays disabled.
     pub fn clear_interrupt(&mut self) {
         self.disable_alarm();
+
+        if self.is_alarm_recurring() {
+            self.rtc.irq_setup_0.modify(|_, w| w.match_ena().set_bit());
+            while self.rtc.irq_setup_0.read().match_active().bit_is_clear() {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Raise the `RTC_IRQ` interrupt when the scheduled alarm fires.
+    pub fn enable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().set_bit());
+    }
+
+    /// Stop raising the `RTC_IRQ` interrupt when the scheduled alarm fires.
+    pub fn disable_interrupt(&mut self) {
+        self.rtc.inte.modify(|_, w| w.rtc().clear_bit());
+    }
+
+    /// Check if the alarm is pending, i.e. the time matches the scheduled alarm.
+    pub fn is_alarm_pending(&self) -> bool {
+        self.rtc.ints.read().rtc().bit_is_set()
+    }
+
+    fn is_alarm_recurring(&self) -> bool {
+        let setup_0 = self.rtc.irq_setup_0.read();
+        let setup_1 = self.rtc.irq_setup_1.read();
+        !(setup_0.year_ena().bit_is_set()
+            && setup_0.month_ena().bit_is_set()
+            && setup_0.day_ena().bit_is_set()
+            && setup_1.hour_ena().bit_is_set()
+            && setup_1.min_ena().bit_is_set()
+            && setup_1.sec_ena().bit_is_set())
     }
 }
 
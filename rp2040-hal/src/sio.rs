@@ -17,9 +17,18 @@
 //! # let sio = Sio::new(peripherals.SIO);
 //! let pins = Pins::new(peripherals.IO_BANK0, peripherals.PADS_BANK0, sio.gpio_bank0, &mut peripherals.RESETS);
 //! ```
+//!
+//! ## Spinlocks
+//!
+//! Spinlocks 0 to 30 are available as [`Spinlock0`] to [`Spinlock30`]. Spinlock 31 is reserved for
+//! the `critical-section` implementation of this crate.
+//!
+//! The spinlocks are not reset by a watchdog reset, so a lock held when the chip was reset stays
+//! locked. Call [`spinlock_reset`] once at startup, before the second core is started.
 
 use super::*;
 use core::convert::Infallible;
+use core::marker::PhantomData;
 
 /// Marker struct for ownership of SIO gpio bank0
 pub struct SioGpioBank0 {
@@ -27,6 +36,9 @@ pub struct SioGpioBank0 {
 }
 
 /// Marker struct for ownership of SIO FIFO
+///
+/// Each core sees its own end of the FIFO: it reads what the other core writes. [`Sio::fifo`] is core
+/// 0's end, core 1 gets its own from [`Sio::core1`].
 pub struct SioFifo {
     _private: (),
 }
@@ -41,6 +53,14 @@ pub struct HwDivider {
     _private: (),
 }
 
+/// Saved state of the hardware divider, see [`HwDivider::save_state`]
+pub struct DividerState {
+    dividend: u32,
+    divisor: u32,
+    remainder: u32,
+    quotient: u32,
+}
+
 /// Result of divide/modulo operation
 pub struct DivResult<T> {
     /// The remainder of divide/modulo operation
@@ -49,7 +69,47 @@ pub struct DivResult<T> {
     pub quotient: T,
 }
 
+/// Ownership of the SIO blocks core 1 has its own instance of
+///
+/// The FIFO, the hardware divider and the interpolators exist once per core at the same address, so the markers in
+/// [`Sio`] are only valid on core 0. Move this into the closure spawned with
+/// [`crate::multicore::Core::spawn`] and [`split`](Self::split) it there.
+pub struct SioCore1 {
+    _private: (),
+}
+
+/// Markers of core 1's instances of the per-core SIO blocks, see [`SioCore1::split`]
+pub struct SioCore1Parts {
+    /// Core 1's end of the inter-core FIFO
+    pub fifo: SioFifo,
+    /// Core 1's hardware divider
+    pub hwdivider: HwDivider,
+    /// Core 1's interpolator 0
+    pub interp0: Interp0,
+    /// Core 1's interpolator 1
+    pub interp1: Interp1,
+}
+
+impl SioCore1 {
+    /// Returns the markers of core 1's SIO blocks.
+    ///
+    /// On core 0 they would alias the markers in [`Sio`], so `self` is given back there.
+    pub fn split(self) -> Result<SioCore1Parts, Self> {
+        if Sio::core() != CoreId::Core1 {
+            return Err(self);
+        }
+        Ok(SioCore1Parts {
+            fifo: SioFifo { _private: () },
+            hwdivider: HwDivider { _private: () },
+            interp0: Interp0 { _private: () },
+            interp1: Interp1 { _private: () },
+        })
+    }
+}
+
 /// Struct containing ownership markers for managing ownership of the SIO registers.
+///
+/// The markers of per-core blocks belong to core 0, see [`SioCore1`] for those of core 1.
 pub struct Sio {
     _sio: pac::SIO,
     /// GPIO Bank 0 registers
@@ -60,12 +120,36 @@ pub struct Sio {
     pub hwdivider: HwDivider,
     /// Inter-core FIFO
     pub fifo: SioFifo,
-    // we can hand out other things here, for example:
-    // interp0
-    // interp1
+    /// Interpolator 0, with blend mode
+    pub interp0: Interp0,
+    /// Interpolator 1, with clamp mode
+    pub interp1: Interp1,
+    /// Core 1's instances of the per-core blocks
+    pub core1: SioCore1,
+}
+
+/// Identifier of a processor core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreId {
+    /// Core 0, which runs the program after boot
+    Core0 = 0,
+    /// Core 1, started with [`crate::multicore::Core::spawn`]
+    Core1 = 1,
 }
 
 impl Sio {
+    /// Returns the core this code runs on.
+    ///
+    /// This only reads the CPUID register, so it does not need the `Sio` instance.
+    pub fn core() -> CoreId {
+        // Safety: read-only register, which reads differently on each core
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        match sio.cpuid.read().bits() {
+            0 => CoreId::Core0,
+            _ => CoreId::Core1,
+        }
+    }
+
     /// Create `Sio` from the PAC.
     pub fn new(sio: pac::SIO) -> Self {
         Self {
@@ -74,6 +158,9 @@ impl Sio {
             gpio_qspi: SioGpioQspi { _private: () },
             fifo: SioFifo { _private: () },
             hwdivider: HwDivider { _private: () },
+            interp0: Interp0 { _private: () },
+            interp1: Interp1 { _private: () },
+            core1: SioCore1 { _private: () },
         }
     }
 }
@@ -126,6 +213,61 @@ impl SioFifo {
         }
     }
 
+    /// Write to the inter-core FIFO if it has space.
+    ///
+    /// Returns `false` and drops the value if the FIFO is full.
+    pub fn write_nb(&mut self, value: u32) -> bool {
+        if self.is_write_ready() {
+            self.write(value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check if the FIFO was written while it was full (WOF sticky flag).
+    pub fn has_write_overflowed(&self) -> bool {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.fifo_st.read().wof().bit_is_set()
+    }
+
+    /// Check if the FIFO was read while it was empty (ROE sticky flag).
+    pub fn has_read_underflowed(&self) -> bool {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.fifo_st.read().roe().bit_is_set()
+    }
+
+    /// Clear the WOF and ROE sticky flags.
+    pub fn clear_errors(&mut self) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.fifo_st.write(|w| unsafe { w.bits(0xff) });
+    }
+
+    /// Enable the FIFO interrupt of the calling core (`SIO_IRQ_PROC0` or `SIO_IRQ_PROC1`) in the
+    /// NVIC.
+    ///
+    /// The interrupt is asserted while this core's RX FIFO holds data, or one of the error flags
+    /// is set, so the handler has to drain the FIFO and [`clear_errors`](Self::clear_errors).
+    ///
+    /// # Safety
+    ///
+    /// This can break mask-based critical sections.
+    pub unsafe fn enable_irq(&mut self) {
+        pac::NVIC::unmask(Self::irq());
+    }
+
+    /// Disable the FIFO interrupt of the calling core in the NVIC.
+    pub fn disable_irq(&mut self) {
+        pac::NVIC::mask(Self::irq());
+    }
+
+    fn irq() -> pac::Interrupt {
+        match Sio::core() {
+            CoreId::Core0 => pac::Interrupt::SIO_IRQ_PROC0,
+            CoreId::Core1 => pac::Interrupt::SIO_IRQ_PROC1,
+        }
+    }
+
     /// Read from the FIFO until it is empty, throwing the contents away.
     pub fn drain(&mut self) {
         while self.read().is_some() {
@@ -166,14 +308,23 @@ impl SioFifo {
 }
 
 impl HwDivider {
+    /// The divider, for code which saves and restores its state around each use
+    #[cfg(feature = "rom-intrinsics")]
+    pub(crate) fn steal() -> Self {
+        HwDivider { _private: () }
+    }
+
     /// Perform hardware unsigned divide/modulo operation
+    ///
+    /// Dividing by zero does not panic: the quotient is `0xffff_ffff` and the remainder is the
+    /// dividend.
     pub fn unsigned(&self, dividend: u32, divisor: u32) -> DivResult<u32> {
         let sio = unsafe { &(*pac::SIO::ptr()) };
         sio.div_udividend.write(|w| unsafe { w.bits(dividend) });
 
         sio.div_udivisor.write(|w| unsafe { w.bits(divisor) });
 
-        cortex_m::asm::delay(8);
+        Self::wait_ready();
 
         // Note: quotient must be read last
         let remainder = sio.div_remainder.read().bits();
@@ -186,6 +337,9 @@ impl HwDivider {
     }
 
     /// Perform hardware signed divide/modulo operation
+    ///
+    /// Dividing by zero does not panic: the quotient is `-1` for a positive or null dividend and
+    /// `1` for a negative one, and the remainder is the dividend.
     pub fn signed(&self, dividend: i32, divisor: i32) -> DivResult<i32> {
         let sio = unsafe { &(*pac::SIO::ptr()) };
         sio.div_sdividend
@@ -194,7 +348,7 @@ impl HwDivider {
         sio.div_sdivisor
             .write(|w| unsafe { w.bits(divisor as u32) });
 
-        cortex_m::asm::delay(8);
+        Self::wait_ready();
 
         // Note: quotient must be read last
         let remainder = sio.div_remainder.read().bits() as i32;
@@ -205,6 +359,313 @@ impl HwDivider {
             quotient,
         }
     }
+
+    /// Save the state of the divider, including a division in progress.
+    ///
+    /// Each core has its own divider. An interrupt handler using it must save its state first, and
+    /// [`restore_state`](Self::restore_state) it before returning, so it does not corrupt a
+    /// division the interrupted code was doing:
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{pac, sio::Sio};
+    ///
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let sio = Sio::new(peripherals.SIO);
+    /// // In the interrupt handler
+    /// let state = sio.hwdivider.save_state();
+    /// let result = sio.hwdivider.unsigned(100, 7);
+    /// sio.hwdivider.restore_state(&state);
+    /// ```
+    pub fn save_state(&self) -> DividerState {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        // The divider may be busy with the division of the interrupted code
+        Self::wait_ready();
+
+        DividerState {
+            dividend: sio.div_udividend.read().bits(),
+            divisor: sio.div_udivisor.read().bits(),
+            remainder: sio.div_remainder.read().bits(),
+            quotient: sio.div_quotient.read().bits(),
+        }
+    }
+
+    /// Restore the state saved with [`save_state`](Self::save_state).
+    ///
+    /// Writing the results back directly makes them available without another division.
+    pub fn restore_state(&self, state: &DividerState) {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        sio.div_udividend
+            .write(|w| unsafe { w.bits(state.dividend) });
+        sio.div_udivisor.write(|w| unsafe { w.bits(state.divisor) });
+        sio.div_remainder
+            .write(|w| unsafe { w.bits(state.remainder) });
+        // Note: quotient must be written last
+        sio.div_quotient
+            .write(|w| unsafe { w.bits(state.quotient) });
+    }
+
+    /// Wait for the result of a division, which takes 8 cycles.
+    fn wait_ready() {
+        let sio = unsafe { &(*pac::SIO::ptr()) };
+        while sio.div_csr.read().ready().bit_is_clear() {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+/// Configuration of an interpolator lane
+///
+/// See Chapter 2, Section 3, §1.6 for the details of each setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaneConfig {
+    /// Logical right shift applied to the accumulator, 0 to 31
+    pub shift: u8,
+    /// Least significant bit of the mask applied after the shift, 0 to 31
+    pub mask_lsb: u8,
+    /// Most significant bit of the mask applied after the shift, 0 to 31
+    pub mask_msb: u8,
+    /// Sign-extend the masked value from `mask_msb` before adding it to the base
+    pub signed: bool,
+    /// Feed the other lane's accumulator into this lane's shift and mask
+    pub cross_input: bool,
+    /// Write the other lane's result into this lane's accumulator on pop
+    pub cross_result: bool,
+    /// Add the raw accumulator (without shift and mask) to the base for this lane's result
+    pub add_raw: bool,
+    /// Value ORed into bits 29:28 of the lane result, 0 to 3
+    pub force_msb: u8,
+}
+
+impl LaneConfig {
+    /// Pass-through configuration: no shift and the full 32 bits kept
+    pub const fn new() -> Self {
+        LaneConfig {
+            shift: 0,
+            mask_lsb: 0,
+            mask_msb: 31,
+            signed: false,
+            cross_input: false,
+            cross_result: false,
+            add_raw: false,
+            force_msb: 0,
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        u32::from(self.shift & 0x1f)
+            | u32::from(self.mask_lsb & 0x1f) << 5
+            | u32::from(self.mask_msb & 0x1f) << 10
+            | u32::from(self.signed) << 15
+            | u32::from(self.cross_input) << 16
+            | u32::from(self.cross_result) << 17
+            | u32::from(self.add_raw) << 18
+            | u32::from(self.force_msb & 0x3) << 19
+    }
+}
+
+impl Default for LaneConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Saved state of an interpolator, see [`Interp0::save`]
+#[derive(Debug, Clone, Copy)]
+pub struct InterpState {
+    accum: [u32; 2],
+    base: [u32; 3],
+    ctrl: [u32; 2],
+}
+
+/// Lane 0 control bit selecting blend mode, only on INTERP0
+const CTRL_LANE0_BLEND: u32 = 1 << 21;
+/// Lane 0 control bit selecting clamp mode, only on INTERP1
+const CTRL_LANE0_CLAMP: u32 = 1 << 22;
+
+macro_rules! impl_interp {
+    ($($name:ident: $interp:ident, $set_mode:ident, $mode_bit:ident, $mode_doc:literal;)*) => {
+        $crate::paste::paste! {
+            $(
+                /// Marker struct for ownership of a SIO interpolator
+                ///
+                /// Each core has its own interpolators, at the same address. The ones in [`Sio`] are
+                /// core 0's, core 1 gets its own from [`Sio::core1`].
+                pub struct $name {
+                    _private: (),
+                }
+
+                impl $name {
+                    /// Configure lane 0. The mode bit of lane 0 is kept.
+                    pub fn set_lane0(&mut self, config: LaneConfig) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _ctrl_lane0>].modify(|r, w| unsafe {
+                            w.bits(config.bits() | (r.bits() & $mode_bit))
+                        });
+                    }
+
+                    /// Configure lane 1.
+                    pub fn set_lane1(&mut self, config: LaneConfig) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _ctrl_lane1>].write(|w| unsafe { w.bits(config.bits()) });
+                    }
+
+                    #[doc = "Enable or disable " $mode_doc]
+                    pub fn $set_mode(&mut self, enable: bool) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _ctrl_lane0>].modify(|r, w| unsafe {
+                            if enable {
+                                w.bits(r.bits() | $mode_bit)
+                            } else {
+                                w.bits(r.bits() & !$mode_bit)
+                            }
+                        });
+                    }
+
+                    /// Write base 0, 1 or 2.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `index` is larger than 2.
+                    pub fn set_base(&mut self, index: u8, value: u32) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        match index {
+                            0 => sio.[<$interp _base0>].write(|w| unsafe { w.bits(value) }),
+                            1 => sio.[<$interp _base1>].write(|w| unsafe { w.bits(value) }),
+                            2 => sio.[<$interp _base2>].write(|w| unsafe { w.bits(value) }),
+                            _ => panic!("Interpolator base index out of range"),
+                        }
+                    }
+
+                    /// Read base 0, 1 or 2.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `index` is larger than 2.
+                    pub fn base(&self, index: u8) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        match index {
+                            0 => sio.[<$interp _base0>].read().bits(),
+                            1 => sio.[<$interp _base1>].read().bits(),
+                            2 => sio.[<$interp _base2>].read().bits(),
+                            _ => panic!("Interpolator base index out of range"),
+                        }
+                    }
+
+                    /// Write base 0 and base 1 at once: the lower 16 bits go to base 0 and the upper
+                    /// 16 bits to base 1, each sign-extended if the lane is signed.
+                    pub fn set_base_1and0(&mut self, value: u32) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _base_1and0>].write(|w| unsafe { w.bits(value) });
+                    }
+
+                    /// Write accumulator 0 or 1.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `index` is larger than 1.
+                    pub fn set_accum(&mut self, index: u8, value: u32) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        match index {
+                            0 => sio.[<$interp _accum0>].write(|w| unsafe { w.bits(value) }),
+                            1 => sio.[<$interp _accum1>].write(|w| unsafe { w.bits(value) }),
+                            _ => panic!("Interpolator accumulator index out of range"),
+                        }
+                    }
+
+                    /// Read accumulator 0 or 1.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if `index` is larger than 1.
+                    pub fn accum(&self, index: u8) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        match index {
+                            0 => sio.[<$interp _accum0>].read().bits(),
+                            1 => sio.[<$interp _accum1>].read().bits(),
+                            _ => panic!("Interpolator accumulator index out of range"),
+                        }
+                    }
+
+                    /// Read the lane 0 result, and write the results back to the accumulators.
+                    pub fn pop_lane0(&mut self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _pop_lane0>].read().bits()
+                    }
+
+                    /// Read the lane 1 result, and write the results back to the accumulators.
+                    pub fn pop_lane1(&mut self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _pop_lane1>].read().bits()
+                    }
+
+                    /// Read the full result, and write the results back to the accumulators.
+                    pub fn pop_full(&mut self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _pop_full>].read().bits()
+                    }
+
+                    /// Read the lane 0 result without altering any state.
+                    pub fn peek_lane0(&self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _peek_lane0>].read().bits()
+                    }
+
+                    /// Read the lane 1 result without altering any state.
+                    pub fn peek_lane1(&self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _peek_lane1>].read().bits()
+                    }
+
+                    /// Read the full result without altering any state.
+                    pub fn peek_full(&self) -> u32 {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _peek_full>].read().bits()
+                    }
+
+                    /// Save the state of the interpolator, like `interp_save` of the SDK.
+                    ///
+                    /// An interrupt handler using the interpolator must save its state first and
+                    /// [`restore`](Self::restore) it before returning.
+                    pub fn save(&self) -> InterpState {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        InterpState {
+                            accum: [
+                                sio.[<$interp _accum0>].read().bits(),
+                                sio.[<$interp _accum1>].read().bits(),
+                            ],
+                            base: [
+                                sio.[<$interp _base0>].read().bits(),
+                                sio.[<$interp _base1>].read().bits(),
+                                sio.[<$interp _base2>].read().bits(),
+                            ],
+                            ctrl: [
+                                sio.[<$interp _ctrl_lane0>].read().bits(),
+                                sio.[<$interp _ctrl_lane1>].read().bits(),
+                            ],
+                        }
+                    }
+
+                    /// Restore the state saved with [`save`](Self::save), like `interp_restore` of
+                    /// the SDK.
+                    pub fn restore(&mut self, state: &InterpState) {
+                        let sio = unsafe { &(*pac::SIO::ptr()) };
+                        sio.[<$interp _accum0>].write(|w| unsafe { w.bits(state.accum[0]) });
+                        sio.[<$interp _accum1>].write(|w| unsafe { w.bits(state.accum[1]) });
+                        sio.[<$interp _base0>].write(|w| unsafe { w.bits(state.base[0]) });
+                        sio.[<$interp _base1>].write(|w| unsafe { w.bits(state.base[1]) });
+                        sio.[<$interp _base2>].write(|w| unsafe { w.bits(state.base[2]) });
+                        sio.[<$interp _ctrl_lane0>].write(|w| unsafe { w.bits(state.ctrl[0]) });
+                        sio.[<$interp _ctrl_lane1>].write(|w| unsafe { w.bits(state.ctrl[1]) });
+                    }
+                }
+            )*
+        }
+    };
+}
+
+impl_interp! {
+    Interp0: interp0, set_blend, CTRL_LANE0_BLEND, "blend mode: the full result is base 0 blended with base 1 by the lower 8 bits of lane 1, and lane 1 returns base 2 plus the signed lane 0 result";
+    Interp1: interp1, set_clamp, CTRL_LANE0_CLAMP, "clamp mode: the lane 0 result is clamped between base 0 and base 1";
 }
 
 /// Trait for all the spinlock. See the documentation of e.g. [`Spinlock0`] for more information
@@ -239,7 +700,13 @@ macro_rules! impl_spinlock {
             #[doc = stringify!($spinlock_name)]
             /// `.
             ///
-            /// When the obtained spinlock goes out of scope, it is automatically unlocked.
+            /// Interrupts are disabled on the current core while the lock is held, so an interrupt
+            /// handler claiming the same lock can not deadlock. When the obtained spinlock goes out of
+            /// scope, it is automatically unlocked and interrupts are restored.
+            ///
+            /// The lock restores the interrupt state it saw when it was claimed, so locks held at the
+            /// same time have to be released in the reverse order of claiming them. For the same
+            /// reason, a lock can't be sent to another core or interrupt handler to be released there.
             ///
             /// **warning**: These spinlocks are not re-entrant, meaning that the following code will cause a deadlock:
             ///
@@ -252,16 +719,32 @@ macro_rules! impl_spinlock {
             /// [`claim`]: #method.claim
             /// [`try_claim`]: #method.try_claim
             /// [`claim_async`]: #method.claim_async
-            pub struct $spinlock_name(core::marker::PhantomData<()>);
+            pub struct $spinlock_name {
+                /// Whether interrupts were enabled when the lock was claimed
+                interrupts_active: bool,
+                /// The interrupt state belongs to the current core, so the lock is not `Send`
+                not_send: PhantomData<*const ()>,
+            }
 
             impl Spinlock for $spinlock_name {
                 fn try_claim() -> Option<$spinlock_name> {
+                    let interrupts_active = cortex_m::register::primask::read().is_active();
+                    cortex_m::interrupt::disable();
+                    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+
                     // Safety: We're only reading from this register
                     let sio = unsafe { &*pac::SIO::ptr() };
                     let lock = sio.$register.read().bits();
                     if lock > 0 {
-                        Some(Self(core::marker::PhantomData))
+                        Some(Self {
+                            interrupts_active,
+                            not_send: PhantomData,
+                        })
                     } else {
+                        if interrupts_active {
+                            // Safety: interrupts were enabled before this call
+                            unsafe { cortex_m::interrupt::enable() };
+                        }
                         None
                     }
                 }
@@ -276,7 +759,13 @@ macro_rules! impl_spinlock {
                     let sio = unsafe { &*pac::SIO::ptr() };
 
                     // Write (any value): release the lock
+                    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
                     sio.$register.write(|b| unsafe { b.bits(1) });
+
+                    if self.interrupts_active {
+                        // Safety: interrupts were enabled when the lock was claimed
+                        unsafe { cortex_m::interrupt::enable() };
+                    }
                 }
             }
         )*
@@ -315,7 +804,24 @@ impl_spinlock! {
     Spinlock28 => spinlock28,
     Spinlock29 => spinlock29,
     Spinlock30 => spinlock30,
-    Spinlock31 => spinlock31,
+}
+
+/// Release all the spinlocks, including the one reserved for the critical sections.
+///
+/// The spinlocks keep their state across a watchdog reset, so a lock held at that point would
+/// never be released. Call this once at startup.
+///
+/// # Safety
+///
+/// This releases locks which may be held by someone else: it must only be called while the other
+/// core is not running, and no spinlock is claimed on this core.
+pub unsafe fn spinlock_reset() {
+    // The 32 spinlock registers follow each other
+    let spinlock0 = &(*pac::SIO::ptr()).spinlock0 as *const _ as *mut u32;
+    for i in 0..32 {
+        // Write (any value): release the lock
+        spinlock0.add(i).write_volatile(1);
+    }
 }
 
 /// Returns the current state of the spinlocks. Each index corresponds to the associated spinlock, e.g. if index `5` is set to `true`, it means that [`Spinlock5`] is currently locked.
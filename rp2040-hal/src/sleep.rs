@@ -0,0 +1,117 @@
+//! Sleeping until a condition is met
+//!
+//! A core waiting for an interrupt usually checks a flag set by the interrupt handler before
+//! executing `WFI`. If the interrupt fires between the check and the `WFI`, the core sleeps until
+//! the next interrupt, which may never come. The functions of this module check the condition
+//! with interrupts disabled and execute `WFI` in the same critical section: a pending interrupt
+//! wakes the core even while interrupts are disabled, and its handler runs as soon as the
+//! critical section ends, before the condition is checked again. [`wait_for_event_until`]
+//! relies on the event register instead.
+//!
+//! - [`light_sleep_until`] only stops the clock of the core.
+//! - [`deep_sleep_until`] gates the clocks not selected by a [`SleepConfig`] as well, once both
+//!   cores sleep.
+//! - [`wait_for_event_until`] uses `WFE` instead, so the other core can wake this one with
+//!   `SEV`, as done by [`SioFifo::write`](crate::sio::SioFifo::write).
+//!
+//! [`light_sleep_until`] and [`deep_sleep_until`] check the condition with interrupts disabled,
+//! so it has to be short. The interrupts which should wake the core must be unmasked in the NVIC,
+//! and these functions must not be called with interrupts disabled, or the handlers never run.
+//!
+//! ## Usage
+//! ```no_run
+//! use core::sync::atomic::{AtomicBool, Ordering};
+//! use rp2040_hal::{clocks::{init_clocks_and_plls, SleepConfig}, pac, sleep::deep_sleep_until, watchdog::Watchdog};
+//! static ALARM_FIRED: AtomicBool = AtomicBool::new(false);
+//!
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut core = pac::CorePeripherals::take().unwrap();
+//! let mut watchdog = Watchdog::new(peripherals.WATCHDOG);
+//! let mut clocks = init_clocks_and_plls(12_000_000, peripherals.XOSC, peripherals.CLOCKS, peripherals.PLL_SYS, peripherals.PLL_USB, &mut peripherals.RESETS, &mut watchdog).ok().unwrap();
+//!
+//! // An interrupt handler of a timer alarm sets ALARM_FIRED. The Cortex-M0+ has no atomic swap,
+//! // so the flag is read and cleared in a critical section.
+//! deep_sleep_until(&mut clocks, &mut core.SCB, SleepConfig::none().with_timer(), || {
+//!     cortex_m::interrupt::free(|_| {
+//!         let fired = ALARM_FIRED.load(Ordering::Relaxed);
+//!         ALARM_FIRED.store(false, Ordering::Relaxed);
+//!         fired
+//!     })
+//! });
+//! ```
+//! See [examples/deep_sleep.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/deep_sleep.rs) for a more complete example,
+//! and [examples/sleep_race.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/sleep_race.rs)
+//! for a check that an interrupt arriving right before the `WFI` wakes the core
+
+use crate::clocks::{ClocksManager, SleepConfig};
+use cortex_m::peripheral::SCB;
+
+/// SLEEPDEEP bit of the System Control Register
+const SCR_SLEEPDEEP: u32 = 1 << 2;
+
+/// Sleep until `condition` returns true, only stopping the clock of the core
+///
+/// SLEEPDEEP is cleared while sleeping, and restored afterwards.
+pub fn light_sleep_until(scb: &mut SCB, condition: impl FnMut() -> bool) {
+    let sleepdeep = scb.scr.read() & SCR_SLEEPDEEP != 0;
+    scb.clear_sleepdeep();
+
+    wait_until(condition);
+
+    if sleepdeep {
+        scb.set_sleepdeep();
+    }
+}
+
+/// Sleep until `condition` returns true, gating the clocks not selected by `config` while both
+/// cores sleep
+///
+/// The peripherals whose interrupts should wake the core have to keep their clocks. The previous
+/// SLEEP_EN configuration and SLEEPDEEP are restored afterwards.
+pub fn deep_sleep_until(
+    clocks: &mut ClocksManager,
+    scb: &mut SCB,
+    config: SleepConfig,
+    condition: impl FnMut() -> bool,
+) {
+    let previous_config = clocks.sleep_config();
+    let sleepdeep = scb.scr.read() & SCR_SLEEPDEEP != 0;
+    clocks.configure_sleep_enable(config);
+    scb.set_sleepdeep();
+
+    wait_until(condition);
+
+    if !sleepdeep {
+        scb.clear_sleepdeep();
+    }
+    clocks.configure_sleep_enable(previous_config);
+}
+
+/// Wait for events until `condition` returns true
+///
+/// The core wakes up when the other core executes `SEV`, or an interrupt is taken. Unlike the
+/// other functions, the condition is checked with interrupts enabled: an `SEV` or the return from
+/// an interrupt handler between the check and `WFE` is latched by the event register, which ends
+/// the `WFE` right away. SLEEPDEEP is not changed, so the clocks are gated if it is set.
+pub fn wait_for_event_until(mut condition: impl FnMut() -> bool) {
+    while !condition() {
+        cortex_m::asm::wfe();
+    }
+}
+
+fn wait_until(mut condition: impl FnMut() -> bool) {
+    loop {
+        let done = cortex_m::interrupt::free(|_| {
+            if condition() {
+                true
+            } else {
+                cortex_m::asm::wfi();
+                false
+            }
+        });
+        if done {
+            return;
+        }
+        // The handler of the interrupt which woke the core has run now
+    }
+}
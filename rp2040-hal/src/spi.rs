@@ -19,6 +19,8 @@
 //! let spi = Spi::<_, _, 8>::new(peripherals.SPI0).init(&mut peripherals.RESETS, 125_000_000u32.Hz(), 16_000_000u32.Hz(), &MODE_0);
 //! ```
 
+use crate::clocks::{ClockGate, ClockGated};
+use crate::dma::{EndlessReadTarget, EndlessWriteTarget, ReadTarget, WriteTarget};
 use crate::resets::SubsystemReset;
 use core::{convert::Infallible, marker::PhantomData, ops::Deref};
 #[cfg(feature = "eh1_0_alpha")]
@@ -45,10 +47,23 @@ impl State for Disabled {}
 impl State for Enabled {}
 
 /// Pac SPI device
-pub trait SpiDevice: Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset {}
+pub trait SpiDevice:
+    Deref<Target = pac::spi0::RegisterBlock> + SubsystemReset + ClockGated
+{
+    /// The DREQ value for the TX FIFO of this SPI
+    const TX_DREQ: u8;
+    /// The DREQ value for the RX FIFO of this SPI
+    const RX_DREQ: u8;
+}
 
-impl SpiDevice for pac::SPI0 {}
-impl SpiDevice for pac::SPI1 {}
+impl SpiDevice for pac::SPI0 {
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI0_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI0_RX;
+}
+impl SpiDevice for pac::SPI1 {
+    const TX_DREQ: u8 = crate::dma::DREQ_SPI1_TX;
+    const RX_DREQ: u8 = crate::dma::DREQ_SPI1_RX;
+}
 
 /// Data size used in spi
 pub trait DataSize {}
@@ -75,6 +90,11 @@ impl<S: State, D: SpiDevice, const DS: u8> Spi<S, D, DS> {
         self.device
     }
 
+    /// The clock gates of the SPI, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate {
+        D::CLOCK_GATE
+    }
+
     /// Set baudrate based on peripheral clock
     ///
     /// Typically the peripheral clock is set to 125_000_000
@@ -238,6 +258,44 @@ macro_rules! impl_write {
             }
         }
 
+        // Safety: Only the data register of the SPI is accessed, which is never freed
+        unsafe impl<D: SpiDevice> ReadTarget for Spi<Enabled, D, $nr> {
+            type ReceivedWord = $type;
+
+            fn rx_treq() -> Option<u8> {
+                Some(D::RX_DREQ)
+            }
+
+            fn rx_address_count(&self) -> (u32, u32) {
+                (self.device.sspdr.as_ptr() as u32, u32::MAX)
+            }
+
+            fn rx_increment(&self) -> bool {
+                false
+            }
+        }
+
+        impl<D: SpiDevice> EndlessReadTarget for Spi<Enabled, D, $nr> {}
+
+        // Safety: Only the data register of the SPI is accessed, which is never freed
+        unsafe impl<D: SpiDevice> WriteTarget for Spi<Enabled, D, $nr> {
+            type TransmittedWord = $type;
+
+            fn tx_treq() -> Option<u8> {
+                Some(D::TX_DREQ)
+            }
+
+            fn tx_address_count(&mut self) -> (u32, u32) {
+                (self.device.sspdr.as_ptr() as u32, u32::MAX)
+            }
+
+            fn tx_increment(&self) -> bool {
+                false
+            }
+        }
+
+        impl<D: SpiDevice> EndlessWriteTarget for Spi<Enabled, D, $nr> {}
+
         impl<D: SpiDevice> spi::write::Default<$type> for Spi<Enabled, D, $nr> {}
         impl<D: SpiDevice> spi::transfer::Default<$type> for Spi<Enabled, D, $nr> {}
         impl<D: SpiDevice> spi::write_iter::Default<$type> for Spi<Enabled, D, $nr> {}
@@ -3,9 +3,9 @@
 
 use embedded_time::duration::Microseconds;
 
+use crate::clocks::{ClockGate, ClockGated};
 use crate::pac::{RESETS, TIMER};
 use crate::resets::SubsystemReset;
-use core::marker::PhantomData;
 
 /// Timer peripheral
 pub struct Timer {
@@ -15,6 +15,10 @@ pub struct Timer {
 
 impl Timer {
     /// Create a new [`Timer`]
+    ///
+    /// The timer counts the ticks of the watchdog tick generator, which must run at one tick per
+    /// microsecond: see [`Watchdog::enable_tick_generation`](crate::watchdog::Watchdog::enable_tick_generation).
+    /// The clock initialization functions of [`clocks`](crate::clocks) already start it.
     pub fn new(timer: TIMER, resets: &mut RESETS) -> Self {
         timer.reset_bring_up(resets);
         Self {
@@ -23,25 +27,113 @@ impl Timer {
         }
     }
 
+    /// The clock gates of the timer, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate {
+        TIMER::CLOCK_GATE
+    }
+
     /// Get the current counter value.
+    ///
+    /// This reads the TIMELR/TIMEHR pair, where reading the low word latches the high word. The
+    /// latch is shared by both cores, so if the other core also reads the counter the two words
+    /// may come from different moments. Use [`get_counter_raw`](Self::get_counter_raw) when the
+    /// counter is read from both cores.
     pub fn get_counter(&self) -> u64 {
-        let mut hi0 = self.timer.timerawh.read().bits();
-        loop {
-            let low = self.timer.timerawl.read().bits();
-            let hi1 = self.timer.timerawh.read().bits();
-            if hi0 == hi1 {
-                break (u64::from(hi0) << 32) | u64::from(low);
-            }
-            hi0 = hi1;
-        }
+        cortex_m::interrupt::free(|_| {
+            let low = self.timer.timelr.read().bits();
+            let high = self.timer.timehr.read().bits();
+            (u64::from(high) << 32) | u64::from(low)
+        })
+    }
+
+    /// Get the current counter value without using the latched registers.
+    ///
+    /// The raw high and low words are read until the high word is the same before and after
+    /// reading the low word. This is safe to use from both cores and from interrupt handlers.
+    pub fn get_counter_raw(&self) -> u64 {
+        read_counter(&self.timer)
+    }
+
+    /// Defines whether the counter should be paused while core 0 or core 1 are halted by the
+    /// debugger.
+    ///
+    /// Both are enabled at reset, so the counter stops (and the alarms don't fire) while either
+    /// core is halted. The counter is driven by the watchdog tick generator, which is not affected
+    /// by this setting or by [`Watchdog::set_pause_on_debug`](crate::watchdog::Watchdog::set_pause_on_debug).
+    pub fn set_pause_on_debug(&mut self, core0: bool, core1: bool) {
+        self.timer
+            .dbgpause
+            .write(|w| w.dbg0().bit(core0).dbg1().bit(core1));
     }
 
     /// Get the value of the least significant word of the counter.
+    ///
+    /// This is a single register read, cheap enough for timestamping events from interrupt
+    /// handlers. The value wraps around every 2^32 microseconds (about 71.6 minutes), so compute
+    /// intervals with `wrapping_sub`.
     pub fn get_counter_low(&self) -> u32 {
         self.timer.timerawl.read().bits()
     }
 
+    /// Set the counter to `value`.
+    ///
+    /// Alarms compare against the low word of the counter, so an armed alarm whose target is
+    /// skipped over would only fire once the counter wraps back around to it, and moving the
+    /// counter backwards would delay it by the same amount. All alarms are therefore disarmed,
+    /// without raising their interrupts, and report that they are finished. Reschedule them
+    /// afterwards, relative to the new counter value.
+    pub fn set_counter(&mut self, value: u64) {
+        cortex_m::interrupt::free(|_| {
+            // Writing 1s to ARMED disarms the alarms
+            self.timer.armed.write(|w| unsafe { w.bits(0b1111) });
+            // TIMELW is only applied to the counter when TIMEHW is written
+            self.timer.timelw.write(|w| unsafe { w.bits(value as u32) });
+            self.timer
+                .timehw
+                .write(|w| unsafe { w.bits((value >> 32) as u32) });
+        })
+    }
+
+    /// Set the counter to 0.
+    ///
+    /// This disarms all alarms, see [`set_counter`](Self::set_counter).
+    pub fn reset(&mut self) {
+        self.set_counter(0)
+    }
+
+    /// Stop the counter.
+    ///
+    /// Alarms don't fire while the counter is paused.
+    pub fn pause(&mut self) {
+        self.timer.pause.write(|w| w.pause().set_bit());
+    }
+
+    /// Let the counter run again after [`pause`](Self::pause).
+    pub fn resume(&mut self) {
+        self.timer.pause.write(|w| w.pause().clear_bit());
+    }
+
+    /// Is the counter paused?
+    pub fn is_paused(&self) -> bool {
+        self.timer.pause.read().pause().bit_is_set()
+    }
+
     /// Initialized a Count Down instance without starting it.
+    ///
+    /// The count down samples the free-running counter instead of using an alarm, so any number of
+    /// them can be used at the same time.
+    ///
+    /// ```no_run
+    /// use embedded_hal::timer::CountDown;
+    /// use embedded_time::duration::Microseconds;
+    /// use rp2040_hal::{pac, timer::Timer};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+    /// let mut count_down = timer.count_down();
+    /// // Wait 100ms
+    /// count_down.start(Microseconds(100_000u64));
+    /// let _ = nb::block!(count_down.wait());
+    /// ```
     pub fn count_down(&self) -> CountDown<'_> {
         CountDown {
             timer: self,
@@ -50,12 +142,30 @@ impl Timer {
         }
     }
 
+    /// Create a blocking delay driven by the microsecond counter.
+    ///
+    /// Unlike `cortex_m::delay::Delay` this doesn't use SysTick and doesn't depend on the system
+    /// clock frequency. The delay has no state and doesn't touch the alarms, so any number of
+    /// them can be used at the same time, including from interrupt handlers.
+    ///
+    /// ```no_run
+    /// use embedded_hal::blocking::delay::DelayMs;
+    /// use rp2040_hal::{pac, timer::Timer};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let timer = Timer::new(peripherals.TIMER, &mut peripherals.RESETS);
+    /// let mut delay = timer.delay();
+    /// delay.delay_ms(500u32);
+    /// ```
+    pub fn delay(&self) -> Delay {
+        Delay { _private: () }
+    }
+
     /// Retrieve a reference to alarm 0. Will only return a value the first time this is called
     pub fn alarm_0(&mut self) -> Option<Alarm0> {
         cortex_m::interrupt::free(|_| {
             if self.alarms[0] {
                 self.alarms[0] = false;
-                Some(Alarm0(PhantomData))
+                Some(Alarm0::new())
             } else {
                 None
             }
@@ -67,7 +177,7 @@ impl Timer {
         cortex_m::interrupt::free(|_| {
             if self.alarms[1] {
                 self.alarms[1] = false;
-                Some(Alarm1(PhantomData))
+                Some(Alarm1::new())
             } else {
                 None
             }
@@ -79,7 +189,7 @@ impl Timer {
         cortex_m::interrupt::free(|_| {
             if self.alarms[2] {
                 self.alarms[2] = false;
-                Some(Alarm2(PhantomData))
+                Some(Alarm2::new())
             } else {
                 None
             }
@@ -91,7 +201,7 @@ impl Timer {
         cortex_m::interrupt::free(|_| {
             if self.alarms[3] {
                 self.alarms[3] = false;
-                Some(Alarm3(PhantomData))
+                Some(Alarm3::new())
             } else {
                 None
             }
@@ -99,7 +209,100 @@ impl Timer {
     }
 }
 
-/// Delay implementation
+/// Read the 64 bit counter without using the latching TIMEHR/TIMELR registers
+fn read_counter(timer: &crate::pac::timer::RegisterBlock) -> u64 {
+    read_halves(
+        || timer.timerawh.read().bits(),
+        || timer.timerawl.read().bits(),
+    )
+}
+
+/// Combine the halves of a running 64 bit counter, reading the high half again until it did not
+/// change while the low half was read
+fn read_halves(mut read_high: impl FnMut() -> u32, mut read_low: impl FnMut() -> u32) -> u64 {
+    let mut hi0 = read_high();
+    loop {
+        let low = read_low();
+        let hi1 = read_high();
+        if hi0 == hi1 {
+            break (u64::from(hi0) << 32) | u64::from(low);
+        }
+        hi0 = hi1;
+    }
+}
+
+/// Blocking delay backed by the free-running microsecond counter
+///
+/// Created with [`Timer::delay`].
+#[derive(Clone, Copy)]
+pub struct Delay {
+    _private: (),
+}
+
+impl Delay {
+    fn wait_us(&self, us: u64) {
+        // safety: These are read actions and should not have any UB
+        let timer = unsafe { &*TIMER::ptr() };
+        let start = read_counter(timer);
+        while read_counter(timer).wrapping_sub(start) < us {}
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayUs<u32> for Delay {
+    fn delay_us(&mut self, us: u32) {
+        self.wait_us(u64::from(us));
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayUs<u16> for Delay {
+    fn delay_us(&mut self, us: u16) {
+        self.wait_us(u64::from(us));
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayUs<u8> for Delay {
+    fn delay_us(&mut self, us: u8) {
+        self.wait_us(u64::from(us));
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayMs<u32> for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.wait_us(u64::from(ms) * 1000);
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayMs<u16> for Delay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.wait_us(u64::from(ms) * 1000);
+    }
+}
+
+impl embedded_hal::blocking::delay::DelayMs<u8> for Delay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.wait_us(u64::from(ms) * 1000);
+    }
+}
+
+#[cfg(feature = "eh1_0_alpha")]
+impl eh1_0_alpha::delay::blocking::DelayUs for Delay {
+    type Error = core::convert::Infallible;
+
+    fn delay_us(&mut self, us: u32) -> Result<(), Self::Error> {
+        self.wait_us(u64::from(us));
+        Ok(())
+    }
+
+    fn delay_ms(&mut self, ms: u32) -> Result<(), Self::Error> {
+        self.wait_us(u64::from(ms) * 1000);
+        Ok(())
+    }
+}
+
+/// Count down implementation backed by the free-running microsecond counter
+///
+/// Created with [`Timer::count_down`]. The count down is periodic: each time `wait` succeeds the
+/// next period starts from the previous deadline, so it doesn't drift.
 pub struct CountDown<'timer> {
     timer: &'timer Timer,
     period: embedded_time::duration::Microseconds<u64>,
@@ -114,12 +317,12 @@ impl embedded_hal::timer::CountDown for CountDown<'_> {
         T: Into<Self::Time>,
     {
         self.period = count.into();
-        self.next_end = Some(self.timer.get_counter().wrapping_add(self.period.0));
+        self.next_end = Some(self.timer.get_counter_raw().wrapping_add(self.period.0));
     }
 
     fn wait(&mut self) -> nb::Result<(), void::Void> {
         if let Some(end) = self.next_end {
-            let ts = self.timer.get_counter();
+            let ts = self.timer.get_counter_raw();
             if ts >= end {
                 self.next_end = Some(end.wrapping_add(self.period.0));
                 Ok(())
@@ -142,13 +345,13 @@ impl eh1_0_alpha::timer::nb::CountDown for CountDown<'_> {
         T: Into<Self::Time>,
     {
         self.period = count.into();
-        self.next_end = Some(self.timer.get_counter().wrapping_add(self.period.0));
+        self.next_end = Some(self.timer.get_counter_raw().wrapping_add(self.period.0));
         Ok(())
     }
 
     fn wait(&mut self) -> nb::Result<(), Self::Error> {
         if let Some(end) = self.next_end {
-            let ts = self.timer.get_counter();
+            let ts = self.timer.get_counter_raw();
             if ts >= end {
                 self.next_end = Some(end.wrapping_add(self.period.0));
                 Ok(())
@@ -156,7 +359,7 @@ impl eh1_0_alpha::timer::nb::CountDown for CountDown<'_> {
                 Err(nb::Error::WouldBlock)
             }
         } else {
-            panic!("CountDown is not running!");
+            Err(nb::Error::Other("CountDown is not running."))
         }
     }
 }
@@ -192,9 +395,15 @@ impl eh1_0_alpha::timer::nb::Cancel for CountDown<'_> {
 macro_rules! impl_alarm {
     ($name:ident  { rb: $timer_alarm:ident, int: $int_alarm:ident, int_name: $int_name:tt, armed_bit_mask: $armed_bit_mask: expr }) => {
         /// An alarm that can be used to schedule events in the future. Alarms can also be configured to trigger interrupts.
-        pub struct $name(PhantomData<()>);
+        pub struct $name {
+            periodic: Option<PeriodicSchedule>,
+        }
 
         impl $name {
+            fn new() -> Self {
+                Self { periodic: None }
+            }
+
             /// Clear the interrupt flag. This should be called after interrupt `
             #[doc = $int_name]
             /// ` is called.
@@ -205,7 +414,10 @@ macro_rules! impl_alarm {
                 let _ = timer;
                 let timer = unsafe { &*TIMER::ptr() };
 
-                timer.intr.modify(|_, w| w.$int_alarm().set_bit());
+                // INTR is write-1-to-clear, so only write this alarm's bit
+                timer.intr.write(|w| w.$int_alarm().set_bit());
+                // Also release an interrupt forced by a periodic alarm that missed its deadline
+                timer.intf.modify(|_, w| w.$int_alarm().clear_bit());
             }
 
             /// Enable this alarm to trigger an interrupt. This alarm will trigger `
@@ -236,34 +448,152 @@ macro_rules! impl_alarm {
             #[doc = $int_name]
             /// ` whenever this time elapses.
             ///
-            /// The RP2040 has been observed to take a little while to schedule an alarm. For this reason, the minimum time that this function accepts is `10.microseconds()`
+            /// See [schedule_at] for the errors this can return.
             ///
             /// [enable_interrupt]: #method.enable_interrupt
+            /// [schedule_at]: #method.schedule_at
             pub fn schedule<TIME: Into<Microseconds>>(
                 &mut self,
                 countdown: TIME,
             ) -> Result<(), ScheduleAlarmError> {
                 let duration = countdown.into().0;
+                self.periodic = None;
+                cortex_m::interrupt::free(|_| {
+                    // safety: This is a read action and should not have any UB
+                    let now = read_counter(unsafe { &*TIMER::ptr() });
+                    self.arm(now.wrapping_add(u64::from(duration)), now)
+                })
+            }
 
-                const MIN_MICROSECONDS: u32 = 10;
-                if duration < MIN_MICROSECONDS {
+            /// Schedule the alarm to be finished when the counter reaches `timestamp`, as returned by
+            /// [`Timer::get_counter`]. If [enable_interrupt] is called, this will trigger interrupt `
+            #[doc = $int_name]
+            /// ` at that time.
+            ///
+            /// The hardware only compares the lower 32 bits of the counter, so `timestamp` can be at
+            /// most `u32::MAX` microseconds (about 71 minutes) in the future, otherwise
+            /// [`ScheduleAlarmError::AlarmTooLate`] is returned.
+            ///
+            /// If `timestamp` has already passed when the alarm is armed, the alarm would only fire
+            /// after the counter wrapped around. In that case the alarm is disarmed again and
+            /// [`ScheduleAlarmError::AlarmTooSoon`] is returned, so the caller can handle the event
+            /// immediately.
+            ///
+            /// [enable_interrupt]: #method.enable_interrupt
+            pub fn schedule_at(&mut self, timestamp: u64) -> Result<(), ScheduleAlarmError> {
+                self.periodic = None;
+                cortex_m::interrupt::free(|_| {
+                    // safety: This is a read action and should not have any UB
+                    let now = read_counter(unsafe { &*TIMER::ptr() });
+                    self.arm(timestamp, now)
+                })
+            }
+
+            fn arm(&mut self, timestamp: u64, now: u64) -> Result<(), ScheduleAlarmError> {
+                if timestamp <= now {
                     return Err(ScheduleAlarmError::AlarmTooSoon);
-                } else {
-                    cortex_m::interrupt::free(|_| {
-                        // safety: This is a read action and should not have any UB
-                        let target_time = unsafe { &*TIMER::ptr() }
-                            .timelr
-                            .read()
-                            .bits()
-                            .wrapping_add(duration);
-
-                        // safety: This is the only code in the codebase that accesses memory address $timer_alarm
-                        unsafe { &*TIMER::ptr() }
-                            .$timer_alarm
-                            .write(|w| unsafe { w.bits(target_time) });
+                }
+                if timestamp - now > u64::from(u32::MAX) {
+                    return Err(ScheduleAlarmError::AlarmTooLate);
+                }
+                let target_time = timestamp as u32;
+
+                // safety: This is the only code in the codebase that accesses memory address $timer_alarm
+                let timer = unsafe { &*TIMER::ptr() };
+                timer.$timer_alarm.write(|w| unsafe { w.bits(target_time) });
+
+                // The alarm only fires when the counter matches the target exactly. If the target
+                // passed while we were arming it, disarm it rather than waiting for the counter to
+                // wrap around.
+                let now = timer.timerawl.read().bits();
+                if !self.finished() && (now.wrapping_sub(target_time) as i32) >= 0 {
+                    self.cancel();
+                    return Err(ScheduleAlarmError::AlarmTooSoon);
+                }
+                Ok(())
+            }
+
+            /// Schedule the alarm to finish every `period`, without drifting.
+            ///
+            /// The first deadline is `period` from now. After handling interrupt `
+            #[doc = $int_name]
+            /// `, call [reschedule] to arm the next deadline, which is computed from the previous
+            /// deadline rather than from the current time so interrupt latency doesn't accumulate.
+            /// `policy` decides what [reschedule] does when deadlines have been missed.
+            ///
+            /// Calling [schedule], [schedule_at] or [cancel] stops the periodic schedule.
+            ///
+            /// [reschedule]: #method.reschedule
+            /// [schedule]: #method.schedule
+            /// [schedule_at]: #method.schedule_at
+            /// [cancel]: #method.cancel
+            pub fn schedule_periodic<TIME: Into<Microseconds>>(
+                &mut self,
+                period: TIME,
+                policy: MissedTickPolicy,
+            ) -> Result<(), ScheduleAlarmError> {
+                let period = u64::from(period.into().0);
+                cortex_m::interrupt::free(|_| {
+                    // safety: This is a read action and should not have any UB
+                    let now = read_counter(unsafe { &*TIMER::ptr() });
+                    let deadline = now.wrapping_add(period);
+                    self.arm(deadline, now)?;
+                    self.periodic = Some(PeriodicSchedule {
+                        deadline,
+                        period,
+                        policy,
                     });
                     Ok(())
-                }
+                })
+            }
+
+            /// Arm the next deadline of a periodic alarm started with [schedule_periodic].
+            ///
+            /// Returns the number of deadlines that had already passed and were handled according
+            /// to the [`MissedTickPolicy`], or [`ScheduleAlarmError::NotPeriodic`] if the alarm is
+            /// not running periodically.
+            ///
+            /// [schedule_periodic]: #method.schedule_periodic
+            pub fn reschedule(&mut self) -> Result<u32, ScheduleAlarmError> {
+                let mut schedule = self.periodic.ok_or(ScheduleAlarmError::NotPeriodic)?;
+                let result = cortex_m::interrupt::free(|_| {
+                    // safety: This is a read action and should not have any UB
+                    let timer = unsafe { &*TIMER::ptr() };
+                    let now = read_counter(timer);
+                    let (deadline, mut missed) = schedule.next_deadline(now);
+                    schedule.deadline = deadline;
+                    if missed > 0 && schedule.policy == MissedTickPolicy::FireImmediately {
+                        // Handle one missed deadline right away, the next reschedule catches up
+                        timer.intf.modify(|_, w| w.$int_alarm().set_bit());
+                        return Ok(missed);
+                    }
+                    loop {
+                        match self.arm(schedule.deadline, read_counter(timer)) {
+                            Ok(()) => break Ok(missed),
+                            Err(ScheduleAlarmError::AlarmTooSoon) => {
+                                // The deadline passed while arming it
+                                schedule.deadline += schedule.period;
+                                missed += 1;
+                            }
+                            Err(e) => break Err(e),
+                        }
+                    }
+                });
+                self.periodic = Some(schedule);
+                result
+            }
+
+            /// Disarm the alarm, so it will not finish or trigger an interrupt.
+            ///
+            /// An interrupt which was already triggered still needs to be cleared with [clear_interrupt].
+            ///
+            /// [clear_interrupt]: #method.clear_interrupt
+            pub fn cancel(&mut self) {
+                self.periodic = None;
+                // safety: ARMED is write-1-to-clear, so this only affects this alarm
+                unsafe { &*TIMER::ptr() }
+                    .armed
+                    .write(|w| unsafe { w.bits($armed_bit_mask) });
             }
 
             /// Return true if this alarm is finished.
@@ -276,12 +606,51 @@ macro_rules! impl_alarm {
     };
 }
 
+/// What a periodic alarm does when one or more of its deadlines have passed before
+/// `AlarmX::reschedule` was called
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MissedTickPolicy {
+    /// Drop the missed deadlines and arm the next deadline that is still in the future.
+    Skip,
+    /// Force the alarm's interrupt immediately for each missed deadline, one per `reschedule`
+    /// call, until the alarm has caught up.
+    FireImmediately,
+}
+
+/// State of a periodic alarm
+#[derive(Copy, Clone)]
+struct PeriodicSchedule {
+    /// The deadline that was armed last
+    deadline: u64,
+    period: u64,
+    policy: MissedTickPolicy,
+}
+
+impl PeriodicSchedule {
+    /// Compute the deadline to arm next, and how many deadlines were missed, at time `now`
+    fn next_deadline(&self, now: u64) -> (u64, u32) {
+        let next = self.deadline + self.period;
+        if next > now {
+            return (next, 0);
+        }
+        let missed = (now - next) / self.period + 1;
+        match self.policy {
+            MissedTickPolicy::Skip => (next + missed * self.period, missed as u32),
+            MissedTickPolicy::FireImmediately => (next, missed as u32),
+        }
+    }
+}
+
 /// Errors that can be returned from any of the `AlarmX::schedule` methods.
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ScheduleAlarmError {
-    /// Alarm time is too low. Should be at least 10 microseconds.
+    /// The alarm time had already passed when the alarm was armed.
     AlarmTooSoon,
+    /// The alarm time is more than `u32::MAX` microseconds in the future.
+    AlarmTooLate,
+    /// `reschedule` was called on an alarm that was not scheduled with `schedule_periodic`.
+    NotPeriodic,
 }
 
 impl_alarm!(Alarm0 {
@@ -311,3 +680,168 @@ impl_alarm!(Alarm3 {
     int_name: "IRQ_TIMER_3",
     armed_bit_mask: 0b1000
 });
+
+#[cfg(feature = "rtic-monotonic")]
+pub use self::monotonic::Monotonic;
+
+#[cfg(feature = "rtic-monotonic")]
+mod monotonic {
+    use super::{Alarm0, Timer};
+    use fugit::{TimerDurationU64, TimerInstantU64};
+
+    /// RTIC monotonic timer based on the timer peripheral and [`Alarm0`]
+    ///
+    /// The instants are the values of the microsecond counter.
+    ///
+    /// ```ignore
+    /// #[monotonic(binds = TIMER_IRQ_0, default = true)]
+    /// type MyMono = rp2040_hal::timer::Monotonic;
+    /// ```
+    pub struct Monotonic {
+        timer: Timer,
+        alarm: Alarm0,
+    }
+
+    impl Monotonic {
+        /// Create a new monotonic timer, taking ownership of the timer and its first alarm
+        pub fn new(timer: Timer, alarm: Alarm0) -> Self {
+            Self { timer, alarm }
+        }
+
+        /// Release the timer and alarm
+        pub fn free(self) -> (Timer, Alarm0) {
+            (self.timer, self.alarm)
+        }
+    }
+
+    impl rtic_monotonic::Monotonic for Monotonic {
+        type Instant = TimerInstantU64<1_000_000>;
+        type Duration = TimerDurationU64<1_000_000>;
+
+        fn now(&mut self) -> Self::Instant {
+            TimerInstantU64::from_ticks(self.timer.get_counter_raw())
+        }
+
+        fn set_compare(&mut self, instant: Self::Instant) {
+            // The alarm only compares the low word. RTIC checks the queue when the interrupt fires,
+            // so an early match for an instant more than 2^32us away is harmless.
+            self.timer
+                .timer
+                .alarm0
+                .write(|w| unsafe { w.bits(instant.ticks() as u32) });
+        }
+
+        fn clear_compare_flag(&mut self) {
+            self.alarm.clear_interrupt(&mut self.timer);
+        }
+
+        fn zero() -> Self::Instant {
+            TimerInstantU64::from_ticks(0)
+        }
+
+        unsafe fn reset(&mut self) {
+            self.timer.reset();
+            self.alarm.enable_interrupt(&mut self.timer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A counter which advances by `step` on every read of either half
+    struct RollingCounter {
+        value: Cell<u64>,
+        step: u64,
+    }
+
+    impl RollingCounter {
+        fn read(&self, high: bool) -> u32 {
+            let value = self.value.get();
+            self.value.set(value + self.step);
+            if high {
+                (value >> 32) as u32
+            } else {
+                value as u32
+            }
+        }
+    }
+
+    #[test]
+    fn read_halves_without_rollover() {
+        let counter = RollingCounter {
+            value: Cell::new(0x1234_0000_0000),
+            step: 1,
+        };
+        let value = read_halves(|| counter.read(true), || counter.read(false));
+        assert_eq!(value, 0x1234_0000_0001);
+    }
+
+    #[test]
+    fn read_halves_with_rollover_between_reads() {
+        for start in 0xffff_fff0..=0xffff_ffff {
+            let counter = RollingCounter {
+                value: Cell::new(0x7_0000_0000 + start),
+                step: 4,
+            };
+            let before = counter.value.get();
+            let value = read_halves(|| counter.read(true), || counter.read(false));
+            let after = counter.value.get();
+            // The low half rolls over after the first read of the high half for some starts.
+            // Combining it with the stale high half would give a value 2^32 too small.
+            assert!(before <= value && value < after, "{:#x}", value);
+        }
+    }
+
+    fn schedule(policy: MissedTickPolicy) -> PeriodicSchedule {
+        PeriodicSchedule {
+            deadline: 1_000,
+            period: 100,
+            policy,
+        }
+    }
+
+    #[test]
+    fn next_deadline_does_not_drift() {
+        let mut schedule = schedule(MissedTickPolicy::Skip);
+        // The alarm is rescheduled some time after each deadline, as by an interrupt handler
+        for (tick, latency) in [3, 42, 0, 99, 17].iter().enumerate() {
+            let now = schedule.deadline + latency;
+            let (deadline, missed) = schedule.next_deadline(now);
+            assert_eq!(deadline, 1_100 + 100 * tick as u64);
+            assert_eq!(missed, 0);
+            schedule.deadline = deadline;
+        }
+    }
+
+    #[test]
+    fn next_deadline_missed_by_one_tick() {
+        // The next deadline passes exactly when the alarm is rescheduled
+        let (deadline, missed) = schedule(MissedTickPolicy::Skip).next_deadline(1_100);
+        assert_eq!((deadline, missed), (1_200, 1));
+        let (deadline, missed) = schedule(MissedTickPolicy::FireImmediately).next_deadline(1_100);
+        assert_eq!((deadline, missed), (1_100, 1));
+    }
+
+    #[test]
+    fn next_deadline_late_by_more_than_one_period() {
+        // 1100, 1200 and 1300 have passed
+        let (deadline, missed) = schedule(MissedTickPolicy::Skip).next_deadline(1_350);
+        assert_eq!((deadline, missed), (1_400, 3));
+        // The deadlines stay on the grid of the period once the alarm has caught up
+        let mut schedule = schedule(MissedTickPolicy::FireImmediately);
+        let mut fired = 0;
+        loop {
+            let (deadline, missed) = schedule.next_deadline(1_350);
+            schedule.deadline = deadline;
+            if missed == 0 {
+                break;
+            }
+            assert_eq!(missed, 3 - fired);
+            fired += 1;
+        }
+        assert_eq!((fired, schedule.deadline), (3, 1_400));
+    }
+}
@@ -4,6 +4,7 @@
 //! UartPeripheral object that can both read and write.
 
 use super::*;
+use crate::clocks::{ClockGate, ClockGated};
 use crate::pac::uart0::uartlcr_h::W as UART_LCR_H_Writer;
 use core::convert::Infallible;
 use core::fmt;
@@ -41,6 +42,14 @@ impl<S: State, D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<S, D, P> {
     pub fn free(self) -> (D, P) {
         (self.device, self.pins)
     }
+
+    /// The clock gates of the UART, see [`ClocksManager::set_wake_enables`](crate::clocks::ClocksManager::set_wake_enables)
+    pub fn clock_gate(&self) -> ClockGate
+    where
+        D: ClockGated,
+    {
+        D::CLOCK_GATE
+    }
 }
 
 impl<D: UartDevice, P: ValidUartPinout<D>> UartPeripheral<Disabled, D, P> {
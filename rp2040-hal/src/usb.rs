@@ -1,5 +1,9 @@
 //! Universal Serial Bus (USB)
 // See [Chapter 4 Section 1](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more details
+//!
+//! [`UsbBus`] implements the `usb-device` crate's `UsbBus` trait for the USB controller in device
+//! mode, so class crates like `usbd-serial` and `usbd-hid` can be used on top of it. It is only
+//! available with the `usb` feature, which is enabled by default.
 //! ## Usage
 //!
 //! Initialize the Usb Bus forcing the VBUS detection.
@@ -32,7 +36,19 @@
 //! // Use the usb_bus as usual.
 //! ```
 //!
-//! See [pico_usb_serial.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/pico/examples/pico_usb_serial.rs) for more complete examples
+//! ## VBUS detection
+//!
+//! The controller only connects to the host while it detects VBUS. Boards which don't route VBUS
+//! to a GPIO pin in [`FunctionUsbAux`] mode, see [`VbusDetectPin`], have to force the detection
+//! with the `force_vbus_detect_bit` of [`UsbBus::new`]. With a detection pin, use
+//! [`UsbBus::new_with_vbus_detect_pin`] instead, and check [`UsbBus::vbus_detected`] to find out if
+//! a host is attached.
+//!
+//! [`UsbBus::disconnect`] and [`UsbBus::connect`] remove and restore the pull-up on D+, so the
+//! device can leave the bus, or defer the enumeration until it is ready.
+//!
+//! See [usb_serial_echo.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/usb_serial_echo.rs)
+//! and [pico_usb_serial.rs](https://github.com/rp-rs/rp-hal/tree/main/boards/rp-pico/examples/pico_usb_serial.rs) for more complete examples
 //!
 //!
 //! ## Enumeration issue with small EP0 max packet size
@@ -58,6 +74,7 @@
 use core::cell::RefCell;
 
 use crate::clocks::UsbClock;
+use crate::gpio::{bank0, FunctionUsbAux, Pin};
 use crate::pac::RESETS;
 use crate::pac::USBCTRL_DPRAM;
 use crate::pac::USBCTRL_REGS;
@@ -114,6 +131,10 @@ struct Inner {
     out_endpoints: [Option<Endpoint>; 16],
     next_offset: u16,
     read_setup: bool,
+    suspended: bool,
+    remote_wakeup_signalled: bool,
+    enabled: bool,
+    connected: bool,
 }
 impl Inner {
     fn new(ctrl_reg: USBCTRL_REGS, ctrl_dpram: USBCTRL_DPRAM) -> Self {
@@ -124,6 +145,10 @@ impl Inner {
             out_endpoints: Default::default(),
             next_offset: 0,
             read_setup: false,
+            suspended: false,
+            remote_wakeup_signalled: false,
+            enabled: false,
+            connected: true,
         }
     }
 
@@ -356,6 +381,21 @@ impl Inner {
     }
 }
 
+/// Declares a pin which can detect VBUS in [`FunctionUsbAux`] mode
+///
+/// These are GPIO1, 4, 7, 10, 13, 16, 19, 22, 25 and 28.
+pub trait VbusDetectPin {}
+
+macro_rules! impl_vbus_detect_pin {
+    ($($pin:ident),*) => {
+        $(
+            impl VbusDetectPin for Pin<bank0::$pin, FunctionUsbAux> {}
+        )*
+    };
+}
+
+impl_vbus_detect_pin!(Gpio1, Gpio4, Gpio7, Gpio10, Gpio13, Gpio16, Gpio19, Gpio22, Gpio25, Gpio28);
+
 /// Usb bus
 pub struct UsbBus {
     inner: Mutex<RefCell<Inner>>,
@@ -363,6 +403,9 @@ pub struct UsbBus {
 
 impl UsbBus {
     /// Create new usb bus struct and bring up usb as device.
+    ///
+    /// `force_vbus_detect_bit` makes the controller act as if VBUS was always present, for boards
+    /// without a [`VbusDetectPin`].
     pub fn new(
         ctrl_reg: USBCTRL_REGS,
         ctrl_dpram: USBCTRL_DPRAM,
@@ -404,6 +447,100 @@ impl UsbBus {
             inner: Mutex::new(RefCell::new(Inner::new(ctrl_reg, ctrl_dpram))),
         }
     }
+
+    /// Create new usb bus struct and bring up usb as device, detecting VBUS on `vbus_detect_pin`.
+    ///
+    /// The device only connects to the host while the pin is high.
+    pub fn new_with_vbus_detect_pin<P: VbusDetectPin>(
+        ctrl_reg: USBCTRL_REGS,
+        ctrl_dpram: USBCTRL_DPRAM,
+        pll: UsbClock,
+        _vbus_detect_pin: P,
+        resets: &mut RESETS,
+    ) -> Self {
+        // The controller takes VBUS from the pin in USB function mode
+        Self::new(ctrl_reg, ctrl_dpram, pll, false, resets)
+    }
+
+    /// Check if VBUS is detected, i.e. a host is attached.
+    ///
+    /// Always `true` if the detection is forced.
+    pub fn vbus_detected(&self) -> bool {
+        interrupt::free(|cs| {
+            let inner = self.inner.borrow(cs).borrow();
+            inner
+                .ctrl_reg
+                .sie_status
+                .read()
+                .vbus_detected()
+                .bit_is_set()
+        })
+    }
+
+    /// Remove the pull-up on D+, so the host sees the device leave the bus.
+    ///
+    /// The device stays disconnected until [`connect`](Self::connect) is called, even if it was
+    /// not enabled by `usb-device` yet, which defers the enumeration. The host resets the bus when
+    /// the device connects again, which resets the state of the device and its endpoints.
+    pub fn disconnect(&self) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.connected = false;
+            inner.suspended = false;
+            inner.remote_wakeup_signalled = false;
+            inner
+                .ctrl_reg
+                .sie_ctrl
+                .modify(|_, w| w.pullup_en().clear_bit());
+        })
+    }
+
+    /// Restore the pull-up on D+ after [`disconnect`](Self::disconnect), so the host enumerates
+    /// the device again.
+    ///
+    /// If the device was not enabled by `usb-device` yet, the pull-up is enabled along with it.
+    pub fn connect(&self) {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.connected = true;
+            if inner.enabled {
+                inner
+                    .ctrl_reg
+                    .sie_ctrl
+                    .modify(|_, w| w.pullup_en().set_bit());
+            }
+        })
+    }
+
+    /// Check if the host suspended the bus.
+    ///
+    /// While suspended, the device may only draw 2.5mA from VBUS, so the application should lower
+    /// its clocks or sleep until the bus is resumed, keeping the USB controller clocked with
+    /// [`SleepConfig::with_usb`](crate::clocks::SleepConfig::with_usb) so the resume raises
+    /// `USBCTRL_IRQ`.
+    pub fn is_suspended(&self) -> bool {
+        interrupt::free(|cs| self.inner.borrow(cs).borrow().suspended)
+    }
+
+    /// Signal a remote wakeup to the host while the bus is suspended.
+    ///
+    /// The host has to enable remote wakeup first, which can be checked with
+    /// `UsbDevice::remote_wakeup_enabled`, and the device must announce the support with
+    /// `UsbDeviceBuilder::supports_remote_wakeup`. The controller drives the resume signalling
+    /// for the required time on its own. The next poll reports `PollResult::Resume`.
+    ///
+    /// Returns `false` if the bus is not suspended, in which case nothing is signalled.
+    pub fn remote_wakeup(&self) -> bool {
+        interrupt::free(|cs| {
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            if !inner.suspended {
+                return false;
+            }
+            inner.ctrl_reg.sie_ctrl.modify(|_, w| w.resume().set_bit());
+            inner.remote_wakeup_signalled = true;
+            true
+        })
+    }
 }
 
 impl UsbBusTrait for UsbBus {
@@ -424,12 +561,13 @@ impl UsbBusTrait for UsbBus {
 
     fn enable(&mut self) {
         interrupt::free(|cs| {
-            let inner = self.inner.borrow(cs).borrow_mut();
+            let mut inner = self.inner.borrow(cs).borrow_mut();
+            inner.enabled = true;
             // at this stage ep's are expected to be in their reset state
             // TODO: is it worth having a debug_assert for that here?
 
             // Enable interrupt generation when a buffer is done, when the bus is reset,
-            // and when a setup packet is received
+            // when a setup packet is received, and when the bus is suspended or resumed
             // this should be sufficient for device mode, will need more for host.
             inner.ctrl_reg.inte.modify(|_, w| {
                 w.buff_status()
@@ -438,13 +576,19 @@ impl UsbBusTrait for UsbBus {
                     .set_bit()
                     .setup_req()
                     .set_bit()
+                    .dev_suspend()
+                    .set_bit()
+                    .dev_resume_from_host()
+                    .set_bit()
             });
 
-            // enable pull up to let the host know we exist.
-            inner
-                .ctrl_reg
-                .sie_ctrl
-                .modify(|_, w| w.pullup_en().set_bit());
+            // enable pull up to let the host know we exist, unless the device was disconnected.
+            if inner.connected {
+                inner
+                    .ctrl_reg
+                    .sie_ctrl
+                    .modify(|_, w| w.pullup_en().set_bit());
+            }
         })
     }
     fn reset(&self) {
@@ -453,6 +597,8 @@ impl UsbBusTrait for UsbBus {
 
             // clear reset flag
             inner.ctrl_reg.sie_status.write(|w| w.bus_reset().set_bit());
+            inner.suspended = false;
+            inner.remote_wakeup_signalled = false;
             inner
                 .ctrl_reg
                 .buff_status
@@ -520,23 +666,38 @@ impl UsbBusTrait for UsbBus {
         })
     }
     fn suspend(&self) {
-        todo!()
+        // The controller stops responding on its own while the bus is suspended, so there is
+        // nothing to do here. Lowering the power consumption is up to the application.
     }
     fn resume(&self) {
-        todo!()
+        // The controller resumes on its own when the host resumes the bus.
     }
     fn poll(&self) -> PollResult {
         interrupt::free(|cs| {
             let mut inner = self.inner.borrow(cs).borrow_mut();
-            // TODO: check for suspend request
-            // TODO: check for resume request
-
             // check for bus reset
             let sie_status = inner.ctrl_reg.sie_status.read();
             if sie_status.bus_reset().bit_is_set() {
                 return PollResult::Reset;
             }
 
+            // check for resume, initiated by the host or by a remote wakeup, and for suspend
+            if sie_status.resume().bit_is_set() || inner.remote_wakeup_signalled {
+                inner.ctrl_reg.sie_status.write(|w| w.resume().set_bit());
+                inner.remote_wakeup_signalled = false;
+                if inner.suspended {
+                    inner.suspended = false;
+                    return PollResult::Resume;
+                }
+            }
+            if sie_status.suspended().bit_is_set() {
+                inner.ctrl_reg.sie_status.write(|w| w.suspended().set_bit());
+                if !inner.suspended {
+                    inner.suspended = true;
+                    return PollResult::Suspend;
+                }
+            }
+
             let (mut ep_out, mut ep_in_complete, mut ep_setup): (u16, u16, u16) = (0, 0, 0);
 
             let buff_status = inner.ctrl_reg.buff_status.read().bits();
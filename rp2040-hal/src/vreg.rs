@@ -0,0 +1,288 @@
+//! On-chip voltage regulator (VREG)
+//!
+//! The voltage regulator supplies the digital core (DVDD). Its output voltage can be changed at
+//! run time, e.g. raised to run the system clock above 133 MHz, or lowered to save power at low
+//! frequencies. See [Chapter 2 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf)
+//! of the datasheet for more details.
+//!
+//! ## Brown-out detection
+//!
+//! The brown-out detector of the same block, see [`Bod`], monitors DVDD and resets the chip when
+//! it drops below its threshold, which is 0.86 V after a power-on reset. Selecting
+//! [`VregVoltage::V0_80`] or [`VregVoltage::V0_85`] therefore resets the chip, unless the
+//! brown-out detector was reconfigured or disabled first. Raising the voltage has no such
+//! interaction.
+//!
+//! A brown-out reset sets the same flag as a power-on reset, so both are reported as
+//! [`ResetReason::PowerOn`](crate::chip_reset::ResetReason::PowerOn).
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::{pac, vreg::{BodThreshold, Vreg, VregVoltage}};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut vreg = Vreg::new(peripherals.VREG_AND_CHIP_RESET);
+//! vreg.set_voltage(VregVoltage::V1_20);
+//! vreg.wait_for_regulation();
+//! assert_eq!(vreg.get_voltage(), VregVoltage::V1_20);
+//!
+//! // Detect a drop of DVDD below 1.075 V
+//! vreg.bod().set_threshold_may_reset(BodThreshold::V1_075);
+//! ```
+
+use crate::pac::VREG_AND_CHIP_RESET;
+
+/// Output voltage of the regulator
+///
+/// Only the voltages defined by the datasheet exist. The variants are ordered by voltage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum VregVoltage {
+    /// 0.80 V, below the power-on brown-out threshold
+    V0_80 = 0b0101,
+    /// 0.85 V, below the power-on brown-out threshold
+    V0_85 = 0b0110,
+    /// 0.90 V
+    V0_90 = 0b0111,
+    /// 0.95 V
+    V0_95 = 0b1000,
+    /// 1.00 V
+    V1_00 = 0b1001,
+    /// 1.05 V
+    V1_05 = 0b1010,
+    /// 1.10 V, the default after a power-on reset
+    V1_10 = 0b1011,
+    /// 1.15 V
+    V1_15 = 0b1100,
+    /// 1.20 V
+    V1_20 = 0b1101,
+    /// 1.25 V
+    V1_25 = 0b1110,
+    /// 1.30 V
+    V1_30 = 0b1111,
+}
+
+impl VregVoltage {
+    fn from_vsel(vsel: u8) -> Self {
+        match vsel {
+            // VSEL 0b0000 to 0b0101 all select 0.80 V
+            0b0000..=0b0101 => VregVoltage::V0_80,
+            0b0110 => VregVoltage::V0_85,
+            0b0111 => VregVoltage::V0_90,
+            0b1000 => VregVoltage::V0_95,
+            0b1001 => VregVoltage::V1_00,
+            0b1010 => VregVoltage::V1_05,
+            0b1011 => VregVoltage::V1_10,
+            0b1100 => VregVoltage::V1_15,
+            0b1101 => VregVoltage::V1_20,
+            0b1110 => VregVoltage::V1_25,
+            _ => VregVoltage::V1_30,
+        }
+    }
+
+    /// The output voltage in millivolts
+    pub fn millivolts(self) -> u16 {
+        // 0.80 V at VSEL 0b0101, in steps of 50 mV
+        800 + 50 * (self as u16 - 0b0101)
+    }
+}
+
+/// Threshold of the brown-out detector
+///
+/// Only the thresholds defined by the datasheet exist. The variants are ordered by voltage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum BodThreshold {
+    /// 0.473 V
+    V0_473 = 0b0000,
+    /// 0.516 V
+    V0_516 = 0b0001,
+    /// 0.559 V
+    V0_559 = 0b0010,
+    /// 0.602 V
+    V0_602 = 0b0011,
+    /// 0.645 V
+    V0_645 = 0b0100,
+    /// 0.688 V
+    V0_688 = 0b0101,
+    /// 0.731 V
+    V0_731 = 0b0110,
+    /// 0.774 V
+    V0_774 = 0b0111,
+    /// 0.817 V
+    V0_817 = 0b1000,
+    /// 0.860 V, the default after a power-on reset
+    V0_860 = 0b1001,
+    /// 0.903 V
+    V0_903 = 0b1010,
+    /// 0.946 V
+    V0_946 = 0b1011,
+    /// 0.989 V
+    V0_989 = 0b1100,
+    /// 1.032 V
+    V1_032 = 0b1101,
+    /// 1.075 V
+    V1_075 = 0b1110,
+    /// 1.118 V
+    V1_118 = 0b1111,
+}
+
+impl BodThreshold {
+    fn from_vsel(vsel: u8) -> Self {
+        match vsel {
+            0b0000 => BodThreshold::V0_473,
+            0b0001 => BodThreshold::V0_516,
+            0b0010 => BodThreshold::V0_559,
+            0b0011 => BodThreshold::V0_602,
+            0b0100 => BodThreshold::V0_645,
+            0b0101 => BodThreshold::V0_688,
+            0b0110 => BodThreshold::V0_731,
+            0b0111 => BodThreshold::V0_774,
+            0b1000 => BodThreshold::V0_817,
+            0b1001 => BodThreshold::V0_860,
+            0b1010 => BodThreshold::V0_903,
+            0b1011 => BodThreshold::V0_946,
+            0b1100 => BodThreshold::V0_989,
+            0b1101 => BodThreshold::V1_032,
+            0b1110 => BodThreshold::V1_075,
+            _ => BodThreshold::V1_118,
+        }
+    }
+
+    /// The threshold in millivolts
+    pub fn millivolts(self) -> u16 {
+        // 0.473 V at VSEL 0b0000, in steps of 43 mV
+        473 + 43 * self as u16
+    }
+}
+
+/// Brown-out detector, see [`Vreg::bod`]
+///
+/// When enabled, the detector resets the chip while DVDD is below the threshold. After the reset,
+/// [`reset_reason`](crate::chip_reset::reset_reason) returns
+/// [`ResetReason::PowerOn`](crate::chip_reset::ResetReason::PowerOn), as the hardware doesn't
+/// tell a brown-out from a power-on reset.
+pub struct Bod<'a> {
+    device: &'a VREG_AND_CHIP_RESET,
+}
+
+impl<'a> Bod<'a> {
+    /// Select the threshold
+    ///
+    /// If the detector is enabled and DVDD is already below `threshold`, e.g. because it is above
+    /// the selected [`VregVoltage`], the chip is reset immediately.
+    pub fn set_threshold_may_reset(&mut self, threshold: BodThreshold) {
+        self.device
+            .bod
+            .modify(|_, w| unsafe { w.vsel().bits(threshold as u8) });
+    }
+
+    /// The selected threshold
+    pub fn threshold(&self) -> BodThreshold {
+        BodThreshold::from_vsel(self.device.bod.read().vsel().bits())
+    }
+
+    /// Enable the detector
+    ///
+    /// If DVDD is below the threshold, the chip is reset immediately.
+    pub fn enable(&mut self) {
+        self.device.bod.modify(|_, w| w.en().set_bit());
+    }
+
+    /// Disable the detector
+    pub fn disable(&mut self) {
+        self.device.bod.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Whether the detector is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.device.bod.read().en().bit_is_set()
+    }
+}
+
+/// Voltage regulator
+pub struct Vreg {
+    device: VREG_AND_CHIP_RESET,
+}
+
+impl Vreg {
+    /// Create a new [`Vreg`]
+    ///
+    /// The regulator keeps its current configuration.
+    pub fn new(device: VREG_AND_CHIP_RESET) -> Self {
+        Self { device }
+    }
+
+    /// Releases the underlying device.
+    pub fn free(self) -> VREG_AND_CHIP_RESET {
+        self.device
+    }
+
+    /// The brown-out detector
+    pub fn bod(&mut self) -> Bod<'_> {
+        Bod {
+            device: &self.device,
+        }
+    }
+
+    /// Select the output voltage
+    ///
+    /// This returns immediately; use [`wait_for_regulation`](Self::wait_for_regulation) before
+    /// relying on the new voltage. See the [module documentation](self) about lowering the
+    /// voltage below the brown-out threshold.
+    pub fn set_voltage(&mut self, voltage: VregVoltage) {
+        self.device
+            .vreg
+            .modify(|_, w| unsafe { w.vsel().bits(voltage as u8) });
+    }
+
+    /// The selected output voltage
+    pub fn get_voltage(&self) -> VregVoltage {
+        VregVoltage::from_vsel(self.device.vreg.read().vsel().bits())
+    }
+
+    /// Whether the output is in regulation
+    pub fn is_regulated(&self) -> bool {
+        self.device.vreg.read().rok().bit_is_set()
+    }
+
+    /// Wait until the output is in regulation
+    pub fn wait_for_regulation(&self) {
+        while !self.is_regulated() {}
+    }
+
+    /// Change the system frequency with `change_frequency`, and the core voltage to `voltage`
+    ///
+    /// A raised voltage is selected and regulated before the frequency is changed, and a lowered
+    /// voltage is only selected after the frequency was changed successfully, so the core never
+    /// runs at a frequency its voltage doesn't support. If `change_frequency` fails, a lowered
+    /// voltage is not selected.
+    ///
+    /// ```no_run
+    /// use rp2040_hal::{pac, vreg::{Vreg, VregVoltage}};
+    /// let mut peripherals = pac::Peripherals::take().unwrap();
+    /// let mut vreg = Vreg::new(peripherals.VREG_AND_CHIP_RESET);
+    /// let result: Result<(), ()> = vreg.with_voltage(VregVoltage::V1_20, || {
+    ///     // Reconfigure clk_sys to a frequency above 133 MHz here
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn with_voltage<T, E>(
+        &mut self,
+        voltage: VregVoltage,
+        change_frequency: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let current = self.get_voltage();
+        if voltage > current {
+            self.set_voltage(voltage);
+            self.wait_for_regulation();
+        }
+
+        let result = change_frequency()?;
+
+        if voltage < current {
+            self.set_voltage(voltage);
+            self.wait_for_regulation();
+        }
+        Ok(result)
+    }
+}
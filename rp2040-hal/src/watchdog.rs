@@ -34,12 +34,37 @@
 //! ```
 //! See [examples/watchdog.rs](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal/examples/watchdog.rs) for a more complete example
 
+use crate::clocks::{Clock, ReferenceClock};
 use crate::pac::WATCHDOG;
 #[cfg(feature = "eh1_0_alpha")]
 use eh1_0_alpha::watchdog::blocking as eh1;
 use embedded_hal::watchdog;
 use embedded_time::{duration, fixed_point::FixedPoint};
 
+/// Scratch registers of the watchdog which are free for the application
+///
+/// The 8 scratch registers keep their value across soft and watchdog resets. Scratch 4 to 7 are
+/// used by the boot ROM, to boot into a given vector after a watchdog reset, so only scratch 0 to
+/// 3 are available here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScratchRegister {
+    /// Scratch register 0
+    Scratch0,
+    /// Scratch register 1
+    Scratch1,
+    /// Scratch register 2
+    Scratch2,
+    /// Scratch register 3
+    Scratch3,
+}
+
+/// Error of [`Watchdog::enable_tick_generation_from`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickError {
+    /// clk_ref is below 1MHz or above 511MHz, so there can't be one tick per microsecond
+    ReferenceClockOutOfRange,
+}
+
 /// Watchdog peripheral
 pub struct Watchdog {
     watchdog: WATCHDOG,
@@ -48,7 +73,14 @@ pub struct Watchdog {
 
 impl Watchdog {
     /// Create a new [`Watchdog`]
+    ///
+    /// This clears the boot vector set by [`reset_to`](Self::reset_to) in scratch registers 4 to
+    /// 7, so a stale handoff can not be taken by a later watchdog reset.
     pub fn new(watchdog: WATCHDOG) -> Self {
+        watchdog.scratch4.write(|w| unsafe { w.bits(0) });
+        watchdog.scratch5.write(|w| unsafe { w.bits(0) });
+        watchdog.scratch6.write(|w| unsafe { w.bits(0) });
+        watchdog.scratch7.write(|w| unsafe { w.bits(0) });
         Self {
             watchdog,
             delay_ms: 0,
@@ -57,6 +89,14 @@ impl Watchdog {
 
     /// Starts tick generation on clk_tick which is driven from clk_ref.
     ///
+    /// The ticks clock both the watchdog countdown and the [`Timer`](crate::timer::Timer), which
+    /// counts microseconds only if there is one tick per microsecond: `cycles` must be the
+    /// frequency of clk_ref in MHz. The tick generator is independent from the countdown, which
+    /// is not started by this.
+    ///
+    /// See [`enable_tick_generation_from`](Self::enable_tick_generation_from) to derive `cycles`
+    /// from the configured clk_ref.
+    ///
     /// # Arguments
     ///
     /// * `cycles` - Total number of tick cycles before the next tick is generated.
@@ -68,6 +108,55 @@ impl Watchdog {
             .write(|w| unsafe { w.bits(WATCHDOG_TICK_ENABLE_BITS | cycles as u32) })
     }
 
+    /// Starts tick generation with one tick per microsecond, from the frequency clk_ref is
+    /// configured to.
+    ///
+    /// If clk_ref is not a whole number of MHz, eg when it runs from the ROSC, the frequency is
+    /// rounded and the ticks are only approximately one microsecond apart.
+    pub fn enable_tick_generation_from(
+        &mut self,
+        reference_clock: &ReferenceClock,
+    ) -> Result<(), TickError> {
+        let freq_hz = reference_clock.freq().integer();
+        let cycles = (freq_hz + 500_000) / 1_000_000;
+        if cycles == 0 || cycles > 0x1ff {
+            return Err(TickError::ReferenceClockOutOfRange);
+        }
+
+        const WATCHDOG_TICK_ENABLE_BITS: u32 = 0x200;
+        self.watchdog
+            .tick
+            .write(|w| unsafe { w.bits(WATCHDOG_TICK_ENABLE_BITS | cycles) });
+        Ok(())
+    }
+
+    /// Whether the tick generator is running
+    pub fn is_tick_running(&self) -> bool {
+        self.watchdog.tick.read().running().bit_is_set()
+    }
+
+    /// Pause the countdown, keeping the remaining time
+    ///
+    /// This clears the enable bit like [`disable`](watchdog::WatchdogDisable::disable), which does
+    /// not reload the counter either. The countdown continues where it stopped on
+    /// [`resume`](Self::resume).
+    pub fn pause(&mut self) {
+        self.enable(false)
+    }
+
+    /// Resume the countdown paused with [`pause`](Self::pause)
+    pub fn resume(&mut self) {
+        self.enable(true)
+    }
+
+    /// Time remaining before the watchdog resets the chip, if it is not fed
+    ///
+    /// This is only meaningful with one tick per microsecond.
+    pub fn remaining(&self) -> duration::Microseconds {
+        // Due to a logic error, the watchdog decrements by 2; see RP2040-E1
+        duration::Microseconds(self.watchdog.ctrl.read().time().bits() / 2)
+    }
+
     /// Defines whether or not the watchdog timer should be paused when processor(s) are in debug mode
     /// or when JTAG is accessing bus fabric
     ///
@@ -75,22 +164,105 @@ impl Watchdog {
     ///
     /// * `pause` - If true, watchdog timer will be paused
     pub fn pause_on_debug(&mut self, pause: bool) {
-        self.watchdog.ctrl.write(|w| {
+        self.set_pause_on_debug(pause, pause, pause)
+    }
+
+    /// Defines individually whether or not the watchdog timer should be paused while core 0 or
+    /// core 1 are halted by the debugger, or while JTAG is accessing the bus fabric.
+    ///
+    /// All three are enabled at reset. This only affects the watchdog countdown: the tick generator
+    /// started with [`enable_tick_generation`](Self::enable_tick_generation) keeps running, so the
+    /// timer peripheral (which counts those ticks) must be paused separately with
+    /// [`Timer::set_pause_on_debug`](crate::timer::Timer::set_pause_on_debug).
+    pub fn set_pause_on_debug(&mut self, core0: bool, core1: bool, jtag: bool) {
+        self.watchdog.ctrl.modify(|_, w| {
             w.pause_dbg0()
-                .bit(pause)
+                .bit(core0)
                 .pause_dbg1()
-                .bit(pause)
+                .bit(core1)
                 .pause_jtag()
-                .bit(pause)
+                .bit(jtag)
         })
     }
 
+    /// Read a scratch register
+    pub fn read_scratch(&self, reg: ScratchRegister) -> u32 {
+        match reg {
+            ScratchRegister::Scratch0 => self.watchdog.scratch0.read().bits(),
+            ScratchRegister::Scratch1 => self.watchdog.scratch1.read().bits(),
+            ScratchRegister::Scratch2 => self.watchdog.scratch2.read().bits(),
+            ScratchRegister::Scratch3 => self.watchdog.scratch3.read().bits(),
+        }
+    }
+
+    /// Write a scratch register, which keeps its value across soft and watchdog resets
+    pub fn write_scratch(&mut self, reg: ScratchRegister, value: u32) {
+        match reg {
+            ScratchRegister::Scratch0 => self.watchdog.scratch0.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch1 => self.watchdog.scratch1.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch2 => self.watchdog.scratch2.write(|w| unsafe { w.bits(value) }),
+            ScratchRegister::Scratch3 => self.watchdog.scratch3.write(|w| unsafe { w.bits(value) }),
+        }
+    }
+
+    /// Reset the whole chip, except the oscillators, and boot normally.
+    ///
+    /// The scratch registers 0 to 3 keep their value.
+    pub fn force_reset(&mut self) -> ! {
+        self.watchdog.scratch4.write(|w| unsafe { w.bits(0) });
+        self.trigger_reset()
+    }
+
+    /// Reset the whole chip, except the oscillators, and have the boot ROM jump to `entry` with the
+    /// stack pointer set to `stack_pointer` instead of booting normally.
+    ///
+    /// This is done through the boot vector in scratch registers 4 to 7 (see Chapter 2, Section 8,
+    /// §1.1), eg to chain-load another application or to re-enter a bootloader. `entry` is the
+    /// address of a thumb function, with the lowest bit set.
+    ///
+    /// The watchdog is stopped before the boot vector is written, so a watchdog started with
+    /// [`start`](watchdog::WatchdogEnable::start) and not [`feed`](watchdog::Watchdog::feed) in
+    /// time can not reset the chip with half a boot vector.
+    pub fn reset_to(&mut self, entry: u32, stack_pointer: u32) -> ! {
+        const BOOT_MAGIC: u32 = 0xb007_c0d3;
+
+        self.enable(false);
+
+        // The magic value is written last, so the vector is never taken if it is incomplete
+        self.watchdog.scratch7.write(|w| unsafe { w.bits(entry) });
+        self.watchdog
+            .scratch6
+            .write(|w| unsafe { w.bits(stack_pointer) });
+        self.watchdog
+            .scratch5
+            .write(|w| unsafe { w.bits(entry ^ BOOT_MAGIC.wrapping_neg()) });
+        self.watchdog
+            .scratch4
+            .write(|w| unsafe { w.bits(BOOT_MAGIC) });
+
+        self.trigger_reset()
+    }
+
+    fn trigger_reset(&mut self) -> ! {
+        // Reset everything but the oscillators. The PSM is not owned by the watchdog, but nothing
+        // else uses WDSEL.
+        const WDSEL_ALL_BUT_OSCILLATORS: u32 = 0x1_fffc;
+        let psm = unsafe { &*crate::pac::PSM::ptr() };
+        psm.wdsel
+            .write(|w| unsafe { w.bits(WDSEL_ALL_BUT_OSCILLATORS) });
+
+        self.watchdog.ctrl.modify(|_, w| w.trigger().set_bit());
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
     fn load_counter(&self, counter: u32) {
         self.watchdog.load.write(|w| unsafe { w.bits(counter) });
     }
 
     fn enable(&self, bit: bool) {
-        self.watchdog.ctrl.write(|w| w.enable().bit(bit))
+        self.watchdog.ctrl.modify(|_, w| w.enable().bit(bit))
     }
 }
 
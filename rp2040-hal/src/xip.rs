@@ -0,0 +1,185 @@
+//! Execute-in-place (XIP) cache and flash interface
+//!
+//! The external flash is read through the XIP cache and the SSI, which the second stage
+//! bootloader configures for the flash chip of the board. [`XipCache`] controls the 16KiB cache
+//! and reads its performance counters, and [`set_clock_divider`] changes the SSI clock, i.e. the
+//! clock of the flash, which is clk_sys divided by the divider. See
+//! [Chapter 2 Section 6.3](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) and
+//! [Chapter 4 Section 10](https://datasheets.raspberrypi.org/rp2040/rp2040_datasheet.pdf) for more
+//! details.
+//!
+//! The [`flash`](crate::flash) functions flush the cache after erasing or programming, and keep
+//! the clock divider selected here.
+//!
+//! ## Usage
+//! ```no_run
+//! use rp2040_hal::{pac, xip::XipCache};
+//! let mut peripherals = pac::Peripherals::take().unwrap();
+//! let mut cache = XipCache::new(peripherals.XIP_CTRL);
+//!
+//! cache.reset_cache_stats();
+//! // Run the code to measure
+//! let (hits, accesses) = cache.cache_stats();
+//! ```
+
+use crate::flash::{ram_read, ram_write, SSI_SR};
+use crate::pac::XIP_CTRL;
+
+/// Start of the cache SRAM, which can be used as RAM while the cache is disabled
+pub const XIP_SRAM_BASE: u32 = 0x1500_0000;
+
+/// Size of the cache SRAM, in bytes
+pub const XIP_SRAM_SIZE: u32 = 16 * 1024;
+
+/// XIP_SSI enable register
+const SSI_SSIENR: *mut u32 = 0x1800_0008 as _;
+/// XIP_SSI clock divider register
+const SSI_BAUDR: *mut u32 = 0x1800_0014 as _;
+/// A transfer is in progress
+const SSI_SR_BUSY: u32 = 1 << 0;
+
+/// Error of [`set_clock_divider`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The divider is odd or less than 2
+    InvalidDivider,
+}
+
+/// XIP cache
+pub struct XipCache {
+    device: XIP_CTRL,
+}
+
+impl XipCache {
+    /// Create a new [`XipCache`]
+    ///
+    /// The cache keeps its current configuration, it is enabled after reset.
+    pub fn new(device: XIP_CTRL) -> Self {
+        Self { device }
+    }
+
+    /// Releases the underlying device.
+    pub fn free(self) -> XIP_CTRL {
+        self.device
+    }
+
+    /// Invalidate all cache lines, and wait until they are invalidated
+    ///
+    /// This is needed after the flash was written without the [`flash`](crate::flash) functions,
+    /// which flush the cache themselves. Accesses bypass the cache while it is flushed.
+    pub fn flush(&mut self) {
+        self.device.flush.write(|w| w.flush().set_bit());
+        // Reading FLUSH stalls until the flush is done
+        let _ = self.device.flush.read();
+    }
+
+    /// Enable the cache
+    pub fn enable(&mut self) {
+        self.device.ctrl.modify(|_, w| w.en().set_bit());
+    }
+
+    /// Disable the cache
+    ///
+    /// Every access to the flash goes to the SSI, which is a lot slower for code running from
+    /// flash. The cache SRAM can be used as plain RAM at [`XIP_SRAM_BASE`] then. The lines which
+    /// are still valid are used again when the cache is enabled, so [`flush`](Self::flush) it
+    /// after using the SRAM.
+    pub fn disable(&mut self) {
+        self.device.ctrl.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Whether the cache is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.device.ctrl.read().en().bit_is_set()
+    }
+
+    /// Disable the cache and power down the cache SRAM, to save power when it is not needed,
+    /// neither as cache nor as RAM
+    ///
+    /// The contents of the SRAM are lost.
+    pub fn power_down(&mut self) {
+        self.device
+            .ctrl
+            .modify(|_, w| w.en().clear_bit().power_down().set_bit());
+    }
+
+    /// Power up the cache SRAM again
+    ///
+    /// The cache is not enabled, and has to be flushed before it is, as its contents were lost.
+    pub fn power_up(&mut self) {
+        self.device.ctrl.modify(|_, w| w.power_down().clear_bit());
+    }
+
+    /// The number of cache hits and the number of cacheable accesses since the counters were
+    /// reset
+    ///
+    /// The counters saturate at `u32::MAX`.
+    pub fn cache_stats(&self) -> (u32, u32) {
+        (
+            self.device.ctr_hit.read().bits(),
+            self.device.ctr_acc.read().bits(),
+        )
+    }
+
+    /// Reset the counters of [`cache_stats`](Self::cache_stats)
+    pub fn reset_cache_stats(&mut self) {
+        // Any write clears the counters
+        self.device.ctr_hit.write(|w| unsafe { w.bits(0) });
+        self.device.ctr_acc.write(|w| unsafe { w.bits(0) });
+    }
+}
+
+/// The divider of clk_sys for the SSI clock
+pub fn clock_divider() -> u8 {
+    // Safety: reading a register which is only written with interrupts disabled
+    unsafe { core::ptr::read_volatile(SSI_BAUDR) as u8 }
+}
+
+/// Change the divider of clk_sys for the SSI clock, i.e. the clock of the flash
+///
+/// The divider has to be even and at least 2. The second stage bootloader of the Raspberry Pi
+/// Pico selects 2, so its flash runs at 62.5MHz when clk_sys runs at 125MHz. The flash chip
+/// limits the frequency, and a fast clock may need a different RX sample delay, which the second
+/// stage bootloader selects for its divider.
+///
+/// The SSI is disabled while the divider is changed, so this runs from RAM with interrupts
+/// disabled.
+///
+/// ```no_run
+/// // 125MHz / 4 = 31.25MHz, e.g. for a slow flash chip
+/// unsafe { rp2040_hal::xip::set_clock_divider(4) }.unwrap();
+/// ```
+///
+/// # Safety
+///
+/// Nothing must execute or read from the flash while the divider is changed, see the
+/// [flash module documentation](crate::flash) about the other core. If the flash can't keep up
+/// with the new clock, every access to it fails, including fetching the code which runs
+/// afterwards.
+pub unsafe fn set_clock_divider(divider: u8) -> Result<(), Error> {
+    if divider < 2 || divider % 2 != 0 {
+        return Err(Error::InvalidDivider);
+    }
+    cortex_m::interrupt::free(|_| set_clock_divider_in_ram(divider));
+    Ok(())
+}
+
+/// Select `divider` again, after the second stage bootloader restored its own. Must be called
+/// with interrupts disabled.
+pub(crate) unsafe fn restore_clock_divider(divider: u8) {
+    if clock_divider() != divider {
+        set_clock_divider_in_ram(divider);
+    }
+}
+
+/// Change the divider while no code is fetched from the flash. The registers are accessed with
+/// the `ram_*` helpers of the flash module, so nothing is called in the flash, whatever the
+/// `opt-level`.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn set_clock_divider_in_ram(divider: u8) {
+    while ram_read(SSI_SR) & SSI_SR_BUSY != 0 {}
+    ram_write(SSI_SSIENR, 0);
+    ram_write(SSI_BAUDR, u32::from(divider));
+    ram_write(SSI_SSIENR, 1);
+}
@@ -8,7 +8,7 @@ use embedded_time::{
     duration::{Duration, Milliseconds},
     fixed_point::FixedPoint,
     fraction::Fraction,
-    rate::{Hertz, Megahertz, Rate},
+    rate::{Hertz, Rate},
 };
 
 use nb::Error::WouldBlock;
@@ -30,7 +30,9 @@ pub struct Stable {
 }
 
 /// XOSC is in dormant mode (see Chapter 2, Section 16, §5)
-pub struct Dormant;
+pub struct Dormant {
+    freq_hz: Hertz,
+}
 
 impl State for Disabled {}
 impl State for Initialized {}
@@ -39,7 +41,7 @@ impl State for Dormant {}
 
 /// Possible errors when initializing the CrystalOscillator
 pub enum Error {
-    /// Frequency is out of the 1-15MHz range (see datasheet)
+    /// Frequency is out of the 1-15MHz range supported by the crystal oscillator (see datasheet)
     FrequencyOutOfRange,
 
     /// Argument is bad : overflows, ...
@@ -89,15 +91,15 @@ impl CrystalOscillator<Disabled> {
     }
 
     /// Initializes the XOSC : frequency range is set, startup delay is calculated and set.
+    ///
+    /// The startup delay is derived from `frequency`, which must be between 1 and 15MHz.
     pub fn initialize(self, frequency: Hertz) -> Result<CrystalOscillator<Initialized>, Error> {
-        const ALLOWED_FREQUENCY_RANGE: RangeInclusive<Megahertz<u32>> =
-            Megahertz(1)..=Megahertz(15);
+        const ALLOWED_FREQUENCY_RANGE: RangeInclusive<Hertz<u32>> =
+            Hertz(1_000_000)..=Hertz(15_000_000);
         const STABLE_DELAY: Milliseconds = Milliseconds(1_u32);
         const DIVIDER: Fraction = Fraction::new(256, 1);
 
-        let freq_mhz: Megahertz = frequency.into();
-
-        if !ALLOWED_FREQUENCY_RANGE.contains(&freq_mhz) {
+        if !ALLOWED_FREQUENCY_RANGE.contains(&frequency) {
             return Err(Error::FrequencyOutOfRange);
         }
 
@@ -185,11 +187,22 @@ impl CrystalOscillator<Stable> {
         //taken from the C SDK
         const XOSC_DORMANT_VALUE: u32 = 0x636f6d61;
 
+        let freq_hz = self.state.freq_hz;
         self.device.dormant.write(|w| {
             w.bits(XOSC_DORMANT_VALUE);
             w
         });
 
-        self.transition(Dormant)
+        self.transition(Dormant { freq_hz })
+    }
+}
+
+impl CrystalOscillator<Dormant> {
+    /// Get the oscillator back after the chip woke up from DORMANT state.
+    ///
+    /// The XOSC restarts by itself, but has to stabilize again before it can be used.
+    pub fn wake(self) -> CrystalOscillator<Initialized> {
+        let freq_hz = self.state.freq_hz;
+        self.transition(Initialized { freq_hz })
     }
 }